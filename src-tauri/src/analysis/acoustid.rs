@@ -0,0 +1,206 @@
+// AcoustID lookup (https://acoustid.org/webservice)
+//
+// Sends a Chromaprint fingerprint plus the track duration and returns the
+// matching AcoustID entries with their MusicBrainz recordings. The base URL
+// is a parameter so the client can be pointed at a local stub in tests.
+
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+
+pub const ACOUSTID_API_BASE: &str = "https://api.acoustid.org/v2";
+const USER_AGENT: &str = concat!("Audion/", env!("CARGO_PKG_VERSION"));
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AcoustIdRecording {
+    /// MusicBrainz recording ID.
+    pub id: String,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub duration: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AcoustIdMatch {
+    pub id: String,
+    pub score: f64,
+    pub recordings: Vec<AcoustIdRecording>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupResponse {
+    status: String,
+    #[serde(default)]
+    results: Vec<RawResult>,
+    error: Option<RawError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawResult {
+    id: String,
+    score: f64,
+    #[serde(default)]
+    recordings: Vec<RawRecording>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawRecording {
+    id: String,
+    title: Option<String>,
+    duration: Option<f64>,
+    #[serde(default)]
+    artists: Vec<RawArtist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawArtist {
+    name: String,
+}
+
+/// Look up an encoded fingerprint. Results are sorted by score, best first.
+pub fn lookup(
+    base_url: &str,
+    client_key: &str,
+    fingerprint: &str,
+    duration_secs: u32,
+) -> Result<Vec<AcoustIdMatch>, String> {
+    let client = Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+    // POST keeps long fingerprints out of the URL
+    let duration = duration_secs.to_string();
+    let response = client
+        .post(format!("{}/lookup", base_url.trim_end_matches('/')))
+        .form(&[
+            ("client", client_key),
+            ("duration", duration.as_str()),
+            ("fingerprint", fingerprint),
+            ("meta", "recordings"),
+            ("format", "json"),
+        ])
+        .send()
+        .map_err(|e| format!("AcoustID request failed: {}", e))?;
+
+    let body: LookupResponse = response
+        .json()
+        .map_err(|e| format!("Invalid AcoustID response: {}", e))?;
+
+    if body.status != "ok" {
+        let message = body
+            .error
+            .map(|e| e.message)
+            .unwrap_or_else(|| body.status.clone());
+        return Err(format!("AcoustID error: {}", message));
+    }
+
+    let mut matches: Vec<AcoustIdMatch> = body
+        .results
+        .into_iter()
+        .map(|r| AcoustIdMatch {
+            id: r.id,
+            score: r.score,
+            recordings: r
+                .recordings
+                .into_iter()
+                .map(|rec| AcoustIdRecording {
+                    id: rec.id,
+                    title: rec.title,
+                    artists: rec.artists.into_iter().map(|a| a.name).collect(),
+                    duration: rec.duration,
+                })
+                .collect(),
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// One-shot HTTP server returning `body`; sends the raw request back.
+    fn spawn_stub(body: &'static str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let Ok((mut socket, _)) = listener.accept() else { return };
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read headers, then the form body announced by Content-Length
+            loop {
+                let n = socket.read(&mut buf).unwrap_or(0);
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(head_end) = text.find("\r\n\r\n") {
+                    let length = text
+                        .lines()
+                        .find_map(|l| {
+                            l.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|v| v.trim().parse::<usize>().unwrap_or(0))
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= head_end + 4 + length {
+                        break;
+                    }
+                }
+            }
+            let _ = tx.send(String::from_utf8_lossy(&request).to_string());
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes());
+        });
+        (format!("http://{}/v2", addr), rx)
+    }
+
+    #[test]
+    fn test_lookup_against_stub() {
+        let (base, requests) = spawn_stub(
+            r#"{"status":"ok","results":[
+                {"id":"low","score":0.4,"recordings":[]},
+                {"id":"b2a6","score":0.97,"recordings":[{"id":"mbid-1","title":"Song","duration":201,"artists":[{"id":"x","name":"Band"}]}]}
+            ]}"#,
+        );
+        let matches = lookup(&base, "key123", "AQAAAA", 201).unwrap();
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("POST /v2/lookup"));
+        assert!(request.contains("client=key123"));
+        assert!(request.contains("fingerprint=AQAAAA"));
+        assert!(request.contains("duration=201"));
+
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].id, "b2a6");
+        assert_eq!(matches[0].recordings[0].artists, vec!["Band".to_string()]);
+    }
+
+    #[test]
+    fn test_lookup_error_status() {
+        let (base, _requests) =
+            spawn_stub(r#"{"status":"error","error":{"code":4,"message":"invalid API key"}}"#);
+        let err = lookup(&base, "bad", "AQAAAA", 10).unwrap_err();
+        assert!(err.contains("invalid API key"), "{}", err);
+    }
+}
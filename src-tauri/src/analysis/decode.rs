@@ -0,0 +1,169 @@
+// Offline decoding for analysis jobs (waveforms, tempo/key, fingerprints,
+// quality checks)
+//
+// Unlike audio.rs this never touches an output device: it decodes a file as
+// fast as possible and hands interleaved f32 blocks to a callback.
+
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Stream properties reported by the decoder.
+#[derive(Debug, Clone, Copy)]
+pub struct AudioProps {
+    pub sample_rate: u32,
+    pub channels: usize,
+    /// Declared sample width; only lossless codecs report one.
+    pub bits_per_sample: Option<u32>,
+    /// Decoder short name ("flac", "mp3", "pcm_s16le", ...).
+    pub codec: &'static str,
+    /// Frames actually decoded.
+    pub frames_decoded: u64,
+}
+
+impl AudioProps {
+    pub fn is_lossless(&self) -> bool {
+        matches!(self.codec, "flac" | "alac" | "wavpack") || self.codec.starts_with("pcm")
+    }
+
+    pub fn duration_secs(&self) -> f64 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+        self.frames_decoded as f64 / self.sample_rate as f64
+    }
+}
+
+/// Decode `path`, calling `on_block(interleaved, channels)` for every decoded
+/// packet. Stops after `limit` of audio when given.
+pub fn decode_file<F>(
+    path: &Path,
+    limit: Option<Duration>,
+    mut on_block: F,
+) -> Result<AudioProps, String>
+where
+    F: FnMut(&[f32], usize),
+{
+    let file = File::open(path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| format!("Failed to probe {:?}: {}", path, e))?;
+
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| format!("No audio track found in {:?}", path))?;
+
+    let track_id = track.id;
+    let mut props = AudioProps {
+        sample_rate: track.codec_params.sample_rate.unwrap_or(44100),
+        channels: track.codec_params.channels.map(|c| c.count()).unwrap_or(2),
+        bits_per_sample: track.codec_params.bits_per_sample,
+        codec: symphonia::default::get_codecs()
+            .get_codec(track.codec_params.codec)
+            .map(|d| d.short_name)
+            .unwrap_or("unknown"),
+        frames_decoded: 0,
+    };
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("Failed to create decoder for {:?}: {}", path, e))?;
+
+    let max_frames = limit.map(|l| (l.as_secs_f64() * props.sample_rate as f64) as u64);
+    let mut sample_buf: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(p) => p,
+            Err(SymphoniaError::IoError(_)) => break, // end of stream
+            Err(SymphoniaError::ResetRequired) => {
+                decoder.reset();
+                continue;
+            }
+            Err(e) => return Err(format!("Failed to read {:?}: {}", path, e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(d) => d,
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(format!("Failed to decode {:?}: {}", path, e)),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let needed = decoded.capacity() * channels;
+        if !matches!(&sample_buf, Some(b) if b.capacity() >= needed) {
+            sample_buf = Some(SampleBuffer::<f32>::new(decoded.capacity() as u64, spec));
+        }
+        let buf = sample_buf.as_mut().expect("sample buffer allocated above");
+        buf.copy_interleaved_ref(decoded);
+
+        let mut samples = buf.samples();
+        if let Some(max) = max_frames {
+            let remaining = max.saturating_sub(props.frames_decoded) as usize;
+            samples = &samples[..samples.len().min(remaining * channels)];
+        }
+
+        props.channels = channels;
+        props.frames_decoded += (samples.len() / channels) as u64;
+        on_block(samples, channels);
+
+        if max_frames.is_some_and(|max| props.frames_decoded >= max) {
+            break;
+        }
+    }
+
+    Ok(props)
+}
+
+/// Average interleaved channels into `out` (cleared first).
+pub fn downmix(interleaved: &[f32], channels: usize, out: &mut Vec<f32>) {
+    out.clear();
+    if channels <= 1 {
+        out.extend_from_slice(interleaved);
+        return;
+    }
+    let scale = 1.0 / channels as f32;
+    out.extend(
+        interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() * scale),
+    );
+}
+
+/// Decode `path` to a single mono buffer, up to `limit` of audio.
+pub fn decode_mono(path: &Path, limit: Option<Duration>) -> Result<(Vec<f32>, AudioProps), String> {
+    let mut mono = Vec::new();
+    let mut block = Vec::new();
+    let props = decode_file(path, limit, |samples, channels| {
+        downmix(samples, channels, &mut block);
+        mono.extend_from_slice(&block);
+    })?;
+    Ok((mono, props))
+}
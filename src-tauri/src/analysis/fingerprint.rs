@@ -0,0 +1,497 @@
+// Chromaprint-compatible acoustic fingerprints
+//
+// Implements Chromaprint's default algorithm (TEST2) so fingerprints can be
+// submitted to AcoustID as-is:
+//
+//   mono 11025 Hz → Hamming-windowed 4096-point FFT every 1365 samples
+//   → 12-band chroma (28–3520 Hz) → 5-tap smoothing → L2 normalization
+//   → 16 Haar-like classifiers over the chroma "image", each quantized to a
+//     2-bit Gray code → one 32-bit sub-fingerprint per frame.
+//
+// The raw sub-fingerprints are what we store and compare locally; the
+// compressed, base64url-encoded form is only needed for AcoustID.
+
+use std::path::Path;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+use crate::analysis::decode;
+use crate::analysis::spectrum::{self, Stft, Window};
+
+pub const SAMPLE_RATE: u32 = 11025;
+/// Like `fpcalc`, only the opening two minutes are fingerprinted.
+pub const MAX_DURATION: Duration = Duration::from_secs(120);
+/// CHROMAPRINT_ALGORITHM_TEST2, the library default.
+pub const ALGORITHM: u8 = 1;
+
+const FRAME_LEN: usize = 4096;
+const HOP: usize = FRAME_LEN / 3;
+const MIN_FREQ: f64 = 28.0;
+const MAX_FREQ: f64 = 3520.0;
+const BANDS: usize = 12;
+const FILTER: [f64; 5] = [0.25, 0.75, 1.0, 0.75, 0.25];
+const NORM_THRESHOLD: f64 = 0.01;
+
+/// Default similarity above which two fingerprints are the same recording.
+pub const DUPLICATE_THRESHOLD: f64 = 0.85;
+/// Alignment search range when comparing (±~5 s of leading silence/offset).
+const MAX_ALIGN_OFFSET: isize = 40;
+/// Minimum overlapping sub-fingerprints (~5 s) for a meaningful comparison.
+const MIN_OVERLAP: usize = 40;
+
+#[derive(Debug, Clone, Copy)]
+struct Classifier {
+    kind: u8,
+    y: usize,
+    height: usize,
+    width: usize,
+    thresholds: [f64; 3],
+}
+
+const fn classifier(kind: u8, y: usize, height: usize, width: usize, t: [f64; 3]) -> Classifier {
+    Classifier {
+        kind,
+        y,
+        height,
+        width,
+        thresholds: t,
+    }
+}
+
+/// Chromaprint's trained TEST2 classifier set.
+const CLASSIFIERS: [Classifier; 16] = [
+    classifier(0, 4, 3, 15, [1.98215, 2.35817, 2.63523]),
+    classifier(4, 4, 6, 15, [-1.03809, -0.651211, -0.282167]),
+    classifier(1, 0, 4, 16, [-0.298702, 0.119262, 0.558497]),
+    classifier(3, 8, 2, 12, [-0.105439, 0.0153946, 0.135898]),
+    classifier(3, 4, 4, 8, [-0.142891, 0.0258736, 0.200632]),
+    classifier(4, 0, 3, 5, [-0.826319, -0.590612, -0.368214]),
+    classifier(1, 2, 2, 9, [-0.557409, -0.233035, 0.0534525]),
+    classifier(2, 7, 3, 4, [-0.0646826, 0.00620476, 0.0784847]),
+    classifier(2, 6, 2, 16, [-0.192387, -0.029699, 0.215855]),
+    classifier(2, 1, 3, 2, [-0.0397818, -0.00568076, 0.0292026]),
+    classifier(5, 10, 1, 15, [-0.53823, -0.369934, -0.190235]),
+    classifier(3, 6, 2, 10, [-0.124877, 0.0296483, 0.139239]),
+    classifier(2, 1, 1, 14, [-0.101475, 0.0225617, 0.126953]),
+    classifier(3, 5, 6, 4, [-0.0799915, -0.00729616, 0.116265]),
+    classifier(2, 1, 1, 9, [-0.0329922, 0.0340059, 0.0963276]),
+    classifier(1, 3, 3, 11, [-0.0578026, -0.0269007, 0.0142432]),
+];
+const MAX_FILTER_WIDTH: usize = 16;
+
+/// Prefix sums over chroma rows: `rows[r][c]` = sum of rows < r, bands < c.
+struct IntegralImage {
+    rows: Vec<[f64; BANDS + 1]>,
+}
+
+impl IntegralImage {
+    fn new() -> Self {
+        Self {
+            rows: vec![[0.0; BANDS + 1]],
+        }
+    }
+
+    fn push(&mut self, features: &[f64; BANDS]) {
+        let prev = *self.rows.last().expect("integral image has a zero row");
+        let mut row = [0.0; BANDS + 1];
+        let mut running = 0.0;
+        for c in 0..BANDS {
+            running += features[c];
+            row[c + 1] = prev[c + 1] + running;
+        }
+        self.rows.push(row);
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len() - 1
+    }
+
+    /// Sum over rows `r1..r2` and bands `c1..c2`.
+    fn area(&self, r1: usize, c1: usize, r2: usize, c2: usize) -> f64 {
+        self.rows[r2][c2] - self.rows[r1][c2] - self.rows[r2][c1] + self.rows[r1][c1]
+    }
+}
+
+fn subtract_log(a: f64, b: f64) -> f64 {
+    ((1.0 + a) / (1.0 + b)).ln()
+}
+
+impl Classifier {
+    fn filter(&self, img: &IntegralImage, x: usize) -> f64 {
+        let (y, w, h) = (self.y, self.width, self.height);
+        let (a, b) = match self.kind {
+            0 => (img.area(x, y, x + w, y + h), 0.0),
+            1 => {
+                let h2 = h / 2;
+                (
+                    img.area(x, y + h2, x + w, y + h),
+                    img.area(x, y, x + w, y + h2),
+                )
+            }
+            2 => {
+                let w2 = w / 2;
+                (
+                    img.area(x + w2, y, x + w, y + h),
+                    img.area(x, y, x + w2, y + h),
+                )
+            }
+            3 => {
+                let (w2, h2) = (w / 2, h / 2);
+                (
+                    img.area(x, y, x + w2, y + h2) + img.area(x + w2, y + h2, x + w, y + h),
+                    img.area(x, y + h2, x + w2, y + h) + img.area(x + w2, y, x + w, y + h2),
+                )
+            }
+            4 => {
+                let h3 = h / 3;
+                (
+                    img.area(x, y + h3, x + w, y + 2 * h3),
+                    img.area(x, y, x + w, y + h3) + img.area(x, y + 2 * h3, x + w, y + h),
+                )
+            }
+            _ => {
+                let w3 = w / 3;
+                (
+                    img.area(x + w3, y, x + 2 * w3, y + h),
+                    img.area(x, y, x + w3, y + h) + img.area(x + 2 * w3, y, x + w, y + h),
+                )
+            }
+        };
+        subtract_log(a, b)
+    }
+
+    /// Quantize into 0..=3 and Gray-code the result.
+    fn classify(&self, img: &IntegralImage, x: usize) -> u32 {
+        let value = self.filter(img, x);
+        let [t0, t1, t2] = self.thresholds;
+        let q = if value < t1 {
+            if value < t0 {
+                0
+            } else {
+                1
+            }
+        } else if value < t2 {
+            2
+        } else {
+            3
+        };
+        [0, 1, 3, 2][q]
+    }
+}
+
+/// Map FFT bins in 28–3520 Hz to chroma bands.
+fn bin_notes() -> (usize, Vec<usize>) {
+    let freq_to_index = |f: f64| (FRAME_LEN as f64 * f / SAMPLE_RATE as f64).round() as usize;
+    let min_index = freq_to_index(MIN_FREQ).max(1);
+    let max_index = freq_to_index(MAX_FREQ).min(FRAME_LEN / 2);
+    let notes = (min_index..max_index)
+        .map(|i| {
+            let freq = i as f64 * SAMPLE_RATE as f64 / FRAME_LEN as f64;
+            let octave = (freq / (440.0 / 16.0)).log2();
+            (BANDS as f64 * (octave - octave.floor())) as usize % BANDS
+        })
+        .collect();
+    (min_index, notes)
+}
+
+/// Raw sub-fingerprints for a mono signal already at `SAMPLE_RATE`.
+pub fn compute(signal: &[f32]) -> Vec<u32> {
+    let stft = Stft::with_window(FRAME_LEN, HOP, Window::Hamming);
+    let (min_index, notes) = bin_notes();
+
+    let mut history: Vec<[f64; BANDS]> = Vec::with_capacity(FILTER.len());
+    let mut image = IntegralImage::new();
+    let mut fingerprint = Vec::new();
+
+    stft.for_each_frame(signal, |_, mags| {
+        let mut chroma = [0.0f64; BANDS];
+        for (offset, &band) in notes.iter().enumerate() {
+            let m = mags[min_index + offset] as f64;
+            chroma[band] += m * m;
+        }
+
+        if history.len() == FILTER.len() {
+            history.remove(0);
+        }
+        history.push(chroma);
+        if history.len() < FILTER.len() {
+            return;
+        }
+
+        let mut smoothed = [0.0f64; BANDS];
+        for (row, coef) in history.iter().zip(FILTER) {
+            for (s, v) in smoothed.iter_mut().zip(row) {
+                *s += v * coef;
+            }
+        }
+        let norm = smoothed.iter().map(|v| v * v).sum::<f64>().sqrt();
+        if norm < NORM_THRESHOLD {
+            smoothed = [0.0; BANDS];
+        } else {
+            for v in smoothed.iter_mut() {
+                *v /= norm;
+            }
+        }
+
+        image.push(&smoothed);
+        if image.len() >= MAX_FILTER_WIDTH {
+            let x = image.len() - MAX_FILTER_WIDTH;
+            let bits = CLASSIFIERS
+                .iter()
+                .fold(0u32, |bits, c| (bits << 2) | c.classify(&image, x));
+            fingerprint.push(bits);
+        }
+    });
+
+    fingerprint
+}
+
+/// Fingerprint the opening `MAX_DURATION` of a file.
+pub fn fingerprint_file(path: &Path) -> Result<Vec<u32>, String> {
+    let (mono, props) = decode::decode_mono(path, Some(MAX_DURATION))?;
+    let signal = spectrum::resample(&mono, props.sample_rate, SAMPLE_RATE);
+    let fingerprint = compute(&signal);
+    if fingerprint.is_empty() {
+        return Err(format!("{:?} is too short to fingerprint", path));
+    }
+    Ok(fingerprint)
+}
+
+/// Little-endian storage form for the database.
+pub fn to_bytes(fingerprint: &[u32]) -> Vec<u8> {
+    fingerprint.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// LSB-first packing of `width`-bit values, as Chromaprint's PackIntNArray.
+fn pack_bits(values: &[u32], width: u32, out: &mut Vec<u8>) {
+    let mut acc: u32 = 0;
+    let mut filled = 0;
+    for &v in values {
+        acc |= (v & ((1 << width) - 1)) << filled;
+        filled += width;
+        while filled >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            filled -= 8;
+        }
+    }
+    if filled > 0 {
+        out.push(acc as u8);
+    }
+}
+
+/// Chromaprint's compressed binary form (before base64).
+pub fn compress(fingerprint: &[u32], algorithm: u8) -> Vec<u8> {
+    const MAX_NORMAL: u32 = 7;
+
+    let mut bits = Vec::new();
+    let mut prev = 0u32;
+    for &item in fingerprint {
+        let mut x = item ^ prev;
+        prev = item;
+        let (mut bit, mut last_bit) = (1u32, 0u32);
+        while x != 0 {
+            if x & 1 != 0 {
+                bits.push(bit - last_bit);
+                last_bit = bit;
+            }
+            x >>= 1;
+            bit += 1;
+        }
+        bits.push(0);
+    }
+
+    let len = fingerprint.len() as u32;
+    let mut out = vec![algorithm, (len >> 16) as u8, (len >> 8) as u8, len as u8];
+    let normal: Vec<u32> = bits.iter().map(|&b| b.min(MAX_NORMAL)).collect();
+    let exceptional: Vec<u32> = bits
+        .iter()
+        .filter(|&&b| b >= MAX_NORMAL)
+        .map(|&b| b - MAX_NORMAL)
+        .collect();
+    pack_bits(&normal, 3, &mut out);
+    pack_bits(&exceptional, 5, &mut out);
+    out
+}
+
+/// The string `fpcalc` prints and AcoustID accepts.
+pub fn encode(fingerprint: &[u32]) -> String {
+    URL_SAFE_NO_PAD.encode(compress(fingerprint, ALGORITHM))
+}
+
+/// Fraction of matching bits at the best alignment (0.5 ≈ unrelated,
+/// 1.0 = identical). Returns 0.0 when the overlap is too short to judge.
+pub fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    let mut best = 0.0f64;
+    for offset in -MAX_ALIGN_OFFSET..=MAX_ALIGN_OFFSET {
+        let (a_start, b_start) = if offset >= 0 {
+            (offset as usize, 0)
+        } else {
+            (0, (-offset) as usize)
+        };
+        if a_start >= a.len() || b_start >= b.len() {
+            continue;
+        }
+        let n = (a.len() - a_start).min(b.len() - b_start);
+        if n < MIN_OVERLAP {
+            continue;
+        }
+        let errors: u32 = a[a_start..a_start + n]
+            .iter()
+            .zip(&b[b_start..b_start + n])
+            .map(|(x, y)| (x ^ y).count_ones())
+            .sum();
+        best = best.max(1.0 - errors as f64 / (32.0 * n as f64));
+    }
+    best
+}
+
+/// A fingerprinted track considered for duplicate clustering.
+#[derive(Debug, Clone)]
+pub struct FingerprintEntry {
+    pub track_id: i64,
+    pub duration_secs: f64,
+    pub fingerprint: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    pub track_ids: Vec<i64>,
+    /// Lowest similarity among the links that formed the cluster.
+    pub min_similarity: f64,
+}
+
+/// Group entries whose fingerprints match above `threshold`. Only tracks
+/// within `max_duration_diff` seconds of each other are compared.
+pub fn find_clusters(
+    entries: &[FingerprintEntry],
+    threshold: f64,
+    max_duration_diff: f64,
+) -> Vec<Cluster> {
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by(|&a, &b| entries[a].duration_secs.total_cmp(&entries[b].duration_secs));
+
+    let mut parent: Vec<usize> = (0..entries.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut link_scores: Vec<(usize, f64)> = Vec::new();
+    for (pos, &i) in order.iter().enumerate() {
+        for &j in &order[pos + 1..] {
+            if entries[j].duration_secs - entries[i].duration_secs > max_duration_diff {
+                break;
+            }
+            let score = similarity(&entries[i].fingerprint, &entries[j].fingerprint);
+            if score >= threshold {
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                if ri != rj {
+                    parent[rj] = ri;
+                }
+                link_scores.push((i, score));
+            }
+        }
+    }
+
+    let mut groups: std::collections::BTreeMap<usize, Vec<usize>> = Default::default();
+    for i in 0..entries.len() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(i);
+    }
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let root = find(&mut parent, members[0]);
+            let min_similarity = link_scores
+                .iter()
+                .filter(|(i, _)| find(&mut parent, *i) == root)
+                .map(|(_, s)| *s)
+                .fold(1.0, f64::min);
+            let mut track_ids: Vec<i64> = members.iter().map(|&m| entries[m].track_id).collect();
+            track_ids.sort_unstable();
+            Cluster {
+                track_ids,
+                min_similarity,
+            }
+        })
+        .collect();
+    clusters.sort_by_key(|c| c.track_ids[0]);
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_matches_chromaprint() {
+        // Vectors from Chromaprint's fingerprint compressor tests
+        assert_eq!(compress(&[1], 0), vec![0, 0, 0, 1, 1]);
+        assert_eq!(compress(&[7], 0), vec![0, 0, 0, 1, 73, 0]);
+        assert_eq!(compress(&[1 << 6], 0), vec![0, 0, 0, 1, 7, 0]);
+        assert_eq!(compress(&[1, 0], 0), vec![0, 0, 0, 2, 65, 0]);
+    }
+
+    fn tone_sequence(seed: u32, secs: f32) -> Vec<f32> {
+        // A melody of sustained notes chosen by a small PRNG
+        let rate = SAMPLE_RATE as f32;
+        let note_len = (0.4 * rate) as usize;
+        let total = (secs * rate) as usize;
+        let mut state = seed;
+        let mut out = Vec::with_capacity(total);
+        while out.len() < total {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let midi = 48 + (state >> 24) % 24;
+            let hz = 440.0 * 2f32.powf((midi as f32 - 69.0) / 12.0);
+            for i in 0..note_len.min(total - out.len()) {
+                let t = i as f32 / rate;
+                out.push(0.5 * (std::f32::consts::TAU * hz * t).sin());
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_similarity_and_clusters() {
+        let a = compute(&tone_sequence(1, 30.0));
+        let b = compute(&tone_sequence(2, 30.0));
+        assert!(!a.is_empty());
+
+        // Same audio with a slight gain change and leading silence
+        let mut shifted = vec![0.0f32; SAMPLE_RATE as usize];
+        shifted.extend(tone_sequence(1, 29.0).iter().map(|s| s * 0.7));
+        let a2 = compute(&shifted);
+
+        assert!(similarity(&a, &a2) > 0.9, "{}", similarity(&a, &a2));
+        assert!(similarity(&a, &b) < 0.75, "{}", similarity(&a, &b));
+
+        let entries = vec![
+            FingerprintEntry { track_id: 1, duration_secs: 30.0, fingerprint: a },
+            FingerprintEntry { track_id: 2, duration_secs: 30.0, fingerprint: b },
+            FingerprintEntry { track_id: 3, duration_secs: 30.5, fingerprint: a2 },
+        ];
+        let clusters = find_clusters(&entries, DUPLICATE_THRESHOLD, 3.0);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].track_ids, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let fp = vec![0xdead_beef, 1, u32::MAX];
+        assert_eq!(from_bytes(&to_bytes(&fp)), fp);
+    }
+}
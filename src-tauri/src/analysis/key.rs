@@ -0,0 +1,310 @@
+// Musical key estimation and key notation
+//
+// A chroma vector (energy per pitch class) is accumulated from the STFT
+// between A1 and ~C7 and correlated against the Krumhansl-Kessler major and
+// minor key profiles in all 12 rotations; the best of the 24 wins.
+//
+// Keys are stored in TKEY-style notation ("C", "F#m", "Bb") and exposed with
+// their Camelot wheel code ("8B", "11A") for harmonic mixing.
+
+use std::fmt;
+
+use crate::analysis::spectrum::{self, Stft};
+
+const TARGET_RATE: u32 = 11025;
+const FRAME_LEN: usize = 4096;
+const HOP: usize = 2048;
+const MIN_HZ: f32 = 55.0;
+const MAX_HZ: f32 = 2000.0;
+
+const MAJOR_PROFILE: [f64; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f64; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// Pitch class names, using the spellings DJ software conventionally shows.
+const MAJOR_NAMES: [&str; 12] = [
+    "C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+];
+const MINOR_NAMES: [&str; 12] = [
+    "C", "C#", "D", "Eb", "E", "F", "F#", "G", "G#", "A", "Bb", "B",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MusicalKey {
+    /// Pitch class of the tonic, C = 0.
+    pub tonic: u8,
+    pub minor: bool,
+}
+
+impl MusicalKey {
+    pub fn new(tonic: u8, minor: bool) -> Self {
+        Self {
+            tonic: tonic % 12,
+            minor,
+        }
+    }
+
+    /// Camelot wheel position: number 1–12 and `'A'` (minor) / `'B'` (major).
+    pub fn camelot(&self) -> (u8, char) {
+        // Relative major shares the number; each fifth up is one step clockwise
+        let major_tonic = if self.minor { (self.tonic + 3) % 12 } else { self.tonic };
+        let number = (major_tonic * 7 + 7) % 12 + 1;
+        (number, if self.minor { 'A' } else { 'B' })
+    }
+
+    pub fn camelot_code(&self) -> String {
+        let (number, letter) = self.camelot();
+        format!("{}{}", number, letter)
+    }
+
+    fn from_camelot(number: u8, letter: char) -> Option<Self> {
+        if !(1..=12).contains(&number) {
+            return None;
+        }
+        // Inverse of `camelot`: 7 is its own inverse mod 12
+        let major_tonic = ((number - 1) * 7 + 11) % 12;
+        match letter.to_ascii_uppercase() {
+            'B' => Some(Self::new(major_tonic, false)),
+            'A' => Some(Self::new(major_tonic + 9, true)),
+            _ => None,
+        }
+    }
+
+    /// Keys that mix harmonically with this one: same key, relative
+    /// major/minor, and one step either way around the wheel.
+    pub fn compatible_keys(&self) -> [MusicalKey; 4] {
+        let (number, letter) = self.camelot();
+        let other = if letter == 'A' { 'B' } else { 'A' };
+        let up = number % 12 + 1;
+        let down = (number + 10) % 12 + 1;
+        [
+            *self,
+            Self::from_camelot(number, other).unwrap_or(*self),
+            Self::from_camelot(up, letter).unwrap_or(*self),
+            Self::from_camelot(down, letter).unwrap_or(*self),
+        ]
+    }
+
+    /// Parse tag notation ("Am", "F# minor", "Bbmaj", "Ebm") or a Camelot
+    /// code ("8A", "12B").
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+
+        let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if !digits.is_empty() {
+            let letter = value[digits.len()..].trim().chars().next()?;
+            return Self::from_camelot(digits.parse().ok()?, letter);
+        }
+
+        let mut chars = value.chars();
+        let tonic = match chars.next()?.to_ascii_uppercase() {
+            'C' => 0,
+            'D' => 2,
+            'E' => 4,
+            'F' => 5,
+            'G' => 7,
+            'A' => 9,
+            'B' => 11,
+            _ => return None,
+        };
+        let rest = chars.as_str();
+        let (tonic, rest) = match rest.chars().next() {
+            Some('#') | Some('♯') => (tonic + 1, &rest[rest.chars().next()?.len_utf8()..]),
+            Some('b') | Some('♭') => (tonic + 11, &rest[rest.chars().next()?.len_utf8()..]),
+            _ => (tonic, rest),
+        };
+
+        let mode = rest.trim().to_ascii_lowercase();
+        let minor = match mode.as_str() {
+            "" | "maj" | "major" | "dur" => false,
+            "m" | "min" | "minor" | "moll" => true,
+            _ => return None,
+        };
+        Some(Self::new(tonic, minor))
+    }
+}
+
+impl fmt::Display for MusicalKey {
+    /// TKEY notation: tonic plus "m" for minor.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.minor {
+            write!(f, "{}m", MINOR_NAMES[self.tonic as usize])
+        } else {
+            f.write_str(MAJOR_NAMES[self.tonic as usize])
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyEstimate {
+    pub key: MusicalKey,
+    /// Correlation with the winning profile (-1..1).
+    pub confidence: f64,
+}
+
+/// Estimate the key of a mono signal. Returns `None` for silence.
+pub fn estimate_key(samples: &[f32], sample_rate: u32) -> Option<KeyEstimate> {
+    let factor = spectrum::decimation_factor(sample_rate, TARGET_RATE);
+    let rate = sample_rate as f32 / factor as f32;
+    let signal = spectrum::decimate(samples, factor);
+
+    let chroma = chromagram(&signal, rate);
+    if chroma.iter().all(|&v| v <= f64::EPSILON) {
+        return None;
+    }
+
+    let mut best: Option<KeyEstimate> = None;
+    for tonic in 0..12u8 {
+        for (minor, profile) in [(false, &MAJOR_PROFILE), (true, &MINOR_PROFILE)] {
+            let rotated: Vec<f64> = (0..12)
+                .map(|pc| profile[(pc + 12 - tonic as usize) % 12])
+                .collect();
+            let r = pearson(&chroma, &rotated);
+            if best.is_none_or(|b| r > b.confidence) {
+                best = Some(KeyEstimate {
+                    key: MusicalKey::new(tonic, minor),
+                    confidence: r,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// Summed per-frame chroma, each frame normalized so loud passages do not
+/// dominate.
+fn chromagram(signal: &[f32], rate: f32) -> [f64; 12] {
+    let stft = Stft::new(FRAME_LEN, HOP);
+    let bin_classes: Vec<Option<usize>> = (0..stft.bins())
+        .map(|bin| {
+            let hz = stft.bin_hz(bin, rate);
+            if !(MIN_HZ..=MAX_HZ).contains(&hz) {
+                return None;
+            }
+            let semitones_from_a = (12.0 * (hz / 440.0).log2()).round() as i32;
+            Some((semitones_from_a + 9).rem_euclid(12) as usize)
+        })
+        .collect();
+
+    let mut chroma = [0.0f64; 12];
+    stft.for_each_frame(signal, |_, mags| {
+        let mut frame = [0.0f64; 12];
+        for (class, &m) in bin_classes.iter().zip(mags) {
+            if let Some(pc) = class {
+                frame[*pc] += (m as f64) * (m as f64);
+            }
+        }
+        let total: f64 = frame.iter().sum();
+        if total > 1e-9 {
+            for (c, v) in chroma.iter_mut().zip(frame) {
+                *c += v / total;
+            }
+        }
+    });
+    chroma
+}
+
+fn pearson(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    if var_a <= 0.0 || var_b <= 0.0 {
+        return 0.0;
+    }
+    cov / (var_a.sqrt() * var_b.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sustained triad plus scale tones, as sine partials.
+    fn chord(midi_notes: &[u8], sample_rate: u32, secs: f32) -> Vec<f32> {
+        let len = (secs * sample_rate as f32) as usize;
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                midi_notes
+                    .iter()
+                    .map(|&n| {
+                        let hz = 440.0 * 2f32.powf((n as f32 - 69.0) / 12.0);
+                        (std::f32::consts::TAU * hz * t).sin()
+                    })
+                    .sum::<f32>()
+                    / midi_notes.len() as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_estimate_key_from_triads() {
+        // C major: C E G with octave doubling
+        let c_major = chord(&[48, 60, 64, 67, 72], 44100, 8.0);
+        assert_eq!(
+            estimate_key(&c_major, 44100).map(|e| e.key),
+            Some(MusicalKey::new(0, false))
+        );
+
+        // A minor: A C E
+        let a_minor = chord(&[45, 57, 60, 64, 69], 44100, 8.0);
+        assert_eq!(
+            estimate_key(&a_minor, 44100).map(|e| e.key),
+            Some(MusicalKey::new(9, true))
+        );
+
+        assert_eq!(estimate_key(&vec![0.0; 44100 * 4], 44100), None);
+    }
+
+    #[test]
+    fn test_notation_and_camelot() {
+        let a_minor = MusicalKey::parse("Am").unwrap();
+        assert_eq!(a_minor.to_string(), "Am");
+        assert_eq!(a_minor.camelot_code(), "8A");
+        assert_eq!(MusicalKey::parse("C").unwrap().camelot_code(), "8B");
+        assert_eq!(MusicalKey::parse("F# minor").unwrap().camelot_code(), "11A");
+        assert_eq!(MusicalKey::parse("Bbmaj").unwrap().to_string(), "Bb");
+        assert_eq!(MusicalKey::parse("11b").unwrap().to_string(), "A");
+        assert_eq!(MusicalKey::parse("2A").unwrap().to_string(), "Ebm");
+        assert_eq!(MusicalKey::parse("H"), None);
+
+        // Every key survives a Camelot roundtrip
+        for tonic in 0..12 {
+            for minor in [false, true] {
+                let key = MusicalKey::new(tonic, minor);
+                assert_eq!(MusicalKey::parse(&key.camelot_code()), Some(key));
+                assert_eq!(MusicalKey::parse(&key.to_string()), Some(key));
+            }
+        }
+    }
+
+    #[test]
+    fn test_compatible_keys() {
+        let codes: Vec<String> = MusicalKey::parse("8A")
+            .unwrap()
+            .compatible_keys()
+            .iter()
+            .map(|k| k.camelot_code())
+            .collect();
+        assert_eq!(codes, vec!["8A", "8B", "9A", "7A"]);
+
+        let wrap: Vec<String> = MusicalKey::parse("1B")
+            .unwrap()
+            .compatible_keys()
+            .iter()
+            .map(|k| k.camelot_code())
+            .collect();
+        assert_eq!(wrap, vec!["1B", "1A", "2B", "12B"]);
+    }
+}
@@ -0,0 +1,9 @@
+// Audio analysis jobs that work on decoded samples rather than tags
+pub mod acoustid;
+pub mod decode;
+pub mod fingerprint;
+pub mod key;
+pub mod quality;
+pub mod spectrum;
+pub mod tempo;
+pub mod waveform;
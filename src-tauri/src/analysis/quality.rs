@@ -0,0 +1,386 @@
+// Lossless authenticity checks
+//
+// Lossy encoders low-pass the signal (LAME at 128 kbps cuts at ~17 kHz, at
+// 320 kbps at ~20.5 kHz), so a "lossless" file decoded from an MP3 shows a
+// brick-wall drop in its long-term spectrum well below Nyquist. The same
+// test catches 96 kHz files whose content stops at ~22 kHz (upsampled CD
+// audio). Bit usage is measured separately: a 24-bit file whose samples
+// are all multiples of 256 only carries 16 bits.
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::decode::{self, AudioProps};
+use crate::analysis::spectrum::Stft;
+
+/// Audio inspected per file; a couple of minutes is plenty for the
+/// long-term spectrum.
+pub const ANALYSIS_WINDOW: Duration = Duration::from_secs(120);
+
+const FRAME_LEN: usize = 8192;
+/// Spectrum smoothing width.
+const SMOOTHING_HZ: f32 = 200.0;
+/// Reference band for the overall level.
+const REFERENCE_BAND: (f32, f32) = (1_000.0, 5_000.0);
+/// Content counts as present down to this far below the reference level.
+const PRESENCE_DB: f64 = 70.0;
+/// A lowpass is "brick-wall" when the level falls this much across
+/// `EDGE_WIDTH_HZ` either side of the cutoff.
+const EDGE_DROP_DB: f64 = 30.0;
+const EDGE_WIDTH_HZ: f32 = 1_000.0;
+/// Lossy encoders never keep content above this; CD anti-alias filters sit
+/// above it.
+const MAX_LOSSY_CUTOFF_HZ: f32 = 20_800.0;
+/// Highest cutoff still explained by a 44.1/48 kHz source (Nyquist plus
+/// the resampler's transition band).
+const MAX_CD_CUTOFF_HZ: f32 = 25_000.0;
+/// Lossy files at or above this bitrate should keep content past ~17 kHz.
+const MIN_HIGH_BITRATE_KBPS: i32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityVerdict {
+    /// Lossless container with full-band content and bit usage.
+    Lossless,
+    /// Lossy codec; nothing to authenticate.
+    Lossy,
+    /// Lossless container (or high-bitrate lossy file) with a low-bitrate
+    /// encoder's lowpass.
+    Transcode,
+    /// High sample rate with content that stops around 22 kHz.
+    Upsampled,
+    /// Fewer bits in use than the container declares.
+    PaddedBitDepth,
+    /// Silent or undecodable.
+    Unknown,
+}
+
+impl QualityVerdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lossless => "lossless",
+            Self::Lossy => "lossy",
+            Self::Transcode => "transcode",
+            Self::Upsampled => "upsampled",
+            Self::PaddedBitDepth => "padded_bit_depth",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "lossless" => Self::Lossless,
+            "lossy" => Self::Lossy,
+            "transcode" => Self::Transcode,
+            "upsampled" => Self::Upsampled,
+            "padded_bit_depth" => Self::PaddedBitDepth,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Verdicts that mean the file is not what its container claims.
+    pub fn is_suspicious(&self) -> bool {
+        matches!(
+            self,
+            Self::Transcode | Self::Upsampled | Self::PaddedBitDepth
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QualityAnalysis {
+    pub verdict: QualityVerdict,
+    pub codec: String,
+    pub sample_rate: u32,
+    pub bits_per_sample: Option<u32>,
+    /// Bits actually carrying signal (lossless sources of up to 24 bits).
+    pub effective_bits: Option<u32>,
+    /// Highest frequency with content; `None` when it reaches Nyquist.
+    pub cutoff_hz: Option<f32>,
+    /// Whether the cutoff is a brick-wall lowpass rather than a natural
+    /// roll-off.
+    pub sharp_cutoff: bool,
+    /// Bitrate class of the lossy encoder implied by the cutoff.
+    pub likely_source: Option<String>,
+}
+
+impl QualityAnalysis {
+    /// Cross-check a lossy file against its declared bitrate: a "320 kbps"
+    /// MP3 with a 16 kHz lowpass was re-encoded from a low-bitrate source.
+    pub fn check_declared_bitrate(&mut self, bitrate_kbps: Option<i32>) {
+        let high_bitrate = bitrate_kbps.is_some_and(|b| b >= MIN_HIGH_BITRATE_KBPS);
+        let low_cutoff = self.sharp_cutoff && self.cutoff_hz.is_some_and(|hz| hz < 17_200.0);
+        if self.verdict == QualityVerdict::Lossy && high_bitrate && low_cutoff {
+            self.verdict = QualityVerdict::Transcode;
+        }
+    }
+}
+
+/// Long-term spectrum and bit-usage accumulator, fed block by block so the
+/// whole file never has to sit in memory.
+pub struct QualityAnalyzer {
+    stft: Stft,
+    pending: Vec<f32>,
+    power: Vec<f64>,
+    frames: usize,
+    /// OR of every sample scaled to 24-bit integers.
+    used_bits: u32,
+    mono: Vec<f32>,
+}
+
+impl Default for QualityAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QualityAnalyzer {
+    pub fn new() -> Self {
+        let stft = Stft::new(FRAME_LEN, FRAME_LEN);
+        let bins = stft.bins();
+        Self {
+            stft,
+            pending: Vec::new(),
+            power: vec![0.0; bins],
+            frames: 0,
+            used_bits: 0,
+            mono: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, interleaved: &[f32], channels: usize) {
+        for &s in interleaved {
+            // Exact for integer sources of up to 24 bits
+            self.used_bits |= ((s as f64 * 8_388_608.0).round() as i64).unsigned_abs() as u32;
+        }
+        decode::downmix(interleaved, channels, &mut self.mono);
+        self.pending.extend_from_slice(&self.mono);
+        if self.pending.len() >= FRAME_LEN * 32 {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let whole = self.pending.len() / FRAME_LEN * FRAME_LEN;
+        let (power, frames) = (&mut self.power, &mut self.frames);
+        self.stft.for_each_frame(&self.pending[..whole], |_, mags| {
+            for (p, &m) in power.iter_mut().zip(mags) {
+                *p += (m as f64) * (m as f64);
+            }
+            *frames += 1;
+        });
+        self.pending.drain(..whole);
+    }
+
+    pub fn finish(mut self, props: &AudioProps) -> QualityAnalysis {
+        self.flush();
+        let rate = props.sample_rate as f32;
+        let lossless = props.is_lossless();
+        let (cutoff_hz, sharp_cutoff) = if self.frames > 0 {
+            detect_cutoff(&self.power, self.stft.bin_hz(1, rate))
+        } else {
+            (None, false)
+        };
+        let effective_bits = effective_bits(self.used_bits, props.bits_per_sample);
+
+        let silent = self.frames == 0 || self.used_bits == 0;
+        let likely_source = cutoff_hz
+            .filter(|&hz| sharp_cutoff && hz <= MAX_LOSSY_CUTOFF_HZ)
+            .map(|hz| lossy_source_for_cutoff(hz).to_string());
+
+        let verdict = if silent {
+            QualityVerdict::Unknown
+        } else if !lossless {
+            QualityVerdict::Lossy
+        } else if likely_source.is_some() {
+            QualityVerdict::Transcode
+        } else if rate / 2.0 > MAX_CD_CUTOFF_HZ
+            && cutoff_hz.is_some_and(|hz| hz <= MAX_CD_CUTOFF_HZ)
+        {
+            QualityVerdict::Upsampled
+        } else if matches!((effective_bits, props.bits_per_sample), (Some(e), Some(b)) if e < b) {
+            QualityVerdict::PaddedBitDepth
+        } else {
+            QualityVerdict::Lossless
+        };
+
+        QualityAnalysis {
+            verdict,
+            codec: props.codec.to_string(),
+            sample_rate: props.sample_rate,
+            bits_per_sample: props.bits_per_sample,
+            effective_bits,
+            cutoff_hz,
+            sharp_cutoff,
+            likely_source,
+        }
+    }
+}
+
+/// Analyse the opening `ANALYSIS_WINDOW` of a file.
+pub fn analyze_file(path: &Path) -> Result<QualityAnalysis, String> {
+    let mut analyzer = QualityAnalyzer::new();
+    let props = decode::decode_file(path, Some(ANALYSIS_WINDOW), |samples, channels| {
+        analyzer.push(samples, channels)
+    })?;
+    Ok(analyzer.finish(&props))
+}
+
+/// Where the content ends: the steepest drop in the few kHz below the
+/// highest frequency still within `PRESENCE_DB` of the reference band.
+/// Returns the cutoff and whether the drop is a brick wall; `(None, false)`
+/// when content reaches Nyquist.
+fn detect_cutoff(power: &[f64], bin_hz: f32) -> (Option<f32>, bool) {
+    let radius = ((SMOOTHING_HZ / bin_hz) as usize / 2).max(1);
+    let mut prefix = Vec::with_capacity(power.len() + 1);
+    prefix.push(0.0f64);
+    for p in power {
+        prefix.push(prefix.last().copied().unwrap_or(0.0) + p);
+    }
+    let level: Vec<f64> = (0..power.len())
+        .map(|i| {
+            let lo = i.saturating_sub(radius);
+            let hi = (i + radius + 1).min(power.len());
+            let mean = (prefix[hi] - prefix[lo]) / (hi - lo) as f64;
+            10.0 * (mean + 1e-20).log10()
+        })
+        .collect();
+
+    let bin_of = |hz: f32| ((hz / bin_hz) as usize).min(level.len().saturating_sub(1));
+    let (ref_from, ref_to) = (bin_of(REFERENCE_BAND.0), bin_of(REFERENCE_BAND.1));
+    let reference = level[ref_from..=ref_to].iter().sum::<f64>() / (ref_to - ref_from + 1) as f64;
+    let threshold = reference - PRESENCE_DB;
+    let Some(top) = level.iter().rposition(|&l| l > threshold) else {
+        return (None, false);
+    };
+
+    // Content up to the last few smoothing widths below Nyquist is full-band
+    if top + 2 * radius >= level.len() {
+        return (None, false);
+    }
+
+    let half_edge = ((EDGE_WIDTH_HZ / bin_hz) as usize / 2).max(1);
+    let steepest = (top.saturating_sub(6 * half_edge).max(half_edge)..=top)
+        .filter(|&i| i + half_edge < level.len())
+        .map(|i| (i, level[i - half_edge] - level[i + half_edge]))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    match steepest {
+        Some((bin, drop)) if drop >= EDGE_DROP_DB => (Some(bin as f32 * bin_hz), true),
+        _ => (Some(top as f32 * bin_hz), false),
+    }
+}
+
+/// Bits carrying signal, counted from the lowest set bit of any sample.
+/// Only meaningful for integer sources of up to 24 bits.
+fn effective_bits(used_bits: u32, declared: Option<u32>) -> Option<u32> {
+    let declared = declared.filter(|&b| b <= 24)?;
+    if used_bits == 0 {
+        return None;
+    }
+    Some(declared.min(24 - used_bits.trailing_zeros().min(24)))
+}
+
+/// Typical encoder settings for a lowpass frequency (LAME/FhG/AAC presets).
+fn lossy_source_for_cutoff(cutoff_hz: f32) -> &'static str {
+    match cutoff_hz {
+        hz if hz < 12_500.0 => "lossy, 96 kbps or lower",
+        hz if hz < 17_200.0 => "lossy, ~128 kbps",
+        hz if hz < 18_600.0 => "lossy, ~160-192 kbps",
+        hz if hz < 19_800.0 => "lossy, ~192-256 kbps (MP3 V0-V2)",
+        _ => "lossy, ~256-320 kbps",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::spectrum;
+
+    /// Deterministic white noise at -6 dBFS.
+    fn noise(len: usize) -> Vec<f32> {
+        let mut state = 0x1234_5678u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                ((state >> 8) as f32 / (1u32 << 24) as f32 - 0.5) * 0.5
+            })
+            .collect()
+    }
+
+    fn quantize(samples: &[f32], bits: u32) -> Vec<f32> {
+        let scale = (1u32 << (bits - 1)) as f32;
+        samples
+            .iter()
+            .map(|s| (s * scale).round() / scale)
+            .collect()
+    }
+
+    fn props(sample_rate: u32, bits: Option<u32>, codec: &'static str) -> AudioProps {
+        AudioProps {
+            sample_rate,
+            channels: 1,
+            bits_per_sample: bits,
+            codec,
+            frames_decoded: 0,
+        }
+    }
+
+    fn analyze(samples: &[f32], props: &AudioProps) -> QualityAnalysis {
+        let mut analyzer = QualityAnalyzer::new();
+        for block in samples.chunks(4096) {
+            analyzer.push(block, 1);
+        }
+        analyzer.finish(props)
+    }
+
+    #[test]
+    fn test_full_band_lossless() {
+        let signal = quantize(&noise(44_100 * 10), 16);
+        let result = analyze(&signal, &props(44_100, Some(16), "flac"));
+        assert_eq!(result.verdict, QualityVerdict::Lossless);
+        assert_eq!(result.cutoff_hz, None);
+        assert_eq!(result.effective_bits, Some(16));
+    }
+
+    #[test]
+    fn test_lowpassed_flac_is_transcode() {
+        // Band-limit below 16 kHz the way an encoder's lowpass would
+        let band_limited = spectrum::resample(&noise(44_100 * 10), 44_100, 32_000);
+        let signal = quantize(&spectrum::resample(&band_limited, 32_000, 44_100), 16);
+        let result = analyze(&signal, &props(44_100, Some(16), "flac"));
+        assert_eq!(result.verdict, QualityVerdict::Transcode, "{:?}", result);
+        let cutoff = result.cutoff_hz.unwrap();
+        assert!((14_000.0..16_500.0).contains(&cutoff), "{}", cutoff);
+        assert!(result.sharp_cutoff);
+
+        // The same audio in a lossy container is simply lossy
+        let mut lossy = analyze(&signal, &props(44_100, None, "mp3"));
+        assert_eq!(lossy.verdict, QualityVerdict::Lossy);
+        lossy.check_declared_bitrate(Some(128));
+        assert_eq!(lossy.verdict, QualityVerdict::Lossy);
+        // ...unless it claims a bitrate that would keep the top octave
+        lossy.check_declared_bitrate(Some(320));
+        assert_eq!(lossy.verdict, QualityVerdict::Transcode);
+    }
+
+    #[test]
+    fn test_upsampled_and_padded() {
+        let upsampled = spectrum::resample(&noise(48_000 * 10), 48_000, 96_000);
+        let result = analyze(&quantize(&upsampled, 24), &props(96_000, Some(24), "flac"));
+        assert_eq!(result.verdict, QualityVerdict::Upsampled, "{:?}", result);
+
+        // 16-bit audio stored in a 24-bit container
+        let padded = quantize(&noise(48_000 * 10), 16);
+        let result = analyze(&padded, &props(48_000, Some(24), "pcm_s24le"));
+        assert_eq!(result.effective_bits, Some(16));
+        assert_eq!(result.verdict, QualityVerdict::PaddedBitDepth);
+    }
+
+    #[test]
+    fn test_silence_is_unknown() {
+        let result = analyze(&vec![0.0; 44_100 * 2], &props(44_100, Some(16), "flac"));
+        assert_eq!(result.verdict, QualityVerdict::Unknown);
+    }
+}
@@ -0,0 +1,155 @@
+// DSP helpers shared by the analysis jobs (resampling, STFT)
+
+use std::sync::Arc;
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+/// Reduce `samples` by an integer `factor`, averaging each group of samples
+/// (a crude but adequate anti-alias filter for onset and chroma analysis).
+pub fn decimate(samples: &[f32], factor: usize) -> Vec<f32> {
+    if factor <= 1 {
+        return samples.to_vec();
+    }
+    let scale = 1.0 / factor as f32;
+    samples
+        .chunks_exact(factor)
+        .map(|c| c.iter().sum::<f32>() * scale)
+        .collect()
+}
+
+/// Integer decimation factor that brings `sample_rate` closest to, but not
+/// below, `target_rate`.
+pub fn decimation_factor(sample_rate: u32, target_rate: u32) -> usize {
+    ((sample_rate / target_rate.max(1)) as usize).max(1)
+}
+
+/// Band-limited resampling with a Blackman-windowed sinc kernel.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() || from_rate == 0 || to_rate == 0 {
+        return samples.to_vec();
+    }
+
+    const ZERO_CROSSINGS: f64 = 16.0;
+    /// Kernel table entries per input sample.
+    const OVERSAMPLE: usize = 256;
+    let ratio = from_rate as f64 / to_rate as f64;
+    // Cutoff relative to the input rate, a little below the slower Nyquist
+    let cutoff = 0.45 / ratio.max(1.0);
+    let half_width = (ZERO_CROSSINGS / (2.0 * cutoff)).ceil() as usize;
+
+    let table: Vec<f32> = (0..=(half_width + 1) * OVERSAMPLE)
+        .map(|k| {
+            let x = k as f64 / OVERSAMPLE as f64;
+            let w = x / (half_width as f64 + 1.0);
+            let window = 0.42
+                + 0.5 * (std::f64::consts::PI * w).cos()
+                + 0.08 * (2.0 * std::f64::consts::PI * w).cos();
+            let arg = std::f64::consts::PI * 2.0 * cutoff * x;
+            let sinc = if arg.abs() < 1e-9 { 1.0 } else { arg.sin() / arg };
+            (2.0 * cutoff * sinc * window) as f32
+        })
+        .collect();
+    let kernel = |x: f64| -> f32 {
+        let pos = x.abs() * OVERSAMPLE as f64;
+        let idx = pos as usize;
+        if idx + 1 >= table.len() {
+            return 0.0;
+        }
+        let frac = (pos - idx as f64) as f32;
+        table[idx] + (table[idx + 1] - table[idx]) * frac
+    };
+
+    let out_len = (samples.len() as f64 / ratio).floor() as usize;
+    (0..out_len)
+        .map(|i| {
+            let centre = i as f64 * ratio;
+            let base = centre.floor() as usize;
+            let first = base.saturating_sub(half_width);
+            let last = (base + half_width).min(samples.len() - 1);
+            (first..=last)
+                .map(|j| samples[j] * kernel(centre - j as f64))
+                .sum()
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    Hann,
+    Hamming,
+}
+
+/// Windowed magnitude spectra over a mono signal.
+pub struct Stft {
+    frame_len: usize,
+    hop: usize,
+    window: Vec<f32>,
+    fft: Arc<dyn Fft<f32>>,
+}
+
+impl Stft {
+    /// Hann-windowed STFT.
+    pub fn new(frame_len: usize, hop: usize) -> Self {
+        Self::with_window(frame_len, hop, Window::Hann)
+    }
+
+    pub fn with_window(frame_len: usize, hop: usize, window: Window) -> Self {
+        let window = (0..frame_len)
+            .map(|i| match window {
+                Window::Hann => {
+                    let x = std::f32::consts::TAU * i as f32 / frame_len as f32;
+                    0.5 - 0.5 * x.cos()
+                }
+                Window::Hamming => {
+                    let x = std::f32::consts::TAU * i as f32 / (frame_len - 1) as f32;
+                    0.54 - 0.46 * x.cos()
+                }
+            })
+            .collect();
+        Self {
+            frame_len,
+            hop: hop.max(1),
+            window,
+            fft: FftPlanner::new().plan_fft_forward(frame_len),
+        }
+    }
+
+    /// Number of useful bins per frame (DC up to, not including, Nyquist).
+    pub fn bins(&self) -> usize {
+        self.frame_len / 2
+    }
+
+    /// Centre frequency of `bin` for a signal at `sample_rate`.
+    pub fn bin_hz(&self, bin: usize, sample_rate: f32) -> f32 {
+        bin as f32 * sample_rate / self.frame_len as f32
+    }
+
+    /// Call `on_frame(index, magnitudes)` for every full frame of `signal`.
+    pub fn for_each_frame<F>(&self, signal: &[f32], mut on_frame: F)
+    where
+        F: FnMut(usize, &[f32]),
+    {
+        if signal.len() < self.frame_len {
+            return;
+        }
+        let mut buf = vec![Complex::new(0.0f32, 0.0); self.frame_len];
+        let mut scratch = vec![Complex::new(0.0f32, 0.0); self.fft.get_inplace_scratch_len()];
+        let mut mags = vec![0.0f32; self.bins()];
+
+        for (index, start) in (0..=signal.len() - self.frame_len)
+            .step_by(self.hop)
+            .enumerate()
+        {
+            let frame = &signal[start..start + self.frame_len];
+            for ((c, s), w) in buf.iter_mut().zip(frame).zip(&self.window) {
+                *c = Complex::new(s * w, 0.0);
+            }
+            self.fft.process_with_scratch(&mut buf, &mut scratch);
+            for (m, c) in mags.iter_mut().zip(&buf) {
+                *m = c.norm();
+            }
+            on_frame(index, &mags);
+        }
+    }
+}
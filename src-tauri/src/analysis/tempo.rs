@@ -0,0 +1,232 @@
+// Tempo (BPM) estimation
+//
+// 1. Downsample to ~11 kHz and take a log-magnitude STFT.
+// 2. Onset strength = positive spectral flux, with a moving average removed
+//    so only local peaks (note attacks, drum hits) remain.
+// 3. Autocorrelate the onset envelope over lags covering 60–200 BPM,
+//    weighted by a log-normal prior centred on 120 BPM so the estimator
+//    prefers the "tapped" tempo over its halves and doubles.
+// 4. Refine the best lag with parabolic interpolation and fold the result
+//    into the 70–180 BPM range DJ software conventionally displays.
+
+use crate::analysis::spectrum::{self, Stft};
+
+const TARGET_RATE: u32 = 11025;
+const FRAME_LEN: usize = 1024;
+const HOP: usize = 128;
+
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+const PRIOR_CENTER_BPM: f64 = 120.0;
+/// Width of the tempo prior, in octaves.
+const PRIOR_WIDTH_OCTAVES: f64 = 1.0;
+
+const FOLD_MIN_BPM: f64 = 70.0;
+const FOLD_MAX_BPM: f64 = 180.0;
+
+/// Window of the moving average subtracted from the onset envelope.
+const DETREND_SECS: f64 = 0.5;
+/// Minimum track length needed for a meaningful estimate.
+const MIN_ANALYSIS_SECS: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoEstimate {
+    pub bpm: f64,
+    /// Normalized autocorrelation at the chosen lag (0..1).
+    pub confidence: f64,
+}
+
+/// Estimate the tempo of a mono signal. Returns `None` for silence or
+/// material too short to analyse.
+pub fn estimate_bpm(samples: &[f32], sample_rate: u32) -> Option<TempoEstimate> {
+    let factor = spectrum::decimation_factor(sample_rate, TARGET_RATE);
+    let rate = sample_rate as f64 / factor as f64;
+    let signal = spectrum::decimate(samples, factor);
+    if (signal.len() as f64) < rate * MIN_ANALYSIS_SECS {
+        return None;
+    }
+
+    let frame_rate = rate / HOP as f64;
+    let envelope = onset_envelope(&signal, frame_rate);
+    if envelope.iter().all(|&v| v <= f32::EPSILON) {
+        return None;
+    }
+
+    let min_lag = (60.0 * frame_rate / MAX_BPM).floor().max(1.0) as usize;
+    let max_lag = (60.0 * frame_rate / MIN_BPM).ceil() as usize;
+    if envelope.len() <= max_lag + 1 {
+        return None;
+    }
+
+    let energy: f64 = envelope.iter().map(|&v| (v as f64) * (v as f64)).sum();
+    if energy <= 0.0 {
+        return None;
+    }
+
+    // Autocorrelation for lags min_lag-1..=max_lag+1 (extra for interpolation)
+    let lags: Vec<usize> = (min_lag.saturating_sub(1).max(1)..=max_lag + 1).collect();
+    let acf: Vec<f64> = lags.iter().map(|&lag| autocorr(&envelope, lag) / energy).collect();
+
+    let (best_idx, _) = lags
+        .iter()
+        .enumerate()
+        .filter(|(_, &lag)| (min_lag..=max_lag).contains(&lag))
+        .map(|(i, &lag)| (i, acf[i] * tempo_prior(60.0 * frame_rate / lag as f64)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    let confidence = acf[best_idx].clamp(0.0, 1.0);
+    if confidence <= 0.0 {
+        return None;
+    }
+
+    let mut lag = lags[best_idx] as f64;
+    if best_idx > 0 && best_idx + 1 < acf.len() {
+        lag += parabolic_offset(acf[best_idx - 1], acf[best_idx], acf[best_idx + 1]);
+    }
+
+    Some(TempoEstimate {
+        bpm: fold_bpm(60.0 * frame_rate / lag),
+        confidence,
+    })
+}
+
+/// Positive log-spectral flux, detrended and half-wave rectified.
+fn onset_envelope(signal: &[f32], frame_rate: f64) -> Vec<f32> {
+    let stft = Stft::new(FRAME_LEN, HOP);
+    let mut prev = vec![0.0f32; stft.bins()];
+    let mut flux = Vec::with_capacity(signal.len() / HOP);
+
+    stft.for_each_frame(signal, |index, mags| {
+        let mut sum = 0.0f32;
+        for (p, &m) in prev.iter_mut().zip(mags) {
+            let v = (1.0 + 100.0 * m).ln();
+            if index > 0 && v > *p {
+                sum += v - *p;
+            }
+            *p = v;
+        }
+        flux.push(sum);
+    });
+
+    let half = ((DETREND_SECS * frame_rate) as usize / 2).max(1);
+    let mut prefix = Vec::with_capacity(flux.len() + 1);
+    prefix.push(0.0f64);
+    for &v in &flux {
+        prefix.push(prefix.last().copied().unwrap_or(0.0) + v as f64);
+    }
+
+    (0..flux.len())
+        .map(|i| {
+            let lo = i.saturating_sub(half);
+            let hi = (i + half + 1).min(flux.len());
+            let mean = (prefix[hi] - prefix[lo]) / (hi - lo) as f64;
+            (flux[i] as f64 - mean).max(0.0) as f32
+        })
+        .collect()
+}
+
+fn autocorr(envelope: &[f32], lag: usize) -> f64 {
+    envelope
+        .iter()
+        .zip(&envelope[lag..])
+        .map(|(&a, &b)| a as f64 * b as f64)
+        .sum()
+}
+
+fn tempo_prior(bpm: f64) -> f64 {
+    let octaves = (bpm / PRIOR_CENTER_BPM).log2() / PRIOR_WIDTH_OCTAVES;
+    (-0.5 * octaves * octaves).exp()
+}
+
+/// Offset of the true peak from the centre sample of three, in samples.
+fn parabolic_offset(left: f64, centre: f64, right: f64) -> f64 {
+    let denom = left - 2.0 * centre + right;
+    if denom.abs() < f64::EPSILON {
+        return 0.0;
+    }
+    (0.5 * (left - right) / denom).clamp(-0.5, 0.5)
+}
+
+/// Halve or double into the conventional display range.
+pub fn fold_bpm(mut bpm: f64) -> f64 {
+    if !bpm.is_finite() || bpm <= 0.0 {
+        return bpm;
+    }
+    while bpm < FOLD_MIN_BPM {
+        bpm *= 2.0;
+    }
+    while bpm > FOLD_MAX_BPM {
+        bpm /= 2.0;
+    }
+    bpm
+}
+
+/// Parse a BPM tag value ("128", "127.96", "128 BPM").
+pub fn parse_bpm_tag(value: &str) -> Option<f64> {
+    let number: String = value
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .map(|c| if c == ',' { '.' } else { c })
+        .collect();
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|bpm| bpm.is_finite() && *bpm > 0.0 && *bpm < 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Short decaying noise bursts at a fixed tempo.
+    fn click_track(bpm: f64, sample_rate: u32, secs: f64) -> Vec<f32> {
+        let len = (secs * sample_rate as f64) as usize;
+        let period = 60.0 / bpm * sample_rate as f64;
+        let burst = (0.01 * sample_rate as f64) as usize;
+        let mut out = vec![0.0f32; len];
+        let mut seed = 0x2545_f491u32;
+        let mut beat = 0.0;
+        while (beat as usize) < len {
+            let start = beat as usize;
+            for i in 0..burst.min(len - start) {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                let noise = (seed as f32 / u32::MAX as f32) * 2.0 - 1.0;
+                out[start + i] = noise * (1.0 - i as f32 / burst as f32);
+            }
+            beat += period;
+        }
+        out
+    }
+
+    #[test]
+    fn test_click_track_tempo() {
+        for bpm in [90.0, 128.0, 174.0] {
+            let signal = click_track(bpm, 44100, 30.0);
+            let est = estimate_bpm(&signal, 44100).expect("tempo estimate");
+            assert!(
+                (est.bpm - bpm).abs() < 1.0,
+                "expected {} BPM, got {:.2}",
+                bpm,
+                est.bpm
+            );
+        }
+    }
+
+    #[test]
+    fn test_silence_has_no_tempo() {
+        assert_eq!(estimate_bpm(&vec![0.0; 44100 * 10], 44100), None);
+        assert_eq!(estimate_bpm(&vec![0.0; 1000], 44100), None);
+    }
+
+    #[test]
+    fn test_fold_and_parse() {
+        assert_eq!(fold_bpm(64.0), 128.0);
+        assert_eq!(fold_bpm(256.0), 128.0);
+        assert_eq!(parse_bpm_tag("127.5"), Some(127.5));
+        assert_eq!(parse_bpm_tag(" 128 BPM"), Some(128.0));
+        assert_eq!(parse_bpm_tag("n/a"), None);
+    }
+}
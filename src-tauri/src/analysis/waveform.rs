@@ -0,0 +1,454 @@
+// Waveform peak generation and on-disk cache
+//
+// Peaks are computed once per track as min/max pairs at a few fixed
+// resolutions and cached in `<app data>/waveforms/<track_id>.peaks`, next to
+// the covers directory. Each cache file records the source file's size and
+// mtime; a mismatch means the file changed and the peaks are regenerated.
+//
+// Cache layout (little-endian):
+//   "AWPK" | version u8 | source size u64 | mtime secs i64 | mtime nanos u32
+//   | sample rate u32 | duration ms u64 | level count u8
+//   | per level: bucket count u32, then bucket count × (min i8, max i8)
+
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::analysis::decode;
+use crate::db::{queries, Database};
+use crate::scanner::{cover_storage, FileStamp};
+
+/// Bucket counts stored per track. Callers pick the closest one.
+pub const RESOLUTIONS: [usize; 3] = [200, 800, 3200];
+
+const MAGIC: &[u8; 4] = b"AWPK";
+const VERSION: u8 = 1;
+/// Frames folded into one fine-grained block before the final reduction.
+const BLOCK_FRAMES: usize = 256;
+
+/// One resolution: `min[i]`/`max[i]` in -1.0..=1.0 for bucket `i`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PeakLevel {
+    pub buckets: usize,
+    pub min: Vec<f32>,
+    pub max: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WaveformPeaks {
+    pub stamp: FileStamp,
+    pub sample_rate: u32,
+    pub duration_ms: u64,
+    pub levels: Vec<PeakLevel>,
+}
+
+/// Returned to the frontend by `get_waveform`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Waveform {
+    pub track_id: i64,
+    pub duration_ms: u64,
+    pub sample_rate: u32,
+    pub buckets: usize,
+    pub min: Vec<f32>,
+    pub max: Vec<f32>,
+}
+
+impl WaveformPeaks {
+    /// Pick the stored level closest to the requested bucket count.
+    pub fn to_waveform(&self, track_id: i64, resolution: Option<usize>) -> Waveform {
+        let wanted = resolution.unwrap_or(RESOLUTIONS[1]);
+        let level = self
+            .levels
+            .iter()
+            .min_by_key(|l| l.buckets.abs_diff(wanted))
+            .cloned()
+            .unwrap_or(PeakLevel { buckets: 0, min: Vec::new(), max: Vec::new() });
+        Waveform {
+            track_id,
+            duration_ms: self.duration_ms,
+            sample_rate: self.sample_rate,
+            buckets: level.buckets,
+            min: level.min,
+            max: level.max,
+        }
+    }
+}
+
+// =============================================================================
+// Peak computation
+// =============================================================================
+
+/// Collects min/max per fixed-size block of mono frames.
+struct BlockPeaks {
+    mins: Vec<f32>,
+    maxs: Vec<f32>,
+    cur_min: f32,
+    cur_max: f32,
+    count: usize,
+}
+
+impl BlockPeaks {
+    fn new() -> Self {
+        Self {
+            mins: Vec::new(),
+            maxs: Vec::new(),
+            cur_min: f32::MAX,
+            cur_max: f32::MIN,
+            count: 0,
+        }
+    }
+
+    fn push(&mut self, sample: f32) {
+        self.cur_min = self.cur_min.min(sample);
+        self.cur_max = self.cur_max.max(sample);
+        self.count += 1;
+        if self.count == BLOCK_FRAMES {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.count > 0 {
+            self.mins.push(self.cur_min);
+            self.maxs.push(self.cur_max);
+        }
+        self.cur_min = f32::MAX;
+        self.cur_max = f32::MIN;
+        self.count = 0;
+    }
+
+    /// Fold the blocks into `buckets` evenly sized buckets.
+    fn reduce(&self, buckets: usize) -> PeakLevel {
+        let blocks = self.mins.len();
+        let mut level = PeakLevel {
+            buckets,
+            min: Vec::with_capacity(buckets),
+            max: Vec::with_capacity(buckets),
+        };
+        for i in 0..buckets {
+            let start = i * blocks / buckets;
+            let end = ((i + 1) * blocks / buckets).max(start + 1).min(blocks);
+            if start >= end {
+                level.min.push(0.0);
+                level.max.push(0.0);
+                continue;
+            }
+            let min = self.mins[start..end].iter().copied().fold(f32::MAX, f32::min);
+            let max = self.maxs[start..end].iter().copied().fold(f32::MIN, f32::max);
+            level.min.push(min.clamp(-1.0, 1.0));
+            level.max.push(max.clamp(-1.0, 1.0));
+        }
+        level
+    }
+}
+
+/// Decode `path` and compute peaks at every resolution in `RESOLUTIONS`.
+pub fn compute_peaks(path: &Path) -> Result<WaveformPeaks, String> {
+    let stamp = FileStamp::of(path)?;
+    let mut peaks = BlockPeaks::new();
+    let mut mono = Vec::new();
+
+    let props = decode::decode_file(path, None, |samples, channels| {
+        decode::downmix(samples, channels, &mut mono);
+        for &s in &mono {
+            peaks.push(s);
+        }
+    })?;
+    peaks.flush();
+
+    Ok(WaveformPeaks {
+        stamp,
+        sample_rate: props.sample_rate,
+        duration_ms: (props.duration_secs() * 1000.0).round() as u64,
+        levels: RESOLUTIONS.iter().map(|&n| peaks.reduce(n)).collect(),
+    })
+}
+
+// =============================================================================
+// Cache
+// =============================================================================
+
+fn cache_path(track_id: i64) -> Result<PathBuf, String> {
+    Ok(cover_storage::get_waveforms_directory()?.join(format!("{}.peaks", track_id)))
+}
+
+fn quantize(v: f32) -> u8 {
+    ((v.clamp(-1.0, 1.0) * 127.0).round() as i8) as u8
+}
+
+fn dequantize(b: u8) -> f32 {
+    (b as i8) as f32 / 127.0
+}
+
+fn encode(peaks: &WaveformPeaks) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.extend_from_slice(&peaks.stamp.size.to_le_bytes());
+    out.extend_from_slice(&peaks.stamp.mtime_secs.to_le_bytes());
+    out.extend_from_slice(&peaks.stamp.mtime_nanos.to_le_bytes());
+    out.extend_from_slice(&peaks.sample_rate.to_le_bytes());
+    out.extend_from_slice(&peaks.duration_ms.to_le_bytes());
+    out.push(peaks.levels.len() as u8);
+    for level in &peaks.levels {
+        out.extend_from_slice(&(level.buckets as u32).to_le_bytes());
+        for (min, max) in level.min.iter().zip(&level.max) {
+            out.push(quantize(*min));
+            out.push(quantize(*max));
+        }
+    }
+    out
+}
+
+fn decode_cache(data: &[u8]) -> Option<WaveformPeaks> {
+    let mut pos = 0usize;
+    let mut take = |n: usize| -> Option<&[u8]> {
+        let slice = data.get(pos..pos + n)?;
+        pos += n;
+        Some(slice)
+    };
+
+    if take(4)? != MAGIC || take(1)?[0] != VERSION {
+        return None;
+    }
+    let size = u64::from_le_bytes(take(8)?.try_into().ok()?);
+    let mtime_secs = i64::from_le_bytes(take(8)?.try_into().ok()?);
+    let mtime_nanos = u32::from_le_bytes(take(4)?.try_into().ok()?);
+    let sample_rate = u32::from_le_bytes(take(4)?.try_into().ok()?);
+    let duration_ms = u64::from_le_bytes(take(8)?.try_into().ok()?);
+    let level_count = take(1)?[0] as usize;
+
+    let mut levels = Vec::with_capacity(level_count);
+    for _ in 0..level_count {
+        let buckets = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+        let pairs = take(buckets.checked_mul(2)?)?;
+        levels.push(PeakLevel {
+            buckets,
+            min: pairs.iter().step_by(2).map(|&b| dequantize(b)).collect(),
+            max: pairs.iter().skip(1).step_by(2).map(|&b| dequantize(b)).collect(),
+        });
+    }
+
+    Some(WaveformPeaks {
+        stamp: FileStamp { size, mtime_secs, mtime_nanos },
+        sample_rate,
+        duration_ms,
+        levels,
+    })
+}
+
+/// Cached peaks for a track, if present and still matching the file on disk.
+pub fn load_cached(track_id: i64, source: &Path) -> Option<WaveformPeaks> {
+    let data = fs::read(cache_path(track_id).ok()?).ok()?;
+    let peaks = decode_cache(&data)?;
+    let current = FileStamp::of(source).ok()?;
+    (peaks.stamp == current).then_some(peaks)
+}
+
+pub fn store_cached(track_id: i64, peaks: &WaveformPeaks) -> Result<(), String> {
+    let path = cache_path(track_id)?;
+    // Write to a temp file first so a crash never leaves a truncated cache.
+    let tmp = path.with_extension("peaks.tmp");
+    fs::write(&tmp, encode(peaks)).map_err(|e| format!("Failed to write waveform cache: {}", e))?;
+    fs::rename(&tmp, &path).map_err(|e| format!("Failed to store waveform cache: {}", e))
+}
+
+/// Return cached peaks or generate (and cache) them now.
+pub fn get_or_generate(track_id: i64, source: &Path) -> Result<WaveformPeaks, String> {
+    if let Some(peaks) = load_cached(track_id, source) {
+        return Ok(peaks);
+    }
+    let peaks = compute_peaks(source)?;
+    if let Err(e) = store_cached(track_id, &peaks) {
+        tracing::warn!("[WAVEFORM] {}", e);
+    }
+    Ok(peaks)
+}
+
+/// Remove cache files whose track no longer exists.
+pub fn cleanup_orphaned_waveforms(conn: &rusqlite::Connection) -> Result<usize, String> {
+    let dir = cover_storage::get_waveforms_directory()?;
+    let mut stmt = conn.prepare("SELECT id FROM tracks").map_err(|e| e.to_string())?;
+    let track_ids: HashSet<i64> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut removed = 0;
+    for entry in fs::read_dir(&dir).map_err(|e| e.to_string())?.flatten() {
+        let path = entry.path();
+        let orphaned = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.parse::<i64>().ok())
+            .is_some_and(|id| !track_ids.contains(&id));
+        if orphaned && fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+// =============================================================================
+// Background queue
+// =============================================================================
+// One worker thread drains a deque of track IDs. `enqueue` appends (library
+// backfill); `prioritize` moves IDs to the front in the given order, so the
+// upcoming play queue is processed before anything else. Finished tracks are
+// announced with a `waveform-ready` event.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WaveformReadyEvent {
+    pub track_id: i64,
+}
+
+#[derive(Default)]
+struct QueueInner {
+    pending: VecDeque<i64>,
+    queued: HashSet<i64>,
+}
+
+pub struct WaveformQueue {
+    shared: Arc<(Mutex<QueueInner>, Condvar)>,
+}
+
+impl WaveformQueue {
+    pub fn new(app_handle: AppHandle) -> Self {
+        let shared = Arc::new((Mutex::new(QueueInner::default()), Condvar::new()));
+        let worker_shared = Arc::clone(&shared);
+
+        std::thread::Builder::new()
+            .name("waveform-worker".into())
+            .spawn(move || run_worker(app_handle, worker_shared))
+            .expect("failed to spawn waveform worker");
+
+        Self { shared }
+    }
+
+    /// Queue tracks behind anything already pending.
+    pub fn enqueue(&self, track_ids: &[i64]) -> usize {
+        let (lock, cvar) = &*self.shared;
+        let Ok(mut inner) = lock.lock() else { return 0 };
+        for &id in track_ids {
+            if inner.queued.insert(id) {
+                inner.pending.push_back(id);
+            }
+        }
+        cvar.notify_one();
+        inner.pending.len()
+    }
+
+    /// Move tracks to the front of the queue, keeping their relative order.
+    pub fn prioritize(&self, track_ids: &[i64]) -> usize {
+        let (lock, cvar) = &*self.shared;
+        let Ok(mut inner) = lock.lock() else { return 0 };
+        let moved: HashSet<i64> = track_ids.iter().copied().collect();
+        inner.pending.retain(|id| !moved.contains(id));
+        for &id in track_ids.iter().rev() {
+            inner.pending.push_front(id);
+        }
+        inner.queued.extend(moved);
+        cvar.notify_one();
+        inner.pending.len()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.shared.0.lock().map(|i| i.pending.len()).unwrap_or(0)
+    }
+}
+
+fn run_worker(app_handle: AppHandle, shared: Arc<(Mutex<QueueInner>, Condvar)>) {
+    let (lock, cvar) = &*shared;
+    loop {
+        let track_id = {
+            let Ok(mut inner) = lock.lock() else { return };
+            loop {
+                if let Some(id) = inner.pending.pop_front() {
+                    inner.queued.remove(&id);
+                    break id;
+                }
+                inner = match cvar.wait(inner) {
+                    Ok(guard) => guard,
+                    Err(_) => return,
+                };
+            }
+        };
+
+        let path = {
+            let db = app_handle.state::<Database>();
+            let Ok(conn) = db.conn.lock() else { continue };
+            match queries::get_track_by_id(&conn, track_id) {
+                Ok(Some(track)) => track.local_src.unwrap_or(track.path),
+                _ => continue,
+            }
+        };
+
+        let source = PathBuf::from(&path);
+        if !source.is_file() {
+            continue;
+        }
+        if load_cached(track_id, &source).is_some() {
+            continue;
+        }
+
+        match compute_peaks(&source) {
+            Ok(peaks) => {
+                if let Err(e) = store_cached(track_id, &peaks) {
+                    tracing::warn!("[WAVEFORM] {}", e);
+                    continue;
+                }
+                let _ = app_handle.emit("waveform-ready", WaveformReadyEvent { track_id });
+            }
+            Err(e) => tracing::warn!("[WAVEFORM] Track {}: {}", track_id, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_reduce() {
+        let mut peaks = BlockPeaks::new();
+        for i in 0..(BLOCK_FRAMES * 4) {
+            // Block n peaks at ±(n + 1) / 4.
+            let amp = ((i / BLOCK_FRAMES) + 1) as f32 / 4.0;
+            peaks.push(if i % 2 == 0 { amp } else { -amp });
+        }
+        peaks.flush();
+
+        let level = peaks.reduce(2);
+        assert_eq!(level.max, vec![0.5, 1.0]);
+        assert_eq!(level.min, vec![-0.5, -1.0]);
+
+        // More buckets than blocks: every bucket still maps to a block.
+        let level = peaks.reduce(8);
+        assert_eq!(level.max.len(), 8);
+        assert!(level.max.iter().all(|&m| m > 0.0));
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let peaks = WaveformPeaks {
+            stamp: FileStamp { size: 1234, mtime_secs: 1_700_000_000, mtime_nanos: 42 },
+            sample_rate: 44100,
+            duration_ms: 180_000,
+            levels: vec![PeakLevel { buckets: 3, min: vec![-1.0, -0.5, 0.0], max: vec![1.0, 0.5, 0.0] }],
+        };
+        let decoded = decode_cache(&encode(&peaks)).unwrap();
+        assert_eq!(decoded.stamp, peaks.stamp);
+        assert_eq!(decoded.duration_ms, 180_000);
+        assert_eq!(decoded.levels[0].max, vec![1.0, 64.0 / 127.0, 0.0]);
+        assert_eq!(decoded.levels[0].min[0], -1.0);
+
+        // Truncated data is rejected rather than misread.
+        let bytes = encode(&peaks);
+        assert!(decode_cache(&bytes[..bytes.len() - 1]).is_none());
+    }
+}
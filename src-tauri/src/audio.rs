@@ -54,6 +54,14 @@
 //   Frontend polls nativeAudioPollEvent() every 50ms — same queue as
 //   TrackFinished / TrackAdvanced. No extra infrastructure.
 //
+// Network streams (internet radio):
+//   PlayStream carries a ready-made MediaSource (radio::StreamReader wrapped in
+//   a ReadOnlySource). Probing it waits on the server, so a worker thread
+//   opens it and sends the OpenedMedia back as StreamReady; the audio thread
+//   never blocks on the network. It goes through the same SymphoniaSource as
+//   files but is flagged as a stream: no duration, no seeking, repeat-one ignored. ICY title
+//   changes arrive from the radio thread via AudioEventSink as StreamTitle.
+//
 // Command architecture:
 //   Tauri commands → crossbeam channel → audio thread (owns AudioEngine).
 //   PlaybackState snapshotted into Arc<Mutex<>> every 100ms for UI reads.
@@ -76,7 +84,7 @@ use symphonia::core::audio::SampleBuffer;
 use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
 use symphonia::core::errors::Error as SymphoniaError;
 use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
-use symphonia::core::io::MediaSourceStream;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 use symphonia::core::units::Time;
@@ -371,6 +379,20 @@ fn db_to_linear(db: f32) -> f32 {
 // Stop sentinel: Duration::MAX sent via seek channel — sets done=true immediately.
 // =============================================================================
 
+/// A probed input and its decoder, not yet wired to the engine. Probing a
+/// network stream blocks on the server, so streams are opened this far on
+/// a worker thread and handed to the audio thread afterwards.
+struct OpenedMedia {
+    format:      Box<dyn FormatReader>,
+    decoder:     Box<dyn symphonia::core::codecs::Decoder>,
+    track_id:    u32,
+    channels:    u16,
+    sample_rate: u32,
+    duration:    Option<Duration>,
+    replay_gain: Option<f32>,
+    is_stream:   bool,
+}
+
 struct SymphoniaSource {
     format:      Box<dyn FormatReader>,
     decoder:     Box<dyn symphonia::core::codecs::Decoder>,
@@ -389,6 +411,7 @@ struct SymphoniaSource {
     repeat_one:    bool,
     event_tx:      Sender<AudioEvent>,
     loop_tx:       Sender<Instant>,
+    is_stream:     bool,
 }
 
 impl SymphoniaSource {
@@ -411,6 +434,55 @@ impl SymphoniaSource {
             hint.with_extension(ext);
         }
 
+        Self::open_media(
+            mss, hint, path, replay_gain_db,
+            seek_rx, repeat_one_rx, event_tx, loop_tx, volume,
+        )
+    }
+
+    /// Probes an unseekable network stream (internet radio). Blocks until the
+    /// server has sent enough to probe, so it runs off the audio thread.
+    /// `label` is the station URL, used for logging.
+    fn probe_stream(
+        media: Box<dyn MediaSource>,
+        extension: Option<&str>,
+        label: &str,
+    ) -> Result<OpenedMedia, String> {
+        let mss = MediaSourceStream::new(media, Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = extension {
+            hint.with_extension(ext);
+        }
+
+        let mut opened = Self::probe(mss, hint, label, None)?;
+        opened.is_stream = true;
+        opened.duration  = None;
+        Ok(opened)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn open_media(
+        mss: MediaSourceStream,
+        hint: Hint,
+        path: &str,
+        replay_gain_db: Option<f32>,
+        seek_rx: Receiver<Duration>,
+        repeat_one_rx: Receiver<bool>,
+        event_tx:      Sender<AudioEvent>,
+        loop_tx:       Sender<Instant>,
+        volume: Arc<AtomicU32>,
+    ) -> Result<Self, String> {
+        let opened = Self::probe(mss, hint, path, replay_gain_db)?;
+        Ok(Self::from_opened(opened, seek_rx, repeat_one_rx, event_tx, loop_tx, volume))
+    }
+
+    fn probe(
+        mss: MediaSourceStream,
+        hint: Hint,
+        path: &str,
+        replay_gain_db: Option<f32>,
+    ) -> Result<OpenedMedia, String> {
         let probed = symphonia::default::get_probe()
             .format(
                 &hint, mss,
@@ -439,11 +511,28 @@ impl SymphoniaSource {
         let replay_gain = resolve_replay_gain(replay_gain_db, &mut format);
 
         tracing::info!("[AUDIO] Track: {}Hz {}ch — {}", sample_rate, channels, path);
-        Ok(Self {
+        Ok(OpenedMedia {
             format, decoder, track_id,
-            sample_buf: None, sample_pos: 0,
-            channels, sample_rate, duration, done: false,
+            channels, sample_rate, duration,
             replay_gain,
+            is_stream: false,
+        })
+    }
+
+    fn from_opened(
+        opened: OpenedMedia,
+        seek_rx: Receiver<Duration>,
+        repeat_one_rx: Receiver<bool>,
+        event_tx:      Sender<AudioEvent>,
+        loop_tx:       Sender<Instant>,
+        volume: Arc<AtomicU32>,
+    ) -> Self {
+        Self {
+            format: opened.format, decoder: opened.decoder, track_id: opened.track_id,
+            sample_buf: None, sample_pos: 0,
+            channels: opened.channels, sample_rate: opened.sample_rate,
+            duration: opened.duration, done: false,
+            replay_gain: opened.replay_gain,
             seek_rx,
             volume,
             frame_count: 0,
@@ -451,7 +540,8 @@ impl SymphoniaSource {
             repeat_one: false,
             event_tx,
             loop_tx,
-        })
+            is_stream: opened.is_stream,
+        }
     }
 
     fn seek(&mut self, pos: Duration) {
@@ -540,7 +630,7 @@ impl Iterator for SymphoniaSource {
                 }
             }
             if !self.refill() {
-                if self.repeat_one {
+                if self.repeat_one && !self.is_stream {
                     self.seek(Duration::ZERO);
                     let _ = self.loop_tx.try_send(Instant::now());
                     let _ = self.event_tx.try_send(AudioEvent::StateChanged { position: 0.0 });
@@ -589,6 +679,15 @@ impl TrackInfo {
 // AudioEngine — owns the pipeline, lives entirely on the audio thread
 // =============================================================================
 
+/// finish signal, seek sender, repeat-one sender, loop receiver, duration
+type AppendedSource = (
+    crossbeam::channel::Receiver<()>,
+    Sender<Duration>,
+    Sender<bool>,
+    Receiver<Instant>,
+    Option<Duration>,
+);
+
 struct AudioEngine {
     queue_input:       Arc<rodio::queue::SourcesQueueInput<f32>>,
     paused_flag:       Arc<AtomicBool>,
@@ -653,20 +752,52 @@ impl AudioEngine {
         &mut self,
         path: &str,
         replay_gain_db: Option<f32>,
-    ) -> Result<(crossbeam::channel::Receiver<()>, Sender<Duration>, Sender<bool>, Receiver<Instant>, Option<Duration>), String> {
+    ) -> Result<AppendedSource, String> {
+        self.append_with(|seek_rx, repeat_one_rx, event_tx, loop_tx, volume| {
+            SymphoniaSource::open(path, replay_gain_db, seek_rx, repeat_one_rx, event_tx, loop_tx, volume)
+        })
+    }
+
+    fn append_with<F>(&mut self, open: F) -> Result<AppendedSource, String>
+    where
+        F: FnOnce(Receiver<Duration>, Receiver<bool>, Sender<AudioEvent>, Sender<Instant>, Arc<AtomicU32>)
+            -> Result<SymphoniaSource, String>,
+    {
         let (seek_tx, seek_rx)             = unbounded::<Duration>();
         let (repeat_one_tx, repeat_one_rx) = unbounded::<bool>();
         let (loop_tx, loop_rx)               = unbounded::<Instant>();
         // Seed with current state so a freshly opened source inherits it immediately.
         let _ = repeat_one_tx.send(self.repeat_one);
-        let src = SymphoniaSource::open(path, replay_gain_db, seek_rx, repeat_one_rx, self.event_tx.clone(), loop_tx, Arc::clone(&self.volume_atomic))?;
+        let src = open(seek_rx, repeat_one_rx, self.event_tx.clone(), loop_tx, Arc::clone(&self.volume_atomic))?;
         let dur = src.duration;
         let finish_rx = self.queue_input.append_with_signal(src);
         Ok((finish_rx, seek_tx, repeat_one_tx, loop_rx, dur))
     }
 
-    // ── play ─────────────────────────────────────────────────────────────────
-    fn play(&mut self, path: &str, replay_gain_db: Option<f32>) -> Result<(), String> {
+    // ── play_stream ──────────────────────────────────────────────────────────
+    fn play_stream(&mut self, url: &str, opened: OpenedMedia) -> Result<(), String> {
+        self.clear_all();
+
+        let (finish_rx, seek_tx, repeat_one_tx, loop_rx, _) =
+            self.append_with(|seek_rx, repeat_one_rx, event_tx, loop_tx, volume| {
+                Ok(SymphoniaSource::from_opened(opened, seek_rx, repeat_one_rx, event_tx, loop_tx, volume))
+            })?;
+        self.seek_tx           = Some(seek_tx);
+        self.repeat_one_tx     = Some(repeat_one_tx);
+        self.loop_rx           = Some(loop_rx);
+        self.current_finish_rx = Some(finish_rx);
+        self.current_info      = Some(TrackInfo {
+            path: url.to_string(), duration: None,
+            started: Instant::now(), offset: Duration::ZERO,
+        });
+        self.paused_flag.store(false, Ordering::Relaxed);
+
+        tracing::info!("[AUDIO] Streaming: {}", url);
+        Ok(())
+    }
+
+    // ── clear_all ────────────────────────────────────────────────────────────
+    fn clear_all(&mut self) {
         // Clear all pending sources from the queue instantly.
         self.queue_input.clear();
 
@@ -687,6 +818,11 @@ impl AudioEngine {
         self.next_loop_rx       = None;
         self.next_path         = None;
         self.next_duration     = None;
+    }
+
+    // ── play ─────────────────────────────────────────────────────────────────
+    fn play(&mut self, path: &str, replay_gain_db: Option<f32>) -> Result<(), String> {
+        self.clear_all();
 
         let (finish_rx, seek_tx, repeat_one_tx, loop_rx, duration) = self.open_and_append(path, replay_gain_db)?;
         self.seek_tx           = Some(seek_tx);
@@ -882,6 +1018,21 @@ pub enum AudioEvent {
     TrackFinished,
     TrackAdvanced { new_path: String },
     StateChanged { position: f64 },
+    /// ICY `StreamTitle` change on the current internet radio stream.
+    StreamTitle { station_id: i64, raw: String, artist: Option<String>, title: String },
+}
+
+/// Handle for pushing events into the frontend poll queue from outside the
+/// audio thread (the radio network thread reports ICY titles through this).
+#[derive(Clone)]
+pub struct AudioEventSink(Arc<Mutex<std::collections::VecDeque<AudioEvent>>>);
+
+impl AudioEventSink {
+    pub fn push(&self, event: AudioEvent) {
+        if let Ok(mut q) = self.0.lock() {
+            q.push_back(event);
+        }
+    }
 }
 
 // =============================================================================
@@ -912,6 +1063,19 @@ enum AudioCommand {
     SetVolume(f32),
     SetEq(EqSettings),
     SetRepeatOne(bool),
+    PlayStream {
+        url:       String,
+        media:     Box<dyn MediaSource>,
+        extension: Option<String>,
+    },
+}
+
+/// A network stream probed on a worker thread, for the `PlayStream` request
+/// numbered `request`.
+struct StreamReady {
+    request: u64,
+    url:     String,
+    opened:  Result<OpenedMedia, String>,
 }
 
 // =============================================================================
@@ -942,6 +1106,10 @@ impl PlaybackStateSync {
             let mut engine_opt: Option<AudioEngine> = None;
             let mut eq_settings = EqSettings::default();
             let mut event_rx_opt: Option<crossbeam::channel::Receiver<AudioEvent>> = None;
+            // Bumped by every Play/Stop/PlayStream, so a stream that finishes
+            // probing after the user moved on is dropped.
+            let mut stream_request: u64 = 0;
+            let (ready_tx, ready_rx) = unbounded::<StreamReady>();
 
             loop {
                 match rx.recv_timeout(Duration::from_millis(100)) {
@@ -966,6 +1134,7 @@ impl PlaybackStateSync {
 
                         match cmd {
                             AudioCommand::Play(path, rg) => {
+                                stream_request += 1;
                                 if let Ok(mut q) = events_clone.lock() { q.clear(); }
                                 if let Err(e) = engine.play(&path, rg) {
                                     tracing::error!("[AUDIO] play error: {}", e);
@@ -979,6 +1148,7 @@ impl PlaybackStateSync {
                             AudioCommand::Pause        => engine.pause(),
                             AudioCommand::Resume       => engine.resume(),
                             AudioCommand::Stop         => {
+                                stream_request += 1;
                                 if let Ok(mut q) = events_clone.lock() { q.clear(); }
                                 engine.stop();
                             }
@@ -993,12 +1163,36 @@ impl PlaybackStateSync {
                                 engine.set_eq(&s);
                             }
                             AudioCommand::SetRepeatOne(v) => engine.set_repeat_one(v),
+                            AudioCommand::PlayStream { url, media, extension } => {
+                                stream_request += 1;
+                                if let Ok(mut q) = events_clone.lock() { q.clear(); }
+                                // The old track stops now; the stream starts
+                                // once the worker has connected and probed it.
+                                engine.stop();
+                                let request = stream_request;
+                                let ready_tx = ready_tx.clone();
+                                std::thread::spawn(move || {
+                                    let opened = SymphoniaSource::probe_stream(media, extension.as_deref(), &url);
+                                    let _ = ready_tx.send(StreamReady { request, url, opened });
+                                });
+                            }
                         }
                     }
                     Err(crossbeam::channel::RecvTimeoutError::Disconnected) => break,
                     Err(crossbeam::channel::RecvTimeoutError::Timeout)      => {}
                 }
 
+                // Start streams probed since the last pass, unless superseded.
+                while let Ok(ready) = ready_rx.try_recv() {
+                    let Some(engine) = engine_opt.as_mut() else { break };
+                    if ready.request != stream_request {
+                        continue;
+                    }
+                    if let Err(e) = ready.opened.and_then(|opened| engine.play_stream(&ready.url, opened)) {
+                        tracing::error!("[AUDIO] stream error: {}", e);
+                    }
+                }
+
                 // Drain backend-pushed events (seek confirmations, loops).
                 if let Some(ref event_rx) = event_rx_opt {
                     while let Ok(evt) = event_rx.try_recv() {
@@ -1030,6 +1224,21 @@ impl PlaybackStateSync {
         self.command_tx.send(cmd).map_err(|e| e.to_string())
     }
 
+    /// Start playing a network stream. The media source is consumed by the
+    /// audio thread; dropping it (on stop or track change) ends the stream.
+    pub fn play_stream(
+        &self,
+        url: String,
+        media: Box<dyn MediaSource>,
+        extension: Option<String>,
+    ) -> Result<(), String> {
+        self.send(AudioCommand::PlayStream { url, media, extension })
+    }
+
+    pub fn event_sink(&self) -> AudioEventSink {
+        AudioEventSink(Arc::clone(&self.event_queue))
+    }
+
     pub fn init_async(_app_handle: tauri::AppHandle) {}
 }
 
@@ -1,5 +1,16 @@
-// Activity-related Tauri commands (liked tracks + play history)
+// Activity-related Tauri commands (liked tracks, ratings + play history)
+use crate::commands::tags::editable_track_path;
+use crate::db::play_counts::{PlayOutcome, SkipThresholds};
+use crate::db::stats::{StatsPeriod, StatsRange};
 use crate::db::{queries, Database};
+use crate::scanner::cover_storage::{self, ImageFormat};
+use crate::scanner::walker::FileStamp;
+use crate::tag_editor::{self, rating};
+use crate::year_review::{self, ReviewCovers};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Datelike;
+use rusqlite::Connection;
+use std::path::Path;
 use tauri::State;
 
 // ============================================================================
@@ -93,19 +104,123 @@ pub async fn get_liked_tracks(db: State<'_, Database>) -> Result<Vec<queries::Tr
     queries::get_liked_tracks(&conn).map_err(|e| e.to_string())
 }
 
+// ============================================================================
+// Ratings commands
+// ============================================================================
+
+/// Write a track's rating to its file when write-back is on. Streams and
+/// formats without a rating tag keep it in the library only.
+fn write_rating_to_file(conn: &Connection, track_id: i64, value: Option<u8>) -> Result<(), String> {
+    if !queries::get_rating_write_back(conn).map_err(|e| e.to_string())? {
+        return Ok(());
+    }
+    let Ok(path) = editable_track_path(conn, track_id) else {
+        return Ok(());
+    };
+    let path = Path::new(&path);
+    if !tag_editor::supports_rating(path) {
+        return Ok(());
+    }
+    tag_editor::write_rating(path, value)?;
+    // Keep the next rescan from reading the file again
+    if let Ok(stamp) = FileStamp::of(path) {
+        queries::set_track_file_stamp(conn, track_id, stamp.mtime_millis(), stamp.size as i64)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// After the user rates a track: write the rating to its file. The rating
+/// is already saved and queued for sync, so failures are only logged.
+fn write_back_rating(conn: &Connection, track_id: i64, value: Option<u8>) {
+    if let Err(e) = write_rating_to_file(conn, track_id, value) {
+        log::warn!(
+            "[Ratings] Failed to write the rating of track {} to its file: {}",
+            track_id,
+            e
+        );
+    }
+}
+
+/// Rate a track in half stars (0-10); `None` clears the rating.
+#[tauri::command]
+pub async fn set_track_rating(
+    track_id: i64,
+    rating: Option<u8>,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let rating = rating::validate(rating)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    if !queries::set_track_rating(&conn, track_id, rating).map_err(|e| e.to_string())? {
+        return Err(format!("Track {} not found", track_id));
+    }
+    if let Some(track) = queries::get_track_by_id(&conn, track_id).map_err(|e| e.to_string())? {
+        queries::enqueue_track_rating_sync_change(&conn, &track, rating)
+            .map_err(|e| e.to_string())?;
+    }
+    write_back_rating(&conn, track_id, rating);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_album_rating(
+    album_id: i64,
+    rating: Option<u8>,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let rating = rating::validate(rating)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    if !queries::set_album_rating(&conn, album_id, rating).map_err(|e| e.to_string())? {
+        return Err(format!("Album {} not found", album_id));
+    }
+    if let Some(album) = queries::get_album_by_id(&conn, album_id).map_err(|e| e.to_string())? {
+        queries::enqueue_album_rating_sync_change(&conn, &album, rating)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_track_ratings(db: State<'_, Database>) -> Result<Vec<queries::Rating>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_track_ratings(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_album_ratings(db: State<'_, Database>) -> Result<Vec<queries::Rating>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_album_ratings(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_rating_write_back(db: State<'_, Database>) -> Result<bool, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_rating_write_back(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_rating_write_back(enabled: bool, db: State<'_, Database>) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::set_rating_write_back(&conn, enabled).map_err(|e| e.to_string())
+}
+
 // ============================================================================
 // Play History commands
 // ============================================================================
 
+/// Record a play of `duration_played` seconds; `position` is where playback
+/// stopped. Returns whether the play counted or was a skip.
 #[tauri::command]
 pub async fn record_play(
     track_id: i64,
     album_id: Option<i64>,
     duration_played: i64,
+    position: Option<i64>,
     db: State<'_, Database>,
-) -> Result<(), String> {
+) -> Result<PlayOutcome, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    queries::record_play(&conn, track_id, album_id, duration_played).map_err(|e| e.to_string())?;
+    let outcome = queries::record_play(&conn, track_id, album_id, duration_played, position)
+        .map_err(|e| e.to_string())?;
 
     // Enqueue sync change
     if queries::is_logged_in(&conn) {
@@ -122,6 +237,7 @@ pub async fn record_play(
                 "album": track.album,
                 "duration": track.duration,
                 "durationPlayed": duration_played,
+                "position": position,
                 "sourceType": track.source_type,
                 "externalId": track.external_id,
                 "playedAt": chrono::Utc::now().to_rfc3339(),
@@ -142,25 +258,56 @@ pub async fn record_play(
         }
     }
 
-    Ok(())
+    Ok(outcome)
+}
+
+#[tauri::command]
+pub async fn get_skip_thresholds(db: State<'_, Database>) -> Result<SkipThresholds, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_skip_thresholds(&conn).map_err(|e| e.to_string())
+}
+
+/// Save new skip thresholds and classify the existing history against
+/// them. Returns the number of plays that changed between completed and
+/// skipped.
+#[tauri::command]
+pub async fn set_skip_thresholds(
+    thresholds: SkipThresholds,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    if !(0.0..=100.0).contains(&thresholds.min_percent) || thresholds.min_seconds < 0 {
+        return Err("Skip thresholds out of range".to_string());
+    }
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::set_skip_thresholds(&conn, &thresholds).map_err(|e| e.to_string())?;
+    queries::reclassify_plays(&conn, &thresholds).map_err(|e| e.to_string())
+}
+
+/// The current period for `range`; the current month when not given.
+fn stats_period(range: Option<StatsRange>) -> Result<StatsPeriod, String> {
+    range.unwrap_or_default().period(chrono::Local::now())
 }
 
 #[tauri::command]
 pub async fn get_top_tracks(
     limit: i32,
+    range: Option<StatsRange>,
     db: State<'_, Database>,
 ) -> Result<Vec<queries::TrackWithCount>, String> {
+    let period = stats_period(range)?;
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    queries::get_top_tracks(&conn, limit).map_err(|e| e.to_string())
+    queries::get_top_tracks(&conn, limit, &period).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_top_albums(
     limit: i32,
+    range: Option<StatsRange>,
     db: State<'_, Database>,
 ) -> Result<Vec<queries::AlbumWithCount>, String> {
+    let period = stats_period(range)?;
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    queries::get_top_albums(&conn, limit).map_err(|e| e.to_string())
+    queries::get_top_albums(&conn, limit, &period).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -175,14 +322,132 @@ pub async fn get_recently_played(
 #[tauri::command]
 pub async fn get_top_artists(
     limit: i32,
+    range: Option<StatsRange>,
     db: State<'_, Database>,
 ) -> Result<Vec<queries::ArtistWithCount>, String> {
+    let period = stats_period(range)?;
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    queries::get_top_artists(&conn, limit).map_err(|e| e.to_string())
+    queries::get_top_artists(&conn, limit, &period).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_stats_summary(db: State<'_, Database>) -> Result<queries::StatsSummary, String> {
+pub async fn get_top_genres(
+    limit: i32,
+    range: Option<StatsRange>,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::GenreWithCount>, String> {
+    let period = stats_period(range)?;
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    queries::get_stats_summary(&conn).map_err(|e| e.to_string())
+    queries::get_top_genres(&conn, limit, &period).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_stats_summary(
+    range: Option<StatsRange>,
+    db: State<'_, Database>,
+) -> Result<queries::StatsSummary, String> {
+    let period = stats_period(range)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_stats_summary(&conn, &period).map_err(|e| e.to_string())
+}
+
+/// Listening time by hour and weekday, streaks and newly discovered
+/// artists for `range`.
+#[tauri::command]
+pub async fn get_listening_breakdown(
+    range: Option<StatsRange>,
+    db: State<'_, Database>,
+) -> Result<queries::ListeningBreakdown, String> {
+    let period = stats_period(range)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_listening_breakdown(&conn, &period).map_err(|e| e.to_string())
+}
+
+fn year_in_review(
+    conn: &Connection,
+    year: Option<i32>,
+    limit: Option<i32>,
+) -> Result<queries::YearInReview, String> {
+    let year = year.unwrap_or_else(|| chrono::Local::now().year());
+    let period = StatsRange::year(year).period(chrono::Local::now())?;
+    queries::get_year_in_review(conn, year, &period, limit.unwrap_or(10).max(1))
+        .map_err(|e| e.to_string())
+}
+
+/// Listening totals, top lists, notable plays and monthly trends for
+/// `year` (this year when not given).
+#[tauri::command]
+pub async fn get_year_in_review(
+    year: Option<i32>,
+    limit: Option<i32>,
+    db: State<'_, Database>,
+) -> Result<queries::YearInReview, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    year_in_review(&conn, year, limit)
+}
+
+/// A stored cover as a data URI; missing or unreadable files are skipped.
+fn embed_cover(path: Option<String>) -> Option<String> {
+    let bytes = std::fs::read(path?).ok()?;
+    let mime = match ImageFormat::from_bytes(&bytes)? {
+        ImageFormat::Jpeg => "image/jpeg",
+        ImageFormat::Png => "image/png",
+        ImageFormat::Webp => "image/webp",
+    };
+    Some(year_review::data_uri(mime, &STANDARD.encode(&bytes)))
+}
+
+/// Write the year in review to `path` as a standalone HTML page with its
+/// cover art embedded. Returns the path written.
+#[tauri::command]
+pub async fn export_year_in_review(
+    year: Option<i32>,
+    limit: Option<i32>,
+    path: String,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let (report, covers) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let report = year_in_review(&conn, year, limit)?;
+        let mut covers = ReviewCovers::default();
+        let tracks = report
+            .top_tracks
+            .iter()
+            .map(|t| &t.track)
+            .chain(report.first_play.iter().map(|p| &p.track))
+            .chain(report.last_play.iter().map(|p| &p.track))
+            .chain(report.most_replayed_day.iter().map(|d| &d.track));
+        for track in tracks {
+            if covers.tracks.contains_key(&track.id) {
+                continue;
+            }
+            if let Some(uri) = cover_storage::get_track_cover_file_path(&conn, track.id)
+                .ok()
+                .and_then(embed_cover)
+            {
+                covers.tracks.insert(track.id, uri);
+            }
+        }
+        let album_ids = report
+            .top_tracks
+            .iter()
+            .filter_map(|t| t.track.album_id)
+            .chain(report.top_albums.iter().map(|a| a.album.id));
+        for album_id in album_ids {
+            if covers.albums.contains_key(&album_id) {
+                continue;
+            }
+            if let Some(uri) = cover_storage::get_album_art_file_path(&conn, album_id)
+                .ok()
+                .and_then(embed_cover)
+            {
+                covers.albums.insert(album_id, uri);
+            }
+        }
+        (report, covers)
+    };
+
+    let html = year_review::render_html(&report, &covers);
+    std::fs::write(&path, html).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(path)
 }
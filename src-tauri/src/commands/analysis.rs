@@ -0,0 +1,423 @@
+// Audio analysis Tauri commands (waveform peaks, tempo/key, lossless checks)
+use crate::analysis::key::{self, MusicalKey};
+use crate::analysis::quality::{self, QualityVerdict};
+use crate::analysis::waveform::{self, Waveform, WaveformQueue};
+use crate::analysis::{decode, tempo};
+use crate::commands::tags::editable_track_path;
+use crate::db::{queries, Database};
+use crate::scanner::FileStamp;
+use crate::tag_editor::fields::{TagField, TagValues};
+use crate::tag_editor::{self, CoverChange};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+/// Tempo and key are estimated from the opening minutes; intros/outros of
+/// long mixes rarely change the answer and decoding the rest is wasted time.
+const ANALYSIS_WINDOW: Duration = Duration::from_secs(6 * 60);
+
+/// Resolve the playable local file for a track (downloaded copy first).
+fn local_audio_path(db: &Database, track_id: i64) -> Result<PathBuf, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let track = queries::get_track_by_id(&conn, track_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Track {} not found", track_id))?;
+    let path = PathBuf::from(track.local_src.unwrap_or(track.path));
+    if !path.is_file() {
+        return Err(format!("Track {} has no local audio file", track_id));
+    }
+    Ok(path)
+}
+
+/// Waveform peaks for a track. Served from the on-disk cache when it still
+/// matches the file; otherwise generated immediately (ahead of the queue).
+/// `resolution` is the desired bucket count; the closest stored level is used.
+#[tauri::command]
+pub async fn get_waveform(
+    track_id: i64,
+    resolution: Option<usize>,
+    db: State<'_, Database>,
+) -> Result<Waveform, String> {
+    let path = local_audio_path(&db, track_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        waveform::get_or_generate(track_id, &path).map(|p| p.to_waveform(track_id, resolution))
+    })
+    .await
+    .map_err(|e| format!("Waveform task failed: {}", e))?
+}
+
+/// Queue tracks for background peak generation. Returns the queue length.
+#[tauri::command]
+pub async fn queue_waveforms(
+    track_ids: Vec<i64>,
+    queue: State<'_, WaveformQueue>,
+) -> Result<usize, String> {
+    Ok(queue.enqueue(&track_ids))
+}
+
+/// Move tracks (typically the upcoming play queue) to the front of the
+/// background queue, in the given order.
+#[tauri::command]
+pub async fn prioritize_waveforms(
+    track_ids: Vec<i64>,
+    queue: State<'_, WaveformQueue>,
+) -> Result<usize, String> {
+    Ok(queue.prioritize(&track_ids))
+}
+
+/// Queue every local track; already-cached tracks are skipped by the worker.
+#[tauri::command]
+pub async fn queue_library_waveforms(
+    db: State<'_, Database>,
+    queue: State<'_, WaveformQueue>,
+) -> Result<usize, String> {
+    let track_ids: Vec<i64> = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id FROM tracks
+                 WHERE source_type IS NULL OR source_type = 'local' OR local_src IS NOT NULL
+                 ORDER BY date_added DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<i64>, _>>()
+            .map_err(|e| e.to_string())?;
+        ids
+    };
+    Ok(queue.enqueue(&track_ids))
+}
+
+#[tauri::command]
+pub async fn get_waveform_queue_size(queue: State<'_, WaveformQueue>) -> Result<usize, String> {
+    Ok(queue.pending())
+}
+
+// ─── Tempo / key ────────────────────────────────────────────────────────────
+
+/// Emitted as "track-analysis-progress" after each analysed track.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackAnalysisProgress {
+    pub track_id: i64,
+    pub current: usize,
+    pub total: usize,
+    pub bpm: Option<f64>,
+    pub musical_key: Option<String>,
+    pub error: Option<String>,
+}
+
+fn analyze_file(path: &Path) -> Result<(Option<f64>, Option<MusicalKey>), String> {
+    let (samples, props) = decode::decode_mono(path, Some(ANALYSIS_WINDOW))?;
+    let bpm = tempo::estimate_bpm(&samples, props.sample_rate)
+        .map(|t| (t.bpm * 100.0).round() / 100.0);
+    let key = key::estimate_key(&samples, props.sample_rate).map(|k| k.key);
+    Ok((bpm, key))
+}
+
+/// Write BPM/key into a track's file through the tag editor, which maps
+/// them to TBPM/TKEY (ID3v2), BPM/INITIALKEY (Vorbis comments) and
+/// tmpo/initialkey (MP4). The database is only locked to look up the path
+/// and to store the new file stamp, not while the file is rewritten.
+fn write_analysis_tags(
+    db: &Database,
+    track_id: i64,
+    bpm: Option<f64>,
+    key: Option<MusicalKey>,
+) -> Result<(), String> {
+    let mut values = TagValues::new();
+    if let Some(bpm) = bpm {
+        // TBPM is an integer string; most players expect the same elsewhere
+        values.insert(TagField::Bpm, Some(format!("{}", bpm.round() as u32)));
+    }
+    if let Some(key) = key {
+        values.insert(TagField::Key, Some(key.to_string()));
+    }
+    if values.is_empty() {
+        return Ok(());
+    }
+
+    let path = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        editable_track_path(&conn, track_id)?
+    };
+    let path = Path::new(&path);
+    tracing::info!("[AUDIT] Writing analysis tags: {:?}", path);
+    tag_editor::write_file_tags(path, &values, &CoverChange::Keep)?;
+    // Keep the next rescan from reading the file again
+    if let Ok(stamp) = FileStamp::of(path) {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::set_track_file_stamp(&conn, track_id, stamp.mtime_millis(), stamp.size as i64)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Analyse tracks in parallel, storing results as they complete.
+fn run_analysis(
+    app: &AppHandle,
+    db: &Database,
+    track_ids: Vec<i64>,
+    write_tags: bool,
+) -> Vec<queries::TrackAnalysis> {
+    let total = track_ids.len();
+    let done = AtomicUsize::new(0);
+
+    track_ids.par_iter().for_each(|&track_id| {
+        let result = local_audio_path(db, track_id).and_then(|path| {
+            let (bpm, key) = analyze_file(&path)?;
+            if write_tags {
+                if let Err(e) = write_analysis_tags(db, track_id, bpm, key) {
+                    tracing::warn!("[ANALYSIS] Tag write-back failed for {}: {}", track_id, e);
+                }
+            }
+            let key_name = key.map(|k| k.to_string());
+            let conn = db.conn.lock().map_err(|e| e.to_string())?;
+            queries::update_track_analysis(&conn, track_id, bpm, key_name.as_deref())
+                .map_err(|e| e.to_string())?;
+            Ok((bpm, key_name))
+        });
+
+        let current = done.fetch_add(1, Ordering::Relaxed) + 1;
+        let (bpm, musical_key, error) = match result {
+            Ok((bpm, key)) => (bpm, key, None),
+            Err(e) => {
+                tracing::warn!("[ANALYSIS] Track {} failed: {}", track_id, e);
+                (None, None, Some(e))
+            }
+        };
+        let _ = app.emit(
+            "track-analysis-progress",
+            TrackAnalysisProgress {
+                track_id,
+                current,
+                total,
+                bpm,
+                musical_key,
+                error,
+            },
+        );
+    });
+
+    db.conn
+        .lock()
+        .ok()
+        .and_then(|conn| queries::get_track_analysis(&conn, &track_ids).ok())
+        .unwrap_or_default()
+}
+
+/// Estimate BPM and key for the given tracks. With `write_tags`, results are
+/// also written back to the files (TBPM/TKEY or the Vorbis equivalents).
+/// Progress is reported through "track-analysis-progress" events.
+#[tauri::command]
+pub async fn analyze_tracks(
+    track_ids: Vec<i64>,
+    write_tags: Option<bool>,
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::TrackAnalysis>, String> {
+    let db = db.inner().clone();
+    let write_tags = write_tags.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || run_analysis(&app, &db, track_ids, write_tags))
+        .await
+        .map_err(|e| format!("Analysis task failed: {}", e))
+}
+
+/// Analyse every local track that has not been analysed yet. With
+/// `only_missing`, tracks already tagged with both BPM and key are skipped.
+/// Returns the number of tracks processed.
+#[tauri::command]
+pub async fn analyze_library(
+    only_missing: Option<bool>,
+    write_tags: Option<bool>,
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    let track_ids = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::get_unanalyzed_track_ids(&conn, only_missing.unwrap_or(true))
+            .map_err(|e| e.to_string())?
+    };
+    let count = track_ids.len();
+    let db = db.inner().clone();
+    let write_tags = write_tags.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || run_analysis(&app, &db, track_ids, write_tags))
+        .await
+        .map_err(|e| format!("Analysis task failed: {}", e))?;
+    Ok(count)
+}
+
+#[tauri::command]
+pub async fn get_track_analysis(
+    track_ids: Vec<i64>,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::TrackAnalysis>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_track_analysis(&conn, &track_ids).map_err(|e| e.to_string())
+}
+
+/// Tracks with tempo/key, filtered by BPM range and Camelot codes, sorted
+/// by tempo or around the Camelot wheel.
+#[tauri::command]
+pub async fn get_dj_tracks(
+    min_bpm: Option<f64>,
+    max_bpm: Option<f64>,
+    camelot_keys: Option<Vec<String>>,
+    sort: Option<queries::DjSort>,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::DjTrack>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_dj_tracks(
+        &conn,
+        min_bpm,
+        max_bpm,
+        &camelot_keys.unwrap_or_default(),
+        sort.unwrap_or(queries::DjSort::Bpm),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Tracks that mix harmonically after `track_id`. `bpm_tolerance` is a
+/// percentage (default 6%, roughly what a turntable pitch fader covers).
+#[tauri::command]
+pub async fn get_harmonic_mix_candidates(
+    track_id: i64,
+    bpm_tolerance: Option<f64>,
+    limit: Option<i32>,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::DjTrack>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_harmonic_mix_candidates(
+        &conn,
+        track_id,
+        bpm_tolerance.unwrap_or(6.0),
+        limit.unwrap_or(50),
+    )
+    .map_err(|e| e.to_string())
+}
+
+// ─── Lossless authenticity ──────────────────────────────────────────────────
+
+/// Emitted as "quality-analysis-progress" after each checked track.
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityAnalysisProgress {
+    pub track_id: i64,
+    pub current: usize,
+    pub total: usize,
+    pub verdict: Option<QualityVerdict>,
+    pub error: Option<String>,
+}
+
+fn check_track_quality(db: &Database, track_id: i64) -> Result<QualityVerdict, String> {
+    let bitrate = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::get_track_by_id(&conn, track_id)
+            .map_err(|e| e.to_string())?
+            .and_then(|t| t.bitrate)
+    };
+    let path = local_audio_path(db, track_id)?;
+    let mut result = quality::analyze_file(&path)?;
+    result.check_declared_bitrate(bitrate);
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::upsert_track_quality(&conn, track_id, &result).map_err(|e| e.to_string())?;
+    Ok(result.verdict)
+}
+
+/// Check tracks in parallel, storing verdicts as they complete.
+fn run_quality_analysis(
+    app: &AppHandle,
+    db: &Database,
+    track_ids: Vec<i64>,
+) -> Vec<queries::TrackQuality> {
+    let total = track_ids.len();
+    let done = AtomicUsize::new(0);
+
+    track_ids.par_iter().for_each(|&track_id| {
+        let result = check_track_quality(db, track_id);
+        let current = done.fetch_add(1, Ordering::Relaxed) + 1;
+        let (verdict, error) = match result {
+            Ok(verdict) => (Some(verdict), None),
+            Err(e) => {
+                tracing::warn!("[ANALYSIS] Quality check of {} failed: {}", track_id, e);
+                (None, Some(e))
+            }
+        };
+        let _ = app.emit(
+            "quality-analysis-progress",
+            QualityAnalysisProgress {
+                track_id,
+                current,
+                total,
+                verdict,
+                error,
+            },
+        );
+    });
+
+    db.conn
+        .lock()
+        .ok()
+        .and_then(|conn| queries::get_track_quality(&conn, &track_ids).ok())
+        .unwrap_or_default()
+}
+
+/// Check the given tracks for lossy transcodes, upsampling and padded bit
+/// depth. Progress is reported through "quality-analysis-progress" events.
+#[tauri::command]
+pub async fn analyze_track_quality(
+    track_ids: Vec<i64>,
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::TrackQuality>, String> {
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || run_quality_analysis(&app, &db, track_ids))
+        .await
+        .map_err(|e| format!("Quality check failed: {}", e))
+}
+
+/// Check the library. By default only lossless-format files that were never
+/// checked are inspected. Returns the number of tracks processed.
+#[tauri::command]
+pub async fn analyze_library_quality(
+    lossless_only: Option<bool>,
+    only_missing: Option<bool>,
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    let track_ids = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::get_quality_candidate_ids(
+            &conn,
+            lossless_only.unwrap_or(true),
+            only_missing.unwrap_or(true),
+        )
+        .map_err(|e| e.to_string())?
+    };
+    let count = track_ids.len();
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || run_quality_analysis(&app, &db, track_ids))
+        .await
+        .map_err(|e| format!("Quality check failed: {}", e))?;
+    Ok(count)
+}
+
+#[tauri::command]
+pub async fn get_track_quality(
+    track_ids: Vec<i64>,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::TrackQuality>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_track_quality(&conn, &track_ids).map_err(|e| e.to_string())
+}
+
+/// Library-wide verdict counts per format and the list of suspicious files.
+#[tauri::command]
+pub async fn get_quality_report(db: State<'_, Database>) -> Result<queries::QualityReport, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_quality_report(&conn).map_err(|e| e.to_string())
+}
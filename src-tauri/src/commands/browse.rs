@@ -0,0 +1,126 @@
+// Browsing by genre, year, decade, composer and label
+use crate::db::{queries, Database};
+use crate::scanner::artists::ArtistRole;
+use std::collections::HashMap;
+use tauri::State;
+
+#[tauri::command]
+pub async fn get_genres(
+    limit: i32,
+    offset: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::CategoryWithCount>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_genres(&conn, limit, offset).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_years(
+    limit: i32,
+    offset: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::YearWithCount>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_years(&conn, limit, offset).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_decades(
+    limit: i32,
+    offset: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::YearWithCount>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_decades(&conn, limit, offset).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_composers(
+    limit: i32,
+    offset: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::CategoryWithCount>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_composers(&conn, limit, offset).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_labels(
+    limit: i32,
+    offset: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::CategoryWithCount>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_labels(&conn, limit, offset).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_tracks_by_genre(
+    genre: String,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::Track>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_tracks_by_genre(&conn, &genre).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_tracks_by_year(
+    year: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::Track>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_tracks_by_year_range(&conn, year, year).map_err(|e| e.to_string())
+}
+
+/// `decade` is its first year, as returned by `get_decades` (1990 for the 1990s).
+#[tauri::command]
+pub async fn get_tracks_by_decade(
+    decade: i32,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::Track>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_tracks_by_year_range(&conn, decade, decade + 9).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_tracks_by_composer(
+    composer: String,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::Track>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_tracks_by_artist_role(&conn, &composer, &[ArtistRole::Composer])
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_tracks_by_label(
+    label: String,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::Track>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_tracks_by_label(&conn, &label).map_err(|e| e.to_string())
+}
+
+/// User genre aliases; built-in ones (see `scanner::genres`) always apply.
+#[tauri::command]
+pub async fn get_genre_aliases(db: State<'_, Database>) -> Result<HashMap<String, String>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_genre_aliases(&conn).map_err(|e| e.to_string())
+}
+
+/// Save genre aliases and re-split every track's genres with them.
+/// Returns the number of tracks processed.
+#[tauri::command]
+pub async fn set_genre_aliases(
+    aliases: HashMap<String, String>,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::set_genre_aliases(&conn, &aliases).map_err(|e| e.to_string())?;
+        queries::rebuild_genres(&conn).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,345 @@
+// Acoustic fingerprint Tauri commands (duplicates, relinking, AcoustID)
+use crate::analysis::acoustid::{self, AcoustIdMatch};
+use crate::analysis::fingerprint::{self, FingerprintEntry};
+use crate::db::queries::{self, Track, TrackUsage};
+use crate::db::Database;
+use crate::scanner::cover_storage;
+use crate::security;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::{AppHandle, Emitter, State};
+
+/// Tracks further apart than this (seconds) are never compared.
+const MAX_DURATION_DIFF: f64 = 3.0;
+/// Duration window (seconds) when looking for the new copy of a missing file.
+const RELINK_DURATION_TOLERANCE: i32 = 2;
+/// AcoustID results below this score are returned but not stored.
+const ACOUSTID_MIN_SCORE: f64 = 0.9;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FingerprintProgress {
+    pub track_id: i64,
+    pub current: usize,
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateTrack {
+    #[serde(flatten)]
+    pub track: Track,
+    #[serde(flatten)]
+    pub usage: TrackUsage,
+    /// The copy we'd keep: lossless first, then bitrate, then history.
+    pub suggested_keep: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateCluster {
+    pub similarity: f64,
+    pub tracks: Vec<DuplicateTrack>,
+}
+
+fn is_lossless(format: Option<&str>) -> bool {
+    matches!(
+        format.map(|f| f.to_ascii_lowercase()).as_deref(),
+        Some("flac" | "wav" | "aiff" | "ape" | "wavpack" | "alac")
+    )
+}
+
+/// Fingerprint one file and store the result.
+fn fingerprint_and_store(db: &Database, track_id: i64, path: &str) -> Result<Vec<u32>, String> {
+    let fp = fingerprint::fingerprint_file(Path::new(path))?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::set_track_fingerprint(&conn, track_id, &fingerprint::to_bytes(&fp))
+        .map_err(|e| e.to_string())?;
+    Ok(fp)
+}
+
+fn run_fingerprinting(app: &AppHandle, db: &Database, tracks: Vec<(i64, String)>) -> usize {
+    let total = tracks.len();
+    let done = AtomicUsize::new(0);
+    let stored = AtomicUsize::new(0);
+
+    tracks.par_iter().for_each(|(track_id, path)| {
+        let error = match fingerprint_and_store(db, *track_id, path) {
+            Ok(_) => {
+                stored.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+            Err(e) => {
+                log::warn!("[FINGERPRINT] Track {} failed: {}", track_id, e);
+                Some(e)
+            }
+        };
+        let current = done.fetch_add(1, Ordering::Relaxed) + 1;
+        let _ = app.emit(
+            "fingerprint-progress",
+            FingerprintProgress {
+                track_id: *track_id,
+                current,
+                total,
+                error,
+            },
+        );
+    });
+
+    stored.into_inner()
+}
+
+/// Fingerprint the given tracks (re-computing existing fingerprints).
+/// Progress is reported through "fingerprint-progress" events. Returns the
+/// number of fingerprints stored.
+#[tauri::command]
+pub async fn fingerprint_tracks(
+    track_ids: Vec<i64>,
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    let tracks: Vec<(i64, String)> = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        track_ids
+            .iter()
+            .filter_map(|&id| queries::get_track_by_id(&conn, id).ok().flatten())
+            .filter(|t| matches!(t.source_type.as_deref(), None | Some("local")))
+            .map(|t| (t.id, t.local_src.unwrap_or(t.path)))
+            .collect()
+    };
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || run_fingerprinting(&app, &db, tracks))
+        .await
+        .map_err(|e| format!("Fingerprint task failed: {}", e))
+}
+
+/// Fingerprint every local track that does not have one yet.
+#[tauri::command]
+pub async fn fingerprint_library(app: AppHandle, db: State<'_, Database>) -> Result<usize, String> {
+    let tracks = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::get_unfingerprinted_tracks(&conn).map_err(|e| e.to_string())?
+    };
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || run_fingerprinting(&app, &db, tracks))
+        .await
+        .map_err(|e| format!("Fingerprint task failed: {}", e))
+}
+
+/// Groups of tracks that are acoustically the same recording. `threshold`
+/// is the minimum fingerprint similarity (0–1, default 0.85).
+#[tauri::command]
+pub async fn get_duplicate_clusters(
+    threshold: Option<f64>,
+    db: State<'_, Database>,
+) -> Result<Vec<DuplicateCluster>, String> {
+    let entries: Vec<FingerprintEntry> = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::get_all_fingerprints(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|(track_id, duration, bytes)| FingerprintEntry {
+                track_id,
+                duration_secs: duration.unwrap_or(0) as f64,
+                fingerprint: fingerprint::from_bytes(&bytes),
+            })
+            .collect()
+    };
+
+    let threshold = threshold.unwrap_or(fingerprint::DUPLICATE_THRESHOLD);
+    let clusters = tauri::async_runtime::spawn_blocking(move || {
+        fingerprint::find_clusters(&entries, threshold, MAX_DURATION_DIFF)
+    })
+    .await
+    .map_err(|e| format!("Duplicate search failed: {}", e))?;
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut result = Vec::with_capacity(clusters.len());
+    for cluster in clusters {
+        let mut tracks: Vec<DuplicateTrack> = cluster
+            .track_ids
+            .iter()
+            .filter_map(|&id| queries::get_track_by_id(&conn, id).ok().flatten())
+            .map(|track| {
+                let usage = queries::get_track_usage(&conn, track.id).unwrap_or_default();
+                DuplicateTrack {
+                    track,
+                    usage,
+                    suggested_keep: false,
+                }
+            })
+            .collect();
+        if tracks.len() < 2 {
+            continue;
+        }
+
+        tracks.sort_by(|a, b| {
+            is_lossless(b.track.format.as_deref())
+                .cmp(&is_lossless(a.track.format.as_deref()))
+                .then(b.track.bitrate.unwrap_or(0).cmp(&a.track.bitrate.unwrap_or(0)))
+                .then(b.usage.play_count.cmp(&a.usage.play_count))
+                .then(b.usage.liked.cmp(&a.usage.liked))
+                .then(a.track.id.cmp(&b.track.id))
+        });
+        tracks[0].suggested_keep = true;
+
+        result.push(DuplicateCluster {
+            similarity: cluster.min_similarity,
+            tracks,
+        });
+    }
+    Ok(result)
+}
+
+/// Keep one copy of a duplicate cluster. Likes, play history and playlist
+/// entries of the removed tracks move to `keep_track_id` before their rows
+/// are deleted. With `delete_files`, the removed files go to the trash.
+#[tauri::command]
+pub async fn resolve_duplicate_cluster(
+    keep_track_id: i64,
+    remove_track_ids: Vec<i64>,
+    delete_files: Option<bool>,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    if queries::get_track_by_id(&conn, keep_track_id)
+        .map_err(|e| e.to_string())?
+        .is_none()
+    {
+        return Err(format!("Track {} not found", keep_track_id));
+    }
+
+    let removed: Vec<Track> = remove_track_ids
+        .iter()
+        .filter(|&&id| id != keep_track_id)
+        .filter_map(|&id| queries::get_track_by_id(&conn, id).ok().flatten())
+        .collect();
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for track in &removed {
+        queries::relink_track(&tx, track.id, keep_track_id).map_err(|e| e.to_string())?;
+        queries::delete_track(&tx, track.id).map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    for track in &removed {
+        let is_local = matches!(track.source_type.as_deref(), None | Some("local"));
+        if delete_files.unwrap_or(false) && is_local {
+            if let Err(e) = security::safe_delete_file(Path::new(&track.path)) {
+                log::error!("[AUDIT] Failed to delete duplicate file {}: {}", track.path, e);
+            }
+        }
+        let _ = cover_storage::delete_track_cover_file(track.track_cover_path.as_deref());
+        let _ = queries::enqueue_track_sync_change(&conn, track, "delete");
+    }
+    let _ = queries::cleanup_empty_albums(&conn);
+
+    log::info!(
+        "[AUDIT] Resolved duplicates of track {}: removed {}",
+        keep_track_id,
+        removed.len()
+    );
+    Ok(removed.len())
+}
+
+/// Look a track up on AcoustID. The best match's ID is stored on the track
+/// when its score is high enough; all matches are returned for review.
+#[tauri::command]
+pub async fn lookup_acoustid(
+    track_id: i64,
+    api_key: String,
+    db: State<'_, Database>,
+) -> Result<Vec<AcoustIdMatch>, String> {
+    let (track, stored) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let track = queries::get_track_by_id(&conn, track_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Track {} not found", track_id))?;
+        let stored = queries::get_track_fingerprint(&conn, track_id).map_err(|e| e.to_string())?;
+        (track, stored)
+    };
+
+    let db_handle = db.inner().clone();
+    let matches = tauri::async_runtime::spawn_blocking(move || {
+        let fp = match stored {
+            Some(bytes) => fingerprint::from_bytes(&bytes),
+            None => fingerprint_and_store(
+                &db_handle,
+                track_id,
+                track.local_src.as_deref().unwrap_or(&track.path),
+            )?,
+        };
+        let duration = track.duration.unwrap_or(0).max(0) as u32;
+        acoustid::lookup(
+            acoustid::ACOUSTID_API_BASE,
+            &api_key,
+            &fingerprint::encode(&fp),
+            duration,
+        )
+    })
+    .await
+    .map_err(|e| format!("AcoustID task failed: {}", e))??;
+
+    if let Some(best) = matches.first().filter(|m| m.score >= ACOUSTID_MIN_SCORE) {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::set_track_acoustid(&conn, track_id, &best.id).map_err(|e| e.to_string())?;
+    }
+    Ok(matches)
+}
+
+/// Resolve tracks whose files disappeared during a rescan. A missing track
+/// whose fingerprint matches a track of similar length (typically the same
+/// file renamed, re-tagged or moved) hands its likes, history and playlist
+/// entries over to that track. Every missing row is deleted either way.
+/// Returns the number of tracks relinked. Blocks on decoding; call it off
+/// the async runtime.
+pub fn relink_missing_tracks(db: &Database, missing: Vec<i64>) -> usize {
+    let mut relinked = 0;
+    for missing_id in missing {
+        let lookup = db.conn.lock().ok().and_then(|conn| {
+            let fp = queries::get_track_fingerprint(&conn, missing_id).ok().flatten()?;
+            let candidates =
+                queries::get_relink_candidates(&conn, missing_id, RELINK_DURATION_TOLERANCE)
+                    .ok()?;
+            Some((fingerprint::from_bytes(&fp), candidates))
+        });
+
+        let target = lookup.and_then(|(fp, candidates)| {
+            candidates
+                .into_iter()
+                .filter(|(_, path, _)| Path::new(path).is_file())
+                .filter_map(|(id, path, stored)| {
+                    let other = match stored {
+                        Some(bytes) => fingerprint::from_bytes(&bytes),
+                        None => fingerprint_and_store(db, id, &path).ok()?,
+                    };
+                    Some((id, fingerprint::similarity(&fp, &other)))
+                })
+                .filter(|(_, score)| *score >= fingerprint::DUPLICATE_THRESHOLD)
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+        });
+
+        let Ok(mut conn) = db.conn.lock() else {
+            break;
+        };
+        let result = conn.transaction().and_then(|tx| {
+            if let Some((target_id, score)) = target {
+                queries::relink_track(&tx, missing_id, target_id)?;
+                log::info!(
+                    "[FINGERPRINT] Relinked missing track {} to {} ({:.2})",
+                    missing_id,
+                    target_id,
+                    score
+                );
+            }
+            queries::delete_track(&tx, missing_id)?;
+            tx.commit()
+        });
+        match result {
+            Ok(()) if target.is_some() => relinked += 1,
+            Ok(()) => {}
+            Err(e) => log::warn!("[FINGERPRINT] Failed to resolve track {}: {}", missing_id, e),
+        }
+    }
+    relinked
+}
@@ -0,0 +1,412 @@
+// Folder playlist files: keeping a folder playlist and the .m3u8 in its
+// directory in step. The decisions live in playlist_io::folder_sync; this
+// reads and writes the file and the playlist.
+use super::playlist::{enqueue_playlist_snapshot, export_entries, match_playlist_entry};
+use crate::db::{queries, Database};
+use crate::playlist_io::folder_sync::{self, FileLine, Member, Slot, SyncAction};
+use crate::playlist_io::{self, PathMode, PlaylistFile, PlaylistFormat};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderSyncOutcome {
+    /// Not a folder playlist, or no .m3u8 in its directory
+    NoFile,
+    /// The playlist and its file already agree
+    Unchanged,
+    /// The playlist was updated from the file
+    Read,
+    /// The file was rewritten from the playlist
+    Written,
+    /// Both changed since the last sync; neither was touched
+    Conflict,
+}
+
+/// Which side wins when resolving a conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictSide {
+    File,
+    Playlist,
+}
+
+/// A playlist file's entries with the library tracks they match.
+fn file_lines(
+    conn: &rusqlite::Connection,
+    bytes: &[u8],
+    dir: &Path,
+) -> Result<Vec<FileLine>, String> {
+    let parsed = playlist_io::parse(PlaylistFormat::M3u8, bytes)?;
+    let mut lines = Vec::with_capacity(parsed.entries.len());
+    for entry in parsed.entries {
+        let track_id = match_playlist_entry(conn, &entry, dir, PlaylistFormat::M3u8)?
+            .map(|(track_id, _)| track_id);
+        lines.push(FileLine { track_id, entry });
+    }
+    Ok(lines)
+}
+
+/// Library tracks a playlist file lists, in order. Entries matching no
+/// track are skipped here; rewriting the file keeps them.
+fn listed_tracks(
+    conn: &rusqlite::Connection,
+    bytes: &[u8],
+    dir: &Path,
+) -> Result<Vec<i64>, String> {
+    Ok(file_lines(conn, bytes, dir)?
+        .into_iter()
+        .filter_map(|line| line.track_id)
+        .collect())
+}
+
+/// The playlist's entries as the sync sees them, and the tracks a file
+/// written now would list.
+fn playlist_members(
+    conn: &rusqlite::Connection,
+    playlist_id: i64,
+    dir: &Path,
+) -> Result<(Vec<Member>, Vec<i64>), String> {
+    let entries = queries::get_playlist_entries(conn, playlist_id).map_err(|e| e.to_string())?;
+    let mut members = Vec::new();
+    let mut local = Vec::new();
+    for entry in entries {
+        let Some(entry_id) = entry.entry_id else {
+            continue;
+        };
+        let is_local = matches!(entry.track.source_type.as_deref(), None | Some("local"));
+        if is_local {
+            local.push(entry.track.id);
+        }
+        members.push(Member {
+            entry_id,
+            track_id: entry.track.id,
+            keep: !is_local || Path::new(&entry.track.path).starts_with(dir),
+        });
+    }
+    Ok((members, local))
+}
+
+/// Make the playlist follow the file's order and extra entries, in one
+/// transaction and one sync change.
+fn apply_file(
+    conn: &rusqlite::Connection,
+    playlist_id: i64,
+    members: &[Member],
+    listed: &[i64],
+) -> Result<(), String> {
+    let result = folder_sync::apply_file_order(members, listed);
+    let unchanged = result.remove.is_empty()
+        && result
+            .order
+            .iter()
+            .copied()
+            .eq(members.iter().map(|m| Slot::Entry(m.entry_id)));
+    if unchanged {
+        return Ok(());
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    queries::remove_playlist_entries(&tx, &result.remove).map_err(|e| e.to_string())?;
+    let mut entry_ids = Vec::with_capacity(result.order.len());
+    for slot in result.order {
+        entry_ids.push(match slot {
+            Slot::Entry(entry_id) => entry_id,
+            Slot::Add(track_id) => queries::insert_playlist_entry(&tx, playlist_id, track_id, 0.0)
+                .map_err(|e| e.to_string())?,
+        });
+    }
+    queries::set_playlist_entry_order(&tx, &entry_ids).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    enqueue_playlist_snapshot(conn, playlist_id, &[playlist_id]);
+    Ok(())
+}
+
+/// Write the playlist to `path`, keeping the entries of the file there
+/// that match no library track; returns the hash of what was written.
+fn write_file(
+    conn: &rusqlite::Connection,
+    playlist_id: i64,
+    dir: &Path,
+    path: &Path,
+) -> Result<String, String> {
+    let name = queries::get_all_playlists(conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|p| p.id == playlist_id)
+        .map(|p| p.name);
+    let existing = match std::fs::read(path) {
+        Ok(bytes) => file_lines(conn, &bytes, dir)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(format!("Failed to read {:?}: {}", path, e)),
+    };
+    let tracks = queries::get_playlist_tracks(conn, playlist_id).map_err(|e| e.to_string())?;
+    let track_ids: Vec<i64> = tracks
+        .iter()
+        .filter(|t| matches!(t.source_type.as_deref(), None | Some("local")))
+        .map(|t| t.id)
+        .collect();
+    let exported = export_entries(tracks, dir, PathMode::Relative, PlaylistFormat::M3u8);
+    let playlist = PlaylistFile {
+        name,
+        entries: folder_sync::keep_unresolved(
+            &existing,
+            track_ids.into_iter().zip(exported).collect(),
+        ),
+    };
+    let text = playlist_io::write(PlaylistFormat::M3u8, &playlist);
+
+    // Write to a temp file first so a crash never leaves a truncated playlist
+    let tmp = path.with_extension("m3u8.tmp");
+    std::fs::write(&tmp, &text).map_err(|e| format!("Failed to write {:?}: {}", tmp, e))?;
+    if let Err(e) = std::fs::rename(&tmp, path) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(format!("Failed to write {:?}: {}", path, e));
+    }
+    Ok(folder_sync::file_hash(text.as_bytes()))
+}
+
+/// Bring a folder playlist and its .m3u8 in line. `playlist_edited` says
+/// the playlist was just changed by the user, as opposed to a rescan or
+/// the file changing on disk; if the file changed too, that's a conflict.
+pub(crate) fn sync_folder_playlist(
+    conn: &rusqlite::Connection,
+    playlist_id: i64,
+    playlist_edited: bool,
+) -> Result<FolderSyncOutcome, String> {
+    let Some(folder) =
+        queries::get_playlist_folder_path(conn, playlist_id).map_err(|e| e.to_string())?
+    else {
+        return Ok(FolderSyncOutcome::NoFile);
+    };
+    let dir = Path::new(&folder);
+    let state = queries::get_folder_playlist_file(conn, playlist_id).map_err(|e| e.to_string())?;
+    let Some(path) = state
+        .as_ref()
+        .map(|s| PathBuf::from(&s.file_path))
+        .or_else(|| folder_sync::find_playlist_file(dir))
+    else {
+        return Ok(FolderSyncOutcome::NoFile);
+    };
+    let path_str = path.to_string_lossy().to_string();
+
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            // Deleted: stop syncing; another .m3u8 is picked up next time
+            queries::delete_folder_playlist_file(conn, playlist_id).map_err(|e| e.to_string())?;
+            log::info!("[Playlist] {:?} is gone, no longer synced", path);
+            return Ok(FolderSyncOutcome::NoFile);
+        }
+        Err(e) => return Err(format!("Failed to read {:?}: {}", path, e)),
+    };
+    let current = folder_sync::file_hash(&bytes);
+    let action = folder_sync::plan(
+        &current,
+        state.as_ref().and_then(|s| s.file_hash.as_deref()),
+        playlist_edited,
+        state.as_ref().is_some_and(|s| s.conflict),
+    );
+
+    let listed = listed_tracks(conn, &bytes, dir)?;
+    let (members, local) = playlist_members(conn, playlist_id, dir)?;
+    if listed == local {
+        // Whatever changed, both sides list the same tracks
+        let recorded = state
+            .as_ref()
+            .is_some_and(|s| !s.conflict && s.file_hash.as_deref() == Some(current.as_str()));
+        if !recorded {
+            queries::set_folder_playlist_file_synced(conn, playlist_id, &path_str, &current)
+                .map_err(|e| e.to_string())?;
+        }
+        return Ok(FolderSyncOutcome::Unchanged);
+    }
+
+    match action {
+        SyncAction::Conflict => {
+            queries::set_folder_playlist_file_conflict(conn, playlist_id, &path_str)
+                .map_err(|e| e.to_string())?;
+            log::warn!(
+                "[Playlist] Playlist {} and {:?} both changed; keeping both until resolved",
+                playlist_id,
+                path
+            );
+            Ok(FolderSyncOutcome::Conflict)
+        }
+        SyncAction::ReadFile => {
+            take_file(conn, playlist_id, dir, &path, &current, &members, &listed)?;
+            Ok(FolderSyncOutcome::Read)
+        }
+        SyncAction::WriteFile => {
+            let hash = write_file(conn, playlist_id, dir, &path)?;
+            queries::set_folder_playlist_file_synced(conn, playlist_id, &path_str, &hash)
+                .map_err(|e| e.to_string())?;
+            Ok(FolderSyncOutcome::Written)
+        }
+    }
+}
+
+/// Apply the file to the playlist and record the sync. Directory tracks
+/// the file didn't list are written back to it straight away.
+fn take_file(
+    conn: &rusqlite::Connection,
+    playlist_id: i64,
+    dir: &Path,
+    path: &Path,
+    current: &str,
+    members: &[Member],
+    listed: &[i64],
+) -> Result<(), String> {
+    apply_file(conn, playlist_id, members, listed)?;
+    let (_, local) = playlist_members(conn, playlist_id, dir)?;
+    let hash = if local == listed {
+        current.to_string()
+    } else {
+        write_file(conn, playlist_id, dir, path)?
+    };
+    queries::set_folder_playlist_file_synced(conn, playlist_id, &path.to_string_lossy(), &hash)
+        .map_err(|e| e.to_string())?;
+    log::info!(
+        "[Playlist] Playlist {} updated from {:?}",
+        playlist_id,
+        path
+    );
+    Ok(())
+}
+
+/// After the user edits a playlist: write its file, if it is a folder
+/// playlist with one. The edit already succeeded, so failures are only
+/// logged.
+pub(crate) fn write_back(conn: &rusqlite::Connection, playlist_id: i64) {
+    if let Err(e) = sync_folder_playlist(conn, playlist_id, true) {
+        log::warn!(
+            "[Playlist] Failed to sync the file of playlist {}: {}",
+            playlist_id,
+            e
+        );
+    }
+}
+
+/// After a scan: pick up edited files, and write new directory tracks to
+/// unchanged ones.
+pub(crate) fn sync_folder_playlists(conn: &rusqlite::Connection, playlist_ids: &[i64]) {
+    for &playlist_id in playlist_ids {
+        if let Err(e) = sync_folder_playlist(conn, playlist_id, false) {
+            log::warn!(
+                "[Playlist] Failed to sync the file of playlist {}: {}",
+                playlist_id,
+                e
+            );
+        }
+    }
+}
+
+/// A playlist name as a file name: path separators and characters Windows
+/// rejects become "_".
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let stem = stem.trim().trim_end_matches('.').to_string();
+    if stem.is_empty() {
+        "playlist".to_string()
+    } else {
+        stem
+    }
+}
+
+/// Sync state of every folder playlist that has a file, conflicts included.
+#[tauri::command]
+pub async fn get_folder_playlist_files(
+    db: State<'_, Database>,
+) -> Result<Vec<queries::FolderPlaylistFile>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_folder_playlist_files(&conn).map_err(|e| e.to_string())
+}
+
+/// Sync one folder playlist with its file now, as a rescan would.
+#[tauri::command]
+pub async fn sync_folder_playlist_file(
+    playlist_id: i64,
+    db: State<'_, Database>,
+) -> Result<FolderSyncOutcome, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    sync_folder_playlist(&conn, playlist_id, false)
+}
+
+/// Start keeping a folder playlist's order in "<name>.m3u8" in its
+/// directory. Returns the file's path.
+#[tauri::command]
+pub async fn create_folder_playlist_file(
+    playlist_id: i64,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let folder = queries::get_playlist_folder_path(&conn, playlist_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Playlist {} is not a folder playlist", playlist_id))?;
+    let dir = Path::new(&folder);
+    if let Some(existing) = queries::get_folder_playlist_file(&conn, playlist_id)
+        .map_err(|e| e.to_string())?
+        .map(|s| PathBuf::from(s.file_path))
+        .or_else(|| folder_sync::find_playlist_file(dir))
+    {
+        return Err(format!("Playlist already has a file: {:?}", existing));
+    }
+
+    let name = queries::get_all_playlists(&conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|p| p.id == playlist_id)
+        .map(|p| p.name)
+        .ok_or_else(|| format!("Playlist {} not found", playlist_id))?;
+    let path = dir.join(format!("{}.m3u8", file_stem(&name)));
+    let hash = write_file(&conn, playlist_id, dir, &path)?;
+    let path_str = path.to_string_lossy().to_string();
+    queries::set_folder_playlist_file_synced(&conn, playlist_id, &path_str, &hash)
+        .map_err(|e| e.to_string())?;
+    Ok(path_str)
+}
+
+/// Settle a conflict by keeping one side: the file's order, or the
+/// playlist as it is in the library.
+#[tauri::command]
+pub async fn resolve_folder_playlist_conflict(
+    playlist_id: i64,
+    keep: ConflictSide,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let state = queries::get_folder_playlist_file(&conn, playlist_id)
+        .map_err(|e| e.to_string())?
+        .filter(|s| s.conflict)
+        .ok_or_else(|| format!("Playlist {} has no conflict", playlist_id))?;
+    let folder = queries::get_playlist_folder_path(&conn, playlist_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Playlist {} is not a folder playlist", playlist_id))?;
+    let dir = Path::new(&folder);
+    let path = PathBuf::from(&state.file_path);
+
+    match keep {
+        ConflictSide::File => {
+            let bytes =
+                std::fs::read(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+            let current = folder_sync::file_hash(&bytes);
+            let listed = listed_tracks(&conn, &bytes, dir)?;
+            let (members, _) = playlist_members(&conn, playlist_id, dir)?;
+            take_file(&conn, playlist_id, dir, &path, &current, &members, &listed)
+        }
+        ConflictSide::Playlist => {
+            let hash = write_file(&conn, playlist_id, dir, &path)?;
+            queries::set_folder_playlist_file_synced(&conn, playlist_id, &state.file_path, &hash)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
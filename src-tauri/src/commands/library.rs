@@ -1,7 +1,13 @@
 // Library-related Tauri commands
 use crate::db::{queries, Database};
-use crate::scanner::{cover_storage, extract_metadata, scan_directory};
+use crate::scanner::artists::{ArtistRole, ArtistSplitRules};
+use crate::scanner::grouping::VariousArtistsPolicy;
+use crate::scanner::rules::{self, ExcludedFile, LibraryRules, RuleMatcher, ScanRules};
+use crate::scanner::{
+    cover_storage, extract_metadata, moves, scan_directory, scan_directory_with_rules, FileStamp,
+};
 use crate::security;
+use crate::watcher::FolderWatcher;
 use base64::{engine::general_purpose::STANDARD, Engine};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -41,9 +47,25 @@ pub struct ScanResult {
     pub tracks_added: usize,
     pub tracks_updated: usize,
     pub tracks_deleted: usize,
+    /// Files skipped because their mtime and size were unchanged.
+    #[serde(default)]
+    pub tracks_unchanged: usize,
+    /// Tracks whose file was moved or renamed, updated in place.
+    #[serde(default)]
+    pub moves: Vec<TrackMove>,
+    /// Files and folders skipped by exclusion rules.
+    #[serde(default)]
+    pub files_excluded: usize,
     pub errors: Vec<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrackMove {
+    pub track_id: i64,
+    pub from: String,
+    pub to: String,
+}
+
 #[tauri::command]
 pub async fn import_audio_file(
     file_path: String,
@@ -74,6 +96,7 @@ pub async fn begin_folder_import(
     window: tauri::Window,
     folder_path: String,
     db: State<'_, Database>,
+    watcher: State<'_, FolderWatcher>,
 ) -> Result<i64, String> {
     let path_buf = std::path::PathBuf::from(&folder_path);
 
@@ -91,19 +114,27 @@ pub async fn begin_folder_import(
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_else(|| folder_str.clone());
 
-    let playlist_id = {
+    let (playlist_id, rules) = {
         let conn = db.conn.lock().map_err(|e| e.to_string())?;
         let id = queries::create_playlist(&conn, &playlist_name)
             .map_err(|e| e.to_string())?;
         queries::set_playlist_folder_path(&conn, id, &folder_str)
             .map_err(|e| e.to_string())?;
         let _ = queries::register_music_folder(&conn, &folder_str);
-        id
+        let (rules, _) =
+            LibraryRules::new(&queries::get_folder_scan_rules(&conn).unwrap_or_default());
+        (id, rules)
     };
+    watcher.refresh();
 
-    let scan_result = scan_directory(&folder_str);
+    let scan_result = rules.scan(&folder_str);
     let all_files = scan_result.audio_files;
     let scan_errors = scan_result.errors;
+    let diff = LibraryDiff {
+        files_excluded: scan_result.excluded.len(),
+        ..Default::default()
+    };
+    let min_durations = rules.min_durations();
 
     let mut file_playlist_map: std::collections::HashMap<String, Vec<i64>> =
         std::collections::HashMap::new();
@@ -117,15 +148,20 @@ pub async fn begin_folder_import(
     tauri::async_runtime::spawn(async move {
         let _ = run_scan_and_import(
             &window,
-            db_conn,
+            Arc::clone(&db_conn),
             all_files,
             file_playlist_map,
-            0,
+            &diff,
+            min_durations,
             scan_errors,
             vec![folder_str],
             ScanSource::FolderImport(playlist_id),
         )
         .await;
+        // An .m3u8 already in the folder sets the order
+        if let Ok(conn) = db_conn.lock() {
+            super::folder_playlist::sync_folder_playlists(&conn, &[playlist_id]);
+        }
     });
 
     Ok(playlist_id)
@@ -420,13 +456,20 @@ pub async fn scan_music(paths: Vec<String>, db: State<'_, Database>) -> Result<S
         tracks_added,
         tracks_updated,
         tracks_deleted,
+        tracks_unchanged: 0,
+        moves: Vec::new(),
+        files_excluded: 0,
         errors,
     })
 }
 
 /// Add a music folder with path validation
 #[tauri::command]
-pub async fn add_folder(path: String, db: State<'_, Database>) -> Result<(), String> {
+pub async fn add_folder(
+    path: String,
+    db: State<'_, Database>,
+    watcher: State<'_, FolderWatcher>,
+) -> Result<(), String> {
     let path_buf = std::path::PathBuf::from(&path);
 
     // Validate path exists and is a directory
@@ -449,19 +492,179 @@ pub async fn add_folder(path: String, db: State<'_, Database>) -> Result<(), Str
     queries::register_music_folder(&conn, &path_str)
         .map_err(|e| format!("Failed to add folder: {}", e))?;
 
+    watcher.refresh();
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct FolderScanRules {
+    pub folder: String,
+    pub rules: ScanRules,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScanRulesPreview {
+    /// Audio files the rules keep.
+    pub included: usize,
+    pub excluded: Vec<ExcludedFile>,
+    /// Tracks currently in the library that a rescan would remove.
+    pub tracks_removed: usize,
+}
+
+#[tauri::command]
+pub async fn get_scan_rules(db: State<'_, Database>) -> Result<Vec<FolderScanRules>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let rules = queries::get_folder_scan_rules(&conn).map_err(|e| e.to_string())?;
+    Ok(rules
+        .into_iter()
+        .map(|(folder, rules)| FolderScanRules { folder, rules })
+        .collect())
+}
+
+/// Save the exclusion rules of a music folder. They take effect on the next
+/// rescan (or the next change the folder watcher sees).
+#[tauri::command]
+pub async fn set_scan_rules(
+    folder: String,
+    rules: ScanRules,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    // Reject patterns that don't compile rather than storing them
+    RuleMatcher::new(&folder, &rules)?;
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    if !queries::set_folder_scan_rules(&conn, &folder, &rules).map_err(|e| e.to_string())? {
+        return Err("Not a registered music folder".to_string());
+    }
     Ok(())
 }
 
-async fn run_scan_and_import(
-    window: &tauri::Window,
+/// Walk `folder` with `rules` without changing anything. For a minimum
+/// duration, stored durations are used where the library has the file and
+/// metadata is read for the rest.
+#[tauri::command]
+pub async fn preview_scan_rules(
+    folder: String,
+    rules: ScanRules,
+    db: State<'_, Database>,
+) -> Result<ScanRulesPreview, String> {
+    if !std::path::Path::new(&folder).is_dir() {
+        return Err("Invalid folder path".to_string());
+    }
+    let matcher = RuleMatcher::new(&folder, &rules)?;
+    let durations = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::get_folder_durations(&conn, &folder).map_err(|e| e.to_string())?
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = scan_directory_with_rules(&folder, &matcher);
+        let mut excluded = result.excluded;
+        let mut included = result.audio_files;
+
+        if matcher.min_duration_secs().is_some() {
+            let short: Vec<ExcludedFile> = included
+                .par_iter()
+                .filter_map(|path| {
+                    let duration = match durations.get(path) {
+                        Some(duration) => *duration,
+                        None => extract_metadata(path).and_then(|t| t.duration),
+                    };
+                    let reason = matcher.check_duration(duration)?;
+                    Some(ExcludedFile {
+                        path: path.clone(),
+                        reason,
+                    })
+                })
+                .collect();
+            let short_paths: std::collections::HashSet<&str> =
+                short.iter().map(|e| e.path.as_str()).collect();
+            included.retain(|path| !short_paths.contains(path.as_str()));
+            excluded.extend(short);
+        }
+
+        let tracks_removed = durations
+            .keys()
+            .filter(|path| {
+                excluded
+                    .iter()
+                    .any(|e| std::path::Path::new(path).starts_with(&e.path))
+            })
+            .count();
+
+        Ok(ScanRulesPreview {
+            included: included.len(),
+            excluded,
+            tracks_removed,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_various_artists_policy(
+    db: State<'_, Database>,
+) -> Result<VariousArtistsPolicy, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_various_artists_policy(&conn).map_err(|e| e.to_string())
+}
+
+/// Change how compilations are filed and regroup the library to match.
+#[tauri::command]
+pub async fn set_various_artists_policy(
+    policy: VariousArtistsPolicy,
+    db: State<'_, Database>,
+) -> Result<queries::RegroupResult, String> {
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::set_various_artists_policy(&conn, policy).map_err(|e| e.to_string())?;
+        regroup_with_art(&conn, policy)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Re-file every track under its (album, album artist, release) album.
+#[tauri::command]
+pub async fn regroup_albums(db: State<'_, Database>) -> Result<queries::RegroupResult, String> {
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let policy = queries::get_various_artists_policy(&conn).map_err(|e| e.to_string())?;
+        regroup_with_art(&conn, policy)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn regroup_with_art(
+    conn: &rusqlite::Connection,
+    policy: VariousArtistsPolicy,
+) -> Result<queries::RegroupResult, String> {
+    let result = queries::regroup_albums(conn, policy).map_err(|e| e.to_string())?;
+    cover_storage::copy_regrouped_album_art(conn, &result.art_to_copy);
+    Ok(result)
+}
+
+/// Extract and import `all_files` in batches, emitting progress through
+/// `window` (a window for user-started scans, the app handle for the folder
+/// watcher).
+pub(crate) async fn run_scan_and_import<E>(
+    window: &E,
     db_conn: Arc<std::sync::Mutex<rusqlite::Connection>>,
     all_files: Vec<String>,
     file_playlist_map: std::collections::HashMap<String, Vec<i64>>,
-    tracks_deleted: usize,
+    diff: &LibraryDiff,
+    min_durations: Vec<(String, u32)>, // from LibraryRules::min_durations
     scan_errors: Vec<String>,
     folders: Vec<String>, // used for timestamp update after batch
     source: ScanSource,
-) -> Result<ScanResult, String> {
+) -> Result<ScanResult, String>
+where
+    E: Emitter<tauri::Wry> + Clone + Send + 'static,
+{
     let total_files = all_files.len();
     let total_start = std::time::Instant::now();
  
@@ -469,7 +672,10 @@ async fn run_scan_and_import(
         let result = ScanResult {
             tracks_added: 0,
             tracks_updated: 0,
-            tracks_deleted,
+            tracks_deleted: diff.tracks_deleted,
+            tracks_unchanged: diff.tracks_unchanged,
+            moves: diff.moves.clone(),
+            files_excluded: diff.files_excluded,
             errors: scan_errors,
         };
         let (complete_event, _) = event_names(&source);
@@ -484,11 +690,18 @@ async fn run_scan_and_import(
     ) = crossbeam::channel::bounded(500);
     let extracted_count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
     let extracted_count_clone = extracted_count.clone();
+    let too_short = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let too_short_clone = too_short.clone();
 
     std::thread::spawn(move || {
         all_files.par_iter().for_each(|file_path| {
             if let Some(track_data) = extract_metadata(file_path) {
-                let _ = tx.send(track_data);
+                let min = rules::min_duration_for(&min_durations, file_path);
+                if min.is_some_and(|min| track_data.duration.is_some_and(|d| d < min as i32)) {
+                    too_short_clone.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                } else {
+                    let _ = tx.send(track_data);
+                }
             }
             // increment regardless of success so the receiver loop exits cleanly
             extracted_count_clone.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -618,9 +831,11 @@ async fn run_scan_and_import(
                         // Update playlist membership
                         if let Some(playlist_ids) = file_playlist_map.get(&track_data.path) {
                             for playlist_id in playlist_ids {
-                                if let Err(e) =
-                                    queries::add_track_to_playlist(&tx_db, *playlist_id, track_id)
-                                {
+                                if let Err(e) = queries::add_track_to_playlist_once(
+                                    &tx_db,
+                                    *playlist_id,
+                                    track_id,
+                                ) {
                                     errors.push(format!(
                                         "Failed to add track {} to playlist {}: {}",
                                         track_id, playlist_id, e
@@ -749,7 +964,11 @@ async fn run_scan_and_import(
     let result = ScanResult {
         tracks_added,
         tracks_updated,
-        tracks_deleted,
+        tracks_deleted: diff.tracks_deleted,
+        tracks_unchanged: diff.tracks_unchanged,
+        moves: diff.moves.clone(),
+        files_excluded: diff.files_excluded
+            + too_short.load(std::sync::atomic::Ordering::Relaxed),
         errors: errors.clone(),
     };
  
@@ -767,35 +986,39 @@ fn event_names(source: &ScanSource) -> (&'static str, &'static str) {
     }
 }
 
+/// Rescan all registered folders. Only new files and files whose mtime or
+/// size changed are re-read; `force_full` re-reads every file.
 #[tauri::command]
 pub async fn rescan_music(
     window: tauri::Window,
+    force_full: Option<bool>,
     db: State<'_, Database>,
 ) -> Result<ScanResult, String> {
-    // 1: Cleanup
-    let (folders, folder_playlists, tracks_deleted) = {
+    let force_full = force_full.unwrap_or(false);
+
+    // 1: Registered folders and their exclusion rules
+    let (folders, folder_playlists, folder_rules) = {
         let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
         let folders = queries::get_music_folders(&conn).map_err(|e| e.to_string())?;
-
-        let tracks_deleted = queries::cleanup_deleted_tracks(&conn, &folders)
-            .map_err(|e| format!("Failed to cleanup deleted tracks: {}", e))?;
-
-        let _ = queries::cleanup_empty_albums(&conn);
-
         let folder_playlists = queries::get_folder_playlists(&conn).unwrap_or_default();
+        let folder_rules = queries::get_folder_scan_rules(&conn).unwrap_or_default();
 
-        (folders, folder_playlists, tracks_deleted)
+        (folders, folder_playlists, folder_rules)
     }; // conn dropped here
+    let (rules, mut scan_errors) = LibraryRules::new(&folder_rules);
 
     // 2: Directory walk
     // Collect files from registered music folders
     let mut all_files = Vec::new();
-    let mut scan_errors = Vec::new();
+    let mut stamps = std::collections::HashMap::new();
+    let mut excluded = Vec::new();
 
     for folder in &folders {
-        let result = scan_directory(folder);
+        let result = rules.scan(folder);
         all_files.extend(result.audio_files);
+        stamps.extend(result.stamps);
+        excluded.extend(result.excluded);
         scan_errors.extend(result.errors);
     }
 
@@ -819,7 +1042,7 @@ pub async fn rescan_music(
                     .push(*playlist_id);
             }
         } else {
-            let result = scan_directory(folder_path);
+            let result = rules.scan(folder_path);
             for file_path in &result.audio_files {
                 file_playlist_map
                     .entry(file_path.clone())
@@ -827,10 +1050,24 @@ pub async fn rescan_music(
                     .push(*playlist_id);
                 all_files.push(file_path.clone());
             }
+            stamps.extend(result.stamps);
+            excluded.extend(result.excluded);
             scan_errors.extend(result.errors);
         }
     }
 
+    // 3: Diff against the library: drop deleted and excluded tracks, skip unchanged files
+    let min_durations = rules.min_durations();
+    let diff = diff_against_library(
+        &db,
+        &folders,
+        &mut all_files,
+        &stamps,
+        &excluded,
+        &min_durations,
+        force_full,
+    )?;
+
     // 3: Parallel metadata extraction + DB import (handled inside run_scan_and_import)
     // run_scan_and_import handles the zero-files case and emits scan-complete there.
     let db_conn = Arc::clone(&db.conn);
@@ -839,24 +1076,178 @@ pub async fn rescan_music(
         db_conn,
         all_files,
         file_playlist_map,
-        tracks_deleted,
+        &diff,
+        min_durations,
         scan_errors,
         folders, // all registered folders, for timestamp update
         ScanSource::Rescan,
     )
     .await?;
+
+    // Folder playlists: read edited .m3u8 files, add new tracks to the rest
+    {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let ids: Vec<i64> = folder_playlists.iter().map(|(id, _)| *id).collect();
+        super::folder_playlist::sync_folder_playlists(&conn, &ids);
+    }
  
-    // Background orphan cleanup (non-blocking)
-    let db_conn_cleanup = Arc::clone(&db.conn);
-    tauri::async_runtime::spawn(async move {
-        if let Ok(conn) = db_conn_cleanup.lock() {
-            let _ = cover_storage::cleanup_orphaned_covers(&conn);
-        }
+    // Background relinking + orphan cleanup (non-blocking)
+    let db_cleanup = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        finish_scan_cleanup(&db_cleanup, diff.relink_pending)
     });
  
     Ok(result)
 }
 
+/// What a walk changes in the library, before any file is read.
+#[derive(Default)]
+pub(crate) struct LibraryDiff {
+    pub tracks_deleted: usize,
+    pub tracks_unchanged: usize,
+    pub moves: Vec<TrackMove>,
+    /// Walk exclusions, counted for `ScanResult::files_excluded`.
+    pub files_excluded: usize,
+    /// Vanished fingerprinted tracks, resolved by `finish_scan_cleanup`.
+    pub relink_pending: Vec<i64>,
+}
+
+/// Compare a walk of `roots` with the library. Tracks under `roots` whose
+/// file is gone are either moved to a matching new file in `files`, or
+/// deleted (fingerprinted ones are kept for relinking). Tracks the walk
+/// `excluded`, or that are shorter than their folder's minimum duration,
+/// are deleted. Unless `force_full`, files whose stamp is unchanged are
+/// dropped from `files` so only new and modified files get re-read.
+/// Candidate files for moves are read without holding the database lock.
+pub(crate) fn diff_against_library(
+    db: &Database,
+    roots: &[String],
+    files: &mut Vec<String>,
+    stamps: &std::collections::HashMap<String, FileStamp>,
+    excluded: &[ExcludedFile],
+    min_durations: &[(String, u32)],
+    force_full: bool,
+) -> Result<LibraryDiff, String> {
+    // Known files the walk didn't find. Each is confirmed on disk so an
+    // unreadable subfolder or offline share doesn't look like a deletion.
+    let (known, mut missing, mut unwanted, missing_tracks) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let known = queries::get_file_stamps(&conn).map_err(|e| e.to_string())?;
+        let mut missing = Vec::new();
+        let mut unwanted = Vec::new();
+        let walked: std::collections::HashSet<&str> = files.iter().map(String::as_str).collect();
+        let track_paths = queries::get_folder_track_paths(&conn, roots)
+            .map_err(|e| format!("Failed to cleanup deleted tracks: {}", e))?;
+        for (id, path) in track_paths {
+            if walked.contains(path.as_str()) {
+                continue;
+            }
+            let path = std::path::Path::new(&path);
+            if excluded.iter().any(|e| path.starts_with(&e.path)) {
+                unwanted.push(id);
+            } else if !path.exists() {
+                missing.push(id);
+            }
+        }
+        for (folder, min) in min_durations {
+            let short = queries::get_short_track_ids(&conn, folder, *min).unwrap_or_default();
+            unwanted.extend(short);
+        }
+        let missing_tracks = queries::get_missing_tracks(&conn, &missing).unwrap_or_default();
+        (known, missing, unwanted, missing_tracks)
+    };
+
+    // Moved files keep their row; they need neither deleting nor importing
+    let new_files: Vec<String> = files
+        .iter()
+        .filter(|path| !known.contains_key(*path))
+        .cloned()
+        .collect();
+    let found = moves::find_moves(
+        missing_tracks,
+        new_files,
+        stamps,
+        |path| extract_metadata(path).map(|t| (t.content_hash, t.duration)),
+        |path| crate::analysis::fingerprint::fingerprint_file(std::path::Path::new(path)).ok(),
+    );
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut moves = Vec::with_capacity(found.len());
+    for (track, to) in found {
+        let stamp = stamps.get(&to);
+        match queries::move_track_path(
+            &conn,
+            track.id,
+            &to,
+            stamp.map(|s| s.mtime_millis()),
+            stamp.map(|s| s.size as i64),
+        ) {
+            Ok(()) => moves.push(TrackMove {
+                track_id: track.id,
+                from: track.path,
+                to,
+            }),
+            Err(e) => log::warn!("[SCAN] Failed to move track {}: {}", track.id, e),
+        }
+    }
+    missing.retain(|id| !moves.iter().any(|m| m.track_id == *id));
+    files.retain(|path| !moves.iter().any(|m| m.to == *path));
+
+    // Fingerprinted tracks may have been renamed and re-tagged; they are
+    // matched against the imported files afterwards.
+    let relink_pending = queries::filter_fingerprinted(&conn, &missing).unwrap_or_default();
+    unwanted.extend(missing.into_iter().filter(|id| !relink_pending.contains(id)));
+    unwanted.sort_unstable();
+    unwanted.dedup();
+    let mut tracks_deleted = 0;
+    for id in unwanted {
+        if queries::delete_track(&conn, id).unwrap_or(false) {
+            tracks_deleted += 1;
+        }
+    }
+
+    let _ = queries::cleanup_empty_albums(&conn);
+
+    let mut tracks_unchanged = 0;
+    if !force_full {
+        let before = files.len();
+        files.retain(|path| {
+            !matches!(
+                (stamps.get(path), known.get(path)),
+                (Some(stamp), Some(stored)) if stamp.matches(*stored)
+            )
+        });
+        tracks_unchanged = before - files.len();
+    }
+
+    if !moves.is_empty() {
+        log::info!("[SCAN] Detected {} moved/renamed files", moves.len());
+    }
+
+    Ok(LibraryDiff {
+        tracks_deleted,
+        tracks_unchanged,
+        moves,
+        files_excluded: excluded.len(),
+        relink_pending,
+    })
+}
+
+/// Post-import housekeeping: relink tracks deferred by
+/// `diff_against_library`, then drop empty albums and orphaned caches.
+/// Blocks on decoding; call it off the async runtime.
+pub(crate) fn finish_scan_cleanup(db: &Database, relink_pending: Vec<i64>) {
+    if !relink_pending.is_empty() {
+        let relinked = super::fingerprint::relink_missing_tracks(db, relink_pending);
+        log::info!("[SCAN] Relinked {} moved/re-tagged tracks", relinked);
+    }
+    if let Ok(conn) = db.conn.lock() {
+        let _ = queries::cleanup_empty_albums(&conn);
+        let _ = cover_storage::cleanup_orphaned_covers(&conn);
+        let _ = crate::analysis::waveform::cleanup_orphaned_waveforms(&conn);
+    }
+}
+
 #[tauri::command]
 pub async fn get_library(db: State<'_, Database>) -> Result<Library, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
@@ -931,10 +1322,17 @@ pub async fn get_tracks_by_album(
 #[tauri::command]
 pub async fn get_tracks_by_artist(
     artist: String,
+    roles: Option<Vec<ArtistRole>>,
     db: State<'_, Database>,
 ) -> Result<Vec<queries::Track>, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    queries::get_tracks_by_artist(&conn, &artist).map_err(|e| e.to_string())
+    match roles {
+        Some(roles) if !roles.is_empty() => {
+            queries::get_tracks_by_artist_role(&conn, &artist, &roles)
+        }
+        _ => queries::get_tracks_by_artist(&conn, &artist),
+    }
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -952,32 +1350,40 @@ pub async fn get_albums_by_artist(
     db: State<'_, Database>,
 ) -> Result<Vec<queries::Album>, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_albums_by_artist(&conn, &artist).map_err(|e| e.to_string())
+}
 
-    let mut stmt = conn
-        .prepare(
-            "SELECT DISTINCT a.id, a.name, a.artist, a.art_data, a.art_path 
-             FROM albums a
-             INNER JOIN tracks t ON t.album_id = a.id
-             WHERE t.artist = ?1
-             ORDER BY a.name",
-        )
-        .map_err(|e| e.to_string())?;
-
-    let albums = stmt
-        .query_map([&artist], |row| {
-            Ok(queries::Album {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                artist: row.get(2)?,
-                art_data: row.get(3)?,
-                art_path: row.get(4)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+/// The individual artists credited on a track, with their roles.
+#[tauri::command]
+pub async fn get_track_credits(
+    track_id: i64,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::TrackArtistCredit>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_track_credits(&conn, track_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_artist_split_rules(db: State<'_, Database>) -> Result<ArtistSplitRules, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_artist_split_rules(&conn).map_err(|e| e.to_string())
+}
 
-    Ok(albums)
+/// Save the artist separator rules and re-split every track with them.
+/// Returns the number of tracks processed.
+#[tauri::command]
+pub async fn set_artist_split_rules(
+    rules: ArtistSplitRules,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::set_artist_split_rules(&conn, &rules).map_err(|e| e.to_string())?;
+        queries::rebuild_artist_credits(&conn).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 /// Delete a track from the library (moves file to trash for safety)
@@ -1150,6 +1556,21 @@ pub async fn add_external_track(
         local_src: None,
         musicbrainz_recording_id: None,
         metadata_json: None,
+        bpm: None,
+        musical_key: None,
+        file_mtime: None,
+        file_size: None,
+        album_artist: None,
+        compilation: false,
+        musicbrainz_release_id: None,
+        composer: None,
+        remixer: None,
+        genre: None,
+        year: None,
+        label: None,
+        musicbrainz_release_group_id: None,
+        musicbrainz_artist_id: None,
+        rating: None,
     };
 
     queries::insert_or_update_track(&conn, &track_insert)
@@ -1172,7 +1593,11 @@ pub async fn reset_database(db: State<'_, Database>) -> Result<(), String> {
         "
         DELETE FROM playlist_tracks;
         DELETE FROM playlists;
+        DELETE FROM playlist_folders;
+        DELETE FROM sync_id_map
+            WHERE entity_type IN ('playlist', 'playlist_track', 'playlist_folder');
         DELETE FROM tracks;
+        DELETE FROM artists;
         DELETE FROM albums;
         DELETE FROM music_folders;
         ",
@@ -1,25 +1,43 @@
 // Tauri IPC commands
 pub mod activity;
+pub mod analysis;
+pub mod browse;
 pub mod covers;
+pub mod fingerprint;
+pub mod folder_playlist;
 pub mod library;
 pub mod listenbrainz;
 pub mod lyrics;
 pub mod metadata;
 pub mod musicbrainz;
 pub mod network;
+pub mod organizer;
 pub mod playlist;
+pub mod playlist_ops;
 pub mod plugin;
+pub mod radio;
 pub mod sync;
+pub mod tags;
+pub mod watcher;
 
 pub use activity::*;
+pub use analysis::*;
+pub use browse::*;
+pub use fingerprint::*;
+pub use folder_playlist::*;
 pub use library::*;
 pub use listenbrainz::*;
 pub use lyrics::*;
 pub use metadata::*;
 pub use musicbrainz::*;
 pub use network::*;
+pub use organizer::*;
 pub use playlist::*;
+pub use playlist_ops::*;
 pub use plugin::*;
+pub use radio::*;
+pub use tags::*;
+pub use watcher::*;
 pub mod window;
 pub use covers::*;
 pub use sync::*;
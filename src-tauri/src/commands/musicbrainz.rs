@@ -1280,3 +1280,403 @@ pub async fn get_artist_top_tracks_mb(artist_mbid: String) -> Result<Vec<MbTrack
 
     Ok(tracks)
 }
+
+// ── Album tagger ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+struct MbTaggerSearchResponse {
+    releases: Option<Vec<MbTaggerSearchRelease>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MbTaggerSearchRelease {
+    id: String,
+    #[serde(rename = "track-count")]
+    track_count: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MbTaggerRelease {
+    id: String,
+    title: String,
+    date: Option<String>,
+    country: Option<String>,
+    #[serde(rename = "artist-credit")]
+    artist_credit: Option<Vec<MbCreditPart>>,
+    #[serde(rename = "release-group")]
+    release_group: Option<MbTaggerReleaseGroup>,
+    #[serde(rename = "label-info")]
+    label_info: Option<Vec<MbLabelInfo>>,
+    media: Option<Vec<MbTaggerMedium>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MbTaggerReleaseGroup {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MbTaggerMedium {
+    position: u32,
+    tracks: Option<Vec<MbTaggerTrackRaw>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MbTaggerTrackRaw {
+    title: String,
+    length: Option<u32>,
+    position: u32,
+    recording: MbTaggerRecording,
+}
+
+#[derive(Debug, Deserialize)]
+struct MbTaggerRecording {
+    id: String,
+    #[serde(rename = "artist-credit")]
+    artist_credit: Option<Vec<MbCreditPart>>,
+}
+
+/// One name of an artist credit, with the phrase joining it to the next
+/// ("A feat. B" is two parts).
+#[derive(Debug, Deserialize)]
+struct MbCreditPart {
+    name: Option<String>,
+    joinphrase: Option<String>,
+    artist: Option<MbArtistCreditArtist>,
+}
+
+/// A release track as proposed for a local track.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MbTaggerTrack {
+    pub recording_id: String,
+    pub title: String,
+    pub artist: String,
+    pub artist_id: Option<String>,
+    pub duration_ms: Option<u32>,
+    pub track_number: u32,
+    pub track_total: u32,
+    pub disc_number: u32,
+}
+
+/// A local track and the release track it matched, if any.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MbTrackMapping {
+    pub track_id: i64,
+    pub current_title: Option<String>,
+    pub current_track_number: Option<i32>,
+    pub current_disc_number: Option<i32>,
+    pub recording: Option<MbTaggerTrack>,
+    /// 0.0 – 1.0; 0.0 when unmatched
+    pub score: f64,
+}
+
+/// A scored release with its proposed mapping. Returned for review and
+/// passed back (possibly with mappings removed) to `apply_album_tagging_mb`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MbReleaseCandidate {
+    pub release_id: String,
+    pub release_group_id: Option<String>,
+    pub title: String,
+    pub artist: String,
+    pub artist_id: Option<String>,
+    pub date: Option<String>,
+    pub country: Option<String>,
+    pub label: Option<String>,
+    pub track_count: u32,
+    pub disc_count: u32,
+    /// 0.0 – 1.0, from track count, durations and titles
+    pub score: f64,
+    pub mappings: Vec<MbTrackMapping>,
+    /// Release tracks no local track matched
+    pub unmatched: Vec<MbTaggerTrack>,
+}
+
+/// Full credit string ("A feat. B") and the first artist's MBID.
+fn credit_string(parts: &[MbCreditPart]) -> (String, Option<String>) {
+    let name = parts
+        .iter()
+        .map(|part| {
+            let name = part
+                .name
+                .clone()
+                .or_else(|| part.artist.as_ref().map(|a| a.name.clone()))
+                .unwrap_or_default();
+            name + part.joinphrase.as_deref().unwrap_or("")
+        })
+        .collect::<String>();
+    let id = parts
+        .first()
+        .and_then(|part| part.artist.as_ref())
+        .map(|a| a.id.clone());
+    (name, id)
+}
+
+fn score_release(
+    release: MbTaggerRelease,
+    local: &[crate::db::queries::Track],
+) -> MbReleaseCandidate {
+    use crate::tag_editor::release_match::{match_tracks, LocalTrack, ReleaseTrack};
+
+    let (artist, artist_id) = credit_string(release.artist_credit.as_deref().unwrap_or_default());
+    let media = release.media.unwrap_or_default();
+    let disc_count = media.len() as u32;
+
+    let mut release_tracks: Vec<MbTaggerTrack> = Vec::new();
+    for medium in media {
+        let tracks = medium.tracks.unwrap_or_default();
+        let track_total = tracks.len() as u32;
+        for track in tracks {
+            let (track_artist, track_artist_id) = match track.recording.artist_credit.as_deref() {
+                Some(parts) if !parts.is_empty() => credit_string(parts),
+                _ => (artist.clone(), artist_id.clone()),
+            };
+            release_tracks.push(MbTaggerTrack {
+                recording_id: track.recording.id,
+                title: track.title,
+                artist: track_artist,
+                artist_id: track_artist_id,
+                duration_ms: track.length,
+                track_number: track.position,
+                track_total,
+                disc_number: medium.position,
+            });
+        }
+    }
+
+    let local_tracks: Vec<LocalTrack> = local
+        .iter()
+        .map(|t| LocalTrack {
+            title: t.title.clone(),
+            duration_secs: t.duration,
+            track_number: t.track_number,
+            disc_number: t.disc_number,
+        })
+        .collect();
+    let release_keys: Vec<ReleaseTrack> = release_tracks
+        .iter()
+        .map(|t| ReleaseTrack {
+            title: t.title.clone(),
+            duration_ms: t.duration_ms,
+            track_number: t.track_number,
+            disc_number: t.disc_number,
+        })
+        .collect();
+    let matched = match_tracks(&local_tracks, &release_keys);
+
+    let mappings = local
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let pair = matched.pairs.iter().find(|p| p.local == i);
+            MbTrackMapping {
+                track_id: t.id,
+                current_title: t.title.clone(),
+                current_track_number: t.track_number,
+                current_disc_number: t.disc_number,
+                recording: pair.map(|p| release_tracks[p.release].clone()),
+                score: pair.map(|p| p.score).unwrap_or(0.0),
+            }
+        })
+        .collect();
+    let unmatched = release_tracks
+        .iter()
+        .enumerate()
+        .filter(|(r, _)| !matched.pairs.iter().any(|p| p.release == *r))
+        .map(|(_, t)| t.clone())
+        .collect();
+
+    let label = release
+        .label_info
+        .as_deref()
+        .and_then(|infos| infos.first())
+        .and_then(|li| li.label.as_ref())
+        .map(|l| l.name.clone());
+
+    MbReleaseCandidate {
+        release_id: release.id,
+        release_group_id: release.release_group.map(|rg| rg.id),
+        title: release.title,
+        artist,
+        artist_id,
+        date: release.date.filter(|d| !d.is_empty()),
+        country: release.country,
+        label,
+        track_count: release_tracks.len() as u32,
+        disc_count,
+        score: matched.score,
+        mappings,
+        unmatched,
+    }
+}
+
+/// Search MusicBrainz releases for a local album and score each candidate
+/// against the album's tracks (track count, durations, titles).
+///
+/// Makes 1 search request plus 1 release lookup per candidate (up to
+/// `limit`, max 8), separated by 1.1 s sleeps. Nothing is written; the
+/// proposed mappings are for review before `apply_album_tagging_mb`.
+#[tauri::command]
+pub async fn search_album_releases_mb(
+    album_id: i64,
+    limit: Option<u32>,
+    db: tauri::State<'_, crate::db::Database>,
+) -> Result<Vec<MbReleaseCandidate>, String> {
+    let (album, tracks) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let album = crate::db::queries::get_album_by_id(&conn, album_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Album {} not found", album_id))?;
+        let tracks =
+            crate::db::queries::get_tracks_by_album(&conn, album_id).map_err(|e| e.to_string())?;
+        (album, tracks)
+    };
+    if tracks.is_empty() {
+        return Err("Album has no tracks".to_string());
+    }
+
+    let artist = album
+        .artist
+        .clone()
+        .or_else(|| tracks.iter().find_map(|t| t.artist.clone()))
+        .unwrap_or_default();
+    let quote = |s: &str| s.replace('"', "\\\"");
+    let mut query = format!("release:\"{}\"", quote(&album.name));
+    if !artist.is_empty() {
+        query.push_str(&format!(" AND artist:\"{}\"", quote(&artist)));
+    }
+
+    let client = mb_client()?;
+    let resp = client
+        .get(format!("{}/release", MB_API_BASE))
+        .query(&[("query", query.as_str()), ("limit", "25"), ("fmt", "json")])
+        .send()
+        .await
+        .map_err(|e| format!("MusicBrainz release search error: {}", e))?;
+
+    if !resp.status().is_success() {
+        return Err(format!("MusicBrainz returned {}", resp.status()));
+    }
+
+    let data: MbTaggerSearchResponse = resp
+        .json()
+        .await
+        .map_err(|e| format!("Parse error: {}", e))?;
+
+    // Look up the releases whose track count is closest first; search
+    // relevance breaks ties (the sort is stable)
+    let mut releases = data.releases.unwrap_or_default();
+    releases.sort_by_key(|r| {
+        r.track_count
+            .map(|n| (n as i64 - tracks.len() as i64).unsigned_abs())
+            .unwrap_or(u64::MAX)
+    });
+    releases.truncate(limit.unwrap_or(5).clamp(1, 8) as usize);
+
+    let mut candidates = Vec::new();
+    for release in releases {
+        sleep(Duration::from_millis(1100)).await;
+
+        let resp = client
+            .get(format!("{}/release/{}", MB_API_BASE, release.id))
+            .query(&[
+                ("inc", "recordings+artist-credits+release-groups+labels"),
+                ("fmt", "json"),
+            ])
+            .send()
+            .await
+            .map_err(|e| format!("MusicBrainz release lookup error: {}", e))?;
+        if !resp.status().is_success() {
+            log::warn!(
+                "[MusicBrainz] Release {} lookup returned {}",
+                release.id,
+                resp.status()
+            );
+            continue;
+        }
+        let detail: MbTaggerRelease = resp
+            .json()
+            .await
+            .map_err(|e| format!("Parse error: {}", e))?;
+        candidates.push(score_release(detail, &tracks));
+    }
+
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(candidates)
+}
+
+/// Tag values a reviewed mapping writes to its file.
+fn tagger_values(
+    candidate: &MbReleaseCandidate,
+    track: &MbTaggerTrack,
+) -> crate::tag_editor::fields::TagValues {
+    use crate::tag_editor::fields::TagField;
+
+    let mut values = crate::tag_editor::fields::TagValues::new();
+    values.insert(TagField::Title, Some(track.title.clone()));
+    values.insert(TagField::Artist, Some(track.artist.clone()));
+    values.insert(TagField::Album, Some(candidate.title.clone()));
+    values.insert(TagField::AlbumArtist, Some(candidate.artist.clone()));
+    values.insert(TagField::TrackNumber, Some(track.track_number.to_string()));
+    values.insert(TagField::TrackTotal, Some(track.track_total.to_string()));
+    values.insert(TagField::DiscNumber, Some(track.disc_number.to_string()));
+    values.insert(TagField::DiscTotal, Some(candidate.disc_count.to_string()));
+    values.insert(
+        TagField::MusicBrainzRecordingId,
+        Some(track.recording_id.clone()),
+    );
+    values.insert(
+        TagField::MusicBrainzReleaseId,
+        Some(candidate.release_id.clone()),
+    );
+    if let Some(date) = &candidate.date {
+        values.insert(TagField::Year, Some(date.clone()));
+    }
+    if let Some(id) = &candidate.release_group_id {
+        values.insert(TagField::MusicBrainzReleaseGroupId, Some(id.clone()));
+    }
+    if let Some(id) = &track.artist_id {
+        values.insert(TagField::MusicBrainzArtistId, Some(id.clone()));
+    }
+    if let Some(id) = &candidate.artist_id {
+        values.insert(TagField::MusicBrainzAlbumArtistId, Some(id.clone()));
+    }
+    values
+}
+
+/// Write a reviewed release candidate to the album's files and database
+/// rows: canonical titles, artists, date, track/disc numbers and the
+/// recording, release, release-group and artist MBIDs. Mappings without a
+/// recording are skipped. Runs as one tag edit batch, so
+/// `undo_tag_edit` reverts it.
+#[tauri::command]
+pub async fn apply_album_tagging_mb(
+    candidate: MbReleaseCandidate,
+    db: tauri::State<'_, crate::db::Database>,
+) -> Result<super::tags::TagEditResult, String> {
+    let edits: Vec<_> = candidate
+        .mappings
+        .iter()
+        .filter_map(|m| {
+            m.recording
+                .as_ref()
+                .map(|track| (m.track_id, tagger_values(&candidate, track)))
+        })
+        .collect();
+    if edits.is_empty() {
+        return Err("No tracks to tag".to_string());
+    }
+
+    let description = format!("MusicBrainz: {} – {}", candidate.artist, candidate.title);
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        super::tags::run_tag_edit_batch(
+            &conn,
+            Some(&description),
+            edits,
+            &crate::tag_editor::CoverChange::Keep,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
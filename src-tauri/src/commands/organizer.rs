@@ -0,0 +1,62 @@
+// File organizer commands: rename and move tracks by a naming template
+use crate::db::{queries, Database};
+use crate::organizer::{self, OrganizeOptions, OrganizePlan, OrganizeResult};
+use tauri::State;
+
+/// Where each track would go, without touching any file.
+#[tauri::command]
+pub async fn preview_organize(
+    track_ids: Vec<i64>,
+    options: OrganizeOptions,
+    db: State<'_, Database>,
+) -> Result<OrganizePlan, String> {
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        organizer::plan(&conn, &track_ids, &options)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Move or copy tracks to where the template puts them. The plan is made
+/// again under the same lock, so it matches what the files look like now.
+#[tauri::command]
+pub async fn organize_files(
+    track_ids: Vec<i64>,
+    options: OrganizeOptions,
+    db: State<'_, Database>,
+) -> Result<OrganizeResult, String> {
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let plan = organizer::plan(&conn, &track_ids, &options)?;
+        organizer::execute(&conn, &plan, &options)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_organize_history(
+    limit: Option<i64>,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::OrganizeBatch>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_organize_batches(&conn, limit.unwrap_or(50)).map_err(|e| e.to_string())
+}
+
+/// Put an organize run's files back where they were.
+#[tauri::command]
+pub async fn undo_organize(
+    batch_id: i64,
+    db: State<'_, Database>,
+) -> Result<OrganizeResult, String> {
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        organizer::undo(&conn, batch_id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
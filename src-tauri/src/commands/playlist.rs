@@ -1,6 +1,10 @@
 // Playlist-related Tauri commands
+use super::folder_playlist::write_back;
+use crate::db::smart_rules::{self, SmartPlaylistRules};
 use crate::db::{queries, Database};
-use rusqlite::params;
+use crate::playlist_io::{self, PathMode, PlaylistEntry, PlaylistFile, PlaylistFormat};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 use tauri::State;
 
 #[tauri::command]
@@ -23,6 +27,72 @@ pub async fn create_playlist(name: String, db: State<'_, Database>) -> Result<i6
     Ok(id)
 }
 
+/// Create a playlist whose tracks are whatever currently matches `rules`.
+#[tauri::command]
+pub async fn create_smart_playlist(
+    name: String,
+    rules: SmartPlaylistRules,
+    db: State<'_, Database>,
+) -> Result<i64, String> {
+    smart_rules::compile(&rules)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let id = queries::create_smart_playlist(&conn, &name, &rules).map_err(|e| e.to_string())?;
+
+    // Enqueue sync change
+    if queries::is_logged_in(&conn) {
+        let payload = serde_json::json!({ "name": name, "smartRules": rules }).to_string();
+        let _ = queries::enqueue_sync_change(
+            &conn,
+            "playlist",
+            &format!("local_{}", id),
+            "create",
+            Some(&payload),
+        );
+    }
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn update_smart_playlist(
+    playlist_id: i64,
+    rules: SmartPlaylistRules,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    smart_rules::compile(&rules)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    if !queries::update_smart_playlist_rules(&conn, playlist_id, &rules)
+        .map_err(|e| e.to_string())?
+    {
+        return Err(format!("Playlist {} is not a smart playlist", playlist_id));
+    }
+
+    // Enqueue sync change
+    if queries::is_logged_in(&conn) {
+        let payload = serde_json::json!({ "smartRules": rules }).to_string();
+        let _ = queries::enqueue_sync_change(
+            &conn,
+            "playlist",
+            &format!("local_{}", playlist_id),
+            "update",
+            Some(&payload),
+        );
+    }
+
+    Ok(())
+}
+
+/// Smart playlists are filled by their rules, not by hand.
+pub(crate) fn ensure_not_smart(
+    conn: &rusqlite::Connection,
+    playlist_id: i64,
+) -> Result<(), String> {
+    if queries::is_smart_playlist(conn, playlist_id).map_err(|e| e.to_string())? {
+        return Err("Smart playlists can't be edited track by track".to_string());
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn get_playlists(db: State<'_, Database>) -> Result<Vec<queries::Playlist>, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
@@ -33,84 +103,169 @@ pub async fn get_playlists(db: State<'_, Database>) -> Result<Vec<queries::Playl
 pub async fn get_playlist_tracks(
     playlist_id: i64,
     db: State<'_, Database>,
-) -> Result<Vec<queries::Track>, String> {
+) -> Result<Vec<queries::PlaylistTrack>, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    queries::get_playlist_tracks(&conn, playlist_id).map_err(|e| e.to_string())
+    queries::get_playlist_entries(&conn, playlist_id).map_err(|e| e.to_string())
 }
 
+/// Sync payload for a playlist entry, with the track's metadata for
+/// cross-device matching.
+fn playlist_entry_payload(
+    conn: &rusqlite::Connection,
+    playlist_id: i64,
+    track_id: i64,
+    position: f64,
+) -> serde_json::Value {
+    let mut payload = serde_json::json!({
+        "playlistId": format!("local_{}", playlist_id),
+        "position": position,
+    });
+    if let Ok(Some(track)) = queries::get_track_by_id(conn, track_id) {
+        let track_hash = queries::build_track_hash_str(
+            track.title.as_deref(),
+            track.artist.as_deref(),
+            track.album.as_deref(),
+        );
+        payload["trackHash"] = serde_json::Value::String(track_hash);
+        payload["title"] = serde_json::json!(track.title);
+        payload["artist"] = serde_json::json!(track.artist);
+        payload["album"] = serde_json::json!(track.album);
+        payload["duration"] = serde_json::json!(track.duration);
+        payload["externalId"] = serde_json::json!(track.external_id);
+        payload["sourceType"] = serde_json::json!(track.source_type);
+        payload["coverUrl"] = serde_json::json!(track.cover_url);
+    }
+    payload
+}
+
+/// Enqueue the sync change for an entry just added to a playlist.
+fn enqueue_playlist_track_added(conn: &rusqlite::Connection, entry_id: i64) {
+    if !queries::is_logged_in(conn) {
+        return;
+    }
+    let Ok(Some((playlist_id, track_id, position))) = queries::get_playlist_entry(conn, entry_id)
+    else {
+        return;
+    };
+    let payload = playlist_entry_payload(conn, playlist_id, track_id, position);
+    let _ = queries::enqueue_sync_change(
+        conn,
+        "playlist_track",
+        &format!("local_entry_{}", entry_id),
+        "create",
+        Some(&payload.to_string()),
+    );
+}
+
+/// Enqueue one sync change carrying the full entry list of each of
+/// `playlist_ids`, for operations that rewrite whole playlists. Receivers
+/// create playlists they don't have and make their entries match.
+pub(crate) fn enqueue_playlist_snapshot(
+    conn: &rusqlite::Connection,
+    entity_playlist_id: i64,
+    playlist_ids: &[i64],
+) {
+    if !queries::is_logged_in(conn) {
+        return;
+    }
+    let Ok(all_playlists) = queries::get_all_playlists(conn) else {
+        return;
+    };
+
+    let mut snapshots = Vec::new();
+    for playlist in all_playlists
+        .iter()
+        .filter(|p| playlist_ids.contains(&p.id))
+    {
+        let Ok(entries) = queries::get_playlist_entries(conn, playlist.id) else {
+            return;
+        };
+        let entries: Vec<serde_json::Value> = entries
+            .iter()
+            .filter_map(|entry| {
+                let mut payload =
+                    playlist_entry_payload(conn, playlist.id, entry.track.id, entry.position?);
+                payload["id"] = serde_json::json!(format!("local_entry_{}", entry.entry_id?));
+                Some(payload)
+            })
+            .collect();
+        snapshots.push(serde_json::json!({
+            "id": format!("local_{}", playlist.id),
+            "name": playlist.name,
+            "folderId": playlist.folder_id.map(|id| format!("local_{}", id)),
+            "position": playlist.position,
+            "entries": entries,
+        }));
+    }
+
+    let payload = serde_json::json!({ "playlists": snapshots }).to_string();
+    let _ = queries::enqueue_sync_change(
+        conn,
+        "playlist_snapshot",
+        &format!("local_{}", entity_playlist_id),
+        "update",
+        Some(&payload),
+    );
+}
+
+/// Append a track to a playlist; returns the entry's id. A track that is
+/// already in the playlist is left as it is (and its entry returned)
+/// unless `allow_duplicate` asks for another entry.
 #[tauri::command]
 pub async fn add_track_to_playlist(
     playlist_id: i64,
     track_id: i64,
+    allow_duplicate: Option<bool>,
     db: State<'_, Database>,
-) -> Result<(), String> {
+) -> Result<i64, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    queries::add_track_to_playlist(&conn, playlist_id, track_id).map_err(|e| e.to_string())?;
-
-    // Enqueue sync change
-    if queries::is_logged_in(&conn) {
-        // Get the position that was just assigned
-        let position: i32 = conn.query_row(
-            "SELECT COALESCE(position, 0) FROM playlist_tracks WHERE playlist_id = ?1 AND track_id = ?2",
-            rusqlite::params![playlist_id, track_id],
-            |row| row.get(0),
-        ).unwrap_or(0);
-
-        let mut payload = serde_json::json!({
-            "playlistId": format!("local_{}", playlist_id),
-            "position": position,
-        });
-        // Attach track metadata for cross-device matching
-        if let Ok(Some(track)) = queries::get_track_by_id(&conn, track_id) {
-            let track_hash = queries::build_track_hash_str(
-                track.title.as_deref(),
-                track.artist.as_deref(),
-                track.album.as_deref(),
-            );
-            payload["trackHash"] = serde_json::Value::String(track_hash);
-            payload["title"] = serde_json::json!(track.title);
-            payload["artist"] = serde_json::json!(track.artist);
-            payload["album"] = serde_json::json!(track.album);
-            payload["duration"] = serde_json::json!(track.duration);
-            payload["externalId"] = serde_json::json!(track.external_id);
-            payload["sourceType"] = serde_json::json!(track.source_type);
-            payload["coverUrl"] = serde_json::json!(track.cover_url);
+    ensure_not_smart(&conn, playlist_id)?;
+    if !allow_duplicate.unwrap_or(false) {
+        if let Some(entry_id) =
+            queries::find_playlist_entry(&conn, playlist_id, track_id).map_err(|e| e.to_string())?
+        {
+            return Ok(entry_id);
         }
-        let _ = queries::enqueue_sync_change(
-            &conn,
-            "playlist_track",
-            &format!("local_{}_{}", playlist_id, track_id),
-            "create",
-            Some(&payload.to_string()),
-        );
     }
+    let entry_id =
+        queries::add_track_to_playlist(&conn, playlist_id, track_id).map_err(|e| e.to_string())?;
 
-    Ok(())
+    enqueue_playlist_track_added(&conn, entry_id);
+    write_back(&conn, playlist_id);
+
+    Ok(entry_id)
 }
 
+/// Remove one entry from a playlist; other entries of the same track stay.
 #[tauri::command]
 pub async fn remove_track_from_playlist(
     playlist_id: i64,
-    track_id: i64,
+    entry_id: i64,
     db: State<'_, Database>,
 ) -> Result<(), String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    queries::remove_track_from_playlist(&conn, playlist_id, track_id).map_err(|e| e.to_string())?;
+    ensure_not_smart(&conn, playlist_id)?;
+    let (track_id, position) =
+        match queries::get_playlist_entry(&conn, entry_id).map_err(|e| e.to_string())? {
+            Some((entry_playlist, track_id, position)) if entry_playlist == playlist_id => {
+                (track_id, position)
+            }
+            _ => return Err(format!("Entry {} is not in this playlist", entry_id)),
+        };
+    queries::remove_playlist_entry(&conn, entry_id).map_err(|e| e.to_string())?;
 
     // Enqueue sync change
     if queries::is_logged_in(&conn) {
-        let payload = serde_json::json!({
-            "playlistId": format!("local_{}", playlist_id),
-        })
-        .to_string();
+        let payload = playlist_entry_payload(&conn, playlist_id, track_id, position);
         let _ = queries::enqueue_sync_change(
             &conn,
             "playlist_track",
-            &format!("local_{}_{}", playlist_id, track_id),
+            &format!("local_entry_{}", entry_id),
             "delete",
-            Some(&payload),
+            Some(&payload.to_string()),
         );
     }
+    write_back(&conn, playlist_id);
 
     Ok(())
 }
@@ -183,97 +338,425 @@ pub async fn update_playlist_cover(
     Ok(())
 }
 
-/// Reorder tracks in a playlist by moving a track from one position to another
-
-/// from_index - The current index of the track to move (0-based)
-/// to_index - The target index where the track should be moved (0-based)
+/// Move one entry of a playlist to `to_index` (0-based, in the order
+/// after the move). Normally only the moved entry's position changes.
 #[tauri::command]
 pub async fn reorder_playlist_tracks(
     playlist_id: i64,
-    from_index: i64,
+    entry_id: i64,
     to_index: i64,
     db: State<'_, Database>,
 ) -> Result<(), String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    ensure_not_smart(&conn, playlist_id)?;
+    if to_index < 0 {
+        return Err(format!("Invalid to_index: {}", to_index));
+    }
 
-    // Get all tracks in the playlist ordered by position
-    let mut stmt = conn
-        .prepare(
-            "SELECT track_id, position FROM playlist_tracks 
-         WHERE playlist_id = ?1 
-         ORDER BY position",
-        )
-        .map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let changed = queries::move_playlist_entry(&tx, playlist_id, entry_id, to_index as usize)
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                format!("Entry {} is not in this playlist", entry_id)
+            }
+            e => e.to_string(),
+        })?;
+    tx.commit().map_err(|e| e.to_string())?;
 
-    let tracks: Vec<(i64, i64)> = stmt
-        .query_map(params![playlist_id], |row| Ok((row.get(0)?, row.get(1)?)))
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
+    // Enqueue sync changes for the moved entry (every entry if the playlist
+    // had to be renumbered)
+    if queries::is_logged_in(&conn) {
+        for (entry_id, position) in changed {
+            let payload = serde_json::json!({
+                "playlistId": format!("local_{}", playlist_id),
+                "position": position,
+            })
+            .to_string();
+            let _ = queries::enqueue_sync_change(
+                &conn,
+                "playlist_track",
+                &format!("local_entry_{}", entry_id),
+                "update",
+                Some(&payload),
+            );
+        }
+    }
+    write_back(&conn, playlist_id);
+
+    Ok(())
+}
+
+/// A playlist file entry that matched no library track.
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingPlaylistEntry {
+    /// 0-based position in the file
+    pub index: usize,
+    pub location: String,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub duration: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistImportReport {
+    pub playlist_id: i64,
+    pub name: String,
+    pub total: usize,
+    /// Entries whose file is in the library
+    pub matched_by_path: usize,
+    /// Entries matched on artist, title and duration instead
+    pub matched_by_tags: usize,
+    pub missing: Vec<MissingPlaylistEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistExportReport {
+    pub path: String,
+    pub written: usize,
+    /// Tracks without a local file (streams, synced placeholders)
+    pub skipped: usize,
+}
+
+/// The library track for one playlist file entry, and whether it was
+/// found by path.
+pub(crate) fn match_playlist_entry(
+    conn: &rusqlite::Connection,
+    entry: &PlaylistEntry,
+    base_dir: &Path,
+    format: PlaylistFormat,
+) -> Result<Option<(i64, bool)>, String> {
+    if let Some(path) = playlist_io::resolve_location(&entry.location, base_dir, format.uses_uris())
+    {
+        if let Some(id) = queries::get_track_id_by_path(conn, &path.to_string_lossy())
+            .map_err(|e| e.to_string())?
+        {
+            return Ok(Some((id, true)));
+        }
+    }
+    let Some(title) = entry.title.as_deref() else {
+        return Ok(None);
+    };
+    let id = queries::find_track_by_tags(conn, entry.artist.as_deref(), title, entry.duration)
         .map_err(|e| e.to_string())?;
+    Ok(id.map(|id| (id, false)))
+}
 
-    if tracks.is_empty() {
-        return Err("Playlist is empty".to_string());
+/// Create a playlist from an M3U/M3U8, PLS or XSPF file. Entries are
+/// matched by path first, then by artist, title and duration; the rest are
+/// reported as missing.
+#[tauri::command]
+pub async fn import_playlist(
+    path: String,
+    name: Option<String>,
+    db: State<'_, Database>,
+) -> Result<PlaylistImportReport, String> {
+    let file_path = PathBuf::from(&path);
+    let format = PlaylistFormat::from_path(&file_path)
+        .ok_or_else(|| format!("Unsupported playlist file: {}", path))?;
+    let bytes = std::fs::read(&file_path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let parsed = playlist_io::parse(format, &bytes)?;
+    let base_dir = file_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let name = name
+        .or(parsed.name.clone())
+        .filter(|n| !n.trim().is_empty())
+        .or_else(|| {
+            file_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| "Imported Playlist".to_string());
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut track_ids = Vec::new();
+    let mut matched_by_path = 0;
+    let mut matched_by_tags = 0;
+    let mut missing = Vec::new();
+    for (index, entry) in parsed.entries.iter().enumerate() {
+        match match_playlist_entry(&conn, entry, &base_dir, format)? {
+            Some((id, by_path)) => {
+                if by_path {
+                    matched_by_path += 1;
+                } else {
+                    matched_by_tags += 1;
+                }
+                track_ids.push(id);
+            }
+            None => missing.push(MissingPlaylistEntry {
+                index,
+                location: entry.location.clone(),
+                artist: entry.artist.clone(),
+                title: entry.title.clone(),
+                duration: entry.duration,
+            }),
+        }
     }
 
-    // Validate indices
-    let max_index = tracks.len() as i64 - 1;
-    if from_index < 0 || from_index > max_index {
-        return Err(format!("Invalid from_index: {}", from_index));
+    let playlist_id = queries::create_playlist(&conn, &name).map_err(|e| e.to_string())?;
+    if queries::is_logged_in(&conn) {
+        let payload = serde_json::json!({ "name": name }).to_string();
+        let _ = queries::enqueue_sync_change(
+            &conn,
+            "playlist",
+            &format!("local_{}", playlist_id),
+            "create",
+            Some(&payload),
+        );
     }
-    if to_index < 0 || to_index > max_index {
-        return Err(format!("Invalid to_index: {}", to_index));
+    for track_id in track_ids {
+        let entry_id = queries::add_track_to_playlist(&conn, playlist_id, track_id)
+            .map_err(|e| e.to_string())?;
+        enqueue_playlist_track_added(&conn, entry_id);
     }
 
-    // If indices are the same, no reordering needed
-    if from_index == to_index {
-        return Ok(());
+    log::info!(
+        "[Playlist] Imported {:?}: {} of {} entries matched",
+        path,
+        matched_by_path + matched_by_tags,
+        parsed.entries.len()
+    );
+    Ok(PlaylistImportReport {
+        playlist_id,
+        name,
+        total: parsed.entries.len(),
+        matched_by_path,
+        matched_by_tags,
+        missing,
+    })
+}
+
+/// Playlist file entries for the tracks with a local file; streams and
+/// synced placeholders are left out.
+pub(crate) fn export_entries(
+    tracks: Vec<queries::Track>,
+    base_dir: &Path,
+    path_mode: PathMode,
+    format: PlaylistFormat,
+) -> Vec<PlaylistEntry> {
+    tracks
+        .into_iter()
+        .filter(|t| matches!(t.source_type.as_deref(), None | Some("local")))
+        .map(|t| PlaylistEntry {
+            location: playlist_io::export_location(Path::new(&t.path), base_dir, path_mode, format),
+            artist: t.artist,
+            title: t.title,
+            duration: t.duration,
+        })
+        .collect()
+}
+
+/// Write a playlist (smart playlists as they currently stand) to `path`.
+/// The format follows the file extension unless given.
+#[tauri::command]
+pub async fn export_playlist(
+    playlist_id: i64,
+    path: String,
+    format: Option<PlaylistFormat>,
+    path_mode: Option<PathMode>,
+    db: State<'_, Database>,
+) -> Result<PlaylistExportReport, String> {
+    let file_path = PathBuf::from(&path);
+    let format = format
+        .or_else(|| PlaylistFormat::from_path(&file_path))
+        .ok_or_else(|| format!("Unsupported playlist file: {}", path))?;
+    let path_mode = path_mode.unwrap_or_default();
+    let base_dir = file_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let (name, tracks) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let name = queries::get_all_playlists(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|p| p.id == playlist_id)
+            .map(|p| p.name)
+            .ok_or_else(|| format!("Playlist {} not found", playlist_id))?;
+        let tracks = queries::get_playlist_tracks(&conn, playlist_id).map_err(|e| e.to_string())?;
+        (name, tracks)
+    };
+
+    let total = tracks.len();
+    let entries = export_entries(tracks, &base_dir, path_mode, format);
+    let written = entries.len();
+    let playlist = PlaylistFile {
+        name: Some(name),
+        entries,
+    };
+
+    std::fs::write(&file_path, playlist_io::write(format, &playlist))
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    log::info!("[Playlist] Exported playlist {} to {:?}", playlist_id, path);
+    Ok(PlaylistExportReport {
+        path,
+        written,
+        skipped: total - written,
+    })
+}
+
+#[tauri::command]
+pub async fn get_playlist_folders(
+    db: State<'_, Database>,
+) -> Result<Vec<queries::PlaylistFolder>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_playlist_folders(&conn).map_err(|e| e.to_string())
+}
+
+fn folder_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Folder name can't be empty".to_string());
     }
+    Ok(name)
+}
 
-    // Create a new ordered list of track IDs
-    let mut track_ids: Vec<i64> = tracks.iter().map(|(id, _)| *id).collect();
+fn ensure_folder_exists(conn: &rusqlite::Connection, folder_id: i64) -> Result<(), String> {
+    match queries::get_playlist_folder(conn, folder_id).map_err(|e| e.to_string())? {
+        Some(_) => Ok(()),
+        None => Err(format!("Playlist folder {} not found", folder_id)),
+    }
+}
 
-    // Remove the track from its current position
-    let moved_track_id = track_ids.remove(from_index as usize);
+/// Enqueue the sync change for a folder, with its current name and place.
+fn enqueue_playlist_folder_change(conn: &rusqlite::Connection, folder_id: i64, operation: &str) {
+    if !queries::is_logged_in(conn) {
+        return;
+    }
+    let payload = match queries::get_playlist_folder(conn, folder_id) {
+        Ok(Some(folder)) => Some(
+            serde_json::json!({
+                "name": folder.name,
+                "parentId": folder.parent_id.map(|id| format!("local_{}", id)),
+                "position": folder.position,
+            })
+            .to_string(),
+        ),
+        _ => None,
+    };
+    let _ = queries::enqueue_sync_change(
+        conn,
+        "playlist_folder",
+        &format!("local_{}", folder_id),
+        operation,
+        payload.as_deref(),
+    );
+}
 
-    // Insert it at the new position
-    track_ids.insert(to_index as usize, moved_track_id);
+/// Create a folder at the end of `parent_id` (the top level when `None`).
+#[tauri::command]
+pub async fn create_playlist_folder(
+    name: String,
+    parent_id: Option<i64>,
+    db: State<'_, Database>,
+) -> Result<i64, String> {
+    let name = folder_name(&name)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    if let Some(parent_id) = parent_id {
+        ensure_folder_exists(&conn, parent_id)?;
+    }
+    let id =
+        queries::create_playlist_folder(&conn, name, parent_id, None).map_err(|e| e.to_string())?;
+    enqueue_playlist_folder_change(&conn, id, "create");
+    Ok(id)
+}
 
-    // Update all positions in the database
-    // Use a transaction to ensure atomicity
-    conn.execute("BEGIN TRANSACTION", [])
+#[tauri::command]
+pub async fn rename_playlist_folder(
+    folder_id: i64,
+    name: String,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let name = folder_name(&name)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    if !queries::rename_playlist_folder(&conn, folder_id, name).map_err(|e| e.to_string())? {
+        return Err(format!("Playlist folder {} not found", folder_id));
+    }
+    enqueue_playlist_folder_change(&conn, folder_id, "update");
+    Ok(())
+}
+
+/// Move a folder (with everything in it) under `parent_id`, at `index`
+/// among its new siblings or at the end.
+#[tauri::command]
+pub async fn move_playlist_folder(
+    folder_id: i64,
+    parent_id: Option<i64>,
+    index: Option<usize>,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    ensure_folder_exists(&conn, folder_id)?;
+    if let Some(parent_id) = parent_id {
+        ensure_folder_exists(&conn, parent_id)?;
+        if queries::playlist_folder_is_within(&conn, parent_id, folder_id)
+            .map_err(|e| e.to_string())?
+        {
+            return Err("A folder can't be moved into itself".to_string());
+        }
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let changed = queries::move_playlist_folder(&tx, folder_id, parent_id, index)
         .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
 
-    for (new_position, track_id) in track_ids.iter().enumerate() {
-        conn.execute(
-            "UPDATE playlist_tracks 
-             SET position = ?1 
-             WHERE playlist_id = ?2 AND track_id = ?3",
-            params![new_position as i64, playlist_id, track_id],
-        )
-        .map_err(|e| {
-            // Rollback on error
-            let _ = conn.execute("ROLLBACK", []);
-            e.to_string()
-        })?;
+    for (id, _) in changed {
+        enqueue_playlist_folder_change(&conn, id, "update");
     }
+    Ok(())
+}
 
-    conn.execute("COMMIT", []).map_err(|e| e.to_string())?;
+/// Delete a folder; its subfolders and playlists move up to its parent.
+#[tauri::command]
+pub async fn delete_playlist_folder(folder_id: i64, db: State<'_, Database>) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    ensure_folder_exists(&conn, folder_id)?;
+    queries::delete_playlist_folder(&conn, folder_id).map_err(|e| e.to_string())?;
+    enqueue_playlist_folder_change(&conn, folder_id, "delete");
+    Ok(())
+}
+
+/// Move a playlist into `folder_id` (the top level when `None`), at `index`
+/// among the folder's playlists or at the end.
+#[tauri::command]
+pub async fn move_playlist(
+    playlist_id: i64,
+    folder_id: Option<i64>,
+    index: Option<usize>,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    if let Some(folder_id) = folder_id {
+        ensure_folder_exists(&conn, folder_id)?;
+    }
 
-    // Enqueue sync change for the reorder
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let changed =
+        queries::move_playlist(&tx, playlist_id, folder_id, index).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Playlist {} not found", playlist_id),
+            e => e.to_string(),
+        })?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    // Enqueue sync changes (every playlist in the folder if it was renumbered)
     if queries::is_logged_in(&conn) {
-        let payload = serde_json::json!({
-            "playlistId": format!("local_{}", playlist_id),
-            "fromIndex": from_index,
-            "toIndex": to_index,
-        })
-        .to_string();
-        let _ = queries::enqueue_sync_change(
-            &conn,
-            "playlist_track",
-            &format!("local_{}_reorder", playlist_id),
-            "update",
-            Some(&payload),
-        );
+        for (id, position) in changed {
+            let payload = serde_json::json!({
+                "folderId": folder_id.map(|id| format!("local_{}", id)),
+                "position": position,
+            })
+            .to_string();
+            let _ = queries::enqueue_sync_change(
+                &conn,
+                "playlist",
+                &format!("local_{}", id),
+                "update",
+                Some(&payload),
+            );
+        }
     }
 
     Ok(())
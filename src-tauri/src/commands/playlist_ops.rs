@@ -0,0 +1,207 @@
+// Bulk playlist commands: sort, dedupe, prune, merge, split and shuffle.
+// Each runs in one transaction and enqueues a single sync change.
+use super::folder_playlist::write_back;
+use super::playlist::{enqueue_playlist_snapshot, ensure_not_smart};
+use crate::db::{queries, Database};
+use crate::playlist_ops::{self, DedupeBy, PlaylistItem, PlaylistSortKey, SplitBy};
+use std::path::Path;
+use tauri::State;
+
+fn entry_ids(items: &[PlaylistItem]) -> Vec<i64> {
+    items.iter().map(|item| item.entry_id).collect()
+}
+
+/// Sort a playlist by one or more keys, each ascending or descending.
+#[tauri::command]
+pub async fn sort_playlist(
+    playlist_id: i64,
+    keys: Vec<PlaylistSortKey>,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    if keys.is_empty() {
+        return Err("No sort keys given".to_string());
+    }
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    ensure_not_smart(&conn, playlist_id)?;
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let mut items = queries::get_playlist_items(&tx, playlist_id).map_err(|e| e.to_string())?;
+    playlist_ops::sort(&mut items, &keys);
+    queries::set_playlist_entry_order(&tx, &entry_ids(&items)).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    enqueue_playlist_snapshot(&conn, playlist_id, &[playlist_id]);
+    write_back(&conn, playlist_id);
+    Ok(())
+}
+
+/// Shuffle a playlist and keep the new order.
+#[tauri::command]
+pub async fn shuffle_playlist(playlist_id: i64, db: State<'_, Database>) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    ensure_not_smart(&conn, playlist_id)?;
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let mut ids =
+        entry_ids(&queries::get_playlist_items(&tx, playlist_id).map_err(|e| e.to_string())?);
+    playlist_ops::shuffle(&mut ids, uuid::Uuid::new_v4().as_u128() as u64);
+    queries::set_playlist_entry_order(&tx, &ids).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    enqueue_playlist_snapshot(&conn, playlist_id, &[playlist_id]);
+    write_back(&conn, playlist_id);
+    Ok(())
+}
+
+/// Remove repeated entries, keeping the first of each. Returns how many
+/// were removed.
+#[tauri::command]
+pub async fn dedupe_playlist(
+    playlist_id: i64,
+    by: Option<DedupeBy>,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    ensure_not_smart(&conn, playlist_id)?;
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let items = queries::get_playlist_items(&tx, playlist_id).map_err(|e| e.to_string())?;
+    let duplicates = playlist_ops::duplicates(&items, by.unwrap_or_default());
+    let removed = queries::remove_playlist_entries(&tx, &duplicates).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    if removed > 0 {
+        enqueue_playlist_snapshot(&conn, playlist_id, &[playlist_id]);
+        write_back(&conn, playlist_id);
+    }
+    Ok(removed)
+}
+
+/// Remove entries whose file no longer exists. Streams are never missing.
+/// Returns how many were removed.
+#[tauri::command]
+pub async fn remove_missing_from_playlist(
+    playlist_id: i64,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    let items = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        ensure_not_smart(&conn, playlist_id)?;
+        queries::get_playlist_items(&conn, playlist_id).map_err(|e| e.to_string())?
+    };
+
+    // Checking each file can be slow on network drives, so do it unlocked
+    let missing: Vec<i64> = items
+        .iter()
+        .filter(|item| item.is_local() && !Path::new(&item.path).exists())
+        .map(|item| item.entry_id)
+        .collect();
+    if missing.is_empty() {
+        return Ok(0);
+    }
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let removed = queries::remove_playlist_entries(&tx, &missing).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    if removed > 0 {
+        log::info!(
+            "[Playlist] Removed {} missing files from playlist {}",
+            removed,
+            playlist_id
+        );
+        enqueue_playlist_snapshot(&conn, playlist_id, &[playlist_id]);
+        write_back(&conn, playlist_id);
+    }
+    Ok(removed)
+}
+
+/// Create a playlist with the tracks of `playlist_ids`, one playlist after
+/// another; smart playlists contribute what they currently match. With
+/// `dedupe`, repeats are dropped. Returns the new playlist's id.
+#[tauri::command]
+pub async fn merge_playlists(
+    playlist_ids: Vec<i64>,
+    name: String,
+    dedupe: Option<DedupeBy>,
+    db: State<'_, Database>,
+) -> Result<i64, String> {
+    if playlist_ids.len() < 2 {
+        return Err("Select at least two playlists to merge".to_string());
+    }
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    for playlist_id in &playlist_ids {
+        if !queries::playlist_exists(&conn, *playlist_id).map_err(|e| e.to_string())? {
+            return Err(format!("Playlist {} not found", playlist_id));
+        }
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let merged_id = queries::create_playlist(&tx, &name).map_err(|e| e.to_string())?;
+    for playlist_id in &playlist_ids {
+        if queries::is_smart_playlist(&tx, *playlist_id).map_err(|e| e.to_string())? {
+            let tracks =
+                queries::get_playlist_tracks(&tx, *playlist_id).map_err(|e| e.to_string())?;
+            for track in tracks {
+                queries::add_track_to_playlist(&tx, merged_id, track.id)
+                    .map_err(|e| e.to_string())?;
+            }
+        } else {
+            let items =
+                queries::get_playlist_items(&tx, *playlist_id).map_err(|e| e.to_string())?;
+            queries::copy_playlist_entries(&tx, merged_id, &entry_ids(&items))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    if let Some(by) = dedupe {
+        let items = queries::get_playlist_items(&tx, merged_id).map_err(|e| e.to_string())?;
+        queries::remove_playlist_entries(&tx, &playlist_ops::duplicates(&items, by))
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    enqueue_playlist_snapshot(&conn, merged_id, &[merged_id]);
+    Ok(merged_id)
+}
+
+/// Split a playlist into new ones (one per artist or album, or chunks of a
+/// fixed size), placed in the same folder. The original is left as it is.
+/// Returns the new playlists' ids.
+#[tauri::command]
+pub async fn split_playlist(
+    playlist_id: i64,
+    by: SplitBy,
+    db: State<'_, Database>,
+) -> Result<Vec<i64>, String> {
+    if by == SplitBy::Size(0) {
+        return Err("Split size must be at least 1".to_string());
+    }
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    ensure_not_smart(&conn, playlist_id)?;
+    let source = queries::get_all_playlists(&conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|p| p.id == playlist_id)
+        .ok_or_else(|| format!("Playlist {} not found", playlist_id))?;
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let items = queries::get_playlist_items(&tx, playlist_id).map_err(|e| e.to_string())?;
+    let mut new_ids = Vec::new();
+    for group in playlist_ops::split(&items, by) {
+        let name = match by {
+            SplitBy::Size(_) => format!("{} ({})", source.name, group.label),
+            _ => format!("{} - {}", source.name, group.label),
+        };
+        let id = queries::create_playlist(&tx, &name).map_err(|e| e.to_string())?;
+        queries::set_playlist_folder(&tx, id, source.folder_id, None).map_err(|e| e.to_string())?;
+        queries::copy_playlist_entries(&tx, id, &group.entry_ids).map_err(|e| e.to_string())?;
+        new_ids.push(id);
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    if !new_ids.is_empty() {
+        enqueue_playlist_snapshot(&conn, playlist_id, &new_ids);
+    }
+    Ok(new_ids)
+}
@@ -0,0 +1,249 @@
+// Internet radio Tauri commands (stations, playback, song history)
+use crate::audio::{AudioEvent, PlaybackStateSync};
+use crate::db::{queries, Database};
+use crate::radio::{self, stations};
+use serde::Serialize;
+use std::path::Path;
+use symphonia::core::io::ReadOnlySource;
+use tauri::State;
+
+/// Returned when playback of a station starts.
+#[derive(Debug, Clone, Serialize)]
+pub struct RadioPlaybackInfo {
+    pub station_id: i64,
+    pub stream_url: String,
+    pub content_type: Option<String>,
+    pub icy_name: Option<String>,
+    pub icy_genre: Option<String>,
+    pub bitrate: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RadioImportResult {
+    pub added: usize,
+    pub updated: usize,
+}
+
+fn validate_station_url(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    if !stations::is_stream_url(url) {
+        return Err(format!("Only http(s) stream URLs are supported: {}", url));
+    }
+    url::Url::parse(url).map_err(|e| format!("Invalid station URL: {}", e))?;
+    Ok(url.to_string())
+}
+
+#[tauri::command]
+pub async fn get_radio_stations(
+    db: State<'_, Database>,
+) -> Result<Vec<queries::RadioStation>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_radio_stations(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_radio_station(
+    name: String,
+    url: String,
+    genre: Option<String>,
+    homepage: Option<String>,
+    favicon_url: Option<String>,
+    db: State<'_, Database>,
+) -> Result<i64, String> {
+    let url = validate_station_url(&url)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let (id, _) = queries::upsert_radio_station(
+        &conn,
+        name.trim(),
+        &url,
+        genre.as_deref(),
+        homepage.as_deref(),
+        favicon_url.as_deref(),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn update_radio_station(
+    station_id: i64,
+    name: String,
+    url: String,
+    genre: Option<String>,
+    homepage: Option<String>,
+    favicon_url: Option<String>,
+    db: State<'_, Database>,
+) -> Result<bool, String> {
+    let url = validate_station_url(&url)?;
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::update_radio_station(
+        &conn,
+        station_id,
+        name.trim(),
+        &url,
+        genre.as_deref(),
+        homepage.as_deref(),
+        favicon_url.as_deref(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_radio_station(station_id: i64, db: State<'_, Database>) -> Result<bool, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::delete_radio_station(&conn, station_id).map_err(|e| e.to_string())
+}
+
+/// Connect to a station and hand the stream to the native audio engine.
+/// ICY title changes are recorded in the station's song history and pushed
+/// to the frontend as `AudioEvent::StreamTitle`.
+#[tauri::command]
+pub async fn radio_play_station(
+    station_id: i64,
+    db: State<'_, Database>,
+    audio: State<'_, PlaybackStateSync>,
+) -> Result<RadioPlaybackInfo, String> {
+    let station = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::get_radio_station(&conn, station_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Radio station {} not found", station_id))?
+    };
+
+    let db_clone = db.inner().clone();
+    let sink = audio.event_sink();
+    let url = station.url.clone();
+
+    let (reader, info) = tauri::async_runtime::spawn_blocking(move || {
+        radio::connect(&url, move |now_playing| {
+            if let Ok(conn) = db_clone.conn.lock() {
+                if let Err(e) = queries::record_radio_song(
+                    &conn,
+                    station_id,
+                    &now_playing.raw,
+                    now_playing.artist.as_deref(),
+                    Some(&now_playing.title),
+                ) {
+                    tracing::warn!("[RADIO] Failed to record song history: {}", e);
+                }
+            }
+            sink.push(AudioEvent::StreamTitle {
+                station_id,
+                raw: now_playing.raw,
+                artist: now_playing.artist,
+                title: now_playing.title,
+            });
+        })
+    })
+    .await
+    .map_err(|e| format!("Radio connect task failed: {}", e))??;
+
+    audio.play_stream(
+        station.url.clone(),
+        Box::new(ReadOnlySource::new(reader)),
+        info.extension_hint().map(str::to_string),
+    )?;
+
+    {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let _ = queries::mark_radio_station_played(&conn, station_id);
+    }
+
+    Ok(RadioPlaybackInfo {
+        station_id,
+        stream_url: info.url,
+        content_type: info.content_type,
+        icy_name: info.icy_name,
+        icy_genre: info.icy_genre,
+        bitrate: info.bitrate,
+    })
+}
+
+#[tauri::command]
+pub async fn get_radio_song_history(
+    station_id: i64,
+    limit: Option<i32>,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::RadioSongPlay>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_radio_song_history(&conn, station_id, limit.unwrap_or(100))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn clear_radio_song_history(
+    station_id: i64,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::clear_radio_song_history(&conn, station_id).map_err(|e| e.to_string())
+}
+
+/// Import stations from a .pls or .m3u/.m3u8 file. Existing stations
+/// (matched by URL) get their name refreshed.
+#[tauri::command]
+pub async fn import_radio_stations(
+    path: String,
+    db: State<'_, Database>,
+) -> Result<RadioImportResult, String> {
+    let content = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let content = String::from_utf8_lossy(&content);
+    let entries = stations::parse_station_list(&content);
+    if entries.is_empty() {
+        return Err("No stream URLs found in station list".to_string());
+    }
+
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut result = RadioImportResult { added: 0, updated: 0 };
+    for entry in &entries {
+        let name = entry.name.as_deref().unwrap_or(&entry.url);
+        let (_, created) = queries::upsert_radio_station(&tx, name, &entry.url, None, None, None)
+            .map_err(|e| e.to_string())?;
+        if created {
+            result.added += 1;
+        } else {
+            result.updated += 1;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    tracing::info!(
+        "[RADIO] Imported {} stations from {} ({} new)",
+        entries.len(),
+        path,
+        result.added
+    );
+    Ok(result)
+}
+
+/// Export all stations. The format follows the file extension (.pls, .m3u,
+/// .m3u8) unless `format` is given explicitly.
+#[tauri::command]
+pub async fn export_radio_stations(
+    path: String,
+    format: Option<String>,
+    db: State<'_, Database>,
+) -> Result<usize, String> {
+    let list_format = format
+        .as_deref()
+        .or_else(|| Path::new(&path).extension().and_then(|e| e.to_str()))
+        .and_then(stations::StationListFormat::from_extension)
+        .ok_or_else(|| "Unknown station list format (use pls, m3u or m3u8)".to_string())?;
+
+    let entries: Vec<stations::StationEntry> = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        queries::get_radio_stations(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|s| stations::StationEntry {
+                name: Some(s.name),
+                url: s.url,
+            })
+            .collect()
+    };
+
+    std::fs::write(&path, stations::write_station_list(&entries, list_format))
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(entries.len())
+}
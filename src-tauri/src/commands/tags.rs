@@ -0,0 +1,314 @@
+// Tag editor commands: edit file tags for one or many tracks, with undo
+use crate::db::{queries, Database};
+use crate::scanner::{cover_storage, extract_metadata};
+use crate::security;
+use crate::tag_editor::fields::{self, TagValues};
+use crate::tag_editor::{self, CoverChange};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::State;
+
+/// Editable tags of one track, read from its file.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackTags {
+    pub track_id: i64,
+    pub path: String,
+    pub values: TagValues,
+    pub has_cover: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CoverEdit {
+    /// Base64 JPEG, PNG or WebP image
+    Set {
+        data: String,
+    },
+    Remove,
+}
+
+/// One edit applied to every selected track. Fields left out are kept;
+/// a null or empty value clears the field.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TagEdit {
+    #[serde(default)]
+    pub fields: TagValues,
+    #[serde(default)]
+    pub cover: Option<CoverEdit>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagEditFailure {
+    pub track_id: i64,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagEditResult {
+    /// Journal batch for `undo_tag_edit`; `None` when nothing was written
+    pub batch_id: Option<i64>,
+    pub updated: Vec<i64>,
+    pub failed: Vec<TagEditFailure>,
+}
+
+/// Path of a local track whose file may be rewritten.
+pub(crate) fn editable_track_path(conn: &Connection, track_id: i64) -> Result<String, String> {
+    let track = queries::get_track_by_id(conn, track_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Track {} not found", track_id))?;
+    if !matches!(track.source_type.as_deref(), None | Some("local")) {
+        return Err("Only local files can be tagged".to_string());
+    }
+    let path = Path::new(&track.path);
+    if !path.exists() {
+        return Err(format!("File not found: {}", track.path));
+    }
+    if !security::is_safe_path(path)? {
+        return Err(format!(
+            "Security: Cannot edit file outside allowed directories: {:?}",
+            path
+        ));
+    }
+    Ok(track.path)
+}
+
+/// Re-read a rewritten file into its track row, covers included.
+fn refresh_track(conn: &Connection, path: &str, cover: &CoverChange) -> Result<(), String> {
+    let track_data =
+        extract_metadata(path).ok_or_else(|| format!("Failed to re-read tags of {}", path))?;
+    let (track_id, _) =
+        queries::insert_or_update_track(conn, &track_data).map_err(|e| e.to_string())?;
+
+    match &track_data.track_cover {
+        Some(bytes) => {
+            let cover_path = cover_storage::save_track_cover(track_id, bytes)?;
+            queries::update_track_cover_path(conn, track_id, Some(&cover_path))
+                .map_err(|e| e.to_string())?;
+        }
+        None if *cover == CoverChange::Remove => {
+            let old = cover_storage::get_track_cover_file_path(conn, track_id)
+                .map_err(|e| e.to_string())?;
+            cover_storage::delete_track_cover_file(old.as_deref())?;
+            queries::update_track_cover_path(conn, track_id, None).map_err(|e| e.to_string())?;
+        }
+        None => {}
+    }
+
+    let album_id: Option<i64> = conn
+        .query_row(
+            "SELECT album_id FROM tracks WHERE id = ?1",
+            [track_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if let (Some(album_id), Some(art_bytes)) = (album_id, &track_data.album_art) {
+        // A cover set on purpose replaces the album's art; otherwise only
+        // fill it in when the album (possibly a new one) has none
+        let has_art: bool = conn
+            .query_row(
+                "SELECT art_path IS NOT NULL FROM albums WHERE id = ?1",
+                [album_id],
+                |row| row.get(0),
+            )
+            .unwrap_or(false);
+        if matches!(cover, CoverChange::Set(_)) || !has_art {
+            let art_path = cover_storage::save_album_art(album_id, art_bytes)?;
+            queries::update_album_art_path(conn, album_id, Some(&art_path))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the edit, journal the track's previous values for the edited
+/// fields and refresh the database row. The journal entry is only added
+/// once the file was written, so a failed write leaves nothing to undo.
+fn edit_track(
+    conn: &Connection,
+    batch_id: i64,
+    track_id: i64,
+    values: &TagValues,
+    cover: &CoverChange,
+) -> Result<(), String> {
+    let path = editable_track_path(conn, track_id)?;
+    let current = tag_editor::read_file_tags(Path::new(&path))?;
+
+    let entry = queries::TagEditJournalEntry {
+        track_id,
+        path: path.clone(),
+        previous_values: values
+            .keys()
+            .map(|field| (*field, current.values.get(field).cloned().flatten()))
+            .collect(),
+        cover_changed: *cover != CoverChange::Keep,
+        previous_cover: if *cover != CoverChange::Keep {
+            current.cover
+        } else {
+            None
+        },
+    };
+
+    log::info!("[AUDIT] Writing tags: {:?}", path);
+    tag_editor::write_file_tags(Path::new(&path), values, cover)?;
+    queries::add_tag_edit_entry(conn, batch_id, &entry).map_err(|e| e.to_string())?;
+    refresh_track(conn, &path, cover)
+}
+
+/// Apply per-track edits as one journaled batch. Tracks that fail are
+/// reported and skipped; the batch is dropped if none succeeded.
+pub(crate) fn run_tag_edit_batch(
+    conn: &Connection,
+    description: Option<&str>,
+    edits: impl IntoIterator<Item = (i64, TagValues)>,
+    cover: &CoverChange,
+) -> Result<TagEditResult, String> {
+    let batch_id = queries::create_tag_edit_batch(conn, description).map_err(|e| e.to_string())?;
+
+    let mut updated = Vec::new();
+    let mut failed = Vec::new();
+    for (track_id, values) in edits {
+        match edit_track(conn, batch_id, track_id, &values, cover) {
+            Ok(()) => updated.push(track_id),
+            Err(error) => {
+                log::warn!("[TagEditor] Track {}: {}", track_id, error);
+                failed.push(TagEditFailure { track_id, error });
+            }
+        }
+    }
+
+    let _ = queries::cleanup_empty_albums(conn);
+    let batch_id = if updated.is_empty() {
+        queries::delete_tag_edit_batch(conn, batch_id).map_err(|e| e.to_string())?;
+        None
+    } else {
+        Some(batch_id)
+    };
+
+    Ok(TagEditResult {
+        batch_id,
+        updated,
+        failed,
+    })
+}
+
+#[tauri::command]
+pub async fn get_track_tags(track_id: i64, db: State<'_, Database>) -> Result<TrackTags, String> {
+    let path = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        editable_track_path(&conn, track_id)?
+    };
+    let tags = tag_editor::read_file_tags(Path::new(&path))?;
+    Ok(TrackTags {
+        track_id,
+        path,
+        values: tags.values,
+        has_cover: tags.cover.is_some(),
+    })
+}
+
+/// Write one edit to the files of `track_ids` and update their rows.
+/// Tracks that fail are reported and skipped; the rest form one batch that
+/// `undo_tag_edit` reverts.
+#[tauri::command]
+pub async fn edit_tags(
+    track_ids: Vec<i64>,
+    edit: TagEdit,
+    db: State<'_, Database>,
+) -> Result<TagEditResult, String> {
+    let values = fields::normalize_values(&edit.fields)?;
+    let cover = match edit.cover {
+        None => CoverChange::Keep,
+        Some(CoverEdit::Remove) => CoverChange::Remove,
+        Some(CoverEdit::Set { data }) => CoverChange::Set(
+            STANDARD
+                .decode(data.trim())
+                .map_err(|e| format!("Failed to decode base64: {}", e))?,
+        ),
+    };
+    if values.is_empty() && cover == CoverChange::Keep {
+        return Err("Nothing to edit".to_string());
+    }
+
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let edits = track_ids.into_iter().map(|id| (id, values.clone()));
+        run_tag_edit_batch(&conn, edit.description.as_deref(), edits, &cover)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_tag_edit_history(
+    limit: Option<i64>,
+    db: State<'_, Database>,
+) -> Result<Vec<queries::TagEditBatch>, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    queries::get_tag_edit_batches(&conn, limit.unwrap_or(50)).map_err(|e| e.to_string())
+}
+
+/// Write a batch's journaled values back to its files. Later batches that
+/// touched the same tracks must be undone first. The batch is only marked
+/// undone once every file was restored, so a partial undo can be retried.
+#[tauri::command]
+pub async fn undo_tag_edit(
+    batch_id: i64,
+    db: State<'_, Database>,
+) -> Result<TagEditResult, String> {
+    let db = db.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let batch = queries::get_tag_edit_batch(&conn, batch_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Tag edit {} not found", batch_id))?;
+        if batch.undone_at.is_some() {
+            return Err("This tag edit was already undone".to_string());
+        }
+        if queries::tag_edit_batch_superseded(&conn, batch_id).map_err(|e| e.to_string())? {
+            return Err("Undo the later edits to these tracks first".to_string());
+        }
+
+        let mut updated = Vec::new();
+        let mut failed = Vec::new();
+        for entry in queries::get_tag_edit_entries(&conn, batch_id).map_err(|e| e.to_string())? {
+            let cover = match (entry.cover_changed, entry.previous_cover) {
+                (false, _) => CoverChange::Keep,
+                (true, Some(data)) => CoverChange::Set(data),
+                (true, None) => CoverChange::Remove,
+            };
+            let result = editable_track_path(&conn, entry.track_id).and_then(|path| {
+                log::info!("[AUDIT] Restoring tags: {:?}", path);
+                tag_editor::write_file_tags(Path::new(&path), &entry.previous_values, &cover)?;
+                refresh_track(&conn, &path, &cover)
+            });
+            match result {
+                Ok(()) => updated.push(entry.track_id),
+                Err(error) => {
+                    log::warn!("[TagEditor] Undo track {}: {}", entry.track_id, error);
+                    failed.push(TagEditFailure {
+                        track_id: entry.track_id,
+                        error,
+                    });
+                }
+            }
+        }
+
+        let _ = queries::cleanup_empty_albums(&conn);
+        if failed.is_empty() {
+            queries::mark_tag_edit_batch_undone(&conn, batch_id).map_err(|e| e.to_string())?;
+        }
+
+        Ok(TagEditResult {
+            batch_id: Some(batch_id),
+            updated,
+            failed,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,29 @@
+// Folder watcher commands
+use tauri::State;
+
+use crate::watcher::{FolderWatcher, WatcherConfig, WatcherStatus};
+
+#[tauri::command]
+pub fn get_folder_watcher_status(watcher: State<'_, FolderWatcher>) -> WatcherStatus {
+    watcher.status()
+}
+
+/// Enable or disable watching; `poll_interval_secs` applies to network mounts.
+#[tauri::command]
+pub fn set_folder_watcher_config(
+    enabled: bool,
+    poll_interval_secs: Option<u64>,
+    watcher: State<'_, FolderWatcher>,
+) -> Result<WatcherStatus, String> {
+    let current = watcher.status();
+    watcher.set_config(WatcherConfig {
+        enabled,
+        poll_interval_secs: poll_interval_secs.unwrap_or(current.poll_interval_secs),
+    })?;
+    Ok(watcher.status())
+}
+
+#[tauri::command]
+pub fn refresh_folder_watcher(watcher: State<'_, FolderWatcher>) {
+    watcher.refresh();
+}
@@ -1,6 +1,9 @@
 // Database module for SQLite operations
+pub mod play_counts;
 pub mod queries;
 pub mod schema;
+pub mod smart_rules;
+pub mod stats;
 
 use rusqlite::Connection;
 use std::path::PathBuf;
@@ -23,6 +26,47 @@ impl Database {
         // Initialize schema
         schema::init_schema(&conn)?;
 
+        // Regroup albums on album artist once, for libraries scanned before
+        // grouping looked past the album name
+        match queries::migrate_album_grouping(&conn) {
+            Ok(Some(result)) => {
+                let copied = crate::scanner::cover_storage::copy_regrouped_album_art(
+                    &conn,
+                    &result.art_to_copy,
+                );
+                log::info!(
+                    "[DB] Regrouped albums: {} -> {} ({} tracks moved, {} covers copied)",
+                    result.albums_before,
+                    result.albums_after,
+                    result.tracks_moved,
+                    copied
+                );
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("[DB] Album regroup migration failed: {}", e),
+        }
+
+        // Split artist strings into the artists tables once
+        match queries::migrate_artist_credits(&conn) {
+            Ok(Some(count)) => log::info!("[DB] Built artist credits for {} tracks", count),
+            Ok(None) => {}
+            Err(e) => log::warn!("[DB] Artist credit migration failed: {}", e),
+        }
+
+        // Promote genre, year and label out of the tag snapshot once
+        match queries::migrate_browse_columns(&conn) {
+            Ok(Some(count)) => log::info!("[DB] Built genre index for {} tracks", count),
+            Ok(None) => {}
+            Err(e) => log::warn!("[DB] Browse column migration failed: {}", e),
+        }
+
+        // Tell skips from completed plays in existing history once
+        match queries::migrate_play_counts(&conn) {
+            Ok(Some(count)) => log::info!("[DB] Backfilled play counts ({} skips found)", count),
+            Ok(None) => {}
+            Err(e) => log::warn!("[DB] Play count backfill failed: {}", e),
+        }
+
         let db = Self {
             conn: Arc::new(Mutex::new(conn)),
         };
@@ -0,0 +1,101 @@
+// Completed plays and skips
+//
+// Every play event lands in play_history with how long it was heard. A play
+// counts once enough of the track was heard; anything shorter is a skip.
+// Tracks carry play_count, skip_count and last_played_at worked out from
+// those rows, so smart playlists and shuffle can sort on them cheaply.
+use serde::{Deserialize, Serialize};
+
+/// When a play counts as completed. The defaults follow the usual scrobbling
+/// rule: half the track, or four minutes, whichever comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SkipThresholds {
+    /// Share of the track, in percent
+    pub min_percent: f64,
+    /// Seconds heard, for long tracks and tracks of unknown length
+    pub min_seconds: i64,
+}
+
+impl Default for SkipThresholds {
+    fn default() -> Self {
+        Self {
+            min_percent: 50.0,
+            min_seconds: 240,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayOutcome {
+    Completed,
+    Skipped,
+}
+
+impl PlayOutcome {
+    pub fn is_skip(self) -> bool {
+        self == PlayOutcome::Skipped
+    }
+}
+
+/// Classify a play of `heard` seconds of a track `track_duration` seconds
+/// long. Zero seconds means the length wasn't recorded (older history and
+/// older sync peers); such plays count as completed.
+pub fn classify(
+    thresholds: &SkipThresholds,
+    heard: i64,
+    track_duration: Option<i64>,
+) -> PlayOutcome {
+    if heard <= 0 || heard >= thresholds.min_seconds {
+        return PlayOutcome::Completed;
+    }
+    match track_duration.filter(|d| *d > 0) {
+        Some(duration) if heard as f64 * 100.0 >= thresholds.min_percent * duration as f64 => {
+            PlayOutcome::Completed
+        }
+        _ => PlayOutcome::Skipped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        use PlayOutcome::*;
+        let t = SkipThresholds::default();
+        // Half of a three-minute track
+        assert_eq!(classify(&t, 90, Some(180)), Completed);
+        assert_eq!(classify(&t, 89, Some(180)), Skipped);
+        // Four minutes of a long mix
+        assert_eq!(classify(&t, 240, Some(3600)), Completed);
+        assert_eq!(classify(&t, 239, Some(3600)), Skipped);
+        // Unknown track length: only the seconds threshold applies
+        assert_eq!(classify(&t, 100, None), Skipped);
+        assert_eq!(classify(&t, 100, Some(0)), Skipped);
+        assert_eq!(classify(&t, 300, None), Completed);
+        // Unknown play length
+        assert_eq!(classify(&t, 0, Some(180)), Completed);
+
+        let strict = SkipThresholds {
+            min_percent: 90.0,
+            min_seconds: 600,
+        };
+        assert_eq!(classify(&strict, 300, Some(400)), Skipped);
+        assert_eq!(classify(&strict, 360, Some(400)), Completed);
+    }
+
+    #[test]
+    fn test_thresholds_serde() {
+        let t: SkipThresholds = serde_json::from_str(r#"{"min_percent": 75}"#).unwrap();
+        assert_eq!(
+            t,
+            SkipThresholds {
+                min_percent: 75.0,
+                min_seconds: 240
+            }
+        );
+    }
+}
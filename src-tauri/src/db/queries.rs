@@ -1,4 +1,14 @@
 // Database query operations
+use crate::analysis::quality::{QualityAnalysis, QualityVerdict};
+use crate::db::play_counts::{self, PlayOutcome, SkipThresholds};
+use crate::db::smart_rules::{self, SmartLimit, SmartPlaylistRules};
+use crate::db::stats::{self, StatsPeriod, Streaks};
+use crate::playlist_ops::PlaylistItem;
+use crate::scanner::artists::{parse_credits, ArtistCredit, ArtistRole, ArtistSplitRules};
+use crate::scanner::genres::{split_genres, GenreAliases};
+use crate::scanner::grouping::{resolve_album_artist, VariousArtistsPolicy};
+use crate::scanner::rules::ScanRules;
+use crate::tag_editor::fields::TagValues;
 use rusqlite::{params, Connection, OptionalExtension, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -51,6 +61,23 @@ pub struct Playlist {
     pub cover_url: Option<String>,
     pub created_at: Option<String>,
     pub folder_path: Option<String>,
+    /// Set for smart playlists, whose tracks come from these rules
+    pub smart_rules: Option<SmartPlaylistRules>,
+    /// Playlist folder, `None` at the top level
+    pub folder_id: Option<i64>,
+    /// Order within the folder; playlists without one sort by name
+    pub position: Option<f64>,
+}
+
+/// One entry of a playlist. The same track can appear more than once, so
+/// entries (not tracks) are what gets removed or moved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistTrack {
+    /// `None` for smart playlists, whose tracks aren't stored
+    pub entry_id: Option<i64>,
+    pub position: Option<f64>,
+    #[serde(flatten)]
+    pub track: Track,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -73,12 +100,48 @@ pub struct TrackInsert {
     pub local_src: Option<String>,
     pub musicbrainz_recording_id: Option<String>,
     pub metadata_json: Option<String>,
+    /// Tempo from the file's BPM tag, if any.
+    pub bpm: Option<f64>,
+    /// Key from the file's TKEY/INITIALKEY tag, in normalized notation.
+    pub musical_key: Option<String>,
+    /// File modification time (unix ms) and size when the tags were read;
+    /// rescans skip files whose stamp is unchanged.
+    pub file_mtime: Option<i64>,
+    pub file_size: Option<i64>,
+    /// AlbumArtist tag as read; albums are grouped on it (see `scanner::grouping`).
+    pub album_artist: Option<String>,
+    /// Compilation flag (TCMP / cpil / COMPILATION).
+    pub compilation: bool,
+    pub musicbrainz_release_id: Option<String>,
+    pub musicbrainz_release_group_id: Option<String>,
+    /// MBID of the first credited track artist.
+    pub musicbrainz_artist_id: Option<String>,
+    /// Composer and remixer tags; multi-value tags are joined with "; ".
+    pub composer: Option<String>,
+    pub remixer: Option<String>,
+    /// Genre tag values joined with "; " (split through `scanner::genres`).
+    pub genre: Option<String>,
+    pub year: Option<i32>,
+    /// Record label (LABEL / TPUB).
+    pub label: Option<String>,
+    /// Rating in half stars from POPM / FMPS_RATING / RATING / `rate`.
+    /// `None` when the file has no rating tag, which keeps the stored
+    /// rating; `Some(None)` when the tag is there but unrated, which
+    /// clears it.
+    pub rating: Option<Option<u8>>,
 }
 
+/// Title, artist and album as stored on a track row.
+type TrackTags = (Option<String>, Option<String>, Option<String>);
+
 // Track operations
 pub fn insert_or_update_track(conn: &Connection, track: &TrackInsert) -> Result<(i64, bool)> {
-    // Check if a track with the same content_hash already exists (skip duplicates)
-    if let Some(ref hash) = track.content_hash {
+    // Skip a non-file entry (synced/streaming placeholder) whose content_hash
+    // matches an existing track. Local files always get their own row: two
+    // recordings can share tags, and real duplicates are found acoustically
+    // (see `analysis::fingerprint`).
+    let is_local_file = matches!(track.source_type.as_deref(), None | Some("local"));
+    if let (false, Some(hash)) = (is_local_file, track.content_hash.as_ref()) {
         let existing: Option<i64> = conn
             .query_row(
                 "SELECT id FROM tracks WHERE content_hash = ?1 AND path != ?2",
@@ -94,28 +157,42 @@ pub fn insert_or_update_track(conn: &Connection, track: &TrackInsert) -> Result<
     }
 
     // Check if track already exists by path
-    let existing_id: Option<i64> = conn
+    let existing: Option<(i64, TrackTags)> = conn
         .query_row(
-            "SELECT id FROM tracks WHERE path = ?1",
+            "SELECT id, title, artist, album FROM tracks WHERE path = ?1",
             params![track.path],
-            |row| row.get(0),
+            |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?, row.get(3)?))),
         )
         .ok();
+    let existing_id = existing.as_ref().map(|(id, _)| *id);
 
     // First, handle album if present
     let album_id = if let Some(album_name) = &track.album {
-        let artist = track.artist.as_deref();
+        let album_artist = resolve_album_artist(
+            track.album_artist.as_deref(),
+            track.artist.as_deref(),
+            track.compilation,
+            get_various_artists_policy(conn)?,
+        );
         Some(get_or_create_album(
             conn,
             album_name,
-            artist,
-            track.album_art.as_deref(),
+            album_artist.as_deref(),
+            track.musicbrainz_release_id.as_deref(),
+            track.compilation,
         )?)
     } else {
         None
     };
 
+    let camelot_key = camelot_for(track.musical_key.as_deref());
+
     if let Some(track_id) = existing_id {
+        let old_rating = match track.rating {
+            Some(_) => get_track_rating(conn, track_id)?,
+            None => None,
+        };
+
         // update existing track
         conn.execute(
             "UPDATE tracks SET
@@ -134,7 +211,23 @@ pub fn insert_or_update_track(conn: &Connection, track: &TrackInsert) -> Result<
                 local_src = ?13,
                 disc_number = ?15,
                 musicbrainz_recording_id = ?16,
-                metadata_json = ?17
+                metadata_json = ?17,
+                bpm = COALESCE(?18, bpm),
+                musical_key = COALESCE(?19, musical_key),
+                camelot_key = COALESCE(?20, camelot_key),
+                file_mtime = COALESCE(?21, file_mtime),
+                file_size = COALESCE(?22, file_size),
+                album_artist = ?23,
+                compilation = ?24,
+                musicbrainz_release_id = ?25,
+                composer = ?26,
+                remixer = ?27,
+                genre = COALESCE(?28, genre),
+                year = ?29,
+                label = ?30,
+                musicbrainz_release_group_id = ?31,
+                musicbrainz_artist_id = ?32,
+                rating = CASE WHEN ?34 THEN ?33 ELSE rating END
              WHERE id = ?14",
             params![
                 track.title,
@@ -154,15 +247,54 @@ pub fn insert_or_update_track(conn: &Connection, track: &TrackInsert) -> Result<
                 track.disc_number,
                 track.musicbrainz_recording_id,
                 track.metadata_json,
+                track.bpm,
+                track.musical_key,
+                camelot_key,
+                track.file_mtime,
+                track.file_size,
+                track.album_artist,
+                track.compilation,
+                track.musicbrainz_release_id,
+                track.composer,
+                track.remixer,
+                track.genre,
+                track.year,
+                track.label,
+                track.musicbrainz_release_group_id,
+                track.musicbrainz_artist_id,
+                track.rating.flatten(),
+                track.rating.is_some(),
             ],
         )?;
+        update_track_credits(conn, track_id, track)?;
+        update_track_genres(conn, track_id)?;
+
+        // Liked tracks are matched across devices by title|artist|album, so a
+        // retagged file has to re-announce its like under the new hash.
+        let tags_changed = existing.as_ref().is_some_and(|(_, (title, artist, album))| {
+            (title, artist, album) != (&track.title, &track.artist, &track.album)
+        });
+        if tags_changed && is_logged_in(conn) && is_track_liked(conn, track_id)? {
+            if let Some(updated) = get_track_by_id(conn, track_id)? {
+                enqueue_liked_track_sync_change(conn, &updated)?;
+            }
+        }
+
+        // A rating set or cleared in another player shows up on rescan
+        if let Some(rating) = track.rating {
+            if rating != old_rating && is_logged_in(conn) {
+                if let Some(updated) = get_track_by_id(conn, track_id)? {
+                    enqueue_track_rating_sync_change(conn, &updated, rating)?;
+                }
+            }
+        }
 
         Ok((track_id, false)) // Return (existing_id, was_new = false)
     } else {
         // insert new track
         conn.execute(
-            "INSERT INTO tracks (path, title, artist, album, track_number, duration, album_id, format, bitrate, source_type, cover_url, external_id, content_hash, local_src, disc_number, musicbrainz_recording_id, metadata_json)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            "INSERT INTO tracks (path, title, artist, album, track_number, duration, album_id, format, bitrate, source_type, cover_url, external_id, content_hash, local_src, disc_number, musicbrainz_recording_id, metadata_json, bpm, musical_key, camelot_key, file_mtime, file_size, album_artist, compilation, musicbrainz_release_id, composer, remixer, genre, year, label, musicbrainz_release_group_id, musicbrainz_artist_id, rating)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33)",
             params![
                 track.path,
                 track.title,
@@ -181,10 +313,30 @@ pub fn insert_or_update_track(conn: &Connection, track: &TrackInsert) -> Result<
                 track.disc_number,
                 track.musicbrainz_recording_id,
                 track.metadata_json,
+                track.bpm,
+                track.musical_key,
+                camelot_key,
+                track.file_mtime,
+                track.file_size,
+                track.album_artist,
+                track.compilation,
+                track.musicbrainz_release_id,
+                track.composer,
+                track.remixer,
+                track.genre,
+                track.year,
+                track.label,
+                track.musicbrainz_release_group_id,
+                track.musicbrainz_artist_id,
+                track.rating.flatten(),
             ],
         )?;
 
-        Ok((conn.last_insert_rowid(), true)) // Return (new_id, was_new = true)
+        let track_id = conn.last_insert_rowid();
+        update_track_credits(conn, track_id, track)?;
+        update_track_genres(conn, track_id)?;
+
+        Ok((track_id, true)) // Return (new_id, was_new = true)
     }
 }
 
@@ -203,6 +355,9 @@ pub fn update_track_mb_data(
          WHERE id = ?3",
         params![mbid, genre, track_id],
     )?;
+    if genre.is_some() {
+        update_track_genres(conn, track_id)?;
+    }
     Ok(())
 }
 
@@ -245,36 +400,87 @@ pub fn get_track_by_id(conn: &Connection, track_id: i64) -> Result<Option<Track>
     .optional()
 }
 
+/// Column list matching `map_track`, for queries that return full tracks.
+pub const TRACK_COLUMNS: &str = "id, path, title, artist, album, track_number, duration, album_id, format, bitrate, source_type, cover_url, external_id, local_src, track_cover, track_cover_path, disc_number, metadata_json, date_added";
+
+/// Map a row selected with `TRACK_COLUMNS` (extra columns may follow).
+pub fn map_track(row: &rusqlite::Row) -> Result<Track> {
+    Ok(Track {
+        id: row.get(0)?,
+        path: row.get(1)?,
+        title: row.get(2)?,
+        artist: row.get(3)?,
+        album: row.get(4)?,
+        track_number: row.get(5)?,
+        duration: row.get(6)?,
+        album_id: row.get(7)?,
+        format: row.get(8)?,
+        bitrate: row.get(9)?,
+        source_type: row.get(10)?,
+        cover_url: row.get(11)?,
+        external_id: row.get(12)?,
+        local_src: row.get(13)?,
+        track_cover: row.get(14)?,
+        track_cover_path: row.get(15)?,
+        disc_number: row.get(16)?,
+        metadata_json: row.get(17)?,
+        date_added: row.get(18)?,
+    })
+}
+
+/// Find the album for (name, album artist, release MBID), creating it if needed.
+///
+/// Names and album artists match case-insensitively. A release MBID only
+/// splits albums when both sides have one: an untagged track joins whichever
+/// release is already there, and an album without an MBID adopts the first
+/// one it sees.
 fn get_or_create_album(
     conn: &Connection,
     name: &str,
-    artist: Option<&str>,
-    art_data: Option<&[u8]>,
+    album_artist: Option<&str>,
+    release_id: Option<&str>,
+    compilation: bool,
 ) -> Result<i64> {
-    // Match by album name only to avoid splitting albums when tracks have different artists
-    let existing: Option<i64> = conn
-        .query_row(
-            "SELECT id FROM albums WHERE name = ?1",
-            params![name],
-            |row| row.get(0),
-        )
-        .ok();
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, musicbrainz_release_id FROM albums
+         WHERE name = ?1 COLLATE NOCASE
+           AND COALESCE(album_artist, '') = COALESCE(?2, '') COLLATE NOCASE
+         ORDER BY id",
+    )?;
+    let candidates: Vec<(i64, Option<String>)> = stmt
+        .query_map(params![name, album_artist], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<Result<_>>()?;
+
+    let existing = match release_id {
+        Some(mbid) => candidates
+            .iter()
+            .find(|(_, id)| id.as_deref() == Some(mbid))
+            .or_else(|| candidates.iter().find(|(_, id)| id.is_none())),
+        None => candidates
+            .iter()
+            .find(|(_, id)| id.is_none())
+            .or(candidates.first()),
+    };
 
-    if let Some(id) = existing {
-        // Update artist if not set yet
-        if let Some(album_artist) = artist {
-            conn.execute(
-                "UPDATE albums SET artist = ?1 WHERE id = ?2 AND artist IS NULL",
-                params![album_artist, id],
-            )?;
-        }
-        return Ok(id);
+    if let Some((id, _)) = existing {
+        conn.execute(
+            "UPDATE albums SET
+                musicbrainz_release_id = COALESCE(musicbrainz_release_id, ?1),
+                compilation = MAX(COALESCE(compilation, 0), ?2),
+                artist = COALESCE(artist, ?3)
+             WHERE id = ?4",
+            params![release_id, compilation, album_artist, id],
+        )?;
+        return Ok(*id);
     }
 
     // Create new album (without art_data, we'll save file separately)
     conn.execute(
-        "INSERT INTO albums (name, artist) VALUES (?1, ?2)",
-        params![name, artist],
+        "INSERT INTO albums (name, artist, album_artist, compilation, musicbrainz_release_id)
+         VALUES (?1, ?2, ?2, ?3, ?4)",
+        params![name, album_artist, compilation, release_id],
     )?;
 
     Ok(conn.last_insert_rowid())
@@ -742,12 +948,16 @@ pub fn get_albums_paginated(conn: &Connection, limit: i32, offset: i32) -> Resul
 pub fn get_all_artists(conn: &Connection) -> Result<Vec<Artist>> {
     let query_start = Instant::now();
 
+    // Anyone credited as a main or featured artist; composers and remixers
+    // are reached through `get_tracks_by_artist_role`
     let mut stmt = conn.prepare(
-        "SELECT artist, COUNT(*) as track_count, COUNT(DISTINCT album) as album_count 
-         FROM tracks 
-         WHERE artist IS NOT NULL 
-         GROUP BY artist 
-         ORDER BY artist",
+        "SELECT a.name, COUNT(DISTINCT t.id) as track_count, COUNT(DISTINCT t.album_id) as album_count
+         FROM artists a
+         INNER JOIN track_artists ta ON ta.artist_id = a.id
+         INNER JOIN tracks t ON t.id = ta.track_id
+         WHERE ta.role IN ('main', 'featured')
+         GROUP BY a.id
+         ORDER BY a.name COLLATE NOCASE",
     )?;
 
     let artists = stmt
@@ -806,38 +1016,70 @@ pub fn get_tracks_by_album(conn: &Connection, album_id: i64) -> Result<Vec<Track
 }
 
 pub fn get_tracks_by_artist(conn: &Connection, artist: &str) -> Result<Vec<Track>> {
+    get_tracks_by_artist_role(conn, artist, &[ArtistRole::Main, ArtistRole::Featured])
+}
+
+/// Tracks crediting `artist` in any of `roles`. A raw artist string that
+/// isn't a split-out artist ("A feat. B") still matches tracks tagged with
+/// exactly that string.
+pub fn get_tracks_by_artist_role(
+    conn: &Connection,
+    artist: &str,
+    roles: &[ArtistRole],
+) -> Result<Vec<Track>> {
+    let role_placeholders: Vec<String> = (0..roles.len()).map(|i| format!("?{}", i + 2)).collect();
+    let sql = format!(
+        "SELECT {} FROM tracks
+         WHERE id IN (
+             SELECT ta.track_id FROM track_artists ta
+             INNER JOIN artists a ON a.id = ta.artist_id
+             WHERE a.name = ?1 COLLATE NOCASE AND ta.role IN ({})
+         ) OR artist = ?1
+         ORDER BY album, disc_number, track_number, title",
+        TRACK_COLUMNS,
+        role_placeholders.join(", ")
+    );
+
+    let values = std::iter::once(artist).chain(roles.iter().map(|role| role.as_str()));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let tracks = stmt
+        .query_map(rusqlite::params_from_iter(values), map_track)?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(tracks)
+}
+
+/// Albums filed under `artist`, or with a track crediting them as a main or
+/// featured artist.
+pub fn get_albums_by_artist(conn: &Connection, artist: &str) -> Result<Vec<Album>> {
     let mut stmt = conn.prepare(
-        "SELECT id, path, title, artist, album, track_number, duration, album_id, format, bitrate, source_type, cover_url, external_id, local_src, track_cover, track_cover_path, disc_number, metadata_json, date_added 
-         FROM tracks WHERE artist = ?1 ORDER BY album, disc_number, track_number, title",
+        "SELECT DISTINCT a.id, a.name, a.artist, a.art_data, a.art_path
+         FROM albums a
+         WHERE a.album_artist = ?1 COLLATE NOCASE
+            OR a.id IN (
+                SELECT t.album_id FROM tracks t
+                INNER JOIN track_artists ta ON ta.track_id = t.id
+                INNER JOIN artists ar ON ar.id = ta.artist_id
+                WHERE ar.name = ?1 COLLATE NOCASE AND ta.role IN ('main', 'featured')
+            )
+            OR a.id IN (SELECT album_id FROM tracks WHERE artist = ?1)
+         ORDER BY a.name",
     )?;
 
-    let tracks = stmt
+    let albums = stmt
         .query_map([artist], |row| {
-            Ok(Track {
+            Ok(Album {
                 id: row.get(0)?,
-                path: row.get(1)?,
-                title: row.get(2)?,
-                artist: row.get(3)?,
-                album: row.get(4)?,
-                track_number: row.get(5)?,
-                duration: row.get(6)?,
-                album_id: row.get(7)?,
-                format: row.get(8)?,
-                bitrate: row.get(9)?,
-                source_type: row.get(10)?,
-                cover_url: row.get(11)?,
-                external_id: row.get(12)?,
-                local_src: row.get(13)?,
-                track_cover: row.get(14)?,
-                track_cover_path: row.get(15)?,
-                disc_number: row.get(16)?,
-                metadata_json: row.get(17)?,
-                date_added: row.get(18)?,
+                name: row.get(1)?,
+                artist: row.get(2)?,
+                art_data: row.get(3)?,
+                art_path: row.get(4)?,
             })
         })?
         .collect::<Result<Vec<_>>>()?;
 
-    Ok(tracks)
+    Ok(albums)
 }
 
 pub fn get_album_by_id(conn: &Connection, album_id: i64) -> Result<Option<Album>> {
@@ -864,17 +1106,23 @@ pub fn create_playlist(conn: &Connection, name: &str) -> Result<i64> {
 }
 
 pub fn get_all_playlists(conn: &Connection) -> Result<Vec<Playlist>> {
-    let mut stmt =
-        conn.prepare("SELECT id, name, cover_url, created_at, folder_path FROM playlists ORDER BY name")?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name, cover_url, created_at, folder_path, smart_rules, folder_id, position
+         FROM playlists ORDER BY position IS NULL, position, name",
+    )?;
 
     let playlists = stmt
         .query_map([], |row| {
+            let smart_rules: Option<String> = row.get(5)?;
             Ok(Playlist {
                 id: row.get(0)?,
                 name: row.get(1)?,
                 cover_url: row.get(2)?,
                 created_at: row.get(3)?,
                 folder_path: row.get(4)?,
+                smart_rules: smart_rules.and_then(|json| parse_smart_rules(&json)),
+                folder_id: row.get(6)?,
+                position: row.get(7)?,
             })
         })?
         .collect::<Result<Vec<_>>>()?;
@@ -883,70 +1131,233 @@ pub fn get_all_playlists(conn: &Connection) -> Result<Vec<Playlist>> {
 }
 
 pub fn get_playlist_tracks(conn: &Connection, playlist_id: i64) -> Result<Vec<Track>> {
+    Ok(get_playlist_entries(conn, playlist_id)?
+        .into_iter()
+        .map(|entry| entry.track)
+        .collect())
+}
+
+pub fn get_playlist_entries(conn: &Connection, playlist_id: i64) -> Result<Vec<PlaylistTrack>> {
+    if let Some(rules) = get_playlist_smart_rules(conn, playlist_id)? {
+        return Ok(get_smart_playlist_tracks(conn, &rules)?
+            .into_iter()
+            .map(|track| PlaylistTrack {
+                entry_id: None,
+                position: None,
+                track,
+            })
+            .collect());
+    }
+
     let mut stmt = conn.prepare(
-        "SELECT t.id, t.path, t.title, t.artist, t.album, t.track_number, t.duration, t.album_id, t.format, t.bitrate, t.source_type, t.cover_url, t.external_id, t.local_src, t.track_cover, t.track_cover_path, t.disc_number, t.metadata_json, t.date_added 
+        "SELECT t.id, t.path, t.title, t.artist, t.album, t.track_number, t.duration, t.album_id, t.format, t.bitrate, t.source_type, t.cover_url, t.external_id, t.local_src, t.track_cover, t.track_cover_path, t.disc_number, t.metadata_json, t.date_added, pt.id, pt.position
          FROM tracks t
          INNER JOIN playlist_tracks pt ON t.id = pt.track_id
          WHERE pt.playlist_id = ?1
-         ORDER BY pt.position",
+         ORDER BY pt.position, pt.id",
     )?;
 
-    let tracks = stmt
+    let entries = stmt
         .query_map([playlist_id], |row| {
-            Ok(Track {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                title: row.get(2)?,
-                artist: row.get(3)?,
-                album: row.get(4)?,
-                track_number: row.get(5)?,
-                duration: row.get(6)?,
-                album_id: row.get(7)?,
-                format: row.get(8)?,
-                bitrate: row.get(9)?,
-                source_type: row.get(10)?,
-                cover_url: row.get(11)?,
-                external_id: row.get(12)?,
-                local_src: row.get(13)?,
-                track_cover: row.get(14)?,
-                track_cover_path: row.get(15)?,
-                disc_number: row.get(16)?,
-                metadata_json: row.get(17)?,
-                date_added: row.get(18)?,
+            Ok(PlaylistTrack {
+                entry_id: row.get(19)?,
+                position: row.get(20)?,
+                track: Track {
+                    id: row.get(0)?,
+                    path: row.get(1)?,
+                    title: row.get(2)?,
+                    artist: row.get(3)?,
+                    album: row.get(4)?,
+                    track_number: row.get(5)?,
+                    duration: row.get(6)?,
+                    album_id: row.get(7)?,
+                    format: row.get(8)?,
+                    bitrate: row.get(9)?,
+                    source_type: row.get(10)?,
+                    cover_url: row.get(11)?,
+                    external_id: row.get(12)?,
+                    local_src: row.get(13)?,
+                    track_cover: row.get(14)?,
+                    track_cover_path: row.get(15)?,
+                    disc_number: row.get(16)?,
+                    metadata_json: row.get(17)?,
+                    date_added: row.get(18)?,
+                },
             })
         })?
         .collect::<Result<Vec<_>>>()?;
 
-    Ok(tracks)
+    Ok(entries)
 }
 
-pub fn add_track_to_playlist(conn: &Connection, playlist_id: i64, track_id: i64) -> Result<()> {
-    let position: i32 = conn.query_row(
+/// Append a track to a playlist and return the new entry's id. A track
+/// already in the playlist is added again.
+pub fn add_track_to_playlist(conn: &Connection, playlist_id: i64, track_id: i64) -> Result<i64> {
+    let position: f64 = conn.query_row(
         "SELECT COALESCE(MAX(position), 0) + 1 FROM playlist_tracks WHERE playlist_id = ?1",
         [playlist_id],
         |row| row.get(0),
     )?;
+    insert_playlist_entry(conn, playlist_id, track_id, position)
+}
+
+/// The first entry of a track in a playlist, if it is in there.
+pub fn find_playlist_entry(
+    conn: &Connection,
+    playlist_id: i64,
+    track_id: i64,
+) -> Result<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM playlist_tracks WHERE playlist_id = ?1 AND track_id = ?2
+         ORDER BY position LIMIT 1",
+        params![playlist_id, track_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Append a track unless the playlist already has it; for playlists that
+/// mirror a folder, where rescans must not add files twice.
+pub fn add_track_to_playlist_once(
+    conn: &Connection,
+    playlist_id: i64,
+    track_id: i64,
+) -> Result<i64> {
+    match find_playlist_entry(conn, playlist_id, track_id)? {
+        Some(entry_id) => Ok(entry_id),
+        None => add_track_to_playlist(conn, playlist_id, track_id),
+    }
+}
 
+/// Insert an entry at an explicit position (entries synced from another
+/// device keep the position they had there).
+pub fn insert_playlist_entry(
+    conn: &Connection,
+    playlist_id: i64,
+    track_id: i64,
+    position: f64,
+) -> Result<i64> {
     conn.execute(
-        "INSERT OR IGNORE INTO playlist_tracks (playlist_id, track_id, position) VALUES (?1, ?2, ?3)",
+        "INSERT INTO playlist_tracks (playlist_id, track_id, position) VALUES (?1, ?2, ?3)",
         params![playlist_id, track_id, position],
     )?;
+    Ok(conn.last_insert_rowid())
+}
 
-    Ok(())
+/// (playlist_id, track_id, position) of an entry.
+pub fn get_playlist_entry(conn: &Connection, entry_id: i64) -> Result<Option<(i64, i64, f64)>> {
+    conn.query_row(
+        "SELECT playlist_id, track_id, position FROM playlist_tracks WHERE id = ?1",
+        [entry_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .optional()
 }
 
+/// Remove the first entry of a track from a playlist, for callers that
+/// only know the track. Returns false if the track wasn't in the playlist.
 pub fn remove_track_from_playlist(
     conn: &Connection,
     playlist_id: i64,
     track_id: i64,
-) -> Result<()> {
-    conn.execute(
-        "DELETE FROM playlist_tracks WHERE playlist_id = ?1 AND track_id = ?2",
+) -> Result<bool> {
+    let removed = conn.execute(
+        "DELETE FROM playlist_tracks WHERE id = (
+            SELECT id FROM playlist_tracks WHERE playlist_id = ?1 AND track_id = ?2
+            ORDER BY position, id LIMIT 1
+         )",
         params![playlist_id, track_id],
     )?;
+    Ok(removed > 0)
+}
+
+/// Returns false if there was no such entry.
+pub fn remove_playlist_entry(conn: &Connection, entry_id: i64) -> Result<bool> {
+    let removed = conn.execute("DELETE FROM playlist_tracks WHERE id = ?1", [entry_id])?;
+    Ok(removed > 0)
+}
+
+pub fn set_playlist_entry_position(conn: &Connection, entry_id: i64, position: f64) -> Result<()> {
+    conn.execute(
+        "UPDATE playlist_tracks SET position = ?2 WHERE id = ?1",
+        params![entry_id, position],
+    )?;
     Ok(())
 }
 
+/// Move an entry to `to_index` (0-based, counted as if the entry were
+/// already removed). The entry takes the midpoint between its new
+/// neighbours, so normally it is the only row written. When repeated moves
+/// into the same gap exhaust float precision, the playlist is renumbered
+/// first. Returns every (entry_id, position) that changed.
+pub fn move_playlist_entry(
+    conn: &Connection,
+    playlist_id: i64,
+    entry_id: i64,
+    to_index: usize,
+) -> Result<Vec<(i64, f64)>> {
+    match get_playlist_entry(conn, entry_id)? {
+        Some((entry_playlist, _, _)) if entry_playlist == playlist_id => {}
+        _ => return Err(rusqlite::Error::QueryReturnedNoRows),
+    }
+
+    let others = playlist_entry_positions(conn, playlist_id, entry_id)?;
+    let to_index = to_index.min(others.len());
+    if let Some(position) = position_between(&others, to_index) {
+        set_playlist_entry_position(conn, entry_id, position)?;
+        return Ok(vec![(entry_id, position)]);
+    }
+
+    // No room left in the gap: renumber in the new order
+    let mut entry_ids: Vec<i64> = conn
+        .prepare(
+            "SELECT id FROM playlist_tracks WHERE playlist_id = ?1 AND id != ?2
+             ORDER BY position, id",
+        )?
+        .query_map(params![playlist_id, entry_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>>>()?;
+    entry_ids.insert(to_index, entry_id);
+    let mut changed = Vec::with_capacity(entry_ids.len());
+    for (i, id) in entry_ids.into_iter().enumerate() {
+        let position = i as f64 + 1.0;
+        set_playlist_entry_position(conn, id, position)?;
+        changed.push((id, position));
+    }
+    Ok(changed)
+}
+
+/// Positions of a playlist's entries in order, leaving out `except_entry`.
+fn playlist_entry_positions(
+    conn: &Connection,
+    playlist_id: i64,
+    except_entry: i64,
+) -> Result<Vec<f64>> {
+    let mut stmt = conn.prepare(
+        "SELECT position FROM playlist_tracks
+         WHERE playlist_id = ?1 AND id != ?2
+         ORDER BY position, id",
+    )?;
+    let positions = stmt
+        .query_map(params![playlist_id, except_entry], |row| row.get(0))?
+        .collect::<Result<Vec<f64>>>()?;
+    Ok(positions)
+}
+
+/// A position that sorts at `index` among `positions`; `None` when the
+/// neighbours are too close to fit one in between.
+fn position_between(positions: &[f64], index: usize) -> Option<f64> {
+    let before = index.checked_sub(1).map(|i| positions[i]);
+    let after = positions.get(index).copied();
+    let position = match (before, after) {
+        (None, None) => 1.0,
+        (Some(before), None) => before + 1.0,
+        (None, Some(after)) => after - 1.0,
+        (Some(before), Some(after)) => before + (after - before) / 2.0,
+    };
+    let fits = before.is_none_or(|b| position > b) && after.is_none_or(|a| position < a);
+    fits.then_some(position)
+}
+
 pub fn delete_playlist(conn: &Connection, playlist_id: i64) -> Result<()> {
     conn.execute("DELETE FROM playlists WHERE id = ?1", [playlist_id])?;
     Ok(())
@@ -1044,6 +1455,52 @@ pub fn remove_music_folder(conn: &Connection, path: &str) -> Result<()> {
     Ok(())
 }
 
+/// Every music folder with its exclusion rules (default when unset or unparsable).
+pub fn get_folder_scan_rules(conn: &Connection) -> Result<Vec<(String, ScanRules)>> {
+    let mut stmt = conn.prepare("SELECT path, scan_rules FROM music_folders ORDER BY path")?;
+    let rows = stmt.query_map([], |row| {
+        let rules: Option<String> = row.get(1)?;
+        Ok((
+            row.get(0)?,
+            rules
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+        ))
+    })?;
+    rows.collect()
+}
+
+/// Returns false when `path` is not a registered music folder.
+pub fn set_folder_scan_rules(conn: &Connection, path: &str, rules: &ScanRules) -> Result<bool> {
+    let json = (rules != &ScanRules::default())
+        .then(|| serde_json::to_string(rules).unwrap_or_default());
+    let updated = conn.execute(
+        "UPDATE music_folders SET scan_rules = ?2 WHERE path = ?1",
+        params![path, json],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Local tracks under `folder` shorter than `min_secs`.
+pub fn get_short_track_ids(conn: &Connection, folder: &str, min_secs: u32) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM tracks
+         WHERE path LIKE ?1 AND duration IS NOT NULL AND duration < ?2
+           AND (source_type IS NULL OR source_type = 'local')",
+    )?;
+    let ids = stmt
+        .query_map(params![format!("{}%", folder), min_secs], |row| row.get(0))?
+        .collect::<Result<Vec<i64>>>()?;
+    Ok(ids)
+}
+
+/// Stored durations of tracks under `folder`, keyed by path.
+pub fn get_folder_durations(conn: &Connection, folder: &str) -> Result<HashMap<String, Option<i32>>> {
+    let mut stmt = conn.prepare("SELECT path, duration FROM tracks WHERE path LIKE ?1")?;
+    let rows = stmt.query_map([format!("{}%", folder)], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
 pub fn update_folder_last_scanned(conn: &Connection, path: &str) -> Result<()> {
     conn.execute(
         "UPDATE music_folders SET last_scanned = CURRENT_TIMESTAMP WHERE path = ?1",
@@ -1052,10 +1509,10 @@ pub fn update_folder_last_scanned(conn: &Connection, path: &str) -> Result<()> {
     Ok(())
 }
 
-// Cleanup tracks that no longer exist on filesystem
-pub fn cleanup_deleted_tracks(conn: &Connection, folder_paths: &[String]) -> Result<usize> {
+/// IDs and paths of tracks under `folder_paths`.
+pub fn get_folder_track_paths(conn: &Connection, folder_paths: &[String]) -> Result<Vec<(i64, String)>> {
     if folder_paths.is_empty() {
-        return Ok(0);
+        return Ok(Vec::new());
     }
 
     // Build query with OR conditions for each folder
@@ -1078,18 +1535,96 @@ pub fn cleanup_deleted_tracks(conn: &Connection, folder_paths: &[String]) -> Res
     let track_rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
         Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
     })?;
+    track_rows.collect()
+}
 
-    let mut deleted_count = 0;
-    for track_result in track_rows {
-        let (id, path) = track_result?;
-        if !std::path::Path::new(&path).exists() {
-            // Track file doesn't exist, remove it
-            conn.execute("DELETE FROM tracks WHERE id = ?1", [id])?;
-            deleted_count += 1;
-        }
+/// IDs of tracks under `folder_paths` whose file no longer exists.
+pub fn find_missing_tracks(conn: &Connection, folder_paths: &[String]) -> Result<Vec<i64>> {
+    let missing = get_folder_track_paths(conn, folder_paths)?
+        .into_iter()
+        .filter(|(_, path)| !std::path::Path::new(path).exists())
+        .map(|(id, _)| id)
+        .collect();
+    Ok(missing)
+}
+
+/// Stored `(file_mtime, file_size)` of a track.
+pub type StoredStamp = (Option<i64>, Option<i64>);
+
+/// Stored stamps of local files, keyed by path. Files imported before
+/// stamps were recorded map to `(None, None)`.
+pub fn get_file_stamps(conn: &Connection) -> Result<HashMap<String, StoredStamp>> {
+    let mut stmt = conn.prepare(
+        "SELECT path, file_mtime, file_size FROM tracks
+         WHERE source_type IS NULL OR source_type = 'local'",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?;
+    rows.collect()
+}
+
+/// What a missing track is matched on when looking for its moved file.
+#[derive(Debug, Clone)]
+pub struct MissingTrack {
+    pub id: i64,
+    pub path: String,
+    pub duration: Option<i32>,
+    pub content_hash: Option<String>,
+    pub file_mtime: Option<i64>,
+    pub file_size: Option<i64>,
+    pub fingerprint: Option<Vec<u8>>,
+}
+
+pub fn get_missing_tracks(conn: &Connection, track_ids: &[i64]) -> Result<Vec<MissingTrack>> {
+    if track_ids.is_empty() {
+        return Ok(Vec::new());
     }
+    let placeholders: Vec<String> = track_ids.iter().map(|_| "?".to_string()).collect();
+    let query = format!(
+        "SELECT id, path, duration, content_hash, file_mtime, file_size, fingerprint
+         FROM tracks WHERE id IN ({})",
+        placeholders.join(",")
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(track_ids.iter()), |row| {
+        Ok(MissingTrack {
+            id: row.get(0)?,
+            path: row.get(1)?,
+            duration: row.get(2)?,
+            content_hash: row.get(3)?,
+            file_mtime: row.get(4)?,
+            file_size: row.get(5)?,
+            fingerprint: row.get(6)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Point a track at its file's new location, keeping its id (and with it
+/// play history, likes and playlist entries).
+pub fn move_track_path(
+    conn: &Connection,
+    track_id: i64,
+    new_path: &str,
+    file_mtime: Option<i64>,
+    file_size: Option<i64>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE tracks SET path = ?2, file_mtime = ?3, file_size = ?4,
+             local_src = CASE WHEN local_src = path THEN ?2 ELSE local_src END
+         WHERE id = ?1",
+        params![track_id, new_path, file_mtime, file_size],
+    )?;
+    Ok(())
+}
 
-    Ok(deleted_count)
+// Cleanup tracks that no longer exist on filesystem
+pub fn cleanup_deleted_tracks(conn: &Connection, folder_paths: &[String]) -> Result<usize> {
+    let missing = find_missing_tracks(conn, folder_paths)?;
+    for id in &missing {
+        // Track file doesn't exist, remove it
+        conn.execute("DELETE FROM tracks WHERE id = ?1", [id])?;
+    }
+    Ok(missing.len())
 }
 
 /// Cleanup albums that have no tracks associated with them
@@ -1160,7 +1695,13 @@ pub struct StatsSummary {
     pub total_plays: i64,
     pub total_duration_seconds: i64,
     pub top_artist: Option<String>,
-    pub top_genre: Option<String>, // Placeholder for now
+    pub top_genre: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenreWithCount {
+    pub genre: String,
+    pub play_count: i64,
 }
 
 pub fn like_track(conn: &Connection, track_id: i64) -> Result<()> {
@@ -1237,32 +1778,65 @@ pub fn get_liked_tracks(conn: &Connection) -> Result<Vec<Track>> {
 // Play History operations
 // ============================================================================
 
+/// Record a play of `duration_played` seconds that stopped at `position`
+/// seconds into the track. The play is classified against the skip
+/// thresholds and the track's counts are brought up to date.
 pub fn record_play(
     conn: &Connection,
     track_id: i64,
     album_id: Option<i64>,
     duration_played: i64,
-) -> Result<()> {
+    position: Option<i64>,
+) -> Result<PlayOutcome> {
+    let track_duration: Option<i64> = conn
+        .query_row(
+            "SELECT duration FROM tracks WHERE id = ?1",
+            [track_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    let outcome =
+        play_counts::classify(&get_skip_thresholds(conn)?, duration_played, track_duration);
     conn.execute(
-        "INSERT INTO play_history (track_id, album_id, duration_played) VALUES (?1, ?2, ?3)",
-        params![track_id, album_id, duration_played],
+        "INSERT INTO play_history (track_id, album_id, duration_played, position, skipped)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            track_id,
+            album_id,
+            duration_played,
+            position,
+            outcome.is_skip()
+        ],
     )?;
-    Ok(())
+    refresh_play_counts(conn, Some(track_id))?;
+    Ok(outcome)
 }
 
-pub fn get_top_tracks(conn: &Connection, limit: i32) -> Result<Vec<TrackWithCount>> {
-    let mut stmt = conn.prepare(
+/// Plays the listening stats count. Plays of five seconds or less were
+/// never recorded before skips were tracked; those skips stay out, so the
+/// stats keep counting what they always did.
+const COUNTED_PLAY_SQL: &str = "NOT (skipped = 1 AND duration_played <= 5)";
+
+pub fn get_top_tracks(
+    conn: &Connection,
+    limit: i32,
+    period: &StatsPeriod,
+) -> Result<Vec<TrackWithCount>> {
+    let mut stmt = conn.prepare(&format!(
         "SELECT t.id, t.path, t.title, t.artist, t.album, t.track_number, t.duration, t.album_id, t.format, t.bitrate, t.source_type, t.cover_url, t.external_id, t.local_src, t.track_cover_path, t.disc_number, t.metadata_json, t.date_added, COUNT(ph.id) as play_count
          FROM tracks t
          INNER JOIN play_history ph ON t.id = ph.track_id
-         WHERE strftime('%Y-%m', ph.played_at) = strftime('%Y-%m', 'now')
+         WHERE {counted}
+         AND (?2 IS NULL OR ph.played_at >= ?2) AND (?3 IS NULL OR ph.played_at < ?3)
          GROUP BY t.id
          ORDER BY play_count DESC
          LIMIT ?1",
-    )?;
+        counted = COUNTED_PLAY_SQL
+    ))?;
 
     let results = stmt
-        .query_map(params![limit], |row| {
+        .query_map(params![limit, period.from, period.to], |row| {
             Ok(TrackWithCount {
                 track: Track {
                     id: row.get(0)?,
@@ -1293,20 +1867,25 @@ pub fn get_top_tracks(conn: &Connection, limit: i32) -> Result<Vec<TrackWithCoun
     Ok(results)
 }
 
-pub fn get_top_albums(conn: &Connection, limit: i32) -> Result<Vec<AlbumWithCount>> {
-    let mut stmt = conn.prepare(
+pub fn get_top_albums(
+    conn: &Connection,
+    limit: i32,
+    period: &StatsPeriod,
+) -> Result<Vec<AlbumWithCount>> {
+    let mut stmt = conn.prepare(&format!(
         "SELECT a.id, a.name, a.artist, a.art_data, a.art_path, COUNT(ph.id) as play_count
          FROM albums a
          INNER JOIN play_history ph ON a.id = ph.album_id
-         WHERE ph.album_id IS NOT NULL 
-         AND strftime('%Y-%m', ph.played_at) = strftime('%Y-%m', 'now')
+         WHERE ph.album_id IS NOT NULL AND {counted}
+         AND (?2 IS NULL OR ph.played_at >= ?2) AND (?3 IS NULL OR ph.played_at < ?3)
          GROUP BY a.id
          ORDER BY play_count DESC
          LIMIT ?1",
-    )?;
+        counted = COUNTED_PLAY_SQL
+    ))?;
 
     let results = stmt
-        .query_map(params![limit], |row| {
+        .query_map(params![limit, period.from, period.to], |row| {
             Ok(AlbumWithCount {
                 album: Album {
                     id: row.get(0)?,
@@ -1324,14 +1903,16 @@ pub fn get_top_albums(conn: &Connection, limit: i32) -> Result<Vec<AlbumWithCoun
 }
 
 pub fn get_recently_played(conn: &Connection, limit: i32) -> Result<Vec<Track>> {
-    let mut stmt = conn.prepare(
+    let mut stmt = conn.prepare(&format!(
         "SELECT DISTINCT t.id, t.path, t.title, t.artist, t.album, t.track_number, t.duration, t.album_id, t.format, t.bitrate, t.source_type, t.cover_url, t.external_id, t.local_src, t.track_cover_path, t.disc_number, t.metadata_json, t.date_added, MAX(ph.played_at) as last_played
          FROM tracks t
          INNER JOIN play_history ph ON t.id = ph.track_id
+         WHERE {counted}
          GROUP BY t.id
          ORDER BY last_played DESC
          LIMIT ?1",
-    )?;
+        counted = COUNTED_PLAY_SQL
+    ))?;
 
     let tracks = stmt
         .query_map(params![limit], |row| {
@@ -1362,20 +1943,26 @@ pub fn get_recently_played(conn: &Connection, limit: i32) -> Result<Vec<Track>>
     Ok(tracks)
 }
 
-pub fn get_top_artists(conn: &Connection, limit: i32) -> Result<Vec<ArtistWithCount>> {
-    let mut stmt = conn.prepare(
-        "SELECT t.artist, COUNT(ph.id) as play_count
-         FROM tracks t
-         INNER JOIN play_history ph ON t.id = ph.track_id
-         WHERE t.artist IS NOT NULL
-         AND strftime('%Y-%m', ph.played_at) = strftime('%Y-%m', 'now')
-         GROUP BY t.artist
+pub fn get_top_artists(
+    conn: &Connection,
+    limit: i32,
+    period: &StatsPeriod,
+) -> Result<Vec<ArtistWithCount>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT a.name, COUNT(DISTINCT ph.id) as play_count
+         FROM artists a
+         INNER JOIN track_artists ta ON ta.artist_id = a.id AND ta.role IN ('main', 'featured')
+         INNER JOIN play_history ph ON ph.track_id = ta.track_id
+         WHERE {counted}
+         AND (?2 IS NULL OR ph.played_at >= ?2) AND (?3 IS NULL OR ph.played_at < ?3)
+         GROUP BY a.id
          ORDER BY play_count DESC
          LIMIT ?1",
-    )?;
+        counted = COUNTED_PLAY_SQL
+    ))?;
 
     let results = stmt
-        .query_map(params![limit], |row| {
+        .query_map(params![limit, period.from, period.to], |row| {
             Ok(ArtistWithCount {
                 artist: row.get(0)?,
                 play_count: row.get(1)?,
@@ -1386,36 +1973,167 @@ pub fn get_top_artists(conn: &Connection, limit: i32) -> Result<Vec<ArtistWithCo
     Ok(results)
 }
 
-pub fn get_stats_summary(conn: &Connection) -> Result<StatsSummary> {
-    let total_plays: i64 =
-        conn.query_row("SELECT COUNT(*) FROM play_history WHERE strftime('%Y-%m', played_at) = strftime('%Y-%m', 'now')", [], |row| row.get(0))?;
+/// Genres by plays; a track with several genres counts for each.
+pub fn get_top_genres(
+    conn: &Connection,
+    limit: i32,
+    period: &StatsPeriod,
+) -> Result<Vec<GenreWithCount>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT g.name, COUNT(ph.id) as play_count
+         FROM genres g
+         INNER JOIN track_genres tg ON tg.genre_id = g.id
+         INNER JOIN play_history ph ON ph.track_id = tg.track_id
+         WHERE {counted}
+         AND (?2 IS NULL OR ph.played_at >= ?2) AND (?3 IS NULL OR ph.played_at < ?3)
+         GROUP BY g.id
+         ORDER BY play_count DESC, g.name
+         LIMIT ?1",
+        counted = COUNTED_PLAY_SQL
+    ))?;
 
-    let total_duration: i64 = conn.query_row(
-        "SELECT COALESCE(SUM(duration_played), 0) FROM play_history WHERE strftime('%Y-%m', played_at) = strftime('%Y-%m', 'now')",
-        [],
-        |row| row.get(0),
+    let results = stmt
+        .query_map(params![limit, period.from, period.to], |row| {
+            Ok(GenreWithCount {
+                genre: row.get(0)?,
+                play_count: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(results)
+}
+
+pub fn get_stats_summary(conn: &Connection, period: &StatsPeriod) -> Result<StatsSummary> {
+    let (total_plays, total_duration): (i64, i64) = conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM(duration_played), 0)
+             FROM play_history
+             WHERE {counted}
+             AND (?1 IS NULL OR played_at >= ?1) AND (?2 IS NULL OR played_at < ?2)",
+            counted = COUNTED_PLAY_SQL
+        ),
+        params![period.from, period.to],
+        |row| Ok((row.get(0)?, row.get(1)?)),
     )?;
 
-    let top_artist: Option<String> = conn
-        .query_row(
-            "SELECT t.artist
-         FROM tracks t
-         INNER JOIN play_history ph ON t.id = ph.track_id
-         WHERE t.artist IS NOT NULL
-         AND strftime('%Y-%m', ph.played_at) = strftime('%Y-%m', 'now')
-         GROUP BY t.artist
-         ORDER BY COUNT(ph.id) DESC
-         LIMIT 1",
-            [],
-            |row| row.get(0),
-        )
-        .optional()?;
+    let top_artist = get_top_artists(conn, 1, period)?
+        .into_iter()
+        .next()
+        .map(|a| a.artist);
+    let top_genre = get_top_genres(conn, 1, period)?
+        .into_iter()
+        .next()
+        .map(|g| g.genre);
 
     Ok(StatsSummary {
         total_plays,
         total_duration_seconds: total_duration,
         top_artist,
-        top_genre: None,
+        top_genre,
+    })
+}
+
+/// A count for one day or month, as grouped by `StatsPeriod::bucket`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodCount {
+    pub period: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListeningBreakdown {
+    /// Seconds listened in each hour of the day, local time, 0 to 23
+    pub by_hour: Vec<i64>,
+    /// Seconds listened on each weekday, Sunday first
+    pub by_weekday: Vec<i64>,
+    pub streaks: Streaks,
+    /// Artists first played in the period
+    pub new_artists: i64,
+    pub new_artists_by_period: Vec<PeriodCount>,
+}
+
+/// Seconds listened per value of `strftime(format)` over local play times,
+/// in a vector indexed by that value.
+fn listening_time_by(
+    conn: &Connection,
+    format: &str,
+    slots: usize,
+    period: &StatsPeriod,
+) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT CAST(strftime(?1, played_at, 'localtime') AS INTEGER), SUM(duration_played)
+         FROM play_history
+         WHERE {counted}
+         AND (?2 IS NULL OR played_at >= ?2) AND (?3 IS NULL OR played_at < ?3)
+         GROUP BY 1",
+        counted = COUNTED_PLAY_SQL
+    ))?;
+    let mut totals = vec![0; slots];
+    let rows = stmt.query_map(params![format, period.from, period.to], |row| {
+        Ok((row.get::<_, Option<i64>>(0)?, row.get::<_, Option<i64>>(1)?))
+    })?;
+    for row in rows {
+        if let (Some(slot), Some(seconds)) = row? {
+            if let Some(total) = totals.get_mut(slot as usize) {
+                *total = seconds;
+            }
+        }
+    }
+    Ok(totals)
+}
+
+pub fn get_listening_breakdown(
+    conn: &Connection,
+    period: &StatsPeriod,
+) -> Result<ListeningBreakdown> {
+    let by_hour = listening_time_by(conn, "%H", 24, period)?;
+    let by_weekday = listening_time_by(conn, "%w", 7, period)?;
+
+    // Every day with a play: the current streak may start before the period
+    let mut stmt = conn.prepare(&format!(
+        "SELECT DISTINCT date(played_at, 'localtime') FROM play_history
+         WHERE {counted} ORDER BY 1",
+        counted = COUNTED_PLAY_SQL
+    ))?;
+    let days: Vec<chrono::NaiveDate> = stmt
+        .query_map([], |row| row.get::<_, Option<String>>(0))?
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .filter_map(|d| chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok())
+        .collect();
+    let streaks = stats::streaks(&days, period);
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT strftime(?1, first_played, 'localtime') AS bucket, COUNT(*)
+         FROM (
+            SELECT ta.artist_id, MIN(ph.played_at) AS first_played
+            FROM track_artists ta
+            INNER JOIN play_history ph ON ph.track_id = ta.track_id
+            WHERE ta.role IN ('main', 'featured') AND {counted}
+            GROUP BY ta.artist_id
+         )
+         WHERE (?2 IS NULL OR first_played >= ?2) AND (?3 IS NULL OR first_played < ?3)
+         GROUP BY bucket
+         ORDER BY bucket",
+        counted = COUNTED_PLAY_SQL
+    ))?;
+    let new_artists_by_period = stmt
+        .query_map(params![period.bucket, period.from, period.to], |row| {
+            Ok(PeriodCount {
+                period: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(ListeningBreakdown {
+        by_hour,
+        by_weekday,
+        streaks,
+        new_artists: new_artists_by_period.iter().map(|p| p.count).sum(),
+        new_artists_by_period,
     })
 }
 
@@ -1778,3 +2496,3198 @@ pub fn enqueue_track_sync_change(conn: &Connection, track: &Track, operation: &s
 
     Ok(())
 }
+
+/// Enqueue a liked-track sync change carrying the track's current hash.
+pub fn enqueue_liked_track_sync_change(conn: &Connection, track: &Track) -> Result<()> {
+    if !is_logged_in(conn) {
+        return Ok(());
+    }
+
+    let track_hash = build_track_hash_str(
+        track.title.as_deref(),
+        track.artist.as_deref(),
+        track.album.as_deref(),
+    );
+
+    let payload = serde_json::json!({
+        "trackHash": track_hash,
+        "title": track.title,
+        "artist": track.artist,
+        "album": track.album,
+        "duration": track.duration,
+        "externalId": track.external_id,
+        "sourceType": track.source_type,
+        "coverUrl": track.cover_url,
+    });
+
+    let _ = enqueue_sync_change(
+        conn,
+        "liked_track",
+        &format!("local_liked_{}", track.id),
+        "create",
+        Some(&payload.to_string()),
+    );
+
+    Ok(())
+}
+
+// =============================================================================
+// INTERNET RADIO
+// =============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RadioStation {
+    pub id: i64,
+    pub name: String,
+    pub url: String,
+    pub genre: Option<String>,
+    pub homepage: Option<String>,
+    pub favicon_url: Option<String>,
+    pub created_at: Option<String>,
+    pub last_played_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RadioSongPlay {
+    pub id: i64,
+    pub station_id: i64,
+    pub raw_title: String,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub played_at: Option<String>,
+}
+
+fn map_radio_station(row: &rusqlite::Row) -> Result<RadioStation> {
+    Ok(RadioStation {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        url: row.get(2)?,
+        genre: row.get(3)?,
+        homepage: row.get(4)?,
+        favicon_url: row.get(5)?,
+        created_at: row.get(6)?,
+        last_played_at: row.get(7)?,
+    })
+}
+
+pub fn get_radio_stations(conn: &Connection) -> Result<Vec<RadioStation>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, url, genre, homepage, favicon_url, created_at, last_played_at
+         FROM radio_stations
+         ORDER BY name COLLATE NOCASE",
+    )?;
+    let stations = stmt
+        .query_map([], map_radio_station)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(stations)
+}
+
+pub fn get_radio_station(conn: &Connection, station_id: i64) -> Result<Option<RadioStation>> {
+    conn.query_row(
+        "SELECT id, name, url, genre, homepage, favicon_url, created_at, last_played_at
+         FROM radio_stations WHERE id = ?1",
+        [station_id],
+        map_radio_station,
+    )
+    .optional()
+}
+
+/// Insert a station, or refresh the name/details of an existing one with the
+/// same URL. Returns the station id and whether it was newly created.
+pub fn upsert_radio_station(
+    conn: &Connection,
+    name: &str,
+    url: &str,
+    genre: Option<&str>,
+    homepage: Option<&str>,
+    favicon_url: Option<&str>,
+) -> Result<(i64, bool)> {
+    let existing: Option<i64> = conn
+        .query_row("SELECT id FROM radio_stations WHERE url = ?1", [url], |row| row.get(0))
+        .optional()?;
+
+    if let Some(id) = existing {
+        conn.execute(
+            "UPDATE radio_stations
+             SET name = ?1,
+                 genre = COALESCE(?2, genre),
+                 homepage = COALESCE(?3, homepage),
+                 favicon_url = COALESCE(?4, favicon_url)
+             WHERE id = ?5",
+            params![name, genre, homepage, favicon_url, id],
+        )?;
+        return Ok((id, false));
+    }
+
+    conn.execute(
+        "INSERT INTO radio_stations (name, url, genre, homepage, favicon_url)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![name, url, genre, homepage, favicon_url],
+    )?;
+    Ok((conn.last_insert_rowid(), true))
+}
+
+pub fn update_radio_station(
+    conn: &Connection,
+    station_id: i64,
+    name: &str,
+    url: &str,
+    genre: Option<&str>,
+    homepage: Option<&str>,
+    favicon_url: Option<&str>,
+) -> Result<bool> {
+    let rows = conn.execute(
+        "UPDATE radio_stations
+         SET name = ?1, url = ?2, genre = ?3, homepage = ?4, favicon_url = ?5
+         WHERE id = ?6",
+        params![name, url, genre, homepage, favicon_url, station_id],
+    )?;
+    Ok(rows > 0)
+}
+
+pub fn delete_radio_station(conn: &Connection, station_id: i64) -> Result<bool> {
+    let rows = conn.execute("DELETE FROM radio_stations WHERE id = ?1", [station_id])?;
+    Ok(rows > 0)
+}
+
+pub fn mark_radio_station_played(conn: &Connection, station_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE radio_stations SET last_played_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        [station_id],
+    )?;
+    Ok(())
+}
+
+/// Record a song heard on a station. Skips the insert when the title equals
+/// the station's most recent entry (reconnects re-announce the current song).
+pub fn record_radio_song(
+    conn: &Connection,
+    station_id: i64,
+    raw_title: &str,
+    artist: Option<&str>,
+    title: Option<&str>,
+) -> Result<bool> {
+    let last: Option<String> = conn
+        .query_row(
+            "SELECT raw_title FROM radio_song_history
+             WHERE station_id = ?1
+             ORDER BY id DESC LIMIT 1",
+            [station_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    if last.as_deref() == Some(raw_title) {
+        return Ok(false);
+    }
+
+    conn.execute(
+        "INSERT INTO radio_song_history (station_id, raw_title, artist, title)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![station_id, raw_title, artist, title],
+    )?;
+    Ok(true)
+}
+
+pub fn get_radio_song_history(
+    conn: &Connection,
+    station_id: i64,
+    limit: i32,
+) -> Result<Vec<RadioSongPlay>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, station_id, raw_title, artist, title, played_at
+         FROM radio_song_history
+         WHERE station_id = ?1
+         ORDER BY id DESC
+         LIMIT ?2",
+    )?;
+    let history = stmt
+        .query_map(params![station_id, limit], |row| {
+            Ok(RadioSongPlay {
+                id: row.get(0)?,
+                station_id: row.get(1)?,
+                raw_title: row.get(2)?,
+                artist: row.get(3)?,
+                title: row.get(4)?,
+                played_at: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(history)
+}
+
+pub fn clear_radio_song_history(conn: &Connection, station_id: i64) -> Result<usize> {
+    conn.execute(
+        "DELETE FROM radio_song_history WHERE station_id = ?1",
+        [station_id],
+    )
+}
+
+// =============================================================================
+// AUDIO ANALYSIS (BPM / KEY)
+// =============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackAnalysis {
+    pub track_id: i64,
+    pub bpm: Option<f64>,
+    pub musical_key: Option<String>,
+    pub camelot_key: Option<String>,
+    /// Set when the values came from audio analysis rather than tags.
+    pub analyzed_at: Option<String>,
+}
+
+/// A track with its tempo and key, for DJ-style views.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DjTrack {
+    #[serde(flatten)]
+    pub track: Track,
+    pub bpm: Option<f64>,
+    pub musical_key: Option<String>,
+    pub camelot_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DjSort {
+    /// Ascending tempo.
+    Bpm,
+    /// Around the Camelot wheel (1A, 1B, 2A, ...), then tempo.
+    Key,
+}
+
+fn camelot_for(musical_key: Option<&str>) -> Option<String> {
+    musical_key
+        .and_then(crate::analysis::key::MusicalKey::parse)
+        .map(|k| k.camelot_code())
+}
+
+fn map_dj_track(row: &rusqlite::Row) -> Result<DjTrack> {
+    Ok(DjTrack {
+        track: map_track(row)?,
+        bpm: row.get(19)?,
+        musical_key: row.get(20)?,
+        camelot_key: row.get(21)?,
+    })
+}
+
+/// Store analysis results. `None` keeps the current (tag) value.
+pub fn update_track_analysis(
+    conn: &Connection,
+    track_id: i64,
+    bpm: Option<f64>,
+    musical_key: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE tracks
+         SET bpm               = COALESCE(?1, bpm),
+             musical_key       = COALESCE(?2, musical_key),
+             camelot_key       = COALESCE(?3, camelot_key),
+             audio_analyzed_at = CURRENT_TIMESTAMP
+         WHERE id = ?4",
+        params![bpm, musical_key, camelot_for(musical_key), track_id],
+    )?;
+    Ok(())
+}
+
+pub fn get_track_analysis(conn: &Connection, track_ids: &[i64]) -> Result<Vec<TrackAnalysis>> {
+    if track_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders: Vec<String> = track_ids.iter().map(|_| "?".to_string()).collect();
+    let query = format!(
+        "SELECT id, bpm, musical_key, camelot_key, audio_analyzed_at FROM tracks WHERE id IN ({})",
+        placeholders.join(",")
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(track_ids.iter()), |row| {
+            Ok(TrackAnalysis {
+                track_id: row.get(0)?,
+                bpm: row.get(1)?,
+                musical_key: row.get(2)?,
+                camelot_key: row.get(3)?,
+                analyzed_at: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Local tracks that have not been through audio analysis yet. With
+/// `only_missing`, tracks whose tags already supply both BPM and key are
+/// skipped too.
+pub fn get_unanalyzed_track_ids(conn: &Connection, only_missing: bool) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM tracks
+         WHERE audio_analyzed_at IS NULL
+           AND (source_type IS NULL OR source_type = 'local' OR local_src IS NOT NULL)
+           AND (?1 = 0 OR bpm IS NULL OR musical_key IS NULL)
+         ORDER BY date_added DESC",
+    )?;
+    let ids = stmt
+        .query_map([only_missing], |row| row.get(0))?
+        .collect::<Result<Vec<i64>>>()?;
+    Ok(ids)
+}
+
+/// Tracks with a known tempo and/or key, optionally limited to a BPM range
+/// and a set of Camelot codes.
+pub fn get_dj_tracks(
+    conn: &Connection,
+    min_bpm: Option<f64>,
+    max_bpm: Option<f64>,
+    camelot_keys: &[String],
+    sort: DjSort,
+) -> Result<Vec<DjTrack>> {
+    let mut conditions = vec!["(bpm IS NOT NULL OR camelot_key IS NOT NULL)".to_string()];
+    let mut values: Vec<rusqlite::types::Value> = Vec::new();
+
+    if let Some(min) = min_bpm {
+        values.push(min.into());
+        conditions.push(format!("bpm >= ?{}", values.len()));
+    }
+    if let Some(max) = max_bpm {
+        values.push(max.into());
+        conditions.push(format!("bpm <= ?{}", values.len()));
+    }
+    if !camelot_keys.is_empty() {
+        let placeholders: Vec<String> = camelot_keys
+            .iter()
+            .map(|code| {
+                values.push(code.to_ascii_uppercase().into());
+                format!("?{}", values.len())
+            })
+            .collect();
+        conditions.push(format!("camelot_key IN ({})", placeholders.join(",")));
+    }
+
+    let order = match sort {
+        DjSort::Bpm => "bpm IS NULL, bpm, artist, title",
+        DjSort::Key => {
+            "camelot_key IS NULL, CAST(camelot_key AS INTEGER), camelot_key, bpm IS NULL, bpm, artist, title"
+        }
+    };
+
+    let query = format!(
+        "SELECT {}, bpm, musical_key, camelot_key FROM tracks WHERE {} ORDER BY {}",
+        TRACK_COLUMNS,
+        conditions.join(" AND "),
+        order
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let tracks = stmt
+        .query_map(rusqlite::params_from_iter(values), map_dj_track)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(tracks)
+}
+
+/// Tracks that mix harmonically into `track_id`: a compatible Camelot key
+/// (same, relative, ±1 on the wheel) and a tempo within `bpm_tolerance`
+/// percent. Exact key matches and closer tempos come first.
+pub fn get_harmonic_mix_candidates(
+    conn: &Connection,
+    track_id: i64,
+    bpm_tolerance: f64,
+    limit: i32,
+) -> Result<Vec<DjTrack>> {
+    let source: Option<(Option<f64>, Option<String>)> = conn
+        .query_row(
+            "SELECT bpm, camelot_key FROM tracks WHERE id = ?1",
+            [track_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((bpm, camelot)) = source else {
+        return Ok(Vec::new());
+    };
+    let key = camelot.as_deref().and_then(crate::analysis::key::MusicalKey::parse);
+    if bpm.is_none() && key.is_none() {
+        return Ok(Vec::new());
+    }
+
+    let mut conditions = vec!["id != ?1".to_string()];
+    let mut values: Vec<rusqlite::types::Value> = vec![track_id.into()];
+
+    if let Some(key) = key {
+        let placeholders: Vec<String> = key
+            .compatible_keys()
+            .iter()
+            .map(|k| {
+                values.push(k.camelot_code().into());
+                format!("?{}", values.len())
+            })
+            .collect();
+        conditions.push(format!("camelot_key IN ({})", placeholders.join(",")));
+    }
+    if let Some(bpm) = bpm {
+        let spread = bpm * bpm_tolerance.max(0.0) / 100.0;
+        values.push((bpm - spread).into());
+        values.push((bpm + spread).into());
+        conditions.push(format!(
+            "bpm BETWEEN ?{} AND ?{}",
+            values.len() - 1,
+            values.len()
+        ));
+    }
+
+    values.push(camelot.into());
+    let same_key = values.len();
+    values.push(bpm.unwrap_or(0.0).into());
+    let source_bpm = values.len();
+    values.push(limit.into());
+
+    let query = format!(
+        "SELECT {}, bpm, musical_key, camelot_key FROM tracks
+         WHERE {}
+         ORDER BY camelot_key IS ?{} DESC, ABS(COALESCE(bpm, 0) - ?{}), artist, title
+         LIMIT ?{}",
+        TRACK_COLUMNS,
+        conditions.join(" AND "),
+        same_key,
+        source_bpm,
+        values.len()
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let tracks = stmt
+        .query_map(rusqlite::params_from_iter(values), map_dj_track)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(tracks)
+}
+
+// =============================================================================
+// ACOUSTIC FINGERPRINTS
+// =============================================================================
+
+/// How much listening history hangs off a track; used to pick which copy
+/// of a duplicate to keep.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrackUsage {
+    pub play_count: i64,
+    pub liked: bool,
+    pub playlist_count: i64,
+}
+
+pub fn set_track_fingerprint(conn: &Connection, track_id: i64, fingerprint: &[u8]) -> Result<()> {
+    conn.execute(
+        "UPDATE tracks SET fingerprint = ?1 WHERE id = ?2",
+        params![fingerprint, track_id],
+    )?;
+    Ok(())
+}
+
+pub fn get_track_fingerprint(conn: &Connection, track_id: i64) -> Result<Option<Vec<u8>>> {
+    conn.query_row(
+        "SELECT fingerprint FROM tracks WHERE id = ?1",
+        [track_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map(Option::flatten)
+}
+
+pub fn set_track_acoustid(conn: &Connection, track_id: i64, acoustid: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE tracks SET acoustid_id = ?1 WHERE id = ?2",
+        params![acoustid, track_id],
+    )?;
+    Ok(())
+}
+
+/// Local tracks without a fingerprint: (id, playable path).
+pub fn get_unfingerprinted_tracks(conn: &Connection) -> Result<Vec<(i64, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, COALESCE(local_src, path) FROM tracks
+         WHERE fingerprint IS NULL
+           AND (source_type IS NULL OR source_type = 'local' OR local_src IS NOT NULL)
+         ORDER BY id",
+    )?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Every stored fingerprint: (id, duration, fingerprint bytes).
+/// (track id, duration, fingerprint bytes)
+pub type FingerprintRow = (i64, Option<i32>, Vec<u8>);
+/// (track id, audio path, fingerprint bytes if computed)
+pub type RelinkCandidate = (i64, String, Option<Vec<u8>>);
+
+pub fn get_all_fingerprints(conn: &Connection) -> Result<Vec<FingerprintRow>> {
+    let mut stmt =
+        conn.prepare("SELECT id, duration, fingerprint FROM tracks WHERE fingerprint IS NOT NULL")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Of `track_ids`, the ones that have a fingerprint.
+pub fn filter_fingerprinted(conn: &Connection, track_ids: &[i64]) -> Result<Vec<i64>> {
+    if track_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders: Vec<String> = track_ids.iter().map(|_| "?".to_string()).collect();
+    let query = format!(
+        "SELECT id FROM tracks WHERE fingerprint IS NOT NULL AND id IN ({})",
+        placeholders.join(",")
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let ids = stmt
+        .query_map(rusqlite::params_from_iter(track_ids.iter()), |row| row.get(0))?
+        .collect::<Result<Vec<i64>>>()?;
+    Ok(ids)
+}
+
+/// Existing local tracks of similar length that could be the new home of a
+/// missing track: (id, playable path, fingerprint if computed).
+pub fn get_relink_candidates(
+    conn: &Connection,
+    missing_track_id: i64,
+    duration_tolerance: i32,
+) -> Result<Vec<RelinkCandidate>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, COALESCE(t.local_src, t.path), t.fingerprint
+         FROM tracks t, tracks m
+         WHERE m.id = ?1 AND t.id != m.id
+           AND (t.source_type IS NULL OR t.source_type = 'local')
+           AND ABS(COALESCE(t.duration, 0) - COALESCE(m.duration, 0)) <= ?2",
+    )?;
+    let rows = stmt
+        .query_map(params![missing_track_id, duration_tolerance], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+pub fn get_track_usage(conn: &Connection, track_id: i64) -> Result<TrackUsage> {
+    conn.query_row(
+        "SELECT
+            (SELECT play_count FROM tracks WHERE id = ?1),
+            EXISTS(SELECT 1 FROM liked_tracks WHERE track_id = ?1),
+            (SELECT COUNT(*) FROM playlist_tracks WHERE track_id = ?1)",
+        [track_id],
+        |row| {
+            Ok(TrackUsage {
+                play_count: row.get(0)?,
+                liked: row.get(1)?,
+                playlist_count: row.get(2)?,
+            })
+        },
+    )
+}
+
+/// Move likes, play history and playlist membership from one track to
+/// another (e.g. a duplicate being removed, or a file that was replaced),
+/// and carry over analysis results the target lacks. The source row is left
+/// in place for the caller to delete.
+pub fn relink_track(conn: &Connection, from_id: i64, to_id: i64) -> Result<()> {
+    if from_id == to_id {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT OR IGNORE INTO liked_tracks (track_id, liked_at)
+         SELECT ?2, liked_at FROM liked_tracks WHERE track_id = ?1",
+        params![from_id, to_id],
+    )?;
+    conn.execute(
+        "UPDATE play_history SET track_id = ?2 WHERE track_id = ?1",
+        params![from_id, to_id],
+    )?;
+    refresh_play_counts(conn, Some(to_id))?;
+    conn.execute(
+        "UPDATE playlist_tracks SET track_id = ?2 WHERE track_id = ?1",
+        params![from_id, to_id],
+    )?;
+    conn.execute(
+        "UPDATE tracks SET
+            bpm = COALESCE(bpm, (SELECT bpm FROM tracks WHERE id = ?1)),
+            musical_key = COALESCE(musical_key, (SELECT musical_key FROM tracks WHERE id = ?1)),
+            camelot_key = COALESCE(camelot_key, (SELECT camelot_key FROM tracks WHERE id = ?1)),
+            musicbrainz_recording_id = COALESCE(musicbrainz_recording_id,
+                (SELECT musicbrainz_recording_id FROM tracks WHERE id = ?1)),
+            acoustid_id = COALESCE(acoustid_id, (SELECT acoustid_id FROM tracks WHERE id = ?1)),
+            rating = COALESCE(rating, (SELECT rating FROM tracks WHERE id = ?1))
+         WHERE id = ?2",
+        params![from_id, to_id],
+    )?;
+    Ok(())
+}
+
+// =============================================================================
+// AUDIO QUALITY
+// =============================================================================
+
+/// Formats (as stored in `tracks.format`) that claim to be lossless. MP4 is
+/// left out: it is usually AAC, and ALAC files are caught when checked
+/// explicitly.
+pub const LOSSLESS_FORMATS: [&str; 5] = ["Flac", "Wav", "Aiff", "Ape", "WavPack"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackQuality {
+    pub track_id: i64,
+    #[serde(flatten)]
+    pub analysis: QualityAnalysis,
+    pub analyzed_at: Option<String>,
+}
+
+/// A track with its quality verdict, for the report's problem list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityTrack {
+    #[serde(flatten)]
+    pub track: Track,
+    pub quality: TrackQuality,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityCount {
+    pub format: Option<String>,
+    pub verdict: QualityVerdict,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityReport {
+    pub checked: i64,
+    /// Local tracks in a lossless format that have not been checked yet.
+    pub unchecked_lossless: i64,
+    pub counts: Vec<QualityCount>,
+    /// Transcodes, upsampled and bit-padded files, worst first.
+    pub suspicious: Vec<QualityTrack>,
+}
+
+const QUALITY_COLUMNS: &str = "q.track_id, q.verdict, q.codec, q.sample_rate, q.bits_per_sample, \
+     q.effective_bits, q.cutoff_hz, q.sharp_cutoff, q.likely_source, q.analyzed_at";
+
+fn lossless_format_list() -> String {
+    LOSSLESS_FORMATS
+        .iter()
+        .map(|f| format!("'{}'", f))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Map `QUALITY_COLUMNS` starting at column `offset`.
+fn map_track_quality(row: &rusqlite::Row, offset: usize) -> Result<TrackQuality> {
+    let verdict: String = row.get(offset + 1)?;
+    Ok(TrackQuality {
+        track_id: row.get(offset)?,
+        analysis: QualityAnalysis {
+            verdict: QualityVerdict::parse(&verdict),
+            codec: row.get::<_, Option<String>>(offset + 2)?.unwrap_or_default(),
+            sample_rate: row.get::<_, Option<u32>>(offset + 3)?.unwrap_or(0),
+            bits_per_sample: row.get(offset + 4)?,
+            effective_bits: row.get(offset + 5)?,
+            cutoff_hz: row.get::<_, Option<f64>>(offset + 6)?.map(|hz| hz as f32),
+            sharp_cutoff: row.get(offset + 7)?,
+            likely_source: row.get(offset + 8)?,
+        },
+        analyzed_at: row.get(offset + 9)?,
+    })
+}
+
+pub fn upsert_track_quality(conn: &Connection, track_id: i64, quality: &QualityAnalysis) -> Result<()> {
+    conn.execute(
+        "INSERT INTO track_quality
+            (track_id, verdict, codec, sample_rate, bits_per_sample, effective_bits,
+             cutoff_hz, sharp_cutoff, likely_source, analyzed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, CURRENT_TIMESTAMP)
+         ON CONFLICT(track_id) DO UPDATE SET
+            verdict = excluded.verdict,
+            codec = excluded.codec,
+            sample_rate = excluded.sample_rate,
+            bits_per_sample = excluded.bits_per_sample,
+            effective_bits = excluded.effective_bits,
+            cutoff_hz = excluded.cutoff_hz,
+            sharp_cutoff = excluded.sharp_cutoff,
+            likely_source = excluded.likely_source,
+            analyzed_at = excluded.analyzed_at",
+        params![
+            track_id,
+            quality.verdict.as_str(),
+            quality.codec,
+            quality.sample_rate,
+            quality.bits_per_sample,
+            quality.effective_bits,
+            quality.cutoff_hz.map(|hz| hz as f64),
+            quality.sharp_cutoff,
+            quality.likely_source,
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn get_track_quality(conn: &Connection, track_ids: &[i64]) -> Result<Vec<TrackQuality>> {
+    if track_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders: Vec<String> = track_ids.iter().map(|_| "?".to_string()).collect();
+    let query = format!(
+        "SELECT {} FROM track_quality q WHERE q.track_id IN ({})",
+        QUALITY_COLUMNS,
+        placeholders.join(",")
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(track_ids.iter()), |row| {
+            map_track_quality(row, 0)
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Local tracks to check, newest first. `lossless_only` limits the job to
+/// lossless formats; `only_missing` skips tracks checked before.
+pub fn get_quality_candidate_ids(
+    conn: &Connection,
+    lossless_only: bool,
+    only_missing: bool,
+) -> Result<Vec<i64>> {
+    let query = format!(
+        "SELECT t.id FROM tracks t
+         LEFT JOIN track_quality q ON q.track_id = t.id
+         WHERE (t.source_type IS NULL OR t.source_type = 'local' OR t.local_src IS NOT NULL)
+           AND (?1 = 0 OR t.format IN ({}))
+           AND (?2 = 0 OR q.track_id IS NULL)
+         ORDER BY t.date_added DESC",
+        lossless_format_list()
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let ids = stmt
+        .query_map(params![lossless_only, only_missing], |row| row.get(0))?
+        .collect::<Result<Vec<i64>>>()?;
+    Ok(ids)
+}
+
+/// Library-wide summary: verdict counts per format plus every suspicious
+/// track.
+pub fn get_quality_report(conn: &Connection) -> Result<QualityReport> {
+    let checked: i64 = conn.query_row("SELECT COUNT(*) FROM track_quality", [], |row| row.get(0))?;
+    let unchecked_lossless: i64 = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM tracks t
+             WHERE (t.source_type IS NULL OR t.source_type = 'local' OR t.local_src IS NOT NULL)
+               AND t.format IN ({})
+               AND NOT EXISTS (SELECT 1 FROM track_quality q WHERE q.track_id = t.id)",
+            lossless_format_list()
+        ),
+        [],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT t.format, q.verdict, COUNT(*) FROM track_quality q
+         JOIN tracks t ON t.id = q.track_id
+         GROUP BY t.format, q.verdict
+         ORDER BY t.format, COUNT(*) DESC",
+    )?;
+    let counts = stmt
+        .query_map([], |row| {
+            let verdict: String = row.get(1)?;
+            Ok(QualityCount {
+                format: row.get(0)?,
+                verdict: QualityVerdict::parse(&verdict),
+                count: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    // Track column names don't clash with track_quality's, so no prefix needed
+    let query = format!(
+        "SELECT {}, {} FROM track_quality q
+         JOIN tracks t ON t.id = q.track_id
+         WHERE q.verdict IN ('transcode', 'upsampled', 'padded_bit_depth')
+         ORDER BY CASE q.verdict WHEN 'transcode' THEN 0 WHEN 'upsampled' THEN 1 ELSE 2 END,
+                  q.cutoff_hz, t.artist, t.album, t.track_number",
+        TRACK_COLUMNS, QUALITY_COLUMNS
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let suspicious = stmt
+        .query_map([], |row| {
+            Ok(QualityTrack {
+                track: map_track(row)?,
+                quality: map_track_quality(row, 19)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(QualityReport {
+        checked,
+        unchecked_lossless,
+        counts,
+        suspicious,
+    })
+}
+
+// =============================================================================
+// LIBRARY SETTINGS & ALBUM GROUPING
+// =============================================================================
+
+const VARIOUS_ARTISTS_POLICY_KEY: &str = "various_artists_policy";
+const ALBUM_GROUPING_MIGRATION_KEY: &str = "album_grouping_migrated";
+
+pub fn get_library_setting(conn: &Connection, key: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT value FROM library_settings WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+pub fn set_library_setting(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO library_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+pub fn get_various_artists_policy(conn: &Connection) -> Result<VariousArtistsPolicy> {
+    Ok(get_library_setting(conn, VARIOUS_ARTISTS_POLICY_KEY)?
+        .and_then(|v| VariousArtistsPolicy::parse(&v))
+        .unwrap_or_default())
+}
+
+pub fn set_various_artists_policy(conn: &Connection, policy: VariousArtistsPolicy) -> Result<()> {
+    set_library_setting(conn, VARIOUS_ARTISTS_POLICY_KEY, policy.as_str())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegroupResult {
+    pub albums_before: usize,
+    pub albums_after: usize,
+    pub tracks_moved: usize,
+    /// (new album id, existing art file) pairs for albums that lost their art
+    /// by being split off; the caller copies the files (see
+    /// `cover_storage::copy_album_art`).
+    #[serde(skip)]
+    pub art_to_copy: Vec<(i64, String)>,
+}
+
+/// A track row as seen by `regroup_albums`.
+struct GroupingRow {
+    id: i64,
+    album: String,
+    album_id: Option<i64>,
+    album_artist: Option<String>,
+    release_id: Option<String>,
+    compilation: bool,
+}
+
+/// Re-file every track under the album `get_or_create_album` would pick for
+/// it today. Existing album ids are kept wherever a group already owns most
+/// of an album, so art, play history and sync links survive; albums left
+/// without tracks are removed.
+pub fn regroup_albums(conn: &Connection, policy: VariousArtistsPolicy) -> Result<RegroupResult> {
+    let tx = conn.unchecked_transaction()?;
+    let albums_before: usize = tx.query_row("SELECT COUNT(*) FROM albums", [], |row| row.get(0))?;
+
+    let art_paths: HashMap<i64, String> = {
+        let mut stmt = tx.prepare("SELECT id, art_path FROM albums WHERE art_path IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<_>>()?
+    };
+
+    let rows: Vec<GroupingRow> = {
+        let mut stmt = tx.prepare(
+            "SELECT id, album, album_id, album_artist, artist, COALESCE(compilation, 0), musicbrainz_release_id
+             FROM tracks WHERE album IS NOT NULL ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let album_artist: Option<String> = row.get(3)?;
+            let artist: Option<String> = row.get(4)?;
+            let compilation: bool = row.get(5)?;
+            Ok(GroupingRow {
+                id: row.get(0)?,
+                album: row.get(1)?,
+                album_id: row.get(2)?,
+                album_artist: resolve_album_artist(
+                    album_artist.as_deref(),
+                    artist.as_deref(),
+                    compilation,
+                    policy,
+                ),
+                release_id: row.get(6)?,
+                compilation,
+            })
+        })?;
+        rows.collect::<Result<_>>()?
+    };
+
+    // Group on (name, album artist), case-insensitively, in first-seen order
+    let mut keys: HashMap<(String, String), usize> = HashMap::new();
+    let mut groups: Vec<Vec<&GroupingRow>> = Vec::new();
+    for row in &rows {
+        let key = (
+            row.album.to_lowercase(),
+            row.album_artist.as_deref().unwrap_or("").to_lowercase(),
+        );
+        let idx = *keys.entry(key).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[idx].push(row);
+    }
+
+    // Split on release MBID; untagged tracks join the largest release
+    let mut albums: Vec<Vec<&GroupingRow>> = Vec::new();
+    for group in groups {
+        let mut releases: Vec<(&str, Vec<&GroupingRow>)> = Vec::new();
+        let mut untagged = Vec::new();
+        for row in group {
+            match row.release_id.as_deref() {
+                Some(mbid) => match releases.iter_mut().find(|(id, _)| *id == mbid) {
+                    Some((_, members)) => members.push(row),
+                    None => releases.push((mbid, vec![row])),
+                },
+                None => untagged.push(row),
+            }
+        }
+        if releases.is_empty() {
+            albums.push(untagged);
+            continue;
+        }
+        let largest = (0..releases.len())
+            .max_by_key(|&i| (releases[i].1.len(), std::cmp::Reverse(i)))
+            .unwrap_or(0);
+        releases[largest].1.extend(untagged);
+        albums.extend(releases.into_iter().map(|(_, members)| members));
+    }
+
+    // Larger albums claim their existing ids first
+    albums.sort_by_key(|members| std::cmp::Reverse(members.len()));
+
+    let mut claimed: std::collections::HashSet<i64> = std::collections::HashSet::new();
+    let mut tracks_moved = 0;
+    let mut art_to_copy = Vec::new();
+
+    for members in &albums {
+        let first = members[0];
+        let compilation = members.iter().any(|r| r.compilation);
+        let release_id = members.iter().find_map(|r| r.release_id.as_deref());
+
+        let mut counts: HashMap<i64, usize> = HashMap::new();
+        for row in members {
+            if let Some(id) = row.album_id {
+                *counts.entry(id).or_default() += 1;
+            }
+        }
+        let mut owned: Vec<(i64, usize)> = counts.into_iter().collect();
+        owned.sort_by_key(|&(id, count)| (std::cmp::Reverse(count), id));
+
+        let album_id = match owned.iter().find(|(id, _)| !claimed.contains(id)) {
+            Some(&(id, _)) => {
+                tx.execute(
+                    "UPDATE albums SET name = ?1, artist = ?2, album_artist = ?2, compilation = ?3, musicbrainz_release_id = ?4
+                     WHERE id = ?5",
+                    params![first.album, first.album_artist, compilation, release_id, id],
+                )?;
+                id
+            }
+            None => {
+                tx.execute(
+                    "INSERT INTO albums (name, artist, album_artist, compilation, musicbrainz_release_id)
+                     VALUES (?1, ?2, ?2, ?3, ?4)",
+                    params![first.album, first.album_artist, compilation, release_id],
+                )?;
+                tx.last_insert_rowid()
+            }
+        };
+        claimed.insert(album_id);
+
+        if !art_paths.contains_key(&album_id) {
+            if let Some(path) = owned.iter().find_map(|(id, _)| art_paths.get(id)) {
+                art_to_copy.push((album_id, path.clone()));
+            }
+        }
+
+        for row in members.iter().filter(|r| r.album_id != Some(album_id)) {
+            tx.execute(
+                "UPDATE tracks SET album_id = ?1 WHERE id = ?2",
+                params![album_id, row.id],
+            )?;
+            tracks_moved += 1;
+        }
+    }
+
+    cleanup_empty_albums(&tx)?;
+    let albums_after: usize = tx.query_row("SELECT COUNT(*) FROM albums", [], |row| row.get(0))?;
+    tx.commit()?;
+
+    Ok(RegroupResult {
+        albums_before,
+        albums_after,
+        tracks_moved,
+        art_to_copy,
+    })
+}
+
+/// One-time upgrade from name-only album grouping: fill the new track columns
+/// from the stored tag snapshot, then regroup. Returns None once done.
+pub fn migrate_album_grouping(conn: &Connection) -> Result<Option<RegroupResult>> {
+    if get_library_setting(conn, ALBUM_GROUPING_MIGRATION_KEY)?.is_some() {
+        return Ok(None);
+    }
+
+    conn.execute(
+        "UPDATE tracks SET
+            album_artist = COALESCE(album_artist, NULLIF(TRIM(json_extract(metadata_json, '$.AlbumArtist')), '')),
+            compilation = CASE
+                WHEN LOWER(TRIM(json_extract(metadata_json, '$.FlagCompilation'))) IN ('1', 'true', 'yes', 'y') THEN 1
+                ELSE COALESCE(compilation, 0)
+            END
+         WHERE metadata_json IS NOT NULL AND json_valid(metadata_json)",
+        [],
+    )?;
+
+    let result = regroup_albums(conn, get_various_artists_policy(conn)?)?;
+    set_library_setting(conn, ALBUM_GROUPING_MIGRATION_KEY, "1")?;
+    Ok(Some(result))
+}
+
+// =============================================================================
+// ARTIST CREDITS
+// =============================================================================
+
+const ARTIST_SPLIT_RULES_KEY: &str = "artist_split_rules";
+const ARTIST_CREDITS_MIGRATION_KEY: &str = "artist_credits_migrated";
+
+pub fn get_artist_split_rules(conn: &Connection) -> Result<ArtistSplitRules> {
+    Ok(get_library_setting(conn, ARTIST_SPLIT_RULES_KEY)?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+pub fn set_artist_split_rules(conn: &Connection, rules: &ArtistSplitRules) -> Result<()> {
+    let json = serde_json::to_string(rules).unwrap_or_default();
+    set_library_setting(conn, ARTIST_SPLIT_RULES_KEY, &json)
+}
+
+/// An artist credit on a track, as returned to the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackArtistCredit {
+    pub artist_id: i64,
+    pub name: String,
+    pub role: ArtistRole,
+}
+
+fn update_track_credits(conn: &Connection, track_id: i64, track: &TrackInsert) -> Result<()> {
+    let credits = parse_credits(
+        track.artist.as_deref(),
+        track.title.as_deref(),
+        track.composer.as_deref(),
+        track.remixer.as_deref(),
+        &get_artist_split_rules(conn)?,
+    );
+    set_track_artists(conn, track_id, &credits)
+}
+
+/// Replace a track's credits. Unchanged rows are kept, so artist ids stay
+/// stable across rescans; artists left without credits are removed by the
+/// `track_artists_ad` trigger.
+pub fn set_track_artists(conn: &Connection, track_id: i64, credits: &[ArtistCredit]) -> Result<()> {
+    let mut wanted: Vec<(i64, &'static str)> = Vec::with_capacity(credits.len());
+    for credit in credits {
+        conn.execute(
+            "INSERT OR IGNORE INTO artists (name) VALUES (?1)",
+            params![credit.name],
+        )?;
+        let artist_id: i64 = conn.query_row(
+            "SELECT id FROM artists WHERE name = ?1 COLLATE NOCASE",
+            params![credit.name],
+            |row| row.get(0),
+        )?;
+        wanted.push((artist_id, credit.role.as_str()));
+    }
+
+    let existing: Vec<(i64, String)> = {
+        let mut stmt =
+            conn.prepare_cached("SELECT artist_id, role FROM track_artists WHERE track_id = ?1")?;
+        let rows = stmt.query_map(params![track_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<_>>()?
+    };
+    for (artist_id, role) in &existing {
+        if !wanted.iter().any(|(id, r)| id == artist_id && r == role) {
+            conn.execute(
+                "DELETE FROM track_artists WHERE track_id = ?1 AND artist_id = ?2 AND role = ?3",
+                params![track_id, artist_id, role],
+            )?;
+        }
+    }
+
+    for (position, (artist_id, role)) in wanted.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO track_artists (track_id, artist_id, role, position)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(track_id, artist_id, role) DO UPDATE SET position = excluded.position",
+            params![track_id, artist_id, role, position as i64],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn get_track_credits(conn: &Connection, track_id: i64) -> Result<Vec<TrackArtistCredit>> {
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.name, ta.role
+         FROM track_artists ta
+         INNER JOIN artists a ON a.id = ta.artist_id
+         WHERE ta.track_id = ?1
+         ORDER BY ta.position",
+    )?;
+    let credits = stmt
+        .query_map(params![track_id], |row| {
+            let role: String = row.get(2)?;
+            Ok((row.get(0)?, row.get(1)?, role))
+        })?
+        .filter_map(|row| match row {
+            Ok((artist_id, name, role)) => ArtistRole::parse(&role).map(|role| {
+                Ok(TrackArtistCredit {
+                    artist_id,
+                    name,
+                    role,
+                })
+            }),
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(credits)
+}
+
+/// Artist, title, composer and remixer as stored on a track row.
+type CreditColumns = (
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
+/// Re-split every track's credits with the current rules. Returns the
+/// number of tracks processed.
+pub fn rebuild_artist_credits(conn: &Connection) -> Result<usize> {
+    let rules = get_artist_split_rules(conn)?;
+    let tx = conn.unchecked_transaction()?;
+
+    let rows: Vec<(i64, CreditColumns)> = {
+        let mut stmt = tx.prepare("SELECT id, artist, title, composer, remixer FROM tracks")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                (row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?),
+            ))
+        })?;
+        rows.collect::<Result<_>>()?
+    };
+
+    for (track_id, (artist, title, composer, remixer)) in &rows {
+        let credits = parse_credits(
+            artist.as_deref(),
+            title.as_deref(),
+            composer.as_deref(),
+            remixer.as_deref(),
+            &rules,
+        );
+        set_track_artists(&tx, *track_id, &credits)?;
+    }
+
+    tx.commit()?;
+    Ok(rows.len())
+}
+
+/// One-time fill of the artist tables for libraries scanned before they
+/// existed: composer and remixer come from the stored tag snapshot.
+pub fn migrate_artist_credits(conn: &Connection) -> Result<Option<usize>> {
+    if get_library_setting(conn, ARTIST_CREDITS_MIGRATION_KEY)?.is_some() {
+        return Ok(None);
+    }
+
+    conn.execute(
+        "UPDATE tracks SET
+            composer = COALESCE(composer, NULLIF(TRIM(json_extract(metadata_json, '$.Composer')), '')),
+            remixer = COALESCE(remixer, NULLIF(TRIM(json_extract(metadata_json, '$.Remixer')), ''))
+         WHERE metadata_json IS NOT NULL AND json_valid(metadata_json)",
+        [],
+    )?;
+
+    let rebuilt = rebuild_artist_credits(conn)?;
+    set_library_setting(conn, ARTIST_CREDITS_MIGRATION_KEY, "1")?;
+    Ok(Some(rebuilt))
+}
+
+// =============================================================================
+// GENRE, YEAR, COMPOSER & LABEL BROWSING
+// =============================================================================
+
+const GENRE_ALIASES_KEY: &str = "genre_aliases";
+const BROWSE_COLUMNS_MIGRATION_KEY: &str = "browse_columns_migrated";
+
+/// A browsable value (genre, composer, label) with how much of the library has it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryWithCount {
+    pub name: String,
+    pub track_count: i64,
+    pub album_count: i64,
+}
+
+/// A release year, or the first year of a decade, with its counts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YearWithCount {
+    pub year: i32,
+    pub track_count: i64,
+    pub album_count: i64,
+}
+
+/// User genre aliases (lowercase spelling -> canonical name).
+pub fn get_genre_aliases(conn: &Connection) -> Result<HashMap<String, String>> {
+    Ok(get_library_setting(conn, GENRE_ALIASES_KEY)?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+pub fn set_genre_aliases(conn: &Connection, aliases: &HashMap<String, String>) -> Result<()> {
+    let json = serde_json::to_string(aliases).unwrap_or_default();
+    set_library_setting(conn, GENRE_ALIASES_KEY, &json)
+}
+
+/// Re-split a track's stored genre value into `track_genres`.
+fn update_track_genres(conn: &Connection, track_id: i64) -> Result<()> {
+    let aliases = GenreAliases::new(&get_genre_aliases(conn)?);
+    let genre: Option<String> = conn
+        .query_row(
+            "SELECT genre FROM tracks WHERE id = ?1",
+            params![track_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    let genres = genre
+        .map(|g| split_genres(&g, &aliases))
+        .unwrap_or_default();
+    set_track_genres(conn, track_id, &genres)
+}
+
+/// Replace a track's genres, keeping unchanged rows (see `set_track_artists`).
+pub fn set_track_genres(conn: &Connection, track_id: i64, genres: &[String]) -> Result<()> {
+    let mut wanted: Vec<i64> = Vec::with_capacity(genres.len());
+    for name in genres {
+        conn.execute(
+            "INSERT OR IGNORE INTO genres (name) VALUES (?1)",
+            params![name],
+        )?;
+        let genre_id: i64 = conn.query_row(
+            "SELECT id FROM genres WHERE name = ?1 COLLATE NOCASE",
+            params![name],
+            |row| row.get(0),
+        )?;
+        wanted.push(genre_id);
+    }
+
+    let placeholders: Vec<String> = (0..wanted.len()).map(|i| format!("?{}", i + 2)).collect();
+    let sql = if wanted.is_empty() {
+        "DELETE FROM track_genres WHERE track_id = ?1".to_string()
+    } else {
+        format!(
+            "DELETE FROM track_genres WHERE track_id = ?1 AND genre_id NOT IN ({})",
+            placeholders.join(", ")
+        )
+    };
+    conn.execute(
+        &sql,
+        rusqlite::params_from_iter(std::iter::once(track_id).chain(wanted.iter().copied())),
+    )?;
+
+    for (position, genre_id) in wanted.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO track_genres (track_id, genre_id, position) VALUES (?1, ?2, ?3)
+             ON CONFLICT(track_id, genre_id) DO UPDATE SET position = excluded.position",
+            params![track_id, genre_id, position as i64],
+        )?;
+    }
+    Ok(())
+}
+
+/// Re-split every track's genres with the current alias map. Returns the
+/// number of tracks processed.
+pub fn rebuild_genres(conn: &Connection) -> Result<usize> {
+    let aliases = GenreAliases::new(&get_genre_aliases(conn)?);
+    let tx = conn.unchecked_transaction()?;
+
+    let rows: Vec<(i64, Option<String>)> = {
+        let mut stmt = tx.prepare("SELECT id, genre FROM tracks")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<_>>()?
+    };
+    for (track_id, genre) in &rows {
+        let genres = genre
+            .as_deref()
+            .map(|g| split_genres(g, &aliases))
+            .unwrap_or_default();
+        set_track_genres(&tx, *track_id, &genres)?;
+    }
+
+    tx.commit()?;
+    Ok(rows.len())
+}
+
+/// One-time fill of genre, year and label for libraries scanned before they
+/// were columns, from the stored tag snapshot.
+pub fn migrate_browse_columns(conn: &Connection) -> Result<Option<usize>> {
+    if get_library_setting(conn, BROWSE_COLUMNS_MIGRATION_KEY)?.is_some() {
+        return Ok(None);
+    }
+
+    conn.execute(
+        "UPDATE tracks SET
+            genre = COALESCE(genre, NULLIF(TRIM(json_extract(metadata_json, '$.Genre')), '')),
+            label = COALESCE(label,
+                NULLIF(TRIM(json_extract(metadata_json, '$.Label')), ''),
+                NULLIF(TRIM(json_extract(metadata_json, '$.Publisher')), '')),
+            year = COALESCE(year, CASE
+                WHEN TRIM(json_extract(metadata_json, '$.Year')) GLOB '[12][0-9][0-9][0-9]*'
+                THEN CAST(SUBSTR(TRIM(json_extract(metadata_json, '$.Year')), 1, 4) AS INTEGER)
+            END)
+         WHERE metadata_json IS NOT NULL AND json_valid(metadata_json)",
+        [],
+    )?;
+
+    let rebuilt = rebuild_genres(conn)?;
+    set_library_setting(conn, BROWSE_COLUMNS_MIGRATION_KEY, "1")?;
+    Ok(Some(rebuilt))
+}
+
+fn map_category(row: &rusqlite::Row) -> Result<CategoryWithCount> {
+    Ok(CategoryWithCount {
+        name: row.get(0)?,
+        track_count: row.get(1)?,
+        album_count: row.get(2)?,
+    })
+}
+
+fn map_year(row: &rusqlite::Row) -> Result<YearWithCount> {
+    Ok(YearWithCount {
+        year: row.get(0)?,
+        track_count: row.get(1)?,
+        album_count: row.get(2)?,
+    })
+}
+
+pub fn get_genres(conn: &Connection, limit: i32, offset: i32) -> Result<Vec<CategoryWithCount>> {
+    let mut stmt = conn.prepare(
+        "SELECT g.name, COUNT(DISTINCT t.id), COUNT(DISTINCT t.album_id)
+         FROM genres g
+         INNER JOIN track_genres tg ON tg.genre_id = g.id
+         INNER JOIN tracks t ON t.id = tg.track_id
+         GROUP BY g.id
+         ORDER BY g.name COLLATE NOCASE
+         LIMIT ?1 OFFSET ?2",
+    )?;
+    let genres = stmt
+        .query_map(params![limit, offset], map_category)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(genres)
+}
+
+pub fn get_composers(conn: &Connection, limit: i32, offset: i32) -> Result<Vec<CategoryWithCount>> {
+    let mut stmt = conn.prepare(
+        "SELECT a.name, COUNT(DISTINCT t.id), COUNT(DISTINCT t.album_id)
+         FROM artists a
+         INNER JOIN track_artists ta ON ta.artist_id = a.id AND ta.role = 'composer'
+         INNER JOIN tracks t ON t.id = ta.track_id
+         GROUP BY a.id
+         ORDER BY a.name COLLATE NOCASE
+         LIMIT ?1 OFFSET ?2",
+    )?;
+    let composers = stmt
+        .query_map(params![limit, offset], map_category)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(composers)
+}
+
+pub fn get_labels(conn: &Connection, limit: i32, offset: i32) -> Result<Vec<CategoryWithCount>> {
+    let mut stmt = conn.prepare(
+        "SELECT MIN(label), COUNT(*), COUNT(DISTINCT album_id)
+         FROM tracks
+         WHERE label IS NOT NULL AND label != ''
+         GROUP BY label COLLATE NOCASE
+         ORDER BY label COLLATE NOCASE
+         LIMIT ?1 OFFSET ?2",
+    )?;
+    let labels = stmt
+        .query_map(params![limit, offset], map_category)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(labels)
+}
+
+/// Release years, newest first.
+pub fn get_years(conn: &Connection, limit: i32, offset: i32) -> Result<Vec<YearWithCount>> {
+    let mut stmt = conn.prepare(
+        "SELECT year, COUNT(*), COUNT(DISTINCT album_id)
+         FROM tracks
+         WHERE year IS NOT NULL
+         GROUP BY year
+         ORDER BY year DESC
+         LIMIT ?1 OFFSET ?2",
+    )?;
+    let years = stmt
+        .query_map(params![limit, offset], map_year)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(years)
+}
+
+/// Decades (1990 for the 1990s), newest first.
+pub fn get_decades(conn: &Connection, limit: i32, offset: i32) -> Result<Vec<YearWithCount>> {
+    let mut stmt = conn.prepare(
+        "SELECT (year / 10) * 10 AS decade, COUNT(*), COUNT(DISTINCT album_id)
+         FROM tracks
+         WHERE year IS NOT NULL
+         GROUP BY decade
+         ORDER BY decade DESC
+         LIMIT ?1 OFFSET ?2",
+    )?;
+    let decades = stmt
+        .query_map(params![limit, offset], map_year)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(decades)
+}
+
+pub fn get_tracks_by_genre(conn: &Connection, genre: &str) -> Result<Vec<Track>> {
+    let sql = format!(
+        "SELECT {} FROM tracks
+         WHERE id IN (
+             SELECT tg.track_id FROM track_genres tg
+             INNER JOIN genres g ON g.id = tg.genre_id
+             WHERE g.name = ?1 COLLATE NOCASE
+         )
+         ORDER BY artist, album, disc_number, track_number, title",
+        TRACK_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let tracks = stmt
+        .query_map([genre], map_track)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(tracks)
+}
+
+pub fn get_tracks_by_label(conn: &Connection, label: &str) -> Result<Vec<Track>> {
+    let sql = format!(
+        "SELECT {} FROM tracks
+         WHERE label = ?1 COLLATE NOCASE
+         ORDER BY year, album, disc_number, track_number, title",
+        TRACK_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let tracks = stmt
+        .query_map([label], map_track)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(tracks)
+}
+
+/// Tracks released from `from` to `to`, inclusive.
+pub fn get_tracks_by_year_range(conn: &Connection, from: i32, to: i32) -> Result<Vec<Track>> {
+    let sql = format!(
+        "SELECT {} FROM tracks
+         WHERE year BETWEEN ?1 AND ?2
+         ORDER BY year, artist, album, disc_number, track_number, title",
+        TRACK_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let tracks = stmt
+        .query_map(params![from, to], map_track)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(tracks)
+}
+
+// =============================================================================
+// TAG EDIT JOURNAL
+// =============================================================================
+
+/// How many batch edits are kept for undo; older ones (and their cover
+/// snapshots) are dropped.
+const TAG_EDIT_HISTORY_LIMIT: i64 = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagEditBatch {
+    pub id: i64,
+    pub description: Option<String>,
+    pub track_count: i64,
+    pub created_at: Option<String>,
+    pub undone_at: Option<String>,
+}
+
+/// A track's file tags as they were before a batch edit.
+#[derive(Debug, Clone)]
+pub struct TagEditJournalEntry {
+    pub track_id: i64,
+    pub path: String,
+    /// Only the fields the edit touched; `None` means the field was absent
+    pub previous_values: TagValues,
+    pub cover_changed: bool,
+    pub previous_cover: Option<Vec<u8>>,
+}
+
+/// Start a batch and prune history beyond `TAG_EDIT_HISTORY_LIMIT`.
+pub fn create_tag_edit_batch(conn: &Connection, description: Option<&str>) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO tag_edit_batches (description) VALUES (?1)",
+        params![description],
+    )?;
+    let batch_id = conn.last_insert_rowid();
+    conn.execute(
+        "DELETE FROM tag_edit_batches WHERE id <= ?1",
+        params![batch_id - TAG_EDIT_HISTORY_LIMIT],
+    )?;
+    Ok(batch_id)
+}
+
+pub fn add_tag_edit_entry(
+    conn: &Connection,
+    batch_id: i64,
+    entry: &TagEditJournalEntry,
+) -> Result<()> {
+    let previous_values = serde_json::to_string(&entry.previous_values).unwrap_or_default();
+    conn.execute(
+        "INSERT INTO tag_edit_journal
+            (batch_id, track_id, path, previous_values, cover_changed, previous_cover)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            batch_id,
+            entry.track_id,
+            entry.path,
+            previous_values,
+            entry.cover_changed,
+            entry.previous_cover,
+        ],
+    )?;
+    conn.execute(
+        "UPDATE tag_edit_batches SET track_count = track_count + 1 WHERE id = ?1",
+        params![batch_id],
+    )?;
+    Ok(())
+}
+
+fn map_tag_edit_batch(row: &rusqlite::Row) -> Result<TagEditBatch> {
+    Ok(TagEditBatch {
+        id: row.get(0)?,
+        description: row.get(1)?,
+        track_count: row.get(2)?,
+        created_at: row.get(3)?,
+        undone_at: row.get(4)?,
+    })
+}
+
+pub fn get_tag_edit_batch(conn: &Connection, batch_id: i64) -> Result<Option<TagEditBatch>> {
+    conn.query_row(
+        "SELECT id, description, track_count, created_at, undone_at
+         FROM tag_edit_batches WHERE id = ?1",
+        params![batch_id],
+        map_tag_edit_batch,
+    )
+    .optional()
+}
+
+/// Most recent batches first.
+pub fn get_tag_edit_batches(conn: &Connection, limit: i64) -> Result<Vec<TagEditBatch>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, description, track_count, created_at, undone_at
+         FROM tag_edit_batches ORDER BY id DESC LIMIT ?1",
+    )?;
+    let batches = stmt
+        .query_map(params![limit], map_tag_edit_batch)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(batches)
+}
+
+pub fn get_tag_edit_entries(conn: &Connection, batch_id: i64) -> Result<Vec<TagEditJournalEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT track_id, path, previous_values, cover_changed, previous_cover
+         FROM tag_edit_journal WHERE batch_id = ?1 ORDER BY id",
+    )?;
+    let entries = stmt
+        .query_map(params![batch_id], |row| {
+            let previous_values: String = row.get(2)?;
+            Ok(TagEditJournalEntry {
+                track_id: row.get(0)?,
+                path: row.get(1)?,
+                previous_values: serde_json::from_str(&previous_values).unwrap_or_default(),
+                cover_changed: row.get(3)?,
+                previous_cover: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(entries)
+}
+
+/// Whether a later batch that is still applied edited any of this batch's
+/// tracks; undoing out of order would overwrite those later edits.
+pub fn tag_edit_batch_superseded(conn: &Connection, batch_id: i64) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (
+            SELECT 1 FROM tag_edit_journal later
+            JOIN tag_edit_batches b ON b.id = later.batch_id
+            WHERE later.batch_id > ?1 AND b.undone_at IS NULL
+              AND later.track_id IN (SELECT track_id FROM tag_edit_journal WHERE batch_id = ?1)
+         )",
+        params![batch_id],
+        |row| row.get(0),
+    )
+}
+
+pub fn mark_tag_edit_batch_undone(conn: &Connection, batch_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE tag_edit_batches SET undone_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![batch_id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_tag_edit_batch(conn: &Connection, batch_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM tag_edit_batches WHERE id = ?1",
+        params![batch_id],
+    )?;
+    Ok(())
+}
+
+// =============================================================================
+// ORGANIZER UNDO LOG
+// =============================================================================
+
+/// How many organize runs are kept for undo.
+const ORGANIZE_HISTORY_LIMIT: i64 = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrganizeBatch {
+    pub id: i64,
+    pub mode: String,
+    pub template: Option<String>,
+    pub file_count: i64,
+    pub created_at: Option<String>,
+    pub undone_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizedFileKind {
+    Track,
+    /// An .lrc sidecar
+    Lyrics,
+    /// Folder art (cover.jpg, folder.png, ...)
+    Cover,
+}
+
+impl OrganizedFileKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Track => "track",
+            Self::Lyrics => "lyrics",
+            Self::Cover => "cover",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "track" => Some(Self::Track),
+            "lyrics" => Some(Self::Lyrics),
+            "cover" => Some(Self::Cover),
+            _ => None,
+        }
+    }
+}
+
+/// One file an organize run moved or copied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrganizeJournalEntry {
+    pub track_id: Option<i64>,
+    pub kind: OrganizedFileKind,
+    pub from_path: String,
+    pub to_path: String,
+}
+
+/// Start a run and prune history beyond `ORGANIZE_HISTORY_LIMIT`.
+pub fn create_organize_batch(conn: &Connection, mode: &str, template: &str) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO organize_batches (mode, template) VALUES (?1, ?2)",
+        params![mode, template],
+    )?;
+    let batch_id = conn.last_insert_rowid();
+    conn.execute(
+        "DELETE FROM organize_batches WHERE id <= ?1",
+        params![batch_id - ORGANIZE_HISTORY_LIMIT],
+    )?;
+    Ok(batch_id)
+}
+
+pub fn add_organize_entry(
+    conn: &Connection,
+    batch_id: i64,
+    entry: &OrganizeJournalEntry,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO organize_journal (batch_id, track_id, kind, from_path, to_path)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            batch_id,
+            entry.track_id,
+            entry.kind.as_str(),
+            entry.from_path,
+            entry.to_path,
+        ],
+    )?;
+    conn.execute(
+        "UPDATE organize_batches SET file_count = file_count + 1 WHERE id = ?1",
+        params![batch_id],
+    )?;
+    Ok(())
+}
+
+fn map_organize_batch(row: &rusqlite::Row) -> Result<OrganizeBatch> {
+    Ok(OrganizeBatch {
+        id: row.get(0)?,
+        mode: row.get(1)?,
+        template: row.get(2)?,
+        file_count: row.get(3)?,
+        created_at: row.get(4)?,
+        undone_at: row.get(5)?,
+    })
+}
+
+pub fn get_organize_batch(conn: &Connection, batch_id: i64) -> Result<Option<OrganizeBatch>> {
+    conn.query_row(
+        "SELECT id, mode, template, file_count, created_at, undone_at
+         FROM organize_batches WHERE id = ?1",
+        params![batch_id],
+        map_organize_batch,
+    )
+    .optional()
+}
+
+/// Most recent runs first.
+pub fn get_organize_batches(conn: &Connection, limit: i64) -> Result<Vec<OrganizeBatch>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, mode, template, file_count, created_at, undone_at
+         FROM organize_batches ORDER BY id DESC LIMIT ?1",
+    )?;
+    let batches = stmt
+        .query_map(params![limit], map_organize_batch)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(batches)
+}
+
+/// A run's files in the order they were moved.
+pub fn get_organize_entries(conn: &Connection, batch_id: i64) -> Result<Vec<OrganizeJournalEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT track_id, kind, from_path, to_path
+         FROM organize_journal WHERE batch_id = ?1 ORDER BY id",
+    )?;
+    let entries = stmt
+        .query_map(params![batch_id], |row| {
+            let kind: String = row.get(1)?;
+            Ok(OrganizeJournalEntry {
+                track_id: row.get(0)?,
+                kind: OrganizedFileKind::parse(&kind).unwrap_or(OrganizedFileKind::Track),
+                from_path: row.get(2)?,
+                to_path: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(entries)
+}
+
+/// Whether a later run that is still applied moved any of this run's
+/// tracks again (see `tag_edit_batch_superseded`).
+pub fn organize_batch_superseded(conn: &Connection, batch_id: i64) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (
+            SELECT 1 FROM organize_journal later
+            JOIN organize_batches b ON b.id = later.batch_id
+            WHERE later.batch_id > ?1 AND b.undone_at IS NULL
+              AND later.track_id IN (
+                  SELECT track_id FROM organize_journal
+                  WHERE batch_id = ?1 AND track_id IS NOT NULL)
+         )",
+        params![batch_id],
+        |row| row.get(0),
+    )
+}
+
+pub fn mark_organize_batch_undone(conn: &Connection, batch_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE organize_batches SET undone_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![batch_id],
+    )?;
+    Ok(())
+}
+
+/// Point track covers and album art stored at `from` to `to`.
+pub fn update_cover_paths(conn: &Connection, from: &str, to: &str) -> Result<usize> {
+    let tracks = conn.execute(
+        "UPDATE tracks SET track_cover_path = ?2 WHERE track_cover_path = ?1",
+        params![from, to],
+    )?;
+    let albums = conn.execute(
+        "UPDATE albums SET art_path = ?2 WHERE art_path = ?1",
+        params![from, to],
+    )?;
+    Ok(tracks + albums)
+}
+
+/// Fields an organizer template can use, for each of `track_ids`.
+#[derive(Debug, Clone)]
+pub struct OrganizeSource {
+    pub track_id: i64,
+    pub path: String,
+    pub source_type: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub year: Option<i32>,
+    pub track_number: Option<i32>,
+    pub disc_number: Option<i32>,
+    pub genre: Option<String>,
+    pub composer: Option<String>,
+    pub label: Option<String>,
+}
+
+pub fn get_organize_sources(conn: &Connection, track_ids: &[i64]) -> Result<Vec<OrganizeSource>> {
+    if track_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders: Vec<String> = (1..=track_ids.len()).map(|i| format!("?{}", i)).collect();
+    let sql = format!(
+        "SELECT id, path, source_type, title, artist, album_artist, album, year,
+                track_number, disc_number, genre, composer, label
+         FROM tracks WHERE id IN ({})
+         ORDER BY path",
+        placeholders.join(", ")
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let sources = stmt
+        .query_map(rusqlite::params_from_iter(track_ids), |row| {
+            Ok(OrganizeSource {
+                track_id: row.get(0)?,
+                path: row.get(1)?,
+                source_type: row.get(2)?,
+                title: row.get(3)?,
+                artist: row.get(4)?,
+                album_artist: row.get(5)?,
+                album: row.get(6)?,
+                year: row.get(7)?,
+                track_number: row.get(8)?,
+                disc_number: row.get(9)?,
+                genre: row.get(10)?,
+                composer: row.get(11)?,
+                label: row.get(12)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(sources)
+}
+
+pub fn track_path_exists(conn: &Connection, path: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM tracks WHERE path = ?1)",
+        params![path],
+        |row| row.get(0),
+    )
+}
+
+// =============================================================================
+// SMART PLAYLISTS
+// =============================================================================
+
+fn parse_smart_rules(json: &str) -> Option<SmartPlaylistRules> {
+    match serde_json::from_str(json) {
+        Ok(rules) => Some(rules),
+        Err(e) => {
+            log::warn!("[DB] Ignoring unreadable smart playlist rules: {}", e);
+            None
+        }
+    }
+}
+
+pub fn create_smart_playlist(
+    conn: &Connection,
+    name: &str,
+    rules: &SmartPlaylistRules,
+) -> Result<i64> {
+    let json = serde_json::to_string(rules)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    conn.execute(
+        "INSERT INTO playlists (name, smart_rules) VALUES (?1, ?2)",
+        params![name, json],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Replace a smart playlist's rules. Normal playlists are left alone, so
+/// their tracks can't be hidden behind rules by accident.
+pub fn update_smart_playlist_rules(
+    conn: &Connection,
+    playlist_id: i64,
+    rules: &SmartPlaylistRules,
+) -> Result<bool> {
+    let json = serde_json::to_string(rules)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    let updated = conn.execute(
+        "UPDATE playlists SET smart_rules = ?2 WHERE id = ?1 AND smart_rules IS NOT NULL",
+        params![playlist_id, json],
+    )?;
+    Ok(updated > 0)
+}
+
+pub fn get_playlist_smart_rules(
+    conn: &Connection,
+    playlist_id: i64,
+) -> Result<Option<SmartPlaylistRules>> {
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT smart_rules FROM playlists WHERE id = ?1",
+            [playlist_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    Ok(json.and_then(|json| parse_smart_rules(&json)))
+}
+
+pub fn playlist_exists(conn: &Connection, playlist_id: i64) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM playlists WHERE id = ?1)",
+        [playlist_id],
+        |row| row.get(0),
+    )
+}
+
+pub fn is_smart_playlist(conn: &Connection, playlist_id: i64) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM playlists WHERE id = ?1 AND smart_rules IS NOT NULL)",
+        [playlist_id],
+        |row| row.get(0),
+    )
+}
+
+/// Tracks matching `rules` right now, sorted and limited.
+pub fn get_smart_playlist_tracks(
+    conn: &Connection,
+    rules: &SmartPlaylistRules,
+) -> Result<Vec<Track>> {
+    let compiled = smart_rules::compile(rules)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+    let limit_sql = match rules.limit {
+        Some(SmartLimit::Count(count)) => format!(" LIMIT {}", count),
+        _ => String::new(),
+    };
+    let sql = format!(
+        "SELECT {} FROM tracks t WHERE {} ORDER BY {}{}",
+        TRACK_COLUMNS, compiled.where_sql, compiled.order_sql, limit_sql
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let mut tracks = stmt
+        .query_map(rusqlite::params_from_iter(&compiled.params), map_track)?
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(SmartLimit::Duration(max_secs)) = rules.limit {
+        // Fill up to the limit in sort order; a track that doesn't fit ends it
+        let mut total: i64 = 0;
+        let keep = tracks
+            .iter()
+            .take_while(|track| {
+                total += track.duration.unwrap_or(0) as i64;
+                total <= max_secs as i64
+            })
+            .count();
+        tracks.truncate(keep);
+    }
+    Ok(tracks)
+}
+
+// =============================================================================
+// PLAYLIST FILE MATCHING
+// =============================================================================
+
+/// Seconds a playlist entry's duration may differ from a track's.
+const PLAYLIST_MATCH_DURATION_TOLERANCE: i32 = 3;
+
+pub fn get_track_id_by_path(conn: &Connection, path: &str) -> Result<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM tracks WHERE path = ?1 OR local_src = ?1 ORDER BY id LIMIT 1",
+        [path],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// A library track for a playlist entry whose file wasn't found, by title
+/// and, where the entry has them, artist and duration. An entry with only
+/// a title matches only when exactly one track has that title.
+pub fn find_track_by_tags(
+    conn: &Connection,
+    artist: Option<&str>,
+    title: &str,
+    duration: Option<i32>,
+) -> Result<Option<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.artist, t.album_artist, t.duration,
+                EXISTS (SELECT 1 FROM track_artists ta
+                        INNER JOIN artists a ON a.id = ta.artist_id
+                        WHERE ta.track_id = t.id AND a.name = ?2 COLLATE NOCASE)
+         FROM tracks t
+         WHERE t.title = ?1 COLLATE NOCASE
+         ORDER BY CASE WHEN t.source_type IS NULL OR t.source_type = 'local' THEN 0 ELSE 1 END, t.id",
+    )?;
+    let candidates = stmt
+        .query_map(params![title.trim(), artist.map(str::trim)], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<i32>>(3)?,
+                row.get::<_, bool>(4)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    let matching: Vec<i64> = candidates
+        .iter()
+        .filter(
+            |(_, track_artist, album_artist, _, credited)| match artist {
+                Some(wanted) => {
+                    *credited
+                        || [track_artist, album_artist].iter().any(|a| {
+                            a.as_deref()
+                                .is_some_and(|a| a.trim().eq_ignore_ascii_case(wanted.trim()))
+                        })
+                }
+                None => true,
+            },
+        )
+        .filter(
+            |(_, _, _, track_duration, _)| match (duration, track_duration) {
+                (Some(wanted), Some(actual)) => {
+                    (wanted - actual).abs() <= PLAYLIST_MATCH_DURATION_TOLERANCE
+                }
+                _ => true,
+            },
+        )
+        .map(|(id, ..)| *id)
+        .collect();
+
+    if artist.is_none() && duration.is_none() && matching.len() > 1 {
+        return Ok(None);
+    }
+    Ok(matching.first().copied())
+}
+
+// =============================================================================
+// PLAYLIST FOLDERS
+// =============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistFolder {
+    pub id: i64,
+    pub name: String,
+    /// `None` at the top level
+    pub parent_id: Option<i64>,
+    /// Order among the parent's subfolders
+    pub position: f64,
+    pub created_at: Option<String>,
+}
+
+fn playlist_folder_from_row(row: &rusqlite::Row) -> Result<PlaylistFolder> {
+    Ok(PlaylistFolder {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        parent_id: row.get(2)?,
+        position: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+/// All folders, parents before their subfolders.
+pub fn get_playlist_folders(conn: &Connection) -> Result<Vec<PlaylistFolder>> {
+    let mut stmt = conn.prepare(
+        "WITH RECURSIVE tree(id, depth) AS (
+            SELECT id, 0 FROM playlist_folders WHERE parent_id IS NULL
+            UNION ALL
+            SELECT f.id, tree.depth + 1 FROM playlist_folders f JOIN tree ON f.parent_id = tree.id
+         )
+         SELECT f.id, f.name, f.parent_id, f.position, f.created_at
+         FROM playlist_folders f JOIN tree ON tree.id = f.id
+         ORDER BY tree.depth, f.parent_id, f.position, f.id",
+    )?;
+    let folders = stmt
+        .query_map([], playlist_folder_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(folders)
+}
+
+pub fn get_playlist_folder(conn: &Connection, folder_id: i64) -> Result<Option<PlaylistFolder>> {
+    conn.query_row(
+        "SELECT id, name, parent_id, position, created_at FROM playlist_folders WHERE id = ?1",
+        [folder_id],
+        playlist_folder_from_row,
+    )
+    .optional()
+}
+
+/// Create a folder at the end of `parent_id`, or at `position` when given
+/// (folders synced from another device keep their position).
+pub fn create_playlist_folder(
+    conn: &Connection,
+    name: &str,
+    parent_id: Option<i64>,
+    position: Option<f64>,
+) -> Result<i64> {
+    let position = match position {
+        Some(position) => position,
+        None => conn.query_row(
+            "SELECT COALESCE(MAX(position), 0) + 1 FROM playlist_folders WHERE parent_id IS ?1",
+            [parent_id],
+            |row| row.get(0),
+        )?,
+    };
+    conn.execute(
+        "INSERT INTO playlist_folders (name, parent_id, position) VALUES (?1, ?2, ?3)",
+        params![name, parent_id, position],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Returns false if there is no such folder.
+pub fn rename_playlist_folder(conn: &Connection, folder_id: i64, name: &str) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE playlist_folders SET name = ?2 WHERE id = ?1",
+        params![folder_id, name],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Whether `folder_id` is `ancestor_id` or somewhere below it.
+pub fn playlist_folder_is_within(
+    conn: &Connection,
+    folder_id: i64,
+    ancestor_id: i64,
+) -> Result<bool> {
+    conn.query_row(
+        "WITH RECURSIVE up(id) AS (
+            SELECT ?1
+            UNION
+            SELECT f.parent_id FROM playlist_folders f JOIN up ON f.id = up.id
+            WHERE f.parent_id IS NOT NULL
+         )
+         SELECT EXISTS(SELECT 1 FROM up WHERE id = ?2)",
+        params![folder_id, ancestor_id],
+        |row| row.get(0),
+    )
+}
+
+/// Move a folder under `parent_id` (`None` for the top level) at `index`
+/// among its new siblings, or to the end. The caller must make sure the
+/// new parent isn't inside the folder. Returns every (folder_id, position)
+/// that changed; normally just the moved folder.
+pub fn move_playlist_folder(
+    conn: &Connection,
+    folder_id: i64,
+    parent_id: Option<i64>,
+    index: Option<usize>,
+) -> Result<Vec<(i64, f64)>> {
+    let siblings: Vec<(i64, Option<f64>)> = conn
+        .prepare(
+            "SELECT id, position FROM playlist_folders WHERE parent_id IS ?1 AND id != ?2
+             ORDER BY position, id",
+        )?
+        .query_map(params![parent_id, folder_id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    let changed = place_among_siblings(&siblings, folder_id, index);
+    conn.execute(
+        "UPDATE playlist_folders SET parent_id = ?2 WHERE id = ?1",
+        params![folder_id, parent_id],
+    )?;
+    for (id, position) in &changed {
+        conn.execute(
+            "UPDATE playlist_folders SET position = ?2 WHERE id = ?1",
+            params![id, position],
+        )?;
+    }
+    Ok(changed)
+}
+
+/// Move a playlist into `folder_id` (`None` for the top level) at `index`
+/// among the folder's playlists, or to the end. Returns every
+/// (playlist_id, position) that changed: normally just the moved playlist,
+/// but playlists still sorted by name get positions the first time one is
+/// placed among them.
+pub fn move_playlist(
+    conn: &Connection,
+    playlist_id: i64,
+    folder_id: Option<i64>,
+    index: Option<usize>,
+) -> Result<Vec<(i64, f64)>> {
+    if !playlist_exists(conn, playlist_id)? {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    let siblings: Vec<(i64, Option<f64>)> = conn
+        .prepare(
+            "SELECT id, position FROM playlists
+             WHERE folder_id IS ?1 AND id != ?2
+             ORDER BY position IS NULL, position, name",
+        )?
+        .query_map(params![folder_id, playlist_id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    let changed = place_among_siblings(&siblings, playlist_id, index);
+    conn.execute(
+        "UPDATE playlists SET folder_id = ?2 WHERE id = ?1",
+        params![playlist_id, folder_id],
+    )?;
+    for (id, position) in &changed {
+        conn.execute(
+            "UPDATE playlists SET position = ?2 WHERE id = ?1",
+            params![id, position],
+        )?;
+    }
+    Ok(changed)
+}
+
+/// New positions for an item placed at `index` (or last) among `siblings`,
+/// which are in display order. Only the item moves when there is room
+/// between its new neighbours; otherwise (or when some siblings have no
+/// position yet) everything is renumbered 1, 2, 3...
+fn place_among_siblings(
+    siblings: &[(i64, Option<f64>)],
+    id: i64,
+    index: Option<usize>,
+) -> Vec<(i64, f64)> {
+    let index = index.unwrap_or(siblings.len()).min(siblings.len());
+    let positions: Option<Vec<f64>> = siblings.iter().map(|(_, position)| *position).collect();
+    if let Some(position) = positions.and_then(|p| position_between(&p, index)) {
+        return vec![(id, position)];
+    }
+    let mut ids: Vec<i64> = siblings.iter().map(|(id, _)| *id).collect();
+    ids.insert(index, id);
+    ids.into_iter()
+        .enumerate()
+        .map(|(i, id)| (id, i as f64 + 1.0))
+        .collect()
+}
+
+/// Delete a folder, moving its subfolders and playlists up to its parent.
+pub fn delete_playlist_folder(conn: &Connection, folder_id: i64) -> Result<()> {
+    let Some(folder) = get_playlist_folder(conn, folder_id)? else {
+        return Ok(());
+    };
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE playlist_folders SET parent_id = ?2 WHERE parent_id = ?1",
+        params![folder_id, folder.parent_id],
+    )?;
+    tx.execute(
+        "UPDATE playlists SET folder_id = ?2 WHERE folder_id = ?1",
+        params![folder_id, folder.parent_id],
+    )?;
+    tx.execute("DELETE FROM playlist_folders WHERE id = ?1", [folder_id])?;
+    tx.commit()
+}
+
+/// Local folder id for a server folder id.
+pub fn find_playlist_folder_by_server_id(
+    conn: &Connection,
+    server_id: &str,
+) -> Result<Option<i64>> {
+    let local_id = get_local_id_from_server(conn, server_id, "playlist_folder")?;
+    Ok(local_id.and_then(|id| id.parse().ok()))
+}
+
+/// Put a playlist in a folder at an explicit position (from sync).
+pub fn set_playlist_folder(
+    conn: &Connection,
+    playlist_id: i64,
+    folder_id: Option<i64>,
+    position: Option<f64>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE playlists SET folder_id = ?2, position = ?3 WHERE id = ?1",
+        params![playlist_id, folder_id, position],
+    )?;
+    Ok(())
+}
+
+/// Move a folder to an explicit parent and position (from sync).
+pub fn set_playlist_folder_parent(
+    conn: &Connection,
+    folder_id: i64,
+    parent_id: Option<i64>,
+    position: f64,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE playlist_folders SET parent_id = ?2, position = ?3 WHERE id = ?1",
+        params![folder_id, parent_id, position],
+    )?;
+    Ok(())
+}
+
+// =============================================================================
+// PLAYLIST BULK OPERATIONS
+// =============================================================================
+
+/// A playlist's entries in order, with the fields bulk operations sort and
+/// group on.
+pub fn get_playlist_items(conn: &Connection, playlist_id: i64) -> Result<Vec<PlaylistItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT pt.id, t.id, t.path, t.source_type, t.content_hash, t.title, t.artist,
+                t.album_artist, t.album, t.genre, t.year, t.disc_number, t.track_number,
+                t.duration, t.bitrate, t.bpm, t.date_added,
+                t.last_played_at, t.play_count
+         FROM playlist_tracks pt
+         JOIN tracks t ON t.id = pt.track_id
+         WHERE pt.playlist_id = ?1
+         ORDER BY pt.position, pt.id",
+    )?;
+    let items = stmt
+        .query_map([playlist_id], |row| {
+            Ok(PlaylistItem {
+                entry_id: row.get(0)?,
+                track_id: row.get(1)?,
+                path: row.get(2)?,
+                source_type: row.get(3)?,
+                content_hash: row.get(4)?,
+                title: row.get(5)?,
+                artist: row.get(6)?,
+                album_artist: row.get(7)?,
+                album: row.get(8)?,
+                genre: row.get(9)?,
+                year: row.get(10)?,
+                disc_number: row.get(11)?,
+                track_number: row.get(12)?,
+                duration: row.get(13)?,
+                bitrate: row.get(14)?,
+                bpm: row.get(15)?,
+                date_added: row.get(16)?,
+                last_played: row.get(17)?,
+                play_count: row.get(18)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(items)
+}
+
+/// Give entries positions 1, 2, 3... in the order of `entry_ids`.
+pub fn set_playlist_entry_order(conn: &Connection, entry_ids: &[i64]) -> Result<()> {
+    let mut stmt = conn.prepare("UPDATE playlist_tracks SET position = ?2 WHERE id = ?1")?;
+    for (i, entry_id) in entry_ids.iter().enumerate() {
+        stmt.execute(params![entry_id, i as f64 + 1.0])?;
+    }
+    Ok(())
+}
+
+pub fn remove_playlist_entries(conn: &Connection, entry_ids: &[i64]) -> Result<usize> {
+    let mut stmt = conn.prepare("DELETE FROM playlist_tracks WHERE id = ?1")?;
+    let mut removed = 0;
+    for entry_id in entry_ids {
+        removed += stmt.execute([entry_id])?;
+    }
+    Ok(removed)
+}
+
+/// Append the tracks of `entry_ids` (entries of any playlist, in that order)
+/// to `playlist_id`.
+pub fn copy_playlist_entries(conn: &Connection, playlist_id: i64, entry_ids: &[i64]) -> Result<()> {
+    let mut position: f64 = conn.query_row(
+        "SELECT COALESCE(MAX(position), 0) FROM playlist_tracks WHERE playlist_id = ?1",
+        [playlist_id],
+        |row| row.get(0),
+    )?;
+    let mut stmt = conn.prepare(
+        "INSERT INTO playlist_tracks (playlist_id, track_id, position)
+         SELECT ?1, track_id, ?3 FROM playlist_tracks WHERE id = ?2",
+    )?;
+    for entry_id in entry_ids {
+        position += 1.0;
+        stmt.execute(params![playlist_id, entry_id, position])?;
+    }
+    Ok(())
+}
+
+// =============================================================================
+// FOLDER PLAYLIST FILES
+// =============================================================================
+
+/// Sync state of the .m3u8 kept in a folder playlist's directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderPlaylistFile {
+    pub playlist_id: i64,
+    pub file_path: String,
+    pub file_hash: Option<String>,
+    pub synced_at: Option<String>,
+    /// Both the file and the playlist changed since the last sync
+    pub conflict: bool,
+}
+
+fn row_to_folder_playlist_file(row: &rusqlite::Row) -> Result<FolderPlaylistFile> {
+    Ok(FolderPlaylistFile {
+        playlist_id: row.get(0)?,
+        file_path: row.get(1)?,
+        file_hash: row.get(2)?,
+        synced_at: row.get(3)?,
+        conflict: row.get(4)?,
+    })
+}
+
+pub fn get_folder_playlist_files(conn: &Connection) -> Result<Vec<FolderPlaylistFile>> {
+    let mut stmt = conn.prepare(
+        "SELECT playlist_id, file_path, file_hash, synced_at, conflict
+         FROM folder_playlist_files ORDER BY playlist_id",
+    )?;
+    let files = stmt
+        .query_map([], row_to_folder_playlist_file)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(files)
+}
+
+pub fn get_folder_playlist_file(
+    conn: &Connection,
+    playlist_id: i64,
+) -> Result<Option<FolderPlaylistFile>> {
+    conn.query_row(
+        "SELECT playlist_id, file_path, file_hash, synced_at, conflict
+         FROM folder_playlist_files WHERE playlist_id = ?1",
+        [playlist_id],
+        row_to_folder_playlist_file,
+    )
+    .optional()
+}
+
+/// The directory a folder playlist mirrors; `None` for other playlists.
+pub fn get_playlist_folder_path(conn: &Connection, playlist_id: i64) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT folder_path FROM playlists WHERE id = ?1",
+            [playlist_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten())
+}
+
+/// Record that the playlist and its file agree, the file hashing to
+/// `file_hash`. Clears any conflict.
+pub fn set_folder_playlist_file_synced(
+    conn: &Connection,
+    playlist_id: i64,
+    file_path: &str,
+    file_hash: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO folder_playlist_files (playlist_id, file_path, file_hash, synced_at, conflict)
+         VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP, 0)
+         ON CONFLICT(playlist_id) DO UPDATE SET
+            file_path = excluded.file_path,
+            file_hash = excluded.file_hash,
+            synced_at = excluded.synced_at,
+            conflict = 0",
+        params![playlist_id, file_path, file_hash],
+    )?;
+    Ok(())
+}
+
+/// Mark the file as conflicting; a file found for the first time is
+/// recorded without a hash, so it still counts as changed afterwards.
+pub fn set_folder_playlist_file_conflict(
+    conn: &Connection,
+    playlist_id: i64,
+    file_path: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO folder_playlist_files (playlist_id, file_path, conflict)
+         VALUES (?1, ?2, 1)
+         ON CONFLICT(playlist_id) DO UPDATE SET conflict = 1",
+        params![playlist_id, file_path],
+    )?;
+    Ok(())
+}
+
+pub fn delete_folder_playlist_file(conn: &Connection, playlist_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM folder_playlist_files WHERE playlist_id = ?1",
+        [playlist_id],
+    )?;
+    Ok(())
+}
+
+// =============================================================================
+// YEAR IN REVIEW
+// =============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewPlay {
+    pub played_at: String,
+    pub track: Track,
+}
+
+/// The day one track was played the most times.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayDay {
+    /// Local date, `YYYY-MM-DD`
+    pub date: String,
+    pub track: Track,
+    pub track_plays: i64,
+    /// All plays that day
+    pub total_plays: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthTrend {
+    /// `YYYY-MM`
+    pub month: String,
+    pub plays: i64,
+    pub minutes: i64,
+    pub top_artist: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct YearInReview {
+    pub year: i32,
+    pub total_plays: i64,
+    pub total_minutes: i64,
+    pub unique_tracks: i64,
+    pub unique_artists: i64,
+    pub top_tracks: Vec<TrackWithCount>,
+    pub top_artists: Vec<ArtistWithCount>,
+    pub top_albums: Vec<AlbumWithCount>,
+    pub top_genres: Vec<GenreWithCount>,
+    pub first_play: Option<ReviewPlay>,
+    pub last_play: Option<ReviewPlay>,
+    pub most_replayed_day: Option<ReplayDay>,
+    /// All twelve months, January first
+    pub monthly: Vec<MonthTrend>,
+}
+
+/// The first (or last) play in `period`, with its track.
+fn get_edge_play(
+    conn: &Connection,
+    period: &StatsPeriod,
+    last: bool,
+) -> Result<Option<ReviewPlay>> {
+    let sql = format!(
+        "SELECT {}, p.played_at FROM tracks
+         INNER JOIN (
+            SELECT track_id, played_at FROM play_history
+            WHERE {counted}
+            AND (?1 IS NULL OR played_at >= ?1) AND (?2 IS NULL OR played_at < ?2)
+            ORDER BY played_at {order}, id {order}
+            LIMIT 1
+         ) p ON p.track_id = tracks.id",
+        TRACK_COLUMNS,
+        counted = COUNTED_PLAY_SQL,
+        order = if last { "DESC" } else { "ASC" }
+    );
+    conn.query_row(&sql, params![period.from, period.to], |row| {
+        Ok(ReviewPlay {
+            track: map_track(row)?,
+            played_at: row.get(19)?,
+        })
+    })
+    .optional()
+}
+
+fn get_most_replayed_day(conn: &Connection, period: &StatsPeriod) -> Result<Option<ReplayDay>> {
+    let sql = format!(
+        "SELECT {}, d.day, d.plays,
+                (SELECT COUNT(*) FROM play_history
+                 WHERE {counted} AND date(played_at, 'localtime') = d.day)
+         FROM tracks
+         INNER JOIN (
+            SELECT track_id, date(played_at, 'localtime') AS day, COUNT(*) AS plays
+            FROM play_history
+            WHERE {counted}
+            AND (?1 IS NULL OR played_at >= ?1) AND (?2 IS NULL OR played_at < ?2)
+            GROUP BY track_id, day
+            ORDER BY plays DESC, day
+            LIMIT 1
+         ) d ON d.track_id = tracks.id",
+        TRACK_COLUMNS,
+        counted = COUNTED_PLAY_SQL
+    );
+    conn.query_row(&sql, params![period.from, period.to], |row| {
+        Ok(ReplayDay {
+            track: map_track(row)?,
+            date: row.get(19)?,
+            track_plays: row.get(20)?,
+            total_plays: row.get(21)?,
+        })
+    })
+    .optional()
+}
+
+fn get_monthly_trends(
+    conn: &Connection,
+    year: i32,
+    period: &StatsPeriod,
+) -> Result<Vec<MonthTrend>> {
+    let mut monthly: Vec<MonthTrend> = (1..=12)
+        .map(|month| MonthTrend {
+            month: format!("{:04}-{:02}", year, month),
+            plays: 0,
+            minutes: 0,
+            top_artist: None,
+        })
+        .collect();
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT strftime('%Y-%m', played_at, 'localtime') AS month, COUNT(*),
+                COALESCE(SUM(duration_played), 0) / 60
+         FROM play_history
+         WHERE {counted}
+         AND (?1 IS NULL OR played_at >= ?1) AND (?2 IS NULL OR played_at < ?2)
+         GROUP BY month",
+        counted = COUNTED_PLAY_SQL
+    ))?;
+    let rows = stmt.query_map(params![period.from, period.to], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+        ))
+    })?;
+    for row in rows {
+        let (month, plays, minutes) = row?;
+        if let Some(trend) = monthly.iter_mut().find(|m| m.month == month) {
+            trend.plays = plays;
+            trend.minutes = minutes;
+        }
+    }
+
+    // Most played artist per month; ties go to the name that sorts first
+    let mut stmt = conn.prepare(&format!(
+        "SELECT month, name FROM (
+            SELECT strftime('%Y-%m', ph.played_at, 'localtime') AS month, a.name,
+                   ROW_NUMBER() OVER (
+                       PARTITION BY strftime('%Y-%m', ph.played_at, 'localtime')
+                       ORDER BY COUNT(DISTINCT ph.id) DESC, a.name
+                   ) AS rank
+            FROM artists a
+            INNER JOIN track_artists ta ON ta.artist_id = a.id AND ta.role IN ('main', 'featured')
+            INNER JOIN play_history ph ON ph.track_id = ta.track_id
+            WHERE {counted}
+            AND (?1 IS NULL OR ph.played_at >= ?1) AND (?2 IS NULL OR ph.played_at < ?2)
+            GROUP BY month, a.id
+         )
+         WHERE rank = 1",
+        counted = COUNTED_PLAY_SQL
+    ))?;
+    let rows = stmt.query_map(params![period.from, period.to], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for row in rows {
+        let (month, artist) = row?;
+        if let Some(trend) = monthly.iter_mut().find(|m| m.month == month) {
+            trend.top_artist = Some(artist);
+        }
+    }
+    Ok(monthly)
+}
+
+/// A year of listening from play_history; `period` covers `year`, and each
+/// top list holds up to `limit` entries.
+pub fn get_year_in_review(
+    conn: &Connection,
+    year: i32,
+    period: &StatsPeriod,
+    limit: i32,
+) -> Result<YearInReview> {
+    let summary = get_stats_summary(conn, period)?;
+    let (unique_tracks, unique_artists): (i64, i64) = conn.query_row(
+        &format!(
+            "SELECT COUNT(DISTINCT ph.track_id),
+                (SELECT COUNT(DISTINCT ta.artist_id)
+                 FROM track_artists ta
+                 INNER JOIN play_history p ON p.track_id = ta.track_id
+                 WHERE ta.role IN ('main', 'featured') AND {counted}
+                 AND (?1 IS NULL OR p.played_at >= ?1) AND (?2 IS NULL OR p.played_at < ?2))
+         FROM play_history ph
+         WHERE {counted}
+         AND (?1 IS NULL OR ph.played_at >= ?1) AND (?2 IS NULL OR ph.played_at < ?2)",
+            counted = COUNTED_PLAY_SQL
+        ),
+        params![period.from, period.to],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    Ok(YearInReview {
+        year,
+        total_plays: summary.total_plays,
+        total_minutes: summary.total_duration_seconds / 60,
+        unique_tracks,
+        unique_artists,
+        top_tracks: get_top_tracks(conn, limit, period)?,
+        top_artists: get_top_artists(conn, limit, period)?,
+        top_albums: get_top_albums(conn, limit, period)?,
+        top_genres: get_top_genres(conn, limit, period)?,
+        first_play: get_edge_play(conn, period, false)?,
+        last_play: get_edge_play(conn, period, true)?,
+        most_replayed_day: get_most_replayed_day(conn, period)?,
+        monthly: get_monthly_trends(conn, year, period)?,
+    })
+}
+
+// =============================================================================
+// PLAY COUNTS & SKIPS
+// =============================================================================
+
+const SKIP_THRESHOLDS_KEY: &str = "skip_thresholds";
+const PLAY_COUNTS_MIGRATION_KEY: &str = "play_counts_migrated";
+
+pub fn get_skip_thresholds(conn: &Connection) -> Result<SkipThresholds> {
+    Ok(get_library_setting(conn, SKIP_THRESHOLDS_KEY)?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+pub fn set_skip_thresholds(conn: &Connection, thresholds: &SkipThresholds) -> Result<()> {
+    let json = serde_json::to_string(thresholds).unwrap_or_default();
+    set_library_setting(conn, SKIP_THRESHOLDS_KEY, &json)
+}
+
+/// Recompute play_count, skip_count and last_played_at from play_history
+/// for one track, or for every track when `track_id` is `None`.
+pub fn refresh_play_counts(conn: &Connection, track_id: Option<i64>) -> Result<usize> {
+    conn.execute(
+        "UPDATE tracks SET
+            play_count = (SELECT COUNT(*) FROM play_history
+                          WHERE track_id = tracks.id AND skipped = 0),
+            skip_count = (SELECT COUNT(*) FROM play_history
+                          WHERE track_id = tracks.id AND skipped = 1),
+            last_played_at = (SELECT MAX(played_at) FROM play_history
+                              WHERE track_id = tracks.id AND skipped = 0)
+         WHERE ?1 IS NULL OR id = ?1",
+        [track_id],
+    )
+}
+
+/// Classify every play in the history again against `thresholds`, then
+/// recompute all track counts. Returns the number of plays whose
+/// classification changed.
+pub fn reclassify_plays(conn: &Connection, thresholds: &SkipThresholds) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    let plays: Vec<(i64, i64, Option<i64>, bool)> = {
+        let mut stmt = tx.prepare(
+            "SELECT ph.id, COALESCE(ph.duration_played, 0), t.duration, ph.skipped
+             FROM play_history ph
+             INNER JOIN tracks t ON t.id = ph.track_id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?;
+        rows.collect::<Result<Vec<_>>>()?
+    };
+
+    let mut changed = 0;
+    {
+        let mut update = tx.prepare("UPDATE play_history SET skipped = ?2 WHERE id = ?1")?;
+        for (id, heard, track_duration, skipped) in plays {
+            let skip = play_counts::classify(thresholds, heard, track_duration).is_skip();
+            if skip != skipped {
+                update.execute(params![id, skip])?;
+                changed += 1;
+            }
+        }
+    }
+    refresh_play_counts(&tx, None)?;
+    tx.commit()?;
+    Ok(changed)
+}
+
+/// One-time backfill for history recorded before skips were told apart:
+/// classify it with the current thresholds and fill in the track counts.
+pub fn migrate_play_counts(conn: &Connection) -> Result<Option<usize>> {
+    if get_library_setting(conn, PLAY_COUNTS_MIGRATION_KEY)?.is_some() {
+        return Ok(None);
+    }
+    let skips = reclassify_plays(conn, &get_skip_thresholds(conn)?)?;
+    set_library_setting(conn, PLAY_COUNTS_MIGRATION_KEY, "1")?;
+    Ok(Some(skips))
+}
+
+// =============================================================================
+// RATINGS
+// =============================================================================
+
+const RATING_WRITE_BACK_KEY: &str = "rating_write_back";
+
+/// A track's or album's rating, in half stars.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rating {
+    pub id: i64,
+    pub rating: u8,
+}
+
+pub fn get_track_rating(conn: &Connection, track_id: i64) -> Result<Option<u8>> {
+    Ok(conn
+        .query_row(
+            "SELECT rating FROM tracks WHERE id = ?1",
+            [track_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten())
+}
+
+/// Set or clear (`None`) a track's rating. Returns false if there is no
+/// such track.
+pub fn set_track_rating(conn: &Connection, track_id: i64, rating: Option<u8>) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE tracks SET rating = ?2 WHERE id = ?1",
+        params![track_id, rating],
+    )?;
+    Ok(updated > 0)
+}
+
+pub fn set_album_rating(conn: &Connection, album_id: i64, rating: Option<u8>) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE albums SET rating = ?2 WHERE id = ?1",
+        params![album_id, rating],
+    )?;
+    Ok(updated > 0)
+}
+
+fn get_ratings(conn: &Connection, table: &str) -> Result<Vec<Rating>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, rating FROM {} WHERE rating IS NOT NULL ORDER BY id",
+        table
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Rating {
+            id: row.get(0)?,
+            rating: row.get(1)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Every rated track; unrated tracks are left out.
+pub fn get_track_ratings(conn: &Connection) -> Result<Vec<Rating>> {
+    get_ratings(conn, "tracks")
+}
+
+pub fn get_album_ratings(conn: &Connection) -> Result<Vec<Rating>> {
+    get_ratings(conn, "albums")
+}
+
+/// Album matched by name and album artist, for ratings from other devices.
+pub fn find_album_by_name(
+    conn: &Connection,
+    name: &str,
+    artist: Option<&str>,
+) -> Result<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM albums
+         WHERE name = ?1 COLLATE NOCASE
+           AND COALESCE(artist, '') = COALESCE(?2, '') COLLATE NOCASE
+         ORDER BY id LIMIT 1",
+        params![name, artist],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Whether rating changes are also written to the files' tags.
+pub fn get_rating_write_back(conn: &Connection) -> Result<bool> {
+    Ok(get_library_setting(conn, RATING_WRITE_BACK_KEY)?.as_deref() == Some("1"))
+}
+
+pub fn set_rating_write_back(conn: &Connection, enabled: bool) -> Result<()> {
+    set_library_setting(conn, RATING_WRITE_BACK_KEY, if enabled { "1" } else { "0" })
+}
+
+/// Record a file's stamp after the app rewrote it, so the next rescan
+/// doesn't read it again.
+pub fn set_track_file_stamp(
+    conn: &Connection,
+    track_id: i64,
+    file_mtime: i64,
+    file_size: i64,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE tracks SET file_mtime = ?2, file_size = ?3 WHERE id = ?1",
+        params![track_id, file_mtime, file_size],
+    )?;
+    Ok(())
+}
+
+/// Queue a track rating for sync. Like liked tracks, ratings are matched
+/// across devices by the title|artist|album hash; `None` clears it.
+pub fn enqueue_track_rating_sync_change(
+    conn: &Connection,
+    track: &Track,
+    rating: Option<u8>,
+) -> Result<()> {
+    if !is_logged_in(conn) {
+        return Ok(());
+    }
+
+    let track_hash = build_track_hash_str(
+        track.title.as_deref(),
+        track.artist.as_deref(),
+        track.album.as_deref(),
+    );
+
+    let payload = serde_json::json!({
+        "trackHash": track_hash,
+        "title": track.title,
+        "artist": track.artist,
+        "album": track.album,
+        "duration": track.duration,
+        "externalId": track.external_id,
+        "sourceType": track.source_type,
+        "coverUrl": track.cover_url,
+        "rating": rating,
+    });
+
+    let _ = enqueue_sync_change(
+        conn,
+        "track_rating",
+        &format!("local_rating_{}", track.id),
+        if rating.is_some() { "update" } else { "delete" },
+        Some(&payload.to_string()),
+    );
+
+    Ok(())
+}
+
+pub fn enqueue_album_rating_sync_change(
+    conn: &Connection,
+    album: &Album,
+    rating: Option<u8>,
+) -> Result<()> {
+    if !is_logged_in(conn) {
+        return Ok(());
+    }
+
+    let payload = serde_json::json!({
+        "albumName": album.name,
+        "albumArtist": album.artist,
+        "rating": rating,
+    });
+
+    let _ = enqueue_sync_change(
+        conn,
+        "album_rating",
+        &format!("local_album_rating_{}", album.id),
+        if rating.is_some() { "update" } else { "delete" },
+        Some(&payload.to_string()),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::schema::init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn track(conn: &Connection, path: &str) -> i64 {
+        conn.execute("INSERT INTO tracks (path) VALUES (?1)", [path])
+            .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    /// Entry ids of a playlist, in order.
+    fn entry_order(conn: &Connection, playlist_id: i64) -> Vec<i64> {
+        get_playlist_entries(conn, playlist_id)
+            .unwrap()
+            .into_iter()
+            .filter_map(|entry| entry.entry_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_position_between() {
+        assert_eq!(position_between(&[], 0), Some(1.0));
+        assert_eq!(position_between(&[1.0, 2.0], 0), Some(0.0));
+        assert_eq!(position_between(&[1.0, 2.0], 1), Some(1.5));
+        assert_eq!(position_between(&[1.0, 2.0], 2), Some(3.0));
+        // Neighbours one float step apart leave no room
+        assert_eq!(position_between(&[1.0, 1.0 + f64::EPSILON], 1), None);
+        assert_eq!(position_between(&[1.0, 1.0], 1), None);
+    }
+
+    #[test]
+    fn test_move_playlist_entry() {
+        let conn = library();
+        let playlist = create_playlist(&conn, "Mix").unwrap();
+        let a = track(&conn, "/a.mp3");
+        let b = track(&conn, "/b.mp3");
+        let first = add_track_to_playlist(&conn, playlist, a).unwrap();
+        let second = add_track_to_playlist(&conn, playlist, b).unwrap();
+        let again = add_track_to_playlist(&conn, playlist, a).unwrap();
+        assert_eq!(
+            add_track_to_playlist_once(&conn, playlist, a).unwrap(),
+            first
+        );
+        assert_eq!(entry_order(&conn, playlist), vec![first, second, again]);
+
+        // Only the moved entry is written
+        let changed = move_playlist_entry(&conn, playlist, again, 1).unwrap();
+        assert_eq!(changed, vec![(again, 1.5)]);
+        assert_eq!(entry_order(&conn, playlist), vec![first, again, second]);
+
+        // Past the end means last
+        move_playlist_entry(&conn, playlist, first, 10).unwrap();
+        assert_eq!(entry_order(&conn, playlist), vec![again, second, first]);
+
+        // Entries of another playlist can't be moved here
+        let other = create_playlist(&conn, "Other").unwrap();
+        assert!(move_playlist_entry(&conn, other, first, 0).is_err());
+    }
+
+    #[test]
+    fn test_move_playlist_entry_renumbers() {
+        let conn = library();
+        let playlist = create_playlist(&conn, "Mix").unwrap();
+        let entries: Vec<i64> = (0..4)
+            .map(|i| {
+                let track_id = track(&conn, &format!("/{}.mp3", i));
+                add_track_to_playlist(&conn, playlist, track_id).unwrap()
+            })
+            .collect();
+
+        // Keep moving the entry after the first into second place from the
+        // back: each move halves the gap until no float fits in it
+        let mut renumbered = None;
+        for round in 0..100 {
+            let order = entry_order(&conn, playlist);
+            let last = order[order.len() - 1];
+            let changed = move_playlist_entry(&conn, playlist, last, 1).unwrap();
+            if changed.len() > 1 {
+                renumbered = Some((round, changed));
+                break;
+            }
+        }
+        let (round, changed) = renumbered.expect("positions never ran out");
+        assert!(round > 40, "renumbered after {} moves", round);
+
+        // Every entry renumbered 1, 2, 3... in the new order
+        let order = entry_order(&conn, playlist);
+        assert_eq!(order[0], entries[0]);
+        let renumbered: Vec<(i64, f64)> = order
+            .iter()
+            .enumerate()
+            .map(|(i, &entry_id)| (entry_id, i as f64 + 1.0))
+            .collect();
+        assert_eq!(changed, renumbered);
+        let mut ids = order;
+        ids.sort();
+        assert_eq!(ids, entries);
+    }
+
+    /// Ids of the folders directly under `parent_id`, in order.
+    fn subfolders(conn: &Connection, parent_id: Option<i64>) -> Vec<i64> {
+        get_playlist_folders(conn)
+            .unwrap()
+            .into_iter()
+            .filter(|folder| folder.parent_id == parent_id)
+            .map(|folder| folder.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_place_among_siblings() {
+        let placed = [(1, Some(1.0)), (2, Some(2.0))];
+        assert_eq!(place_among_siblings(&placed, 9, Some(0)), vec![(9, 0.0)]);
+        assert_eq!(place_among_siblings(&placed, 9, Some(1)), vec![(9, 1.5)]);
+        assert_eq!(place_among_siblings(&placed, 9, None), vec![(9, 3.0)]);
+        assert_eq!(place_among_siblings(&placed, 9, Some(7)), vec![(9, 3.0)]);
+        assert_eq!(place_among_siblings(&[], 9, None), vec![(9, 1.0)]);
+
+        // A sibling without a position renumbers everything
+        let unplaced = [(1, Some(1.0)), (2, None)];
+        assert_eq!(
+            place_among_siblings(&unplaced, 9, Some(1)),
+            vec![(1, 1.0), (9, 2.0), (2, 3.0)]
+        );
+        // So does a gap too small to split
+        let crowded = [(1, Some(1.0)), (2, Some(1.0))];
+        assert_eq!(
+            place_among_siblings(&crowded, 9, Some(1)),
+            vec![(1, 1.0), (9, 2.0), (2, 3.0)]
+        );
+    }
+
+    #[test]
+    fn test_playlist_folder_is_within() {
+        let conn = library();
+        let root = create_playlist_folder(&conn, "Root", None, None).unwrap();
+        let child = create_playlist_folder(&conn, "Child", Some(root), None).unwrap();
+        let grandchild = create_playlist_folder(&conn, "Grandchild", Some(child), None).unwrap();
+        let other = create_playlist_folder(&conn, "Other", None, None).unwrap();
+
+        assert!(playlist_folder_is_within(&conn, grandchild, root).unwrap());
+        assert!(playlist_folder_is_within(&conn, child, root).unwrap());
+        assert!(playlist_folder_is_within(&conn, root, root).unwrap());
+        assert!(!playlist_folder_is_within(&conn, root, grandchild).unwrap());
+        assert!(!playlist_folder_is_within(&conn, other, root).unwrap());
+
+        // After moving the branch, the old ancestor no longer contains it
+        move_playlist_folder(&conn, child, Some(other), None).unwrap();
+        assert!(playlist_folder_is_within(&conn, grandchild, other).unwrap());
+        assert!(!playlist_folder_is_within(&conn, grandchild, root).unwrap());
+    }
+
+    #[test]
+    fn test_move_playlist_folder() {
+        let conn = library();
+        let a = create_playlist_folder(&conn, "A", None, None).unwrap();
+        let b = create_playlist_folder(&conn, "B", None, None).unwrap();
+        let c = create_playlist_folder(&conn, "C", None, None).unwrap();
+        assert_eq!(subfolders(&conn, None), vec![a, b, c]);
+
+        // Only the moved folder is written
+        assert_eq!(
+            move_playlist_folder(&conn, c, None, Some(0)).unwrap(),
+            vec![(c, 0.0)]
+        );
+        assert_eq!(subfolders(&conn, None), vec![c, a, b]);
+        move_playlist_folder(&conn, c, None, Some(2)).unwrap();
+        assert_eq!(subfolders(&conn, None), vec![a, b, c]);
+
+        // Into another folder, after its existing subfolder
+        let inner = create_playlist_folder(&conn, "Inner", Some(a), None).unwrap();
+        move_playlist_folder(&conn, c, Some(a), None).unwrap();
+        assert_eq!(subfolders(&conn, None), vec![a, b]);
+        assert_eq!(subfolders(&conn, Some(a)), vec![inner, c]);
+        move_playlist_folder(&conn, c, Some(a), Some(0)).unwrap();
+        assert_eq!(subfolders(&conn, Some(a)), vec![c, inner]);
+    }
+
+    #[test]
+    fn test_move_playlist_among_unplaced() {
+        let conn = library();
+        let folder = create_playlist_folder(&conn, "Folder", None, None).unwrap();
+        let zed = create_playlist(&conn, "Zed").unwrap();
+        let alpha = create_playlist(&conn, "Alpha").unwrap();
+        let moved = create_playlist(&conn, "Moved").unwrap();
+        set_playlist_folder(&conn, zed, Some(folder), None).unwrap();
+        set_playlist_folder(&conn, alpha, Some(folder), None).unwrap();
+
+        // Siblings still sorted by name get positions in that order
+        let changed = move_playlist(&conn, moved, Some(folder), Some(1)).unwrap();
+        assert_eq!(changed, vec![(alpha, 1.0), (moved, 2.0), (zed, 3.0)]);
+        assert!(move_playlist(&conn, 999, Some(folder), None).is_err());
+    }
+}
@@ -49,12 +49,14 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
             created_at TEXT DEFAULT CURRENT_TIMESTAMP
         );
 
-        -- Playlist tracks junction table
+        -- Playlist entries; a track may appear in a playlist more than once.
+        -- Positions are fractional so a move only rewrites the moved entry
         CREATE TABLE IF NOT EXISTS playlist_tracks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
             playlist_id INTEGER NOT NULL,
             track_id INTEGER NOT NULL,
-            position INTEGER,
-            PRIMARY KEY (playlist_id, track_id),
+            position REAL NOT NULL,
+            added_at TEXT DEFAULT CURRENT_TIMESTAMP,
             FOREIGN KEY (playlist_id) REFERENCES playlists(id) ON DELETE CASCADE,
             FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE
         );
@@ -116,6 +118,27 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
         ("date_added", "TEXT DEFAULT CURRENT_TIMESTAMP"),
         ("genre", "TEXT"),
         ("metadata_json", "TEXT"),
+        ("bpm", "REAL"),
+        ("musical_key", "TEXT"),
+        ("camelot_key", "TEXT"),
+        ("audio_analyzed_at", "TEXT"),
+        ("fingerprint", "BLOB"),
+        ("acoustid_id", "TEXT"),
+        ("file_mtime", "INTEGER"),
+        ("file_size", "INTEGER"),
+        ("album_artist", "TEXT"),
+        ("compilation", "INTEGER DEFAULT 0"),
+        ("musicbrainz_release_id", "TEXT"),
+        ("composer", "TEXT"),
+        ("remixer", "TEXT"),
+        ("year", "INTEGER"),
+        ("label", "TEXT"),
+        ("musicbrainz_release_group_id", "TEXT"),
+        ("musicbrainz_artist_id", "TEXT"),
+        ("play_count", "INTEGER DEFAULT 0"),
+        ("skip_count", "INTEGER DEFAULT 0"),
+        ("last_played_at", "TEXT"),
+        ("rating", "INTEGER"),
     ];
 
     for (col_name, col_def) in tracks_columns {
@@ -162,12 +185,53 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
         [],
     );
 
+    // Indexes for DJ-style browsing (tempo ranges, harmonic mixing)
+    let _ = conn.execute("CREATE INDEX IF NOT EXISTS idx_tracks_bpm ON tracks(bpm)", []);
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_tracks_camelot_key ON tracks(camelot_key)",
+        [],
+    );
+
+    // Indexes for browsing by year and label
+    let _ = conn.execute("CREATE INDEX IF NOT EXISTS idx_tracks_year ON tracks(year)", []);
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_tracks_label ON tracks(label COLLATE NOCASE)",
+        [],
+    );
+
+    // Play events: whether the play was a skip, and where playback stopped
+    // (seconds). play_count, skip_count and last_played_at on tracks are
+    // kept in step with these rows (see db::play_counts)
+    let _ = conn.execute(
+        "ALTER TABLE play_history ADD COLUMN skipped INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE play_history ADD COLUMN position INTEGER", []);
+
     // Verify or add columns to albums table
     if !column_exists(conn, "albums", "art_path")? {
         println!("[DB] Adding missing column 'art_path' to albums table...");
         let _ = conn.execute("ALTER TABLE albums ADD COLUMN art_path TEXT", []);
     }
 
+    // Album grouping: albums are keyed on (name, album artist, release MBID)
+    let _ = conn.execute("ALTER TABLE albums ADD COLUMN album_artist TEXT", []);
+    let _ = conn.execute(
+        "ALTER TABLE albums ADD COLUMN compilation INTEGER DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE albums ADD COLUMN musicbrainz_release_id TEXT",
+        [],
+    );
+    // Ratings in half stars (0-10), NULL when unrated; tracks carry theirs
+    // in tracks_columns
+    let _ = conn.execute("ALTER TABLE albums ADD COLUMN rating INTEGER", []);
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_albums_name ON albums(name COLLATE NOCASE)",
+        [],
+    );
+
     // ─── Sync infrastructure tables ──────────────────────────────────────────
     conn.execute_batch(
         "
@@ -212,57 +276,306 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
         [],
     );
     let _ = conn.execute("ALTER TABLE playlists ADD COLUMN folder_path TEXT", []);
+    // Smart playlists: JSON rule tree (see db::smart_rules); NULL for normal playlists
+    let _ = conn.execute("ALTER TABLE playlists ADD COLUMN smart_rules TEXT", []);
+
+    // Playlist folders (see the playlist_folders table); NULL position sorts by name
+    let _ = conn.execute(
+        "ALTER TABLE playlists ADD COLUMN folder_id INTEGER
+            REFERENCES playlist_folders(id) ON DELETE SET NULL",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE playlists ADD COLUMN position REAL", []);
+
+    // Per-folder exclusion rules (JSON-encoded ScanRules)
+    let _ = conn.execute("ALTER TABLE music_folders ADD COLUMN scan_rules TEXT", []);
     let _ = conn.execute(
         "CREATE UNIQUE INDEX IF NOT EXISTS idx_playlists_server_id ON playlists(server_id)",
         [],
     );
 
-    // Initialize playlist positions for existing playlists
-    initialize_playlist_positions(conn)?;
+    // ─── Internet radio ─────────────────────────────────────────────────────
+    conn.execute_batch(
+        "
+        -- Saved radio stations
+        CREATE TABLE IF NOT EXISTS radio_stations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            url TEXT UNIQUE NOT NULL,
+            genre TEXT,
+            homepage TEXT,
+            favicon_url TEXT,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            last_played_at TEXT
+        );
 
-    Ok(())
-}
+        -- Songs heard on a station (one row per ICY StreamTitle change)
+        CREATE TABLE IF NOT EXISTS radio_song_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            station_id INTEGER NOT NULL,
+            raw_title TEXT NOT NULL,
+            artist TEXT,
+            title TEXT,
+            played_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (station_id) REFERENCES radio_stations(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_radio_history_station
+            ON radio_song_history(station_id, played_at);
+        ",
+    )?;
 
-/// Initialize positions for playlists that don't have them
-/// Safe to run multiple times - only affects playlists with NULL positions
-fn initialize_playlist_positions(conn: &Connection) -> Result<()> {
-    use rusqlite::params;
+    // ─── Audio quality ──────────────────────────────────────────────────────
+    conn.execute_batch(
+        "
+        -- Lossless authenticity results (one row per analysed track)
+        CREATE TABLE IF NOT EXISTS track_quality (
+            track_id INTEGER PRIMARY KEY,
+            verdict TEXT NOT NULL,      -- 'lossless' | 'lossy' | 'transcode' | 'upsampled' | 'padded_bit_depth' | 'unknown'
+            codec TEXT,
+            sample_rate INTEGER,
+            bits_per_sample INTEGER,
+            effective_bits INTEGER,
+            cutoff_hz REAL,
+            sharp_cutoff INTEGER DEFAULT 0,
+            likely_source TEXT,
+            analyzed_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_track_quality_verdict
+            ON track_quality(verdict);
+        ",
+    )?;
 
-    // Get all unique playlist IDs that have tracks without positions
-    let mut stmt = conn.prepare(
-        "SELECT DISTINCT playlist_id 
-         FROM playlist_tracks 
-         WHERE position IS NULL",
+    // ─── Artists ────────────────────────────────────────────────────────────
+    conn.execute_batch(
+        "
+        -- Individual artists, split out of the free-text artist/composer tags
+        CREATE TABLE IF NOT EXISTS artists (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_artists_name
+            ON artists(name COLLATE NOCASE);
+
+        -- Who is credited on a track, and as what
+        CREATE TABLE IF NOT EXISTS track_artists (
+            track_id INTEGER NOT NULL,
+            artist_id INTEGER NOT NULL,
+            role TEXT NOT NULL,          -- 'main' | 'featured' | 'remixer' | 'composer'
+            position INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (track_id, artist_id, role),
+            FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE,
+            FOREIGN KEY (artist_id) REFERENCES artists(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_track_artists_artist
+            ON track_artists(artist_id, role);
+
+        -- Drop artists once nothing credits them
+        CREATE TRIGGER IF NOT EXISTS track_artists_ad AFTER DELETE ON track_artists BEGIN
+            DELETE FROM artists WHERE id = old.artist_id
+                AND NOT EXISTS (SELECT 1 FROM track_artists WHERE artist_id = old.artist_id);
+        END;
+        ",
     )?;
 
-    let playlist_ids: Vec<i64> = stmt
-        .query_map([], |row| row.get(0))?
-        .collect::<Result<Vec<_>>>()?;
-
-    // For each playlist, assign sequential positions
-    for playlist_id in playlist_ids {
-        // Get all track_ids in this playlist (in insertion order via rowid)
-        let mut track_stmt = conn.prepare(
-            "SELECT track_id 
-             FROM playlist_tracks 
-             WHERE playlist_id = ?1 
-             ORDER BY rowid",
-        )?;
-
-        let track_ids: Vec<i64> = track_stmt
-            .query_map(params![playlist_id], |row| row.get(0))?
-            .collect::<Result<Vec<_>>>()?;
-
-        // Assign sequential positions
-        for (pos, track_id) in track_ids.iter().enumerate() {
-            conn.execute(
-                "UPDATE playlist_tracks 
-                 SET position = ?1 
-                 WHERE playlist_id = ?2 AND track_id = ?3",
-                params![pos as i64, playlist_id, track_id],
-            )?;
-        }
+    // ─── Genres ─────────────────────────────────────────────────────────────
+    conn.execute_batch(
+        "
+        -- Canonical genres, split out of tracks.genre through the alias map
+        CREATE TABLE IF NOT EXISTS genres (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_genres_name
+            ON genres(name COLLATE NOCASE);
+
+        CREATE TABLE IF NOT EXISTS track_genres (
+            track_id INTEGER NOT NULL,
+            genre_id INTEGER NOT NULL,
+            position INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (track_id, genre_id),
+            FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE,
+            FOREIGN KEY (genre_id) REFERENCES genres(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_track_genres_genre
+            ON track_genres(genre_id);
+
+        -- Drop genres once no track has them
+        CREATE TRIGGER IF NOT EXISTS track_genres_ad AFTER DELETE ON track_genres BEGIN
+            DELETE FROM genres WHERE id = old.genre_id
+                AND NOT EXISTS (SELECT 1 FROM track_genres WHERE genre_id = old.genre_id);
+        END;
+        ",
+    )?;
+
+    // ─── Tag edit journal ───────────────────────────────────────────────────
+    conn.execute_batch(
+        "
+        -- One row per batch tag edit, so a whole batch can be undone
+        CREATE TABLE IF NOT EXISTS tag_edit_batches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            description TEXT,
+            track_count INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            undone_at TEXT
+        );
+
+        -- File tag values before the edit, for the fields the edit touched
+        CREATE TABLE IF NOT EXISTS tag_edit_journal (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            batch_id INTEGER NOT NULL,
+            track_id INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            previous_values TEXT NOT NULL,  -- JSON object: field -> value or null
+            cover_changed INTEGER NOT NULL DEFAULT 0,
+            previous_cover BLOB,
+            FOREIGN KEY (batch_id) REFERENCES tag_edit_batches(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_tag_edit_journal_batch
+            ON tag_edit_journal(batch_id);
+        ",
+    )?;
+
+    // ─── Organizer undo log ─────────────────────────────────────────────────
+    conn.execute_batch(
+        "
+        -- One row per organize run (files moved or copied to a naming template)
+        CREATE TABLE IF NOT EXISTS organize_batches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            mode TEXT NOT NULL,             -- 'move' | 'copy'
+            template TEXT,
+            file_count INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            undone_at TEXT
+        );
+
+        -- Every file an organize run moved or copied, in order
+        CREATE TABLE IF NOT EXISTS organize_journal (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            batch_id INTEGER NOT NULL,
+            track_id INTEGER,               -- NULL for lyrics and folder art
+            kind TEXT NOT NULL,             -- 'track' | 'lyrics' | 'cover'
+            from_path TEXT NOT NULL,
+            to_path TEXT NOT NULL,
+            FOREIGN KEY (batch_id) REFERENCES organize_batches(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_organize_journal_batch
+            ON organize_journal(batch_id);
+        ",
+    )?;
+
+    // ─── Playlist folders ───────────────────────────────────────────────────
+    conn.execute_batch(
+        "
+        -- User-defined folders for grouping playlists; folders nest
+        CREATE TABLE IF NOT EXISTS playlist_folders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            parent_id INTEGER,              -- NULL at the top level
+            position REAL NOT NULL,         -- order among siblings
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (parent_id) REFERENCES playlist_folders(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_playlist_folders_parent
+            ON playlist_folders(parent_id, position);
+        ",
+    )?;
+
+    // ─── Folder playlist files ──────────────────────────────────────────────
+    conn.execute_batch(
+        "
+        -- The .m3u8 kept in a folder playlist's directory, and what it held
+        -- when it and the playlist last agreed
+        CREATE TABLE IF NOT EXISTS folder_playlist_files (
+            playlist_id INTEGER PRIMARY KEY,
+            file_path TEXT NOT NULL,
+            file_hash TEXT,                 -- SHA-256 of the contents at the last sync
+            synced_at TEXT,
+            conflict INTEGER NOT NULL DEFAULT 0, -- both sides changed since the last sync
+            FOREIGN KEY (playlist_id) REFERENCES playlists(id) ON DELETE CASCADE
+        );
+        ",
+    )?;
+
+    // ─── Library settings ───────────────────────────────────────────────────
+    conn.execute_batch(
+        "
+        -- Library-wide options and one-time migration markers
+        CREATE TABLE IF NOT EXISTS library_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+        ",
+    )?;
+
+    // Older databases keyed playlist entries on (playlist_id, track_id)
+    migrate_playlist_entries(conn)?;
+    conn.execute_batch(
+        "
+        CREATE INDEX IF NOT EXISTS idx_playlist_tracks_position
+            ON playlist_tracks(playlist_id, position);
+        CREATE INDEX IF NOT EXISTS idx_playlist_tracks_track
+            ON playlist_tracks(track_id);
+        ",
+    )?;
+
+    Ok(())
+}
+
+/// Rebuild `playlist_tracks` with an id per entry, so the same track can be
+/// added twice and entries can be addressed individually. Positions become
+/// 1, 2, 3... in the existing order (entries without a position last, in
+/// insertion order). Sync mappings and queued changes for the old
+/// "{playlist}_{track}" keys move to the new "entry_{id}" keys; keys with no
+/// surviving entry (queued deletes) are left as they were.
+fn migrate_playlist_entries(conn: &Connection) -> Result<()> {
+    if column_exists(conn, "playlist_tracks", "id")? {
+        return Ok(());
     }
+    println!("[DB] Migrating playlist_tracks to per-entry ids...");
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(
+        "
+        CREATE TABLE playlist_tracks_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            playlist_id INTEGER NOT NULL,
+            track_id INTEGER NOT NULL,
+            position REAL NOT NULL,
+            added_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (playlist_id) REFERENCES playlists(id) ON DELETE CASCADE,
+            FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE
+        );
+        INSERT INTO playlist_tracks_new (playlist_id, track_id, position)
+            SELECT playlist_id, track_id, ROW_NUMBER() OVER (
+                PARTITION BY playlist_id
+                ORDER BY position IS NULL, position, rowid
+            )
+            FROM playlist_tracks
+            ORDER BY playlist_id, position IS NULL, position, rowid;
+
+        UPDATE sync_id_map
+        SET local_id = 'entry_' || (
+            SELECT n.id FROM playlist_tracks_new n
+            WHERE n.playlist_id || '_' || n.track_id = sync_id_map.local_id
+        )
+        WHERE entity_type = 'playlist_track'
+          AND local_id IN (SELECT playlist_id || '_' || track_id FROM playlist_tracks_new);
+        UPDATE sync_queue
+        SET entity_id = 'local_entry_' || (
+            SELECT n.id FROM playlist_tracks_new n
+            WHERE 'local_' || n.playlist_id || '_' || n.track_id = sync_queue.entity_id
+        )
+        WHERE entity_type = 'playlist_track'
+          AND entity_id IN (
+              SELECT 'local_' || playlist_id || '_' || track_id FROM playlist_tracks_new
+          );
+
+        DROP TABLE playlist_tracks;
+        ALTER TABLE playlist_tracks_new RENAME TO playlist_tracks;
+        ",
+    )?;
+    tx.commit()?;
 
     Ok(())
 }
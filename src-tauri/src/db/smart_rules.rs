@@ -0,0 +1,511 @@
+// Smart playlist rules: a JSON tree of AND/OR groups compiled to SQL
+//
+// A rule is either a group or a condition:
+//   {"type": "group", "match": "all", "rules": [...]}
+//   {"type": "condition", "field": "genre", "op": "is", "value": "Jazz"}
+//
+// Rules compile to a WHERE expression over `tracks t` with positional
+// parameters. Nothing is stored per track, so a smart playlist always
+// reflects the library as it is when it is read.
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartField {
+    Artist,
+    Genre,
+    Year,
+    DateAdded,
+    PlayCount,
+    SkipCount,
+    LastPlayed,
+    Liked,
+    /// Half stars, 0-10; unrated tracks never match
+    Rating,
+    Format,
+    Bitrate,
+    /// Seconds
+    Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartOp {
+    Is,
+    IsNot,
+    Contains,
+    NotContains,
+    StartsWith,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// Inclusive; value is `[low, high]`
+    Between,
+    /// Value is a number of days
+    InLastDays,
+    NotInLastDays,
+    /// Value is a `YYYY-MM-DD` date
+    Before,
+    After,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    #[default]
+    All,
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SmartRule {
+    Group {
+        #[serde(default, rename = "match")]
+        match_mode: MatchMode,
+        rules: Vec<SmartRule>,
+    },
+    Condition {
+        field: SmartField,
+        op: SmartOp,
+        #[serde(default)]
+        value: serde_json::Value,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartSortField {
+    #[default]
+    Artist,
+    Title,
+    Album,
+    Year,
+    DateAdded,
+    PlayCount,
+    SkipCount,
+    LastPlayed,
+    Rating,
+    Duration,
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmartSort {
+    pub field: SmartSortField,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "by", content = "value", rename_all = "snake_case")]
+pub enum SmartLimit {
+    /// Number of tracks
+    Count(u32),
+    /// Total length in seconds
+    Duration(u32),
+}
+
+/// Everything stored for a smart playlist.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmartPlaylistRules {
+    pub rules: SmartRule,
+    #[serde(default)]
+    pub sort: SmartSort,
+    #[serde(default)]
+    pub limit: Option<SmartLimit>,
+}
+
+/// A compiled rule tree: SQL over `tracks t` plus its parameters, which
+/// are numbered from `?1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledRules {
+    pub where_sql: String,
+    pub order_sql: String,
+    pub params: Vec<Value>,
+}
+
+#[derive(Clone, Copy)]
+enum FieldKind {
+    Text,
+    Number,
+    Date,
+    Bool,
+}
+
+fn field_kind(field: SmartField) -> FieldKind {
+    match field {
+        SmartField::Artist | SmartField::Genre | SmartField::Format => FieldKind::Text,
+        SmartField::Year
+        | SmartField::PlayCount
+        | SmartField::SkipCount
+        | SmartField::Rating
+        | SmartField::Bitrate
+        | SmartField::Duration => FieldKind::Number,
+        SmartField::DateAdded | SmartField::LastPlayed => FieldKind::Date,
+        SmartField::Liked => FieldKind::Bool,
+    }
+}
+
+/// Escape `%`, `_` and `\` for a `LIKE ... ESCAPE '\'` pattern.
+fn like_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+struct Compiler {
+    params: Vec<Value>,
+}
+
+impl Compiler {
+    fn param(&mut self, value: Value) -> String {
+        self.params.push(value);
+        format!("?{}", self.params.len())
+    }
+
+    fn rule(&mut self, rule: &SmartRule) -> Result<String, String> {
+        match rule {
+            SmartRule::Group { match_mode, rules } => {
+                if rules.is_empty() {
+                    // An empty group matches everything
+                    return Ok("1".to_string());
+                }
+                let joiner = match match_mode {
+                    MatchMode::All => " AND ",
+                    MatchMode::Any => " OR ",
+                };
+                let parts = rules
+                    .iter()
+                    .map(|r| self.rule(r))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!("({})", parts.join(joiner)))
+            }
+            SmartRule::Condition { field, op, value } => self.condition(*field, *op, value),
+        }
+    }
+
+    fn condition(
+        &mut self,
+        field: SmartField,
+        op: SmartOp,
+        value: &serde_json::Value,
+    ) -> Result<String, String> {
+        let invalid = || format!("Invalid value for {:?} {:?}: {}", field, op, value);
+        match (field_kind(field), op) {
+            (FieldKind::Text, SmartOp::IsNot | SmartOp::NotContains) => {
+                let positive = if op == SmartOp::IsNot {
+                    SmartOp::Is
+                } else {
+                    SmartOp::Contains
+                };
+                let expr = self.condition(field, positive, value)?;
+                Ok(format!("NOT COALESCE({}, 0)", expr))
+            }
+            (FieldKind::Text, SmartOp::Is | SmartOp::Contains | SmartOp::StartsWith) => {
+                let text = value.as_str().ok_or_else(invalid)?.trim();
+                let test = match op {
+                    SmartOp::Is => {
+                        format!("= {} COLLATE NOCASE", self.param(text.to_string().into()))
+                    }
+                    SmartOp::Contains => format!(
+                        "LIKE {} ESCAPE '\\'",
+                        self.param(format!("%{}%", like_escape(text)).into())
+                    ),
+                    _ => format!(
+                        "LIKE {} ESCAPE '\\'",
+                        self.param(format!("{}%", like_escape(text)).into())
+                    ),
+                };
+                Ok(match field {
+                    SmartField::Artist => format!(
+                        "(t.artist {test} OR t.album_artist {test} OR EXISTS (
+                            SELECT 1 FROM track_artists ta
+                            INNER JOIN artists a ON a.id = ta.artist_id
+                            WHERE ta.track_id = t.id AND a.name {test}))",
+                        test = test
+                    ),
+                    SmartField::Genre => format!(
+                        "EXISTS (SELECT 1 FROM track_genres tg
+                            INNER JOIN genres g ON g.id = tg.genre_id
+                            WHERE tg.track_id = t.id AND g.name {})",
+                        test
+                    ),
+                    _ => format!("t.format {}", test),
+                })
+            }
+            (FieldKind::Number, _) => {
+                let column = match field {
+                    SmartField::Year => "t.year",
+                    SmartField::PlayCount => "t.play_count",
+                    SmartField::SkipCount => "t.skip_count",
+                    SmartField::Rating => "t.rating",
+                    SmartField::Bitrate => "t.bitrate",
+                    _ => "t.duration",
+                };
+                if op == SmartOp::Between {
+                    let bounds = value
+                        .as_array()
+                        .filter(|b| b.len() == 2)
+                        .ok_or_else(invalid)?;
+                    let low = bounds[0].as_f64().ok_or_else(invalid)?;
+                    let high = bounds[1].as_f64().ok_or_else(invalid)?;
+                    return Ok(format!(
+                        "{} BETWEEN {} AND {}",
+                        column,
+                        self.param(low.into()),
+                        self.param(high.into())
+                    ));
+                }
+                let operator = match op {
+                    SmartOp::Is => "=",
+                    SmartOp::IsNot => "!=",
+                    SmartOp::Gt => ">",
+                    SmartOp::Gte => ">=",
+                    SmartOp::Lt => "<",
+                    SmartOp::Lte => "<=",
+                    _ => return Err(format!("{:?} cannot be used with {:?}", op, field)),
+                };
+                let number = value.as_f64().ok_or_else(invalid)?;
+                Ok(format!(
+                    "{} {} {}",
+                    column,
+                    operator,
+                    self.param(number.into())
+                ))
+            }
+            (FieldKind::Date, _) => {
+                let column = match field {
+                    SmartField::DateAdded => "t.date_added",
+                    _ => "t.last_played_at",
+                };
+                match op {
+                    SmartOp::InLastDays | SmartOp::NotInLastDays => {
+                        let days = value.as_u64().ok_or_else(invalid)?;
+                        let since = self.param(format!("-{} days", days).into());
+                        Ok(if op == SmartOp::InLastDays {
+                            format!("{} >= datetime('now', {})", column, since)
+                        } else {
+                            // Never played counts as not played recently
+                            format!(
+                                "({c} IS NULL OR {c} < datetime('now', {s}))",
+                                c = column,
+                                s = since
+                            )
+                        })
+                    }
+                    SmartOp::Before | SmartOp::After => {
+                        let date = value
+                            .as_str()
+                            .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                            .ok_or_else(invalid)?;
+                        let date = self.param(date.format("%Y-%m-%d").to_string().into());
+                        Ok(if op == SmartOp::Before {
+                            format!("{} < {}", column, date)
+                        } else {
+                            format!("{} >= date({}, '+1 day')", column, date)
+                        })
+                    }
+                    _ => Err(format!("{:?} cannot be used with {:?}", op, field)),
+                }
+            }
+            (FieldKind::Bool, SmartOp::Is | SmartOp::IsNot) => {
+                let wanted = value.as_bool().ok_or_else(invalid)? == (op == SmartOp::Is);
+                let exists = "EXISTS (SELECT 1 FROM liked_tracks l WHERE l.track_id = t.id)";
+                Ok(if wanted {
+                    exists.to_string()
+                } else {
+                    format!("NOT {}", exists)
+                })
+            }
+            _ => Err(format!("{:?} cannot be used with {:?}", op, field)),
+        }
+    }
+}
+
+fn order_sql(sort: &SmartSort) -> String {
+    let direction = if sort.descending { "DESC" } else { "ASC" };
+    let primary = match sort.field {
+        SmartSortField::Random => return "RANDOM()".to_string(),
+        SmartSortField::Artist => "t.artist COLLATE NOCASE",
+        SmartSortField::Title => "t.title COLLATE NOCASE",
+        SmartSortField::Album => "t.album COLLATE NOCASE",
+        SmartSortField::Year => "t.year",
+        SmartSortField::DateAdded => "t.date_added",
+        SmartSortField::PlayCount => "t.play_count",
+        SmartSortField::SkipCount => "t.skip_count",
+        SmartSortField::LastPlayed => "t.last_played_at",
+        SmartSortField::Rating => "t.rating",
+        SmartSortField::Duration => "t.duration",
+    };
+    format!(
+        "{} {}, t.artist COLLATE NOCASE, t.album COLLATE NOCASE, t.disc_number, t.track_number, t.title",
+        primary, direction
+    )
+}
+
+/// Compile a smart playlist to SQL, rejecting fields and operators that
+/// don't go together and values of the wrong shape.
+pub fn compile(rules: &SmartPlaylistRules) -> Result<CompiledRules, String> {
+    let mut compiler = Compiler { params: Vec::new() };
+    let where_sql = compiler.rule(&rules.rules)?;
+    Ok(CompiledRules {
+        where_sql,
+        order_sql: order_sql(&rules.sort),
+        params: compiler.params,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn parse(json: &str) -> SmartPlaylistRules {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn matching(conn: &Connection, rules: &SmartPlaylistRules) -> Vec<i64> {
+        let compiled = compile(rules).unwrap();
+        let sql = format!(
+            "SELECT t.id FROM tracks t WHERE {} ORDER BY {}",
+            compiled.where_sql, compiled.order_sql
+        );
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let ids = stmt
+            .query_map(rusqlite::params_from_iter(&compiled.params), |row| {
+                row.get(0)
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<i64>>>()
+            .unwrap();
+        ids
+    }
+
+    fn library() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE tracks (id INTEGER PRIMARY KEY, title TEXT, artist TEXT,
+                album_artist TEXT, album TEXT, year INTEGER, format TEXT, bitrate INTEGER,
+                duration INTEGER, date_added TEXT, disc_number INTEGER, track_number INTEGER,
+                play_count INTEGER DEFAULT 0, skip_count INTEGER DEFAULT 0, last_played_at TEXT,
+                rating INTEGER);
+            CREATE TABLE artists (id INTEGER PRIMARY KEY, name TEXT);
+            CREATE TABLE track_artists (track_id INTEGER, artist_id INTEGER);
+            CREATE TABLE genres (id INTEGER PRIMARY KEY, name TEXT);
+            CREATE TABLE track_genres (track_id INTEGER, genre_id INTEGER);
+            CREATE TABLE liked_tracks (track_id INTEGER);
+
+            INSERT INTO tracks VALUES
+                (1, 'So What', 'Miles Davis', NULL, 'Kind of Blue', 1959, 'flac', 900, 562,
+                 datetime('now', '-400 days'), 1, 1, 2, 0, datetime('now', '-1 days'), 9),
+                (2, 'Under Pressure', 'Queen & David Bowie', 'Queen', 'Hot Space', 1982, 'mp3',
+                 320, 248, datetime('now', '-2 days'), 1, 11, 1, 3, datetime('now', '-90 days'), 4),
+                (3, '100% Pure', 'Someone', NULL, 'Odd', NULL, 'mp3', 128, 180,
+                 datetime('now', '-10 days'), 1, 1, 0, 1, NULL, NULL);
+            INSERT INTO artists VALUES (1, 'Queen'), (2, 'David Bowie'), (3, 'Miles Davis');
+            INSERT INTO track_artists VALUES (1, 3), (2, 1), (2, 2);
+            INSERT INTO genres VALUES (1, 'Jazz'), (2, 'Rock');
+            INSERT INTO track_genres VALUES (1, 1), (2, 2);
+            INSERT INTO liked_tracks VALUES (2);
+            ",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_conditions() {
+        let conn = library();
+        let single = |field: &str, op: &str, value: &str| {
+            parse(&format!(
+                r#"{{"rules": {{"type": "condition", "field": "{}", "op": "{}", "value": {}}}}}"#,
+                field, op, value
+            ))
+        };
+        assert_eq!(
+            matching(&conn, &single("artist", "is", r#""david bowie""#)),
+            vec![2]
+        );
+        assert_eq!(
+            matching(&conn, &single("artist", "not_contains", r#""queen""#)),
+            vec![1, 3]
+        );
+        assert_eq!(
+            matching(&conn, &single("genre", "is_not", r#""Jazz""#)),
+            vec![2, 3]
+        );
+        assert_eq!(
+            matching(&conn, &single("year", "between", "[1950, 1970]")),
+            vec![1]
+        );
+        assert_eq!(matching(&conn, &single("year", "lt", "1990")), vec![1, 2]);
+        assert_eq!(
+            matching(&conn, &single("play_count", "gte", "1")),
+            vec![1, 2]
+        );
+        assert_eq!(
+            matching(&conn, &single("skip_count", "gt", "0")),
+            vec![2, 3]
+        );
+        assert_eq!(matching(&conn, &single("rating", "gte", "8")), vec![1]);
+        assert_eq!(matching(&conn, &single("rating", "lt", "8")), vec![2]);
+        assert_eq!(
+            matching(&conn, &single("last_played", "in_last_days", "7")),
+            vec![1]
+        );
+        assert_eq!(
+            matching(&conn, &single("last_played", "not_in_last_days", "7")),
+            vec![2, 3]
+        );
+        assert_eq!(
+            matching(&conn, &single("date_added", "in_last_days", "30")),
+            vec![2, 3]
+        );
+        assert_eq!(matching(&conn, &single("liked", "is", "false")), vec![1, 3]);
+        assert_eq!(
+            matching(&conn, &single("format", "starts_with", r#""FL""#)),
+            vec![1]
+        );
+        // LIKE wildcards in the value are matched literally
+        assert_eq!(
+            matching(&conn, &single("artist", "contains", r#""%""#)),
+            Vec::<i64>::new()
+        );
+
+        assert!(compile(&single("liked", "contains", "true")).is_err());
+        assert!(compile(&single("year", "is", r#""nineteen""#)).is_err());
+        assert!(compile(&single("date_added", "before", r#""yesterday""#)).is_err());
+    }
+
+    #[test]
+    fn test_groups_and_sort() {
+        let conn = library();
+        let rules = parse(
+            r#"{
+                "rules": {"type": "group", "match": "any", "rules": [
+                    {"type": "condition", "field": "liked", "op": "is", "value": true},
+                    {"type": "group", "match": "all", "rules": [
+                        {"type": "condition", "field": "format", "op": "is", "value": "mp3"},
+                        {"type": "condition", "field": "bitrate", "op": "lt", "value": 192}
+                    ]}
+                ]},
+                "sort": {"field": "duration", "descending": true},
+                "limit": {"by": "count", "value": 10}
+            }"#,
+        );
+        assert_eq!(matching(&conn, &rules), vec![2, 3]);
+        assert_eq!(rules.limit, Some(SmartLimit::Count(10)));
+
+        let everything = parse(r#"{"rules": {"type": "group", "rules": []}}"#);
+        assert_eq!(matching(&conn, &everything), vec![1, 2, 3]);
+    }
+}
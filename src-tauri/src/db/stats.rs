@@ -0,0 +1,276 @@
+// Listening statistics: the period a stats query covers, and listening
+// streaks
+//
+// play_history.played_at is UTC text ("YYYY-MM-DD HH:MM:SS", as SQLite's
+// CURRENT_TIMESTAMP writes it). Periods are calendar periods in local
+// time, so "today" starts at the user's midnight; they are turned into
+// UTC bounds that compare directly with played_at.
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StatsRange {
+    Day,
+    /// From Monday
+    Week,
+    #[default]
+    Month,
+    Year,
+    All,
+    /// `YYYY-MM-DD` dates, both included; a missing end is open
+    Custom {
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+/// A range resolved against the current time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsPeriod {
+    /// Inclusive lower bound on played_at; `None` is open
+    pub from: Option<String>,
+    /// Exclusive upper bound on played_at; `None` is open
+    pub to: Option<String>,
+    /// The same bounds as local dates
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+    pub today: NaiveDate,
+    /// strftime format grouping plays into days or months, whichever
+    /// suits the length of the range
+    pub bucket: &'static str,
+}
+
+impl StatsPeriod {
+    pub fn contains_date(&self, date: NaiveDate) -> bool {
+        self.from_date.is_none_or(|from| date >= from) && self.to_date.is_none_or(|to| date < to)
+    }
+}
+
+fn parse_date(value: &Option<String>) -> Result<Option<NaiveDate>, String> {
+    match value.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| format!("Invalid date: {}", s)),
+        None => Ok(None),
+    }
+}
+
+fn first_of_month(year: i32, month: u32) -> Option<NaiveDate> {
+    if month > 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month, 1)
+    }
+}
+
+impl StatsRange {
+    /// The calendar year `year`.
+    pub fn year(year: i32) -> Self {
+        StatsRange::Custom {
+            from: Some(format!("{:04}-01-01", year)),
+            to: Some(format!("{:04}-12-31", year)),
+        }
+    }
+
+    /// First day and the day after the last, in local dates.
+    fn dates(&self, today: NaiveDate) -> Result<(Option<NaiveDate>, Option<NaiveDate>), String> {
+        Ok(match self {
+            StatsRange::Day => (Some(today), today.succ_opt()),
+            StatsRange::Week => {
+                let start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+                (Some(start), Some(start + Duration::days(7)))
+            }
+            StatsRange::Month => (
+                first_of_month(today.year(), today.month()),
+                first_of_month(today.year(), today.month() + 1),
+            ),
+            StatsRange::Year => (
+                NaiveDate::from_ymd_opt(today.year(), 1, 1),
+                NaiveDate::from_ymd_opt(today.year() + 1, 1, 1),
+            ),
+            StatsRange::All => (None, None),
+            StatsRange::Custom { from, to } => {
+                let (from, to) = (parse_date(from)?, parse_date(to)?);
+                if let (Some(from), Some(to)) = (from, to) {
+                    if from > to {
+                        return Err(format!("Range starts after it ends: {} > {}", from, to));
+                    }
+                }
+                (from, to.and_then(|to| to.succ_opt()))
+            }
+        })
+    }
+
+    /// Resolve the range at `now`, in `now`'s time zone.
+    pub fn period<Tz: TimeZone>(&self, now: DateTime<Tz>) -> Result<StatsPeriod, String> {
+        let tz = now.timezone();
+        let today = now.date_naive();
+        let (from_date, to_date) = self.dates(today)?;
+        // Local midnight in UTC; a midnight skipped by DST falls back to UTC
+        let bound = |date: NaiveDate| {
+            let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+            let utc = tz
+                .from_local_datetime(&midnight)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc).naive_utc())
+                .unwrap_or(midnight);
+            utc.format("%Y-%m-%d %H:%M:%S").to_string()
+        };
+        let days = match (from_date, to_date) {
+            (Some(from), Some(to)) => Some((to - from).num_days()),
+            _ => None,
+        };
+        Ok(StatsPeriod {
+            from: from_date.map(bound),
+            to: to_date.map(bound),
+            from_date,
+            to_date,
+            today,
+            bucket: match days {
+                Some(days) if days <= 62 => "%Y-%m-%d",
+                _ => "%Y-%m",
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Streaks {
+    /// Consecutive days up to today, or up to yesterday when nothing has
+    /// been played yet today
+    pub current_days: i64,
+    /// Longest run of consecutive days among `days`
+    pub longest_days: i64,
+}
+
+/// Runs of consecutive days in `days` (sorted, distinct): the longest one
+/// within `period`, and the one that is still going on `period.today`.
+pub fn streaks(days: &[NaiveDate], period: &StatsPeriod) -> Streaks {
+    let mut result = Streaks::default();
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days.iter().filter(|d| period.contains_date(**d)) {
+        run = match previous {
+            Some(p) if p.succ_opt() == Some(day) => run + 1,
+            _ => 1,
+        };
+        result.longest_days = result.longest_days.max(run);
+        previous = Some(day);
+    }
+
+    let yesterday = period.today.pred_opt();
+    let mut expected = match days.last() {
+        Some(&last) if last == period.today || Some(last) == yesterday => Some(last),
+        _ => None,
+    };
+    for &day in days.iter().rev() {
+        if Some(day) != expected {
+            break;
+        }
+        result.current_days += 1;
+        expected = day.pred_opt();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_periods() {
+        // Wednesday 2024-02-14, 00:30 at UTC+2
+        let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+        let now = tz.with_ymd_and_hms(2024, 2, 14, 0, 30, 0).unwrap();
+
+        let day = StatsRange::Day.period(now).unwrap();
+        assert_eq!(day.from.as_deref(), Some("2024-02-13 22:00:00"));
+        assert_eq!(day.to.as_deref(), Some("2024-02-14 22:00:00"));
+        assert_eq!(day.bucket, "%Y-%m-%d");
+
+        let week = StatsRange::Week.period(now).unwrap();
+        assert_eq!(week.from_date, Some(date("2024-02-12")));
+        assert_eq!(week.to_date, Some(date("2024-02-19")));
+
+        let month = StatsRange::Month.period(now).unwrap();
+        assert_eq!(month.from_date, Some(date("2024-02-01")));
+        assert_eq!(month.to_date, Some(date("2024-03-01")));
+
+        let year = StatsRange::Year.period(now).unwrap();
+        assert_eq!(year.to_date, Some(date("2025-01-01")));
+        assert_eq!(year.bucket, "%Y-%m");
+
+        let all = StatsRange::All.period(now).unwrap();
+        assert_eq!((all.from, all.to), (None, None));
+
+        let custom = StatsRange::Custom {
+            from: Some("2023-12-01".to_string()),
+            to: Some("2023-12-31".to_string()),
+        };
+        let custom = custom.period(now).unwrap();
+        assert_eq!(custom.from.as_deref(), Some("2023-11-30 22:00:00"));
+        assert_eq!(custom.to_date, Some(date("2024-01-01")));
+        assert!(custom.contains_date(date("2023-12-31")));
+        assert!(!custom.contains_date(date("2024-01-01")));
+
+        let open = StatsRange::Custom {
+            from: None,
+            to: Some("2023-12-31".to_string()),
+        };
+        assert_eq!(open.period(now).unwrap().from, None);
+        let reversed = StatsRange::Custom {
+            from: Some("2024-01-02".to_string()),
+            to: Some("2024-01-01".to_string()),
+        };
+        assert!(reversed.period(now).is_err());
+
+        let year = StatsRange::year(2023).period(now).unwrap();
+        assert_eq!(year.from.as_deref(), Some("2022-12-31 22:00:00"));
+        assert_eq!(year.to_date, Some(date("2024-01-01")));
+        assert_eq!(year.bucket, "%Y-%m");
+    }
+
+    #[test]
+    fn test_streaks() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap();
+        let days: Vec<NaiveDate> = [
+            "2024-02-27",
+            "2024-02-28",
+            "2024-02-29",
+            "2024-03-01",
+            "2024-03-05",
+            "2024-03-08",
+            "2024-03-09",
+        ]
+        .iter()
+        .map(|d| date(d))
+        .collect();
+
+        let all = StatsRange::All.period(now).unwrap();
+        assert_eq!(
+            streaks(&days, &all),
+            Streaks {
+                current_days: 2,
+                longest_days: 4
+            }
+        );
+        // Only March counts towards the longest run; the current one doesn't
+        // depend on the range
+        let month = StatsRange::Month.period(now).unwrap();
+        assert_eq!(streaks(&days, &month).longest_days, 2);
+        assert_eq!(streaks(&days, &month).current_days, 2);
+
+        let later = Utc.with_ymd_and_hms(2024, 3, 12, 12, 0, 0).unwrap();
+        assert_eq!(
+            streaks(&days, &StatsRange::All.period(later).unwrap()).current_days,
+            0
+        );
+        assert_eq!(streaks(&[], &all), Streaks::default());
+    }
+}
@@ -1,14 +1,22 @@
 // Audion - Local Spotify-style Music Player
 // Main library entry point
 
+mod analysis;
 mod commands;
 mod db;
 #[cfg(desktop)]
 mod discord;
+mod organizer;
+mod playlist_io;
+mod playlist_ops;
+mod radio;
 mod scanner;
 mod security;
 mod sync;
+mod tag_editor;
 mod utils;
+mod watcher;
+mod year_review;
 
 // =============================================================================
 // NATIVE AUDIO BACKEND
@@ -322,6 +330,8 @@ pub fn run() {
 
             app.manage(database);
             app.manage(commands::listenbrainz::ListenBrainzState::new());
+            app.manage(analysis::waveform::WaveformQueue::new(app.handle().clone()));
+            app.manage(watcher::FolderWatcher::new(app.handle().clone()));
 
             // Initialize Discord RPC state (desktop only)
             #[cfg(desktop)]
@@ -489,15 +499,27 @@ pub fn run() {
                     // Activity commands (liked tracks + play history)
                     commands::like_track,
                     commands::unlike_track,
+                    commands::set_track_rating,
+                    commands::set_album_rating,
+                    commands::get_track_ratings,
+                    commands::get_album_ratings,
+                    commands::get_rating_write_back,
+                    commands::set_rating_write_back,
                     commands::is_track_liked,
                     commands::get_liked_track_ids,
                     commands::get_liked_tracks,
                     commands::record_play,
+                    commands::get_skip_thresholds,
+                    commands::set_skip_thresholds,
                     commands::get_top_tracks,
                     commands::get_top_albums,
                     commands::get_recently_played,
                     commands::get_top_artists,
                     commands::get_stats_summary,
+                    commands::get_top_genres,
+                    commands::get_listening_breakdown,
+                    commands::get_year_in_review,
+                    commands::export_year_in_review,
                     // Lyrics commands
                     commands::save_lrc_file,
                     commands::save_api_lrc_file,
@@ -594,6 +616,142 @@ pub fn run() {
                     audio::audio_get_state,
                     audio::audio_set_eq,
                     audio::native_audio_available,
+                    // =========================================================================
+                    // INTERNET RADIO COMMANDS
+                    // =========================================================================
+                    commands::get_radio_stations,
+                    commands::add_radio_station,
+                    commands::update_radio_station,
+                    commands::delete_radio_station,
+                    commands::radio_play_station,
+                    commands::get_radio_song_history,
+                    commands::clear_radio_song_history,
+                    commands::import_radio_stations,
+                    commands::export_radio_stations,
+                    // =========================================================================
+                    // WAVEFORM COMMANDS
+                    // =========================================================================
+                    commands::get_waveform,
+                    commands::queue_waveforms,
+                    commands::prioritize_waveforms,
+                    commands::queue_library_waveforms,
+                    commands::get_waveform_queue_size,
+                    // =========================================================================
+                    // TEMPO / KEY ANALYSIS
+                    // =========================================================================
+                    commands::analyze_tracks,
+                    commands::analyze_library,
+                    commands::get_track_analysis,
+                    commands::get_dj_tracks,
+                    commands::get_harmonic_mix_candidates,
+                    // =========================================================================
+                    // ACOUSTIC FINGERPRINTS
+                    // =========================================================================
+                    commands::fingerprint_tracks,
+                    commands::fingerprint_library,
+                    commands::get_duplicate_clusters,
+                    commands::resolve_duplicate_cluster,
+                    commands::lookup_acoustid,
+                    // =========================================================================
+                    // AUDIO QUALITY
+                    // =========================================================================
+                    commands::analyze_track_quality,
+                    commands::analyze_library_quality,
+                    commands::get_track_quality,
+                    commands::get_quality_report,
+                    // =========================================================================
+                    // FOLDER WATCHER
+                    // =========================================================================
+                    commands::get_folder_watcher_status,
+                    commands::set_folder_watcher_config,
+                    commands::refresh_folder_watcher,
+                    // =========================================================================
+                    // LIBRARY EXCLUSION RULES
+                    // =========================================================================
+                    commands::get_scan_rules,
+                    commands::set_scan_rules,
+                    commands::preview_scan_rules,
+                    // =========================================================================
+                    // ALBUM GROUPING
+                    // =========================================================================
+                    commands::get_various_artists_policy,
+                    commands::set_various_artists_policy,
+                    commands::regroup_albums,
+                    // =========================================================================
+                    // ARTIST CREDITS
+                    // =========================================================================
+                    commands::get_track_credits,
+                    commands::get_artist_split_rules,
+                    commands::set_artist_split_rules,
+                    // =========================================================================
+                    // BROWSE BY GENRE / YEAR / COMPOSER / LABEL
+                    // =========================================================================
+                    commands::get_genres,
+                    commands::get_years,
+                    commands::get_decades,
+                    commands::get_composers,
+                    commands::get_labels,
+                    commands::get_tracks_by_genre,
+                    commands::get_tracks_by_year,
+                    commands::get_tracks_by_decade,
+                    commands::get_tracks_by_composer,
+                    commands::get_tracks_by_label,
+                    commands::get_genre_aliases,
+                    commands::set_genre_aliases,
+                    // =========================================================================
+                    // TAG EDITOR
+                    // =========================================================================
+                    commands::get_track_tags,
+                    commands::edit_tags,
+                    commands::get_tag_edit_history,
+                    commands::undo_tag_edit,
+                    // =========================================================================
+                    // MUSICBRAINZ ALBUM TAGGER
+                    // =========================================================================
+                    commands::search_album_releases_mb,
+                    commands::apply_album_tagging_mb,
+                    // =========================================================================
+                    // FILE ORGANIZER
+                    // =========================================================================
+                    commands::preview_organize,
+                    commands::organize_files,
+                    commands::get_organize_history,
+                    commands::undo_organize,
+                    // =========================================================================
+                    // SMART PLAYLISTS
+                    // =========================================================================
+                    commands::create_smart_playlist,
+                    commands::update_smart_playlist,
+                    // =========================================================================
+                    // PLAYLIST FILES
+                    // =========================================================================
+                    commands::import_playlist,
+                    commands::export_playlist,
+                    // =========================================================================
+                    // PLAYLIST FOLDERS
+                    // =========================================================================
+                    commands::get_playlist_folders,
+                    commands::create_playlist_folder,
+                    commands::rename_playlist_folder,
+                    commands::move_playlist_folder,
+                    commands::delete_playlist_folder,
+                    commands::move_playlist,
+                    // =========================================================================
+                    // PLAYLIST BULK OPERATIONS
+                    // =========================================================================
+                    commands::sort_playlist,
+                    commands::shuffle_playlist,
+                    commands::dedupe_playlist,
+                    commands::remove_missing_from_playlist,
+                    commands::merge_playlists,
+                    commands::split_playlist,
+                    // =========================================================================
+                    // FOLDER PLAYLIST FILES
+                    // =========================================================================
+                    commands::get_folder_playlist_files,
+                    commands::sync_folder_playlist_file,
+                    commands::create_folder_playlist_file,
+                    commands::resolve_folder_playlist_conflict,
                     commands::proxy_fetch_bytes,
                     commands::save_image_to_gallery,
                 ]
@@ -644,15 +802,27 @@ pub fn run() {
                     // Activity commands (liked tracks + play history)
                     commands::like_track,
                     commands::unlike_track,
+                    commands::set_track_rating,
+                    commands::set_album_rating,
+                    commands::get_track_ratings,
+                    commands::get_album_ratings,
+                    commands::get_rating_write_back,
+                    commands::set_rating_write_back,
                     commands::is_track_liked,
                     commands::get_liked_track_ids,
                     commands::get_liked_tracks,
                     commands::record_play,
+                    commands::get_skip_thresholds,
+                    commands::set_skip_thresholds,
                     commands::get_top_tracks,
                     commands::get_top_albums,
                     commands::get_recently_played,
                     commands::get_top_artists,
                     commands::get_stats_summary,
+                    commands::get_top_genres,
+                    commands::get_listening_breakdown,
+                    commands::get_year_in_review,
+                    commands::export_year_in_review,
                     // Lyrics commands
                     commands::save_lrc_file,
                     commands::save_api_lrc_file,
@@ -736,6 +906,142 @@ pub fn run() {
                     audio::audio_get_state,
                     audio::audio_set_eq,
                     audio::native_audio_available,
+                    // =========================================================================
+                    // INTERNET RADIO COMMANDS
+                    // =========================================================================
+                    commands::get_radio_stations,
+                    commands::add_radio_station,
+                    commands::update_radio_station,
+                    commands::delete_radio_station,
+                    commands::radio_play_station,
+                    commands::get_radio_song_history,
+                    commands::clear_radio_song_history,
+                    commands::import_radio_stations,
+                    commands::export_radio_stations,
+                    // =========================================================================
+                    // WAVEFORM COMMANDS
+                    // =========================================================================
+                    commands::get_waveform,
+                    commands::queue_waveforms,
+                    commands::prioritize_waveforms,
+                    commands::queue_library_waveforms,
+                    commands::get_waveform_queue_size,
+                    // =========================================================================
+                    // TEMPO / KEY ANALYSIS
+                    // =========================================================================
+                    commands::analyze_tracks,
+                    commands::analyze_library,
+                    commands::get_track_analysis,
+                    commands::get_dj_tracks,
+                    commands::get_harmonic_mix_candidates,
+                    // =========================================================================
+                    // ACOUSTIC FINGERPRINTS
+                    // =========================================================================
+                    commands::fingerprint_tracks,
+                    commands::fingerprint_library,
+                    commands::get_duplicate_clusters,
+                    commands::resolve_duplicate_cluster,
+                    commands::lookup_acoustid,
+                    // =========================================================================
+                    // AUDIO QUALITY
+                    // =========================================================================
+                    commands::analyze_track_quality,
+                    commands::analyze_library_quality,
+                    commands::get_track_quality,
+                    commands::get_quality_report,
+                    // =========================================================================
+                    // FOLDER WATCHER
+                    // =========================================================================
+                    commands::get_folder_watcher_status,
+                    commands::set_folder_watcher_config,
+                    commands::refresh_folder_watcher,
+                    // =========================================================================
+                    // LIBRARY EXCLUSION RULES
+                    // =========================================================================
+                    commands::get_scan_rules,
+                    commands::set_scan_rules,
+                    commands::preview_scan_rules,
+                    // =========================================================================
+                    // ALBUM GROUPING
+                    // =========================================================================
+                    commands::get_various_artists_policy,
+                    commands::set_various_artists_policy,
+                    commands::regroup_albums,
+                    // =========================================================================
+                    // ARTIST CREDITS
+                    // =========================================================================
+                    commands::get_track_credits,
+                    commands::get_artist_split_rules,
+                    commands::set_artist_split_rules,
+                    // =========================================================================
+                    // BROWSE BY GENRE / YEAR / COMPOSER / LABEL
+                    // =========================================================================
+                    commands::get_genres,
+                    commands::get_years,
+                    commands::get_decades,
+                    commands::get_composers,
+                    commands::get_labels,
+                    commands::get_tracks_by_genre,
+                    commands::get_tracks_by_year,
+                    commands::get_tracks_by_decade,
+                    commands::get_tracks_by_composer,
+                    commands::get_tracks_by_label,
+                    commands::get_genre_aliases,
+                    commands::set_genre_aliases,
+                    // =========================================================================
+                    // TAG EDITOR
+                    // =========================================================================
+                    commands::get_track_tags,
+                    commands::edit_tags,
+                    commands::get_tag_edit_history,
+                    commands::undo_tag_edit,
+                    // =========================================================================
+                    // MUSICBRAINZ ALBUM TAGGER
+                    // =========================================================================
+                    commands::search_album_releases_mb,
+                    commands::apply_album_tagging_mb,
+                    // =========================================================================
+                    // FILE ORGANIZER
+                    // =========================================================================
+                    commands::preview_organize,
+                    commands::organize_files,
+                    commands::get_organize_history,
+                    commands::undo_organize,
+                    // =========================================================================
+                    // SMART PLAYLISTS
+                    // =========================================================================
+                    commands::create_smart_playlist,
+                    commands::update_smart_playlist,
+                    // =========================================================================
+                    // PLAYLIST FILES
+                    // =========================================================================
+                    commands::import_playlist,
+                    commands::export_playlist,
+                    // =========================================================================
+                    // PLAYLIST FOLDERS
+                    // =========================================================================
+                    commands::get_playlist_folders,
+                    commands::create_playlist_folder,
+                    commands::rename_playlist_folder,
+                    commands::move_playlist_folder,
+                    commands::delete_playlist_folder,
+                    commands::move_playlist,
+                    // =========================================================================
+                    // PLAYLIST BULK OPERATIONS
+                    // =========================================================================
+                    commands::sort_playlist,
+                    commands::shuffle_playlist,
+                    commands::dedupe_playlist,
+                    commands::remove_missing_from_playlist,
+                    commands::merge_playlists,
+                    commands::split_playlist,
+                    // =========================================================================
+                    // FOLDER PLAYLIST FILES
+                    // =========================================================================
+                    commands::get_folder_playlist_files,
+                    commands::sync_folder_playlist_file,
+                    commands::create_folder_playlist_file,
+                    commands::resolve_folder_playlist_conflict,
                     commands::proxy_fetch_bytes,
                     commands::save_image_to_gallery,
                 ]
@@ -0,0 +1,1074 @@
+// File organizer: move or copy tracks into a folder layout built from a
+// naming template, taking their lyrics and folder art along.
+//
+// A run is planned first (the plan is also the preview), then executed:
+// every file is transferred before the database is touched, and the
+// database changes are written in one transaction. If that transaction
+// fails, the transfers are reverted, so files and rows never disagree.
+pub mod template;
+
+use crate::db::queries::{self, OrganizeJournalEntry, OrganizeSource, OrganizedFileKind};
+use crate::scanner::walker::{is_supported_audio_file, FileStamp};
+use crate::security;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use template::{NamingTemplate, Placeholder, SanitizeRules, TemplateValues};
+
+/// Highest " (n)" suffix tried by `CollisionPolicy::Rename`.
+const MAX_RENAME_SUFFIX: u32 = 99;
+
+/// Folder art that moves along once a folder's last track has left it.
+const FOLDER_ART_NAMES: &[&str] = &["cover", "folder", "front", "album"];
+const FOLDER_ART_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizeMode {
+    #[default]
+    Move,
+    /// Copy the files and point the tracks at the copies; originals stay
+    Copy,
+}
+
+impl OrganizeMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Move => "move",
+            Self::Copy => "copy",
+        }
+    }
+}
+
+/// What to do when the destination file already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    #[default]
+    Skip,
+    /// Append " (2)", " (3)", ... to the file name
+    Rename,
+    /// Move the existing file to the trash once the run is recorded.
+    /// Never replaces another library track; undo does not bring the old
+    /// file back.
+    Overwrite,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrganizeOptions {
+    pub template: String,
+    /// Folder the template is rendered into; must be inside a music folder
+    pub destination: String,
+    #[serde(default)]
+    pub mode: OrganizeMode,
+    #[serde(default)]
+    pub collision: CollisionPolicy,
+    #[serde(default)]
+    pub sanitize: SanitizeRules,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStatus {
+    Ready,
+    /// Already where the template puts it
+    Unchanged,
+    /// Destination taken; a numbered name is used instead
+    Renamed,
+    /// Destination taken; the existing file goes to the trash
+    Overwrite,
+    Skipped,
+}
+
+/// A file transferred alongside a track (lyrics) or a folder (art).
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFile {
+    pub kind: OrganizedFileKind,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedMove {
+    pub track_id: i64,
+    pub from: String,
+    pub to: Option<String>,
+    pub status: PlanStatus,
+    /// Why the track is skipped
+    pub reason: Option<String>,
+    /// Lyrics sidecars that move with the track
+    pub sidecars: Vec<PlannedFile>,
+}
+
+impl PlannedMove {
+    fn is_active(&self) -> bool {
+        matches!(
+            self.status,
+            PlanStatus::Ready | PlanStatus::Renamed | PlanStatus::Overwrite
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrganizePlan {
+    pub moves: Vec<PlannedMove>,
+    pub folder_art: Vec<PlannedFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrganizeFailure {
+    pub track_id: i64,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrganizeResult {
+    /// Journal batch for `undo`; `None` when nothing was transferred
+    pub batch_id: Option<i64>,
+    pub moved: Vec<i64>,
+    pub unchanged: Vec<i64>,
+    pub failed: Vec<OrganizeFailure>,
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// Key for spotting two planned files with the same destination. Case is
+/// ignored so a plan also holds on case-insensitive filesystems.
+fn collision_key(path: &Path) -> String {
+    path_string(path).to_lowercase()
+}
+
+/// Whether `a` and `b` name the same existing file, as with a case-only
+/// rename on a case-insensitive filesystem.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn template_values(source: &OrganizeSource, from: &Path) -> TemplateValues {
+    let stem = from.file_stem().map(|s| s.to_string_lossy().to_string());
+    let ext = from.extension().map(|e| e.to_string_lossy().to_lowercase());
+    let artist = source.artist.as_deref().map(|a| a.replace("; ", ", "));
+    let album_artist = source
+        .album_artist
+        .clone()
+        .or_else(|| artist.clone())
+        .unwrap_or_else(|| "Unknown Artist".to_string());
+    let genre = source
+        .genre
+        .as_deref()
+        .and_then(|g| g.split(';').map(str::trim).find(|g| !g.is_empty()));
+
+    let mut values = TemplateValues::default();
+    values.set(Placeholder::AlbumArtist, Some(&album_artist));
+    values.set(
+        Placeholder::Artist,
+        Some(artist.as_deref().unwrap_or(&album_artist)),
+    );
+    values.set(
+        Placeholder::Album,
+        Some(source.album.as_deref().unwrap_or("Unknown Album")),
+    );
+    values.set(
+        Placeholder::Title,
+        source.title.as_deref().or(stem.as_deref()),
+    );
+    values.set(
+        Placeholder::Year,
+        source
+            .year
+            .filter(|y| *y > 0)
+            .map(|y| y.to_string())
+            .as_deref(),
+    );
+    values.set(
+        Placeholder::Track,
+        source
+            .track_number
+            .filter(|n| *n > 0)
+            .map(|n| format!("{:02}", n))
+            .as_deref(),
+    );
+    values.set(
+        Placeholder::Disc,
+        source
+            .disc_number
+            .filter(|n| *n > 0)
+            .map(|n| n.to_string())
+            .as_deref(),
+    );
+    values.set(Placeholder::Genre, genre);
+    values.set(
+        Placeholder::Composer,
+        source
+            .composer
+            .as_deref()
+            .map(|c| c.replace("; ", ", "))
+            .as_deref(),
+    );
+    values.set(Placeholder::Label, source.label.as_deref());
+    values.set(Placeholder::Ext, ext.as_deref());
+    values.set(Placeholder::Filename, stem.as_deref());
+    values
+}
+
+/// `song.flac` -> `song (n).flac`
+fn numbered(path: &Path, n: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{} ({}).{}", stem, n, ext.to_string_lossy()),
+        None => format!("{} ({})", stem, n),
+    };
+    path.with_file_name(name)
+}
+
+/// Lyrics sidecars beside `from`, paired with their place beside `to`.
+/// Sidecars whose destination is taken are left behind.
+fn lyrics_sidecars(from: &Path, to: &Path) -> Vec<PlannedFile> {
+    let from_stem = from.file_stem().unwrap_or_default().to_string_lossy();
+    let to_stem = to.file_stem().unwrap_or_default().to_string_lossy();
+    ["lrc", "api.lrc"]
+        .iter()
+        .filter_map(|suffix| {
+            let source = from.with_file_name(format!("{}.{}", from_stem, suffix));
+            let target = to.with_file_name(format!("{}.{}", to_stem, suffix));
+            let free = !target.exists() || same_file(&source, &target);
+            (source.is_file() && free).then(|| PlannedFile {
+                kind: OrganizedFileKind::Lyrics,
+                from: path_string(&source),
+                to: path_string(&target),
+            })
+        })
+        .collect()
+}
+
+fn is_folder_art(path: &Path) -> bool {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    FOLDER_ART_NAMES.contains(&stem.as_str()) && FOLDER_ART_EXTENSIONS.contains(&ext.as_str())
+}
+
+/// Audio files directly inside `dir`.
+fn audio_files_in(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file() && is_supported_audio_file(path))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Folder art of every source folder whose tracks all go to the same new
+/// folder. When moving, the source folder must also be left without audio.
+fn plan_folder_art(moves: &[PlannedMove], mode: OrganizeMode) -> Vec<PlannedFile> {
+    let mut targets: BTreeMap<PathBuf, HashSet<PathBuf>> = BTreeMap::new();
+    let mut leaving: HashSet<PathBuf> = HashSet::new();
+    for planned in moves.iter().filter(|m| m.is_active()) {
+        let from = Path::new(&planned.from);
+        let (Some(source_dir), Some(target_dir)) = (
+            from.parent(),
+            planned.to.as_deref().and_then(|to| Path::new(to).parent()),
+        ) else {
+            continue;
+        };
+        targets
+            .entry(source_dir.to_path_buf())
+            .or_default()
+            .insert(target_dir.to_path_buf());
+        leaving.insert(from.to_path_buf());
+    }
+
+    let mut art = Vec::new();
+    for (source_dir, target_dirs) in targets {
+        if target_dirs.len() != 1 {
+            continue;
+        }
+        let target_dir = target_dirs.into_iter().next().unwrap_or_default();
+        if target_dir == source_dir {
+            continue;
+        }
+        if mode == OrganizeMode::Move
+            && audio_files_in(&source_dir)
+                .iter()
+                .any(|file| !leaving.contains(file))
+        {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&source_dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if !path.is_file() || !is_folder_art(&path) {
+                continue;
+            }
+            let target = target_dir.join(path.file_name().unwrap_or_default());
+            if !target.exists() {
+                art.push(PlannedFile {
+                    kind: OrganizedFileKind::Cover,
+                    from: path_string(&path),
+                    to: path_string(&target),
+                });
+            }
+        }
+    }
+    art
+}
+
+/// Work out where every track goes, without touching any file.
+pub fn plan(
+    conn: &Connection,
+    track_ids: &[i64],
+    options: &OrganizeOptions,
+) -> Result<OrganizePlan, String> {
+    plan_with_check(conn, track_ids, options, &security::is_safe_path)
+}
+
+/// `plan`, with `is_safe` as the app-wide path check that source files and
+/// the destination must pass besides being inside a music folder.
+fn plan_with_check(
+    conn: &Connection,
+    track_ids: &[i64],
+    options: &OrganizeOptions,
+    is_safe: &dyn Fn(&Path) -> Result<bool, String>,
+) -> Result<OrganizePlan, String> {
+    let template = NamingTemplate::parse(&options.template)?;
+    let destination = PathBuf::from(&options.destination);
+    if !destination.is_dir() {
+        return Err(format!(
+            "Destination folder not found: {}",
+            options.destination
+        ));
+    }
+    let allowed = AllowedPaths {
+        roots: canonical_roots(conn),
+        is_safe,
+    };
+    if !allowed.allows(&destination)? {
+        return Err(format!(
+            "Destination must be inside a music folder: {}",
+            options.destination
+        ));
+    }
+
+    let sources = queries::get_organize_sources(conn, track_ids).map_err(|e| e.to_string())?;
+    let mut claimed: HashSet<String> = HashSet::new();
+    let mut moves = Vec::with_capacity(sources.len());
+    for source in sources {
+        let planned = plan_track(
+            conn,
+            &source,
+            &template,
+            &destination,
+            &allowed,
+            options,
+            &claimed,
+        )
+        .unwrap_or_else(|reason| PlannedMove {
+            track_id: source.track_id,
+            from: source.path.clone(),
+            to: None,
+            status: PlanStatus::Skipped,
+            reason: Some(reason),
+            sidecars: Vec::new(),
+        });
+        if let Some(to) = &planned.to {
+            claimed.insert(collision_key(Path::new(to)));
+        }
+        moves.push(planned);
+    }
+
+    let folder_art = plan_folder_art(&moves, options.mode);
+    Ok(OrganizePlan { moves, folder_art })
+}
+
+fn plan_track(
+    conn: &Connection,
+    source: &OrganizeSource,
+    template: &NamingTemplate,
+    destination: &Path,
+    allowed: &AllowedPaths,
+    options: &OrganizeOptions,
+    claimed: &HashSet<String>,
+) -> Result<PlannedMove, String> {
+    if !matches!(source.source_type.as_deref(), None | Some("local")) {
+        return Err("Only local files can be organized".to_string());
+    }
+    let from = Path::new(&source.path);
+    if !from.is_file() {
+        return Err(format!("File not found: {}", source.path));
+    }
+    if !allowed.allows(from)? {
+        return Err("File is outside the music folders".to_string());
+    }
+
+    let relative = template.render(&template_values(source, from), &options.sanitize)?;
+    let mut to = destination.join(relative);
+    let mut status = PlanStatus::Ready;
+    if to == from {
+        status = PlanStatus::Unchanged;
+    } else {
+        let is_taken = |path: &Path| -> Result<bool, String> {
+            let on_disk = path.exists() && !same_file(from, path);
+            let in_library =
+                queries::track_path_exists(conn, &path_string(path)).map_err(|e| e.to_string())?;
+            Ok(on_disk || in_library || claimed.contains(&collision_key(path)))
+        };
+        if is_taken(&to)? {
+            match options.collision {
+                CollisionPolicy::Skip => {
+                    return Err(format!("Destination exists: {}", path_string(&to)));
+                }
+                CollisionPolicy::Rename => {
+                    let free = (2..=MAX_RENAME_SUFFIX)
+                        .map(|n| numbered(&to, n))
+                        .find(|candidate| matches!(is_taken(candidate), Ok(false)));
+                    to = free.ok_or_else(|| format!("No free name for {}", path_string(&to)))?;
+                    status = PlanStatus::Renamed;
+                }
+                CollisionPolicy::Overwrite => {
+                    let key = collision_key(&to);
+                    if claimed.contains(&key)
+                        || queries::track_path_exists(conn, &path_string(&to))
+                            .map_err(|e| e.to_string())?
+                    {
+                        return Err(format!(
+                            "Destination belongs to another track: {}",
+                            path_string(&to)
+                        ));
+                    }
+                    status = PlanStatus::Overwrite;
+                }
+            }
+        }
+    }
+
+    let sidecars = if status == PlanStatus::Unchanged {
+        Vec::new()
+    } else {
+        lyrics_sidecars(from, &to)
+    };
+    Ok(PlannedMove {
+        track_id: source.track_id,
+        from: source.path.clone(),
+        to: Some(path_string(&to)),
+        status,
+        reason: None,
+        sidecars,
+    })
+}
+
+/// Move (rename, or copy and delete across filesystems) or copy one file.
+/// Refuses to replace an existing file other than `from` itself.
+fn transfer(from: &Path, to: &Path, mode: OrganizeMode) -> Result<(), String> {
+    if to.exists() && !same_file(from, to) {
+        return Err(format!("Destination exists: {:?}", to));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create folder {:?}: {}", parent, e))?;
+    }
+    match mode {
+        OrganizeMode::Move => {
+            if fs::rename(from, to).is_err() {
+                fs::copy(from, to).map_err(|e| format!("Failed to copy {:?}: {}", from, e))?;
+                if let Err(e) = fs::remove_file(from) {
+                    let _ = fs::remove_file(to);
+                    return Err(format!("Failed to remove {:?}: {}", from, e));
+                }
+            }
+        }
+        OrganizeMode::Copy => {
+            fs::copy(from, to).map_err(|e| format!("Failed to copy {:?}: {}", from, e))?;
+        }
+    }
+    log::info!(
+        "[AUDIT] Organizer {}: {:?} -> {:?}",
+        mode.as_str(),
+        from,
+        to
+    );
+    Ok(())
+}
+
+/// Undo one `transfer`: move the file back, or delete the copy.
+fn revert_transfer(entry: &OrganizeJournalEntry, mode: OrganizeMode) -> Result<(), String> {
+    let from = Path::new(&entry.from_path);
+    let to = Path::new(&entry.to_path);
+    match mode {
+        OrganizeMode::Move => transfer(to, from, OrganizeMode::Move),
+        OrganizeMode::Copy => security::safe_delete_file(to).map(|_| ()),
+    }
+}
+
+/// Hidden name beside `path` for a file on its way in or out.
+fn staging_path(path: &Path, purpose: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.{}", name, purpose))
+}
+
+/// Transfer `from` onto the existing file `to`. The new file is staged
+/// beside the old one first, and the old one is only set aside once that
+/// worked; it is returned so the caller can trash or restore it. On error
+/// both files are where they were.
+fn transfer_over(from: &Path, to: &Path, mode: OrganizeMode) -> Result<PathBuf, String> {
+    let staged = staging_path(to, "organizing");
+    let replaced = staging_path(to, "replaced");
+    if replaced.exists() {
+        return Err(format!("Leftover file in the way: {:?}", replaced));
+    }
+    transfer(from, &staged, mode)?;
+
+    let swapped = fs::rename(to, &replaced).and_then(|()| {
+        fs::rename(&staged, to).inspect_err(|_| {
+            let _ = fs::rename(&replaced, to);
+        })
+    });
+    if let Err(e) = swapped {
+        let unstaged = match mode {
+            OrganizeMode::Move => transfer(&staged, from, OrganizeMode::Move),
+            OrganizeMode::Copy => fs::remove_file(&staged).map_err(|e| e.to_string()),
+        };
+        if let Err(error) = unstaged {
+            log::error!("[Organizer] Failed to unstage {:?}: {}", staged, error);
+        }
+        return Err(format!("Failed to replace {:?}: {}", to, e));
+    }
+    Ok(replaced)
+}
+
+/// Remove `dir` and its parents while they are empty, stopping at the
+/// music folders themselves.
+fn remove_empty_dirs(dir: &Path, roots: &[PathBuf]) {
+    let mut current = Some(dir);
+    while let Some(dir) = current {
+        let inside_root = roots
+            .iter()
+            .any(|root| dir.starts_with(root) && dir != root);
+        let is_empty = fs::read_dir(dir)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false);
+        if !inside_root || !is_empty || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+}
+
+fn music_roots(conn: &Connection) -> Vec<PathBuf> {
+    queries::get_music_folders(conn)
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// The music folders with symlinks and `..` resolved. Organizing never
+/// reads or writes outside them.
+fn canonical_roots(conn: &Connection) -> Vec<PathBuf> {
+    music_roots(conn)
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .collect()
+}
+
+fn inside_roots(path: &Path, roots: &[PathBuf]) -> bool {
+    path.canonicalize()
+        .is_ok_and(|path| roots.iter().any(|root| path.starts_with(root)))
+}
+
+/// Where the organizer may read and write: inside a music folder, and
+/// allowed by the app-wide path check as well.
+struct AllowedPaths<'a> {
+    roots: Vec<PathBuf>,
+    is_safe: &'a dyn Fn(&Path) -> Result<bool, String>,
+}
+
+impl AllowedPaths<'_> {
+    fn allows(&self, path: &Path) -> Result<bool, String> {
+        Ok(inside_roots(path, &self.roots) && (self.is_safe)(path)?)
+    }
+}
+
+/// Write journaled transfers to the database in one transaction: track
+/// rows follow their files and stored cover paths follow folder art.
+fn record_batch(
+    conn: &Connection,
+    options: &OrganizeOptions,
+    entries: &[OrganizeJournalEntry],
+) -> rusqlite::Result<i64> {
+    let tx = conn.unchecked_transaction()?;
+    let batch_id = queries::create_organize_batch(&tx, options.mode.as_str(), &options.template)?;
+    for entry in entries {
+        match (entry.kind, entry.track_id) {
+            (OrganizedFileKind::Track, Some(track_id)) => {
+                let stamp = FileStamp::of(Path::new(&entry.to_path)).ok();
+                queries::move_track_path(
+                    &tx,
+                    track_id,
+                    &entry.to_path,
+                    stamp.map(|s| s.mtime_millis()),
+                    stamp.map(|s| s.size as i64),
+                )?;
+            }
+            (OrganizedFileKind::Cover, _) => {
+                queries::update_cover_paths(&tx, &entry.from_path, &entry.to_path)?;
+            }
+            _ => {}
+        }
+        queries::add_organize_entry(&tx, batch_id, entry)?;
+    }
+    tx.commit()?;
+    Ok(batch_id)
+}
+
+/// Carry out a plan from `plan`. Tracks whose transfer fails are reported
+/// and skipped; everything that was transferred forms one undoable batch.
+pub fn execute(
+    conn: &Connection,
+    plan: &OrganizePlan,
+    options: &OrganizeOptions,
+) -> Result<OrganizeResult, String> {
+    let mut entries: Vec<OrganizeJournalEntry> = Vec::new();
+    // Files set aside by `transfer_over`, with the place they came from
+    let mut replaced: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut moved = Vec::new();
+    let mut unchanged = Vec::new();
+    let mut failed = Vec::new();
+
+    for planned in &plan.moves {
+        match planned.status {
+            PlanStatus::Unchanged => {
+                unchanged.push(planned.track_id);
+                continue;
+            }
+            PlanStatus::Skipped => {
+                failed.push(OrganizeFailure {
+                    track_id: planned.track_id,
+                    error: planned.reason.clone().unwrap_or_default(),
+                });
+                continue;
+            }
+            _ => {}
+        }
+        let Some(to) = planned.to.as_deref() else {
+            continue;
+        };
+
+        let from = Path::new(&planned.from);
+        let result = if planned.status == PlanStatus::Overwrite && Path::new(to).exists() {
+            transfer_over(from, Path::new(to), options.mode)
+                .map(|old| replaced.push((old, PathBuf::from(to))))
+        } else {
+            transfer(from, Path::new(to), options.mode)
+        };
+        if let Err(error) = result {
+            log::warn!("[Organizer] Track {}: {}", planned.track_id, error);
+            failed.push(OrganizeFailure {
+                track_id: planned.track_id,
+                error,
+            });
+            continue;
+        }
+        entries.push(OrganizeJournalEntry {
+            track_id: Some(planned.track_id),
+            kind: OrganizedFileKind::Track,
+            from_path: planned.from.clone(),
+            to_path: to.to_string(),
+        });
+        moved.push(planned.track_id);
+
+        for sidecar in &planned.sidecars {
+            match transfer(
+                Path::new(&sidecar.from),
+                Path::new(&sidecar.to),
+                options.mode,
+            ) {
+                Ok(()) => entries.push(OrganizeJournalEntry {
+                    track_id: Some(planned.track_id),
+                    kind: sidecar.kind,
+                    from_path: sidecar.from.clone(),
+                    to_path: sidecar.to.clone(),
+                }),
+                Err(error) => log::warn!("[Organizer] Lyrics {}: {}", sidecar.from, error),
+            }
+        }
+    }
+
+    // Art only follows folders whose tracks have all left by now
+    for art in &plan.folder_art {
+        let source_dir = Path::new(&art.from).parent().unwrap_or(Path::new(""));
+        if options.mode == OrganizeMode::Move && !audio_files_in(source_dir).is_empty() {
+            continue;
+        }
+        match transfer(Path::new(&art.from), Path::new(&art.to), options.mode) {
+            Ok(()) => entries.push(OrganizeJournalEntry {
+                track_id: None,
+                kind: art.kind,
+                from_path: art.from.clone(),
+                to_path: art.to.clone(),
+            }),
+            Err(error) => log::warn!("[Organizer] Folder art {}: {}", art.from, error),
+        }
+    }
+
+    if entries.is_empty() {
+        return Ok(OrganizeResult {
+            batch_id: None,
+            moved,
+            unchanged,
+            failed,
+        });
+    }
+
+    let batch_id = match record_batch(conn, options, &entries) {
+        Ok(batch_id) => batch_id,
+        Err(e) => {
+            for entry in entries.iter().rev() {
+                if let Err(error) = revert_transfer(entry, options.mode) {
+                    log::error!("[Organizer] Failed to revert {}: {}", entry.to_path, error);
+                }
+            }
+            for (old, place) in &replaced {
+                if let Err(error) = fs::rename(old, place) {
+                    log::error!("[Organizer] Failed to restore {:?}: {}", place, error);
+                }
+            }
+            return Err(format!("Failed to update the library: {}", e));
+        }
+    };
+
+    for (old, _) in &replaced {
+        if let Err(error) = security::safe_delete_file(old) {
+            log::warn!("[Organizer] Failed to trash {:?}: {}", old, error);
+        }
+    }
+
+    if options.mode == OrganizeMode::Move {
+        let roots = music_roots(conn);
+        let source_dirs: HashSet<&Path> = entries
+            .iter()
+            .filter_map(|entry| Path::new(&entry.from_path).parent())
+            .collect();
+        for dir in source_dirs {
+            remove_empty_dirs(dir, &roots);
+        }
+    }
+
+    Ok(OrganizeResult {
+        batch_id: Some(batch_id),
+        moved,
+        unchanged,
+        failed,
+    })
+}
+
+/// Put a batch's files back where they were. Later runs that moved the
+/// same tracks must be undone first. The batch is only marked undone once
+/// every file was restored, so a partial undo can be retried.
+pub fn undo(conn: &Connection, batch_id: i64) -> Result<OrganizeResult, String> {
+    let batch = queries::get_organize_batch(conn, batch_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Organize run {} not found", batch_id))?;
+    if batch.undone_at.is_some() {
+        return Err("This organize run was already undone".to_string());
+    }
+    if queries::organize_batch_superseded(conn, batch_id).map_err(|e| e.to_string())? {
+        return Err("Undo the later organize runs for these tracks first".to_string());
+    }
+    let mode = if batch.mode == "copy" {
+        OrganizeMode::Copy
+    } else {
+        OrganizeMode::Move
+    };
+
+    let entries = queries::get_organize_entries(conn, batch_id).map_err(|e| e.to_string())?;
+    let mut restored: Vec<&OrganizeJournalEntry> = Vec::new();
+    let mut moved = Vec::new();
+    let mut failed = Vec::new();
+    for entry in entries.iter().rev() {
+        // A file that is already back in place (or a copy that is already
+        // gone) needs no work
+        let done = match mode {
+            OrganizeMode::Move => {
+                Path::new(&entry.from_path).exists() && !Path::new(&entry.to_path).exists()
+            }
+            OrganizeMode::Copy => !Path::new(&entry.to_path).exists(),
+        };
+        let result = if done {
+            Ok(())
+        } else {
+            revert_transfer(entry, mode)
+        };
+        match result {
+            Ok(()) => {
+                restored.push(entry);
+                if entry.kind == OrganizedFileKind::Track {
+                    moved.extend(entry.track_id);
+                }
+            }
+            Err(error) => {
+                log::warn!("[Organizer] Undo {}: {}", entry.to_path, error);
+                if let Some(track_id) = entry.track_id {
+                    failed.push(OrganizeFailure { track_id, error });
+                }
+            }
+        }
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    for entry in &restored {
+        match (entry.kind, entry.track_id) {
+            (OrganizedFileKind::Track, Some(track_id)) => {
+                let stamp = FileStamp::of(Path::new(&entry.from_path)).ok();
+                queries::move_track_path(
+                    &tx,
+                    track_id,
+                    &entry.from_path,
+                    stamp.map(|s| s.mtime_millis()),
+                    stamp.map(|s| s.size as i64),
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            (OrganizedFileKind::Cover, _) => {
+                queries::update_cover_paths(&tx, &entry.to_path, &entry.from_path)
+                    .map_err(|e| e.to_string())?;
+            }
+            _ => {}
+        }
+    }
+    if failed.is_empty() && restored.len() == entries.len() {
+        queries::mark_organize_batch_undone(&tx, batch_id).map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    let roots = music_roots(conn);
+    let target_dirs: HashSet<&Path> = restored
+        .iter()
+        .filter_map(|entry| Path::new(&entry.to_path).parent())
+        .collect();
+    for dir in target_dirs {
+        remove_empty_dirs(dir, &roots);
+    }
+
+    Ok(OrganizeResult {
+        batch_id: Some(batch_id),
+        moved,
+        unchanged: Vec::new(),
+        failed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Library {
+        conn: Connection,
+        root: PathBuf,
+    }
+
+    impl Library {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!(
+                "audion_organizer_{}_{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            let root = root.canonicalize().unwrap();
+            let conn = Connection::open_in_memory().unwrap();
+            crate::db::schema::init_schema(&conn).unwrap();
+            queries::add_music_folder(&conn, &path_string(&root)).unwrap();
+            Library { conn, root }
+        }
+
+        fn track(&self, file: &str, title: &str) -> i64 {
+            // Moves clear out the emptied inbox, so it may need recreating
+            let inbox = self.root.join("Inbox");
+            fs::create_dir_all(&inbox).unwrap();
+            let path = inbox.join(file);
+            fs::write(&path, title).unwrap();
+            self.conn
+                .execute(
+                    "INSERT INTO tracks (path, title, artist, album) VALUES (?1, ?2, 'Artist', 'Album')",
+                    rusqlite::params![path_string(&path), title],
+                )
+                .unwrap();
+            self.conn.last_insert_rowid()
+        }
+
+        fn path_of(&self, track_id: i64) -> String {
+            self.conn
+                .query_row("SELECT path FROM tracks WHERE id = ?1", [track_id], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        }
+
+        fn options(&self, mode: OrganizeMode, collision: CollisionPolicy) -> OrganizeOptions {
+            OrganizeOptions {
+                template: "%artist%/%album%/%title%.%ext%".to_string(),
+                destination: path_string(&self.root),
+                mode,
+                collision,
+                sanitize: SanitizeRules::default(),
+            }
+        }
+
+        fn run(&self, ids: &[i64], options: &OrganizeOptions) -> OrganizeResult {
+            let plan = plan(&self.conn, ids, options).unwrap();
+            execute(&self.conn, &plan, options).unwrap()
+        }
+    }
+
+    impl Drop for Library {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn test_destination_outside_music_folders() {
+        let library = Library::new("outside");
+        let id = library.track("a.mp3", "Song");
+        let outside =
+            std::env::temp_dir().join(format!("audion_organizer_elsewhere_{}", std::process::id()));
+        fs::create_dir_all(&outside).unwrap();
+
+        let mut options = library.options(OrganizeMode::Move, CollisionPolicy::Skip);
+        options.destination = path_string(&outside);
+        let error = plan(&library.conn, &[id], &options).unwrap_err();
+        assert!(error.contains("inside a music folder"), "{}", error);
+
+        // `..` does not get around the check either
+        options.destination = path_string(&library.root.join("Inbox/../.."));
+        assert!(plan(&library.conn, &[id], &options).is_err());
+        let _ = fs::remove_dir_all(&outside);
+    }
+
+    #[test]
+    fn test_path_check_rejects() {
+        let library = Library::new("unsafe");
+        let id = library.track("a.mp3", "Song");
+        let options = library.options(OrganizeMode::Move, CollisionPolicy::Skip);
+
+        // Inside a music folder is not enough when the path check refuses
+        let refuse_all = |_: &Path| -> Result<bool, String> { Ok(false) };
+        let error = plan_with_check(&library.conn, &[id], &options, &refuse_all).unwrap_err();
+        assert!(error.contains("inside a music folder"), "{}", error);
+
+        let inbox = library.root.join("Inbox");
+        let refuse_inbox = |path: &Path| -> Result<bool, String> { Ok(!path.starts_with(&inbox)) };
+        let plan = plan_with_check(&library.conn, &[id], &options, &refuse_inbox).unwrap();
+        assert_eq!(plan.moves[0].status, PlanStatus::Skipped);
+        assert_eq!(plan.moves[0].to, None);
+        assert!(Path::new(&library.path_of(id)).is_file());
+    }
+
+    #[test]
+    fn test_move_and_undo() {
+        let library = Library::new("move");
+        let id = library.track("a.mp3", "Song");
+        let from = library.path_of(id);
+        let options = library.options(OrganizeMode::Move, CollisionPolicy::Skip);
+
+        let result = library.run(&[id], &options);
+        let to = library.root.join("Artist/Album/Song.mp3");
+        assert_eq!(result.moved, vec![id]);
+        assert_eq!(library.path_of(id), path_string(&to));
+        assert!(to.is_file());
+        assert!(!Path::new(&from).exists());
+
+        undo(&library.conn, result.batch_id.unwrap()).unwrap();
+        assert_eq!(library.path_of(id), from);
+        assert_eq!(fs::read_to_string(&from).unwrap(), "Song");
+        assert!(!to.exists());
+        assert!(undo(&library.conn, result.batch_id.unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_copy_keeps_original() {
+        let library = Library::new("copy");
+        let id = library.track("a.mp3", "Song");
+        let from = library.path_of(id);
+        let options = library.options(OrganizeMode::Copy, CollisionPolicy::Skip);
+
+        let result = library.run(&[id], &options);
+        let to = library.root.join("Artist/Album/Song.mp3");
+        assert_eq!(library.path_of(id), path_string(&to));
+        assert!(to.is_file());
+        assert!(Path::new(&from).is_file());
+
+        undo(&library.conn, result.batch_id.unwrap()).unwrap();
+        assert_eq!(library.path_of(id), from);
+        assert!(Path::new(&from).is_file());
+        assert!(!to.exists());
+    }
+
+    #[test]
+    fn test_rename_on_collision() {
+        let library = Library::new("rename");
+        let first = library.track("a.mp3", "Song");
+        let second = library.track("b.mp3", "Song");
+        let options = library.options(OrganizeMode::Move, CollisionPolicy::Rename);
+
+        let plan = plan(&library.conn, &[first, second], &options).unwrap();
+        let statuses: Vec<PlanStatus> = plan.moves.iter().map(|m| m.status).collect();
+        assert_eq!(statuses, vec![PlanStatus::Ready, PlanStatus::Renamed]);
+
+        execute(&library.conn, &plan, &options).unwrap();
+        let album = library.root.join("Artist/Album");
+        assert_eq!(library.path_of(first), path_string(&album.join("Song.mp3")));
+        assert_eq!(
+            library.path_of(second),
+            path_string(&album.join("Song (2).mp3"))
+        );
+
+        // Skip leaves a taken destination alone
+        let third = library.track("c.mp3", "Song (2)");
+        let options = library.options(OrganizeMode::Move, CollisionPolicy::Skip);
+        let result = library.run(&[third], &options);
+        assert_eq!(result.failed.len(), 1);
+        assert!(result.batch_id.is_none());
+    }
+
+    #[test]
+    fn test_overwrite_replaces_only_after_transfer() {
+        let library = Library::new("overwrite");
+        let album = library.root.join("Artist/Album");
+        fs::create_dir_all(&album).unwrap();
+        let existing = album.join("Song.mp3");
+        fs::write(&existing, "old").unwrap();
+
+        // A transfer that fails leaves the existing file in place
+        let missing = library.root.join("Inbox/missing.mp3");
+        assert!(transfer_over(&missing, &existing, OrganizeMode::Move).is_err());
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "old");
+        assert!(!staging_path(&existing, "organizing").exists());
+        assert!(!staging_path(&existing, "replaced").exists());
+
+        let id = library.track("a.mp3", "Song");
+        let options = library.options(OrganizeMode::Move, CollisionPolicy::Overwrite);
+        let plan = plan(&library.conn, &[id], &options).unwrap();
+        assert_eq!(plan.moves[0].status, PlanStatus::Overwrite);
+        execute(&library.conn, &plan, &options).unwrap();
+        assert_eq!(library.path_of(id), path_string(&existing));
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "Song");
+    }
+}
@@ -0,0 +1,359 @@
+// Naming templates: `%albumartist%/[%year% - ]%album%/%track% %title%.%ext%`
+//
+// `%name%` is replaced by a track field and `/` separates folders. A
+// `[...]` section is dropped when any field inside it is empty, so optional
+// parts don't leave stray separators behind. Field values are sanitized
+// before they are substituted, so a "/" in a title never creates a folder.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Characters no common filesystem accepts in a file name.
+const ILLEGAL_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names Windows refuses as file names, with or without extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Placeholder {
+    AlbumArtist,
+    Artist,
+    Album,
+    Title,
+    Year,
+    Track,
+    Disc,
+    Genre,
+    Composer,
+    Label,
+    Ext,
+    Filename,
+}
+
+impl Placeholder {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "albumartist" | "album_artist" => Self::AlbumArtist,
+            "artist" => Self::Artist,
+            "album" => Self::Album,
+            "title" => Self::Title,
+            "year" => Self::Year,
+            "track" | "tracknumber" => Self::Track,
+            "disc" | "discnumber" => Self::Disc,
+            "genre" => Self::Genre,
+            "composer" => Self::Composer,
+            "label" => Self::Label,
+            "ext" => Self::Ext,
+            "filename" => Self::Filename,
+            _ => return None,
+        })
+    }
+}
+
+/// How field values and path components are cleaned up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SanitizeRules {
+    /// Replaces illegal and control characters
+    pub replacement: String,
+    /// Longest folder or file name, in characters; file names keep their
+    /// extension when shortened
+    pub max_component_len: usize,
+}
+
+impl Default for SanitizeRules {
+    fn default() -> Self {
+        Self {
+            replacement: "_".to_string(),
+            max_component_len: 120,
+        }
+    }
+}
+
+impl SanitizeRules {
+    fn clean_value(&self, value: &str) -> String {
+        let replacement: String = self
+            .replacement
+            .chars()
+            .filter(|c| !ILLEGAL_CHARS.contains(c) && !c.is_control())
+            .collect();
+        let mut out = String::with_capacity(value.len());
+        for c in value.chars() {
+            if ILLEGAL_CHARS.contains(&c) || c.is_control() {
+                out.push_str(&replacement);
+            } else {
+                out.push(c);
+            }
+        }
+        out.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Trim a rendered folder or file name; `None` when nothing is left.
+    fn clean_component(&self, component: &str, is_file_name: bool) -> Option<String> {
+        // Literal template text is cleaned like field values. Then drop
+        // separators left dangling by an empty field ("- Album", "Title -"),
+        // and trailing dots and spaces, which Windows strips silently
+        let component = self.clean_value(component);
+        let trimmed = component
+            .trim_matches(|c: char| c.is_whitespace() || c == '-' || c == '_')
+            .trim_end_matches('.')
+            .trim_end();
+        if trimmed.is_empty() || trimmed == "." || trimmed == ".." {
+            return None;
+        }
+
+        let (stem, ext) = match trimmed.rfind('.') {
+            Some(dot) if is_file_name && dot > 0 => (&trimmed[..dot], &trimmed[dot..]),
+            _ => (trimmed, ""),
+        };
+        let max_stem = self
+            .max_component_len
+            .saturating_sub(ext.chars().count())
+            .max(1);
+        let mut stem: String = stem.chars().take(max_stem).collect();
+        stem = stem.trim_end().to_string();
+
+        let base = stem.split('.').next().unwrap_or_default();
+        if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(base)) {
+            stem.push('_');
+        }
+        Some(stem + ext)
+    }
+}
+
+/// Field values for one track. Missing fields render empty.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues {
+    values: HashMap<Placeholder, String>,
+}
+
+impl TemplateValues {
+    pub fn set(&mut self, placeholder: Placeholder, value: Option<&str>) {
+        match value.map(str::trim).filter(|v| !v.is_empty()) {
+            Some(v) => self.values.insert(placeholder, v.to_string()),
+            None => self.values.remove(&placeholder),
+        };
+    }
+
+    fn get(&self, placeholder: Placeholder) -> Option<&str> {
+        self.values.get(&placeholder).map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field(Placeholder),
+    Optional(Vec<Segment>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamingTemplate {
+    segments: Vec<Segment>,
+}
+
+impl NamingTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let template = template.trim();
+        if template.is_empty() {
+            return Err("Template is empty".to_string());
+        }
+        if template.starts_with('/') || template.starts_with('\\') {
+            return Err("Template must be a relative path".to_string());
+        }
+
+        let mut stack: Vec<Vec<Segment>> = vec![Vec::new()];
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '%' => {
+                    let name: String = chars.by_ref().take_while(|&c| c != '%').collect();
+                    let placeholder = Placeholder::parse(&name)
+                        .ok_or_else(|| format!("Unknown template field: %{}%", name))?;
+                    flush(&mut literal, &mut stack);
+                    stack.last_mut().unwrap().push(Segment::Field(placeholder));
+                }
+                '[' => {
+                    flush(&mut literal, &mut stack);
+                    stack.push(Vec::new());
+                }
+                ']' => {
+                    flush(&mut literal, &mut stack);
+                    if stack.len() < 2 {
+                        return Err("Unmatched ']' in template".to_string());
+                    }
+                    let section = stack.pop().unwrap();
+                    stack.last_mut().unwrap().push(Segment::Optional(section));
+                }
+                '\\' => literal.push('/'),
+                c => literal.push(c),
+            }
+        }
+        flush(&mut literal, &mut stack);
+        if stack.len() != 1 {
+            return Err("Unclosed '[' in template".to_string());
+        }
+
+        let segments = stack.pop().unwrap();
+        let has_parent_ref = segments.iter().any(
+            |s| matches!(s, Segment::Literal(l) if l.split('/').any(|part| part.trim() == "..")),
+        );
+        if has_parent_ref {
+            return Err("Template must not contain '..'".to_string());
+        }
+        Ok(Self { segments })
+    }
+
+    /// Relative path for one track.
+    pub fn render(
+        &self,
+        values: &TemplateValues,
+        rules: &SanitizeRules,
+    ) -> Result<PathBuf, String> {
+        let rendered = render_segments(&self.segments, values, rules, false).unwrap_or_default();
+        let parts: Vec<&str> = rendered.split('/').collect();
+        let last = parts.len() - 1;
+        let components: Vec<String> = parts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, part)| rules.clean_component(part, i == last))
+            .collect();
+        if components.is_empty() || rendered.ends_with('/') {
+            return Err(format!(
+                "Template renders an empty file name: {:?}",
+                rendered
+            ));
+        }
+        Ok(components.iter().collect())
+    }
+}
+
+fn flush(literal: &mut String, stack: &mut [Vec<Segment>]) {
+    if !literal.is_empty() {
+        let segment = Segment::Literal(std::mem::take(literal));
+        stack.last_mut().unwrap().push(segment);
+    }
+}
+
+/// Empty fields render as nothing, except inside an `optional` section,
+/// where they make the whole section (`None`) disappear.
+fn render_segments(
+    segments: &[Segment],
+    values: &TemplateValues,
+    rules: &SanitizeRules,
+    optional: bool,
+) -> Option<String> {
+    let mut out = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Literal(text) => out.push_str(text),
+            Segment::Field(placeholder) => match values.get(*placeholder) {
+                Some(value) => out.push_str(&rules.clean_value(value)),
+                None if optional => return None,
+                None => {}
+            },
+            Segment::Optional(inner) => {
+                if let Some(text) = render_segments(inner, values, rules, true) {
+                    out.push_str(&text);
+                }
+            }
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(Placeholder, &str)]) -> TemplateValues {
+        let mut values = TemplateValues::default();
+        for (placeholder, value) in pairs {
+            values.set(*placeholder, Some(value));
+        }
+        values
+    }
+
+    #[test]
+    fn test_render() {
+        let template = NamingTemplate::parse(
+            "%albumartist%/[%year% - ]%album%/[%disc%-]%track% %title%.%ext%",
+        )
+        .unwrap();
+        let rules = SanitizeRules::default();
+        let full = values(&[
+            (Placeholder::AlbumArtist, "AC/DC"),
+            (Placeholder::Year, "1980"),
+            (Placeholder::Album, "Back in Black"),
+            (Placeholder::Disc, "1"),
+            (Placeholder::Track, "06"),
+            (Placeholder::Title, "What? Me: Worry*"),
+            (Placeholder::Ext, "flac"),
+        ]);
+        assert_eq!(
+            template.render(&full, &rules).unwrap(),
+            PathBuf::from("AC_DC/1980 - Back in Black/1-06 What_ Me_ Worry_.flac")
+        );
+
+        let sparse = values(&[
+            (Placeholder::AlbumArtist, "Nas"),
+            (Placeholder::Album, "Illmatic"),
+            (Placeholder::Track, "01"),
+            (Placeholder::Title, "The Genesis"),
+            (Placeholder::Ext, "mp3"),
+        ]);
+        assert_eq!(
+            template.render(&sparse, &rules).unwrap(),
+            PathBuf::from("Nas/Illmatic/01 The Genesis.mp3")
+        );
+
+        // Without brackets, dangling separators are trimmed
+        let plain = NamingTemplate::parse("%artist%/%year% - %album%/%title%.%ext%").unwrap();
+        let partial = values(&[
+            (Placeholder::Artist, "CON"),
+            (Placeholder::Album, "Record."),
+            (Placeholder::Title, "Song"),
+            (Placeholder::Ext, "ogg"),
+        ]);
+        assert_eq!(
+            plain.render(&partial, &rules).unwrap(),
+            PathBuf::from("CON_/Record/Song.ogg")
+        );
+    }
+
+    #[test]
+    fn test_long_names_keep_extension() {
+        let template = NamingTemplate::parse("%title%.%ext%").unwrap();
+        let rules = SanitizeRules {
+            max_component_len: 10,
+            ..SanitizeRules::default()
+        };
+        let long = values(&[
+            (Placeholder::Title, "A very long title"),
+            (Placeholder::Ext, "flac"),
+        ]);
+        assert_eq!(
+            template.render(&long, &rules).unwrap(),
+            PathBuf::from("A ver.flac")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(NamingTemplate::parse("%nope%.%ext%").is_err());
+        assert!(NamingTemplate::parse("[%year%.%ext%").is_err());
+        assert!(NamingTemplate::parse("%year%].%ext%").is_err());
+        assert!(NamingTemplate::parse("../%title%.%ext%").is_err());
+        assert!(NamingTemplate::parse("/abs/%title%").is_err());
+        assert!(NamingTemplate::parse("").is_err());
+
+        let template = NamingTemplate::parse("[%title%]").unwrap();
+        assert!(template
+            .render(&TemplateValues::default(), &SanitizeRules::default())
+            .is_err());
+    }
+}
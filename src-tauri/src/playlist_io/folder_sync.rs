@@ -0,0 +1,275 @@
+// Two-way sync between a folder playlist and an .m3u8 in its directory
+//
+// A folder playlist holds whatever audio files are in its directory. An
+// .m3u8 next to them adds what a directory can't: an order, and entries
+// from elsewhere in the library. The file is read when it changes on disk
+// and rewritten when the playlist is edited; if both happened since they
+// last agreed, neither side is overwritten until the user picks one.
+use super::PlaylistEntry;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// SHA-256 of a playlist file's contents, to tell whether it changed.
+pub fn file_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// The playlist file for `dir`: the first .m3u8 directly in it, by name.
+pub fn find_playlist_file(dir: &Path) -> Option<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("m3u8"))
+        })
+        .collect();
+    files.sort();
+    files.into_iter().next()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncAction {
+    /// Only the file changed: the playlist follows it
+    ReadFile,
+    /// The file is as last synced: it follows the playlist
+    WriteFile,
+    /// Both changed, or an earlier conflict is still unresolved
+    Conflict,
+}
+
+/// What to do with a playlist file whose contents now hash to `current`,
+/// given the hash at the last sync and whether the playlist was edited
+/// since. A file never synced before counts as changed.
+pub fn plan(
+    current: &str,
+    synced: Option<&str>,
+    playlist_edited: bool,
+    in_conflict: bool,
+) -> SyncAction {
+    if in_conflict {
+        return SyncAction::Conflict;
+    }
+    let file_changed = synced != Some(current);
+    match (file_changed, playlist_edited) {
+        (false, _) => SyncAction::WriteFile,
+        (true, false) => SyncAction::ReadFile,
+        (true, true) => SyncAction::Conflict,
+    }
+}
+
+/// A playlist entry as the file sync sees it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Member {
+    pub entry_id: i64,
+    pub track_id: i64,
+    /// Stays even when the file leaves it out: the track is in the
+    /// playlist's directory, or is a stream a file can't hold
+    pub keep: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    /// An existing entry
+    Entry(i64),
+    /// A new entry for this track
+    Add(i64),
+}
+
+/// The playlist after reading its file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileOrder {
+    pub order: Vec<Slot>,
+    /// Entries to drop
+    pub remove: Vec<i64>,
+}
+
+/// Follow the file's track order (`listed`), reusing existing entries
+/// where the track is already in the playlist. Kept entries the file
+/// leaves out go after the listed ones: the directory decides what is in
+/// the playlist, the file only orders it and adds to it. Other entries the
+/// file no longer lists are dropped.
+pub fn apply_file_order(members: &[Member], listed: &[i64]) -> FileOrder {
+    let mut used = vec![false; members.len()];
+    let mut order = Vec::with_capacity(listed.len());
+    for &track_id in listed {
+        let existing = members
+            .iter()
+            .enumerate()
+            .position(|(i, m)| !used[i] && m.track_id == track_id);
+        match existing {
+            Some(i) => {
+                used[i] = true;
+                order.push(Slot::Entry(members[i].entry_id));
+            }
+            None => order.push(Slot::Add(track_id)),
+        }
+    }
+
+    let mut remove = Vec::new();
+    for (member, used) in members.iter().zip(used) {
+        if used {
+            continue;
+        }
+        if member.keep {
+            order.push(Slot::Entry(member.entry_id));
+        } else {
+            remove.push(member.entry_id);
+        }
+    }
+    FileOrder { order, remove }
+}
+
+/// An entry of the playlist file as read: the library track it resolves
+/// to, if any. Unresolved ones (files not scanned yet, drives not mounted)
+/// are kept when the file is rewritten.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileLine {
+    pub track_id: Option<i64>,
+    pub entry: PlaylistEntry,
+}
+
+/// The entries to write: the playlist's `tracks` in order, with the file's
+/// unresolved entries put back after the track they followed in the file.
+/// When that track is gone they follow the one before it, and so on; with
+/// none left they go first.
+pub fn keep_unresolved(file: &[FileLine], tracks: Vec<(i64, PlaylistEntry)>) -> Vec<PlaylistEntry> {
+    let mut in_playlist: HashMap<i64, usize> = HashMap::new();
+    for (track_id, _) in &tracks {
+        *in_playlist.entry(*track_id).or_default() += 1;
+    }
+
+    // Anchor: the nth occurrence of a track that is still in the playlist
+    let mut seen: HashMap<i64, usize> = HashMap::new();
+    let mut anchor: Option<(i64, usize)> = None;
+    let mut kept: HashMap<Option<(i64, usize)>, Vec<PlaylistEntry>> = HashMap::new();
+    for line in file {
+        match line.track_id {
+            Some(track_id) => {
+                let n = seen.entry(track_id).or_default();
+                if *n < in_playlist.get(&track_id).copied().unwrap_or(0) {
+                    anchor = Some((track_id, *n));
+                }
+                *n += 1;
+            }
+            None => kept.entry(anchor).or_default().push(line.entry.clone()),
+        }
+    }
+
+    let mut entries = kept.remove(&None).unwrap_or_default();
+    let mut written: HashMap<i64, usize> = HashMap::new();
+    for (track_id, entry) in tracks {
+        entries.push(entry);
+        let n = written.entry(track_id).or_default();
+        entries.extend(kept.remove(&Some((track_id, *n))).unwrap_or_default());
+        *n += 1;
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(entry_id: i64, track_id: i64, keep: bool) -> Member {
+        Member {
+            entry_id,
+            track_id,
+            keep,
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        use SyncAction::*;
+        assert_eq!(plan("a", Some("a"), false, false), WriteFile);
+        assert_eq!(plan("a", Some("a"), true, false), WriteFile);
+        assert_eq!(plan("b", Some("a"), false, false), ReadFile);
+        assert_eq!(plan("b", Some("a"), true, false), Conflict);
+        assert_eq!(plan("a", None, false, false), ReadFile);
+        assert_eq!(plan("a", Some("a"), false, true), Conflict);
+    }
+
+    #[test]
+    fn test_apply_file_order() {
+        // Entries: folder tracks 10, 11, 12 (12 twice), outside track 20
+        let members = [
+            member(1, 10, true),
+            member(2, 11, true),
+            member(3, 12, true),
+            member(4, 20, false),
+            member(5, 12, true),
+        ];
+        // The file reorders, lists 12 three times, adds 30, leaves out 11 and 20
+        let result = apply_file_order(&members, &[12, 10, 12, 30, 12]);
+        assert_eq!(
+            result.order,
+            vec![
+                Slot::Entry(3),
+                Slot::Entry(1),
+                Slot::Entry(5),
+                Slot::Add(30),
+                Slot::Add(12),
+                Slot::Entry(2),
+            ]
+        );
+        assert_eq!(result.remove, vec![4]);
+
+        let unchanged = apply_file_order(&members, &[10, 11, 12, 20, 12]);
+        assert_eq!(
+            unchanged.order,
+            (1..=5).map(Slot::Entry).collect::<Vec<_>>()
+        );
+        assert!(unchanged.remove.is_empty());
+    }
+
+    #[test]
+    fn test_keep_unresolved() {
+        let entry = |location: &str| PlaylistEntry {
+            location: location.to_string(),
+            ..Default::default()
+        };
+        let line = |track_id: Option<i64>, location: &str| FileLine {
+            track_id,
+            entry: entry(location),
+        };
+        let file = [
+            line(None, "first.mp3"),
+            line(Some(1), "a.mp3"),
+            line(None, "after-a.mp3"),
+            line(Some(2), "b.mp3"),
+            line(None, "after-b.mp3"),
+            line(Some(3), "c.mp3"),
+            line(None, "after-c.mp3"),
+        ];
+        // Reordered, 2 removed, 4 added
+        let tracks = vec![
+            (3, entry("c.mp3")),
+            (1, entry("a.mp3")),
+            (4, entry("d.mp3")),
+        ];
+        let locations: Vec<String> = keep_unresolved(&file, tracks)
+            .into_iter()
+            .map(|e| e.location)
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                "first.mp3",
+                "c.mp3",
+                "after-c.mp3",
+                "a.mp3",
+                "after-a.mp3",
+                "after-b.mp3",
+                "d.mp3",
+            ]
+        );
+
+        // Nothing to keep: just the playlist
+        let plain = keep_unresolved(&[line(Some(1), "a.mp3")], vec![(1, entry("a.mp3"))]);
+        assert_eq!(plain, vec![entry("a.mp3")]);
+    }
+}
@@ -0,0 +1,112 @@
+// M3U / M3U8 playlists, with extended #EXTINF lines
+//
+//   #EXTM3U
+//   #PLAYLIST:Road Trip
+//   #EXTINF:354,Queen - Bohemian Rhapsody
+//   ../Queen/A Night at the Opera/11 Bohemian Rhapsody.flac
+use super::{split_display_title, PlaylistEntry, PlaylistFile};
+
+pub fn parse(text: &str) -> PlaylistFile {
+    let mut playlist = PlaylistFile::default();
+    let mut pending: Option<PlaylistEntry> = None;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            // "#EXTINF:<seconds>[ key="value" ...],<display title>"
+            let (head, display) = info.split_once(',').unwrap_or((info, ""));
+            let seconds = head
+                .split_whitespace()
+                .next()
+                .and_then(|s| s.parse::<f64>().ok())
+                .filter(|s| *s > 0.0)
+                .map(|s| s.round() as i32);
+            let (artist, title) = split_display_title(display);
+            pending = Some(PlaylistEntry {
+                location: String::new(),
+                artist,
+                title,
+                duration: seconds,
+            });
+        } else if let Some(name) = line.strip_prefix("#PLAYLIST:") {
+            playlist.name = Some(name.trim().to_string()).filter(|n| !n.is_empty());
+        } else if line.starts_with('#') {
+            continue;
+        } else {
+            let mut entry = pending.take().unwrap_or_default();
+            entry.location = line.to_string();
+            playlist.entries.push(entry);
+        }
+    }
+    playlist
+}
+
+pub fn write(playlist: &PlaylistFile) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    if let Some(name) = &playlist.name {
+        out.push_str(&format!("#PLAYLIST:{}\n", name));
+    }
+    for entry in &playlist.entries {
+        let display = match (&entry.artist, &entry.title) {
+            (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+            (None, Some(title)) => title.clone(),
+            (Some(artist), None) => artist.clone(),
+            (None, None) => String::new(),
+        };
+        out.push_str(&format!(
+            "#EXTINF:{},{}\n{}\n",
+            entry.duration.unwrap_or(-1),
+            display,
+            entry.location
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = "\u{feff}#EXTM3U\r\n#PLAYLIST:Mix\r\n\
+            #EXTINF:354 tvg-id=\"x\",Queen - Bohemian Rhapsody\r\n\
+            Queen/11 Bohemian Rhapsody.flac\r\n\
+            \r\n\
+            # a comment\r\n\
+            /music/plain.mp3\r\n\
+            #EXTINF:-1,Radio\r\n\
+            http://example.com/stream\r\n";
+        let playlist = parse(text.trim_start_matches('\u{feff}'));
+        assert_eq!(playlist.name.as_deref(), Some("Mix"));
+        assert_eq!(playlist.entries.len(), 3);
+        assert_eq!(
+            playlist.entries[0],
+            PlaylistEntry {
+                location: "Queen/11 Bohemian Rhapsody.flac".to_string(),
+                artist: Some("Queen".to_string()),
+                title: Some("Bohemian Rhapsody".to_string()),
+                duration: Some(354),
+            }
+        );
+        assert_eq!(playlist.entries[1].location, "/music/plain.mp3");
+        assert_eq!(playlist.entries[1].title, None);
+        assert_eq!(playlist.entries[2].title.as_deref(), Some("Radio"));
+        assert_eq!(playlist.entries[2].duration, None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let playlist = PlaylistFile {
+            name: Some("Mix".to_string()),
+            entries: vec![PlaylistEntry {
+                location: "a b/c.mp3".to_string(),
+                artist: Some("AC/DC".to_string()),
+                title: Some("T.N.T.".to_string()),
+                duration: Some(214),
+            }],
+        };
+        assert_eq!(parse(&write(&playlist)), playlist);
+    }
+}
@@ -0,0 +1,297 @@
+// Playlist files: reading and writing M3U/M3U8, PLS and XSPF
+//
+// Each format parses into the same `PlaylistFile`: an optional name and a
+// list of entries, each a location (path or URI, as written in the file)
+// with whatever artist, title and duration the file carried. Matching the
+// entries to library tracks is left to the caller.
+pub mod folder_sync;
+pub mod m3u;
+pub mod pls;
+pub mod xspf;
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistFormat {
+    M3u,
+    M3u8,
+    Pls,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "m3u" => Some(Self::M3u),
+            "m3u8" => Some(Self::M3u8),
+            "pls" => Some(Self::Pls),
+            "xspf" => Some(Self::Xspf),
+            _ => None,
+        }
+    }
+
+    /// Whether locations are URIs rather than plain paths.
+    pub fn uses_uris(&self) -> bool {
+        *self == Self::Xspf
+    }
+}
+
+/// How track locations are written on export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathMode {
+    /// Relative to the playlist file, so a music folder can be moved along
+    /// with its playlists. Falls back to absolute across drives.
+    #[default]
+    Relative,
+    Absolute,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistEntry {
+    pub location: String,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    /// Seconds
+    pub duration: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistFile {
+    pub name: Option<String>,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// Split an "Artist - Title" display string as written by most players.
+/// Without a separator the whole string is taken as the title.
+pub(crate) fn split_display_title(display: &str) -> (Option<String>, Option<String>) {
+    let non_empty = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+    match display.split_once(" - ") {
+        Some((artist, title)) if non_empty(artist).is_some() && non_empty(title).is_some() => {
+            (non_empty(artist), non_empty(title))
+        }
+        _ => (None, non_empty(display)),
+    }
+}
+
+/// Playlist text as UTF-8, without BOM. Files that aren't valid UTF-8
+/// (older .m3u and .pls files) are read as Latin-1.
+pub fn decode_text(bytes: &[u8]) -> String {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    };
+    text.trim_start_matches('\u{feff}').to_string()
+}
+
+pub fn parse(format: PlaylistFormat, bytes: &[u8]) -> Result<PlaylistFile, String> {
+    let text = decode_text(bytes);
+    match format {
+        PlaylistFormat::M3u | PlaylistFormat::M3u8 => Ok(m3u::parse(&text)),
+        PlaylistFormat::Pls => Ok(pls::parse(&text)),
+        PlaylistFormat::Xspf => xspf::parse(&text),
+    }
+}
+
+/// Playlist file contents; entry locations must already be in the form
+/// `export_location` gives for this format.
+pub fn write(format: PlaylistFormat, playlist: &PlaylistFile) -> String {
+    match format {
+        PlaylistFormat::M3u | PlaylistFormat::M3u8 => m3u::write(playlist),
+        PlaylistFormat::Pls => pls::write(playlist),
+        PlaylistFormat::Xspf => xspf::write(playlist),
+    }
+}
+
+/// Resolve `.` and `..` without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push("..");
+                }
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+/// The file an entry location points to, for a playlist in `base_dir`.
+/// `None` for URLs other than `file://` (streams).
+pub fn resolve_location(location: &str, base_dir: &Path, is_uri: bool) -> Option<PathBuf> {
+    let location = location.trim();
+    if location.starts_with("file:") || location.contains("://") {
+        let url = Url::parse(location).ok()?;
+        return match url.scheme() {
+            "file" => url.to_file_path().ok().map(|p| normalize(&p)),
+            _ => None,
+        };
+    }
+    if is_uri {
+        let base = Url::from_directory_path(base_dir).ok()?;
+        return base
+            .join(location)
+            .ok()?
+            .to_file_path()
+            .ok()
+            .map(|p| normalize(&p));
+    }
+
+    // Playlists written on Windows use backslashes
+    let location = if cfg!(windows) {
+        location.to_string()
+    } else {
+        location.replace('\\', "/")
+    };
+    let path = Path::new(&location);
+    Some(normalize(&if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir.join(path)
+    }))
+}
+
+/// `target` relative to `base_dir`; `None` when they share no root (a
+/// different drive on Windows). Both must be absolute.
+pub fn relative_path(base_dir: &Path, target: &Path) -> Option<PathBuf> {
+    let (base_dir, target) = (normalize(base_dir), normalize(target));
+    let base: Vec<Component> = base_dir.components().collect();
+    let target: Vec<Component> = target.components().collect();
+    if base.first() != target.first() {
+        return None;
+    }
+    let common = base
+        .iter()
+        .zip(target.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut out = PathBuf::new();
+    for _ in common..base.len() {
+        out.push("..");
+    }
+    for component in &target[common..] {
+        out.push(component.as_os_str());
+    }
+    Some(out)
+}
+
+/// How `track_path` is written in a playlist saved in `base_dir`.
+pub fn export_location(
+    track_path: &Path,
+    base_dir: &Path,
+    mode: PathMode,
+    format: PlaylistFormat,
+) -> String {
+    if format.uses_uris() {
+        let Ok(target) = Url::from_file_path(track_path) else {
+            return track_path.to_string_lossy().to_string();
+        };
+        let relative = match mode {
+            PathMode::Relative => Url::from_directory_path(base_dir)
+                .ok()
+                .and_then(|base| base.make_relative(&target))
+                .filter(|rel| !rel.starts_with("file:")),
+            PathMode::Absolute => None,
+        };
+        return relative.unwrap_or_else(|| target.to_string());
+    }
+
+    let path = match mode {
+        PathMode::Relative => {
+            relative_path(base_dir, track_path).unwrap_or_else(|| track_path.to_path_buf())
+        }
+        PathMode::Absolute => track_path.to_path_buf(),
+    };
+    path.to_string_lossy().to_string()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_location() {
+        let base = Path::new("/music/Playlists");
+        assert_eq!(
+            resolve_location("../Queen/a.flac", base, false),
+            Some(PathBuf::from("/music/Queen/a.flac"))
+        );
+        assert_eq!(
+            resolve_location("..\\Queen\\a.flac", base, false),
+            Some(PathBuf::from("/music/Queen/a.flac"))
+        );
+        assert_eq!(
+            resolve_location("/other/b.mp3", base, false),
+            Some(PathBuf::from("/other/b.mp3"))
+        );
+        assert_eq!(
+            resolve_location("file:///music/A%20B/c.mp3", base, false),
+            Some(PathBuf::from("/music/A B/c.mp3"))
+        );
+        assert_eq!(
+            resolve_location("../A%20B/c.mp3", base, true),
+            Some(PathBuf::from("/music/A B/c.mp3"))
+        );
+        assert_eq!(
+            resolve_location("http://example.com/a.mp3", base, false),
+            None
+        );
+    }
+
+    #[test]
+    fn test_export_location() {
+        let base = Path::new("/music/Playlists");
+        let track = Path::new("/music/A B/c.mp3");
+        assert_eq!(
+            export_location(track, base, PathMode::Relative, PlaylistFormat::M3u8),
+            "../A B/c.mp3"
+        );
+        assert_eq!(
+            export_location(track, base, PathMode::Absolute, PlaylistFormat::Pls),
+            "/music/A B/c.mp3"
+        );
+        assert_eq!(
+            export_location(track, base, PathMode::Relative, PlaylistFormat::Xspf),
+            "../A%20B/c.mp3"
+        );
+        assert_eq!(
+            export_location(track, base, PathMode::Absolute, PlaylistFormat::Xspf),
+            "file:///music/A%20B/c.mp3"
+        );
+        // Every exported location resolves back to the track
+        for format in [PlaylistFormat::M3u, PlaylistFormat::Xspf] {
+            for mode in [PathMode::Relative, PathMode::Absolute] {
+                let location = export_location(track, base, mode, format);
+                assert_eq!(
+                    resolve_location(&location, base, format.uses_uris()).as_deref(),
+                    Some(track)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(
+            split_display_title("Queen - Bohemian Rhapsody"),
+            (
+                Some("Queen".to_string()),
+                Some("Bohemian Rhapsody".to_string())
+            )
+        );
+        assert_eq!(
+            split_display_title(" - Untitled"),
+            (None, Some("- Untitled".to_string()))
+        );
+        assert_eq!(decode_text(b"\xef\xbb\xbfcaf\xc3\xa9"), "café");
+        assert_eq!(decode_text(b"caf\xe9"), "café");
+    }
+}
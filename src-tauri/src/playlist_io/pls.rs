@@ -0,0 +1,97 @@
+// PLS playlists: an INI file with numbered File/Title/Length keys
+//
+//   [playlist]
+//   File1=Queen/11 Bohemian Rhapsody.flac
+//   Title1=Queen - Bohemian Rhapsody
+//   Length1=354
+//   NumberOfEntries=1
+//   Version=2
+use super::{split_display_title, PlaylistEntry, PlaylistFile};
+use std::collections::BTreeMap;
+
+pub fn parse(text: &str) -> PlaylistFile {
+    let mut entries: BTreeMap<u32, PlaylistEntry> = BTreeMap::new();
+    for line in text.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let (field, index) =
+            key.split_at(key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len()));
+        let Ok(index) = index.parse::<u32>() else {
+            continue;
+        };
+        let entry = entries.entry(index).or_default();
+        match field {
+            "file" => entry.location = value.to_string(),
+            "title" => {
+                let (artist, title) = split_display_title(value);
+                entry.artist = artist;
+                entry.title = title;
+            }
+            "length" => {
+                entry.duration = value.parse::<i32>().ok().filter(|secs| *secs > 0);
+            }
+            _ => {}
+        }
+    }
+    PlaylistFile {
+        name: None,
+        entries: entries
+            .into_values()
+            .filter(|entry| !entry.location.is_empty())
+            .collect(),
+    }
+}
+
+pub fn write(playlist: &PlaylistFile) -> String {
+    let mut out = String::from("[playlist]\n");
+    for (i, entry) in playlist.entries.iter().enumerate() {
+        let n = i + 1;
+        out.push_str(&format!("File{}={}\n", n, entry.location));
+        let display = match (&entry.artist, &entry.title) {
+            (Some(artist), Some(title)) => Some(format!("{} - {}", artist, title)),
+            (None, Some(title)) => Some(title.clone()),
+            _ => None,
+        };
+        if let Some(display) = display {
+            out.push_str(&format!("Title{}={}\n", n, display));
+        }
+        out.push_str(&format!("Length{}={}\n", n, entry.duration.unwrap_or(-1)));
+    }
+    out.push_str(&format!(
+        "NumberOfEntries={}\nVersion=2\n",
+        playlist.entries.len()
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = "[playlist]\n\
+            File2=/music/b.mp3\n\
+            Title2=Just A Title\n\
+            File1=a.flac\n\
+            Title1=Artist - Song\n\
+            Length1=200\n\
+            Length2=-1\n\
+            NumberOfEntries=2\n\
+            Version=2\n";
+        let playlist = parse(text);
+        assert_eq!(playlist.entries.len(), 2);
+        assert_eq!(playlist.entries[0].location, "a.flac");
+        assert_eq!(playlist.entries[0].artist.as_deref(), Some("Artist"));
+        assert_eq!(playlist.entries[0].duration, Some(200));
+        assert_eq!(playlist.entries[1].location, "/music/b.mp3");
+        assert_eq!(playlist.entries[1].artist, None);
+        assert_eq!(playlist.entries[1].title.as_deref(), Some("Just A Title"));
+        assert_eq!(playlist.entries[1].duration, None);
+
+        assert_eq!(parse(&write(&playlist)).entries, playlist.entries);
+    }
+}
@@ -0,0 +1,160 @@
+// XSPF playlists (https://xspf.org), the XML format
+//
+// Track locations are URIs: `file:///...` for absolute paths, or a
+// relative URI resolved against the playlist file. Durations are in
+// milliseconds.
+use super::{PlaylistEntry, PlaylistFile};
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+pub fn parse(text: &str) -> Result<PlaylistFile, String> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+
+    let mut playlist = PlaylistFile::default();
+    let mut path: Vec<String> = Vec::new();
+    let mut track: Option<PlaylistEntry> = None;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_lowercase();
+                if name == "track" {
+                    track = Some(PlaylistEntry::default());
+                }
+                path.push(name);
+            }
+            Ok(Event::End(e)) => {
+                if e.local_name().as_ref().eq_ignore_ascii_case(b"track") {
+                    if let Some(entry) = track.take().filter(|t| !t.location.is_empty()) {
+                        playlist.entries.push(entry);
+                    }
+                }
+                path.pop();
+            }
+            Ok(Event::Text(t)) => {
+                let value = t.unescape().map_err(|e| e.to_string())?.trim().to_string();
+                let element = path.last().map(String::as_str).unwrap_or_default();
+                match track.as_mut() {
+                    Some(entry) => match element {
+                        // Only the first location is used; the rest are alternatives
+                        "location" if entry.location.is_empty() => entry.location = value,
+                        "title" => entry.title = Some(value),
+                        "creator" => entry.artist = Some(value),
+                        "duration" => {
+                            entry.duration = value
+                                .parse::<i64>()
+                                .ok()
+                                .filter(|ms| *ms > 0)
+                                .map(|ms| ((ms + 500) / 1000) as i32)
+                        }
+                        _ => {}
+                    },
+                    None if element == "title" && path.len() == 2 => {
+                        playlist.name = Some(value);
+                    }
+                    None => {}
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => {
+                return Err(format!(
+                    "Invalid XSPF at position {}: {}",
+                    reader.error_position(),
+                    e
+                ))
+            }
+        }
+    }
+    Ok(playlist)
+}
+
+/// Write a playlist whose entry locations are already URIs.
+pub fn write(playlist: &PlaylistFile) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+    );
+    if let Some(name) = &playlist.name {
+        out.push_str(&format!("  <title>{}</title>\n", escape(name.as_str())));
+    }
+    out.push_str("  <trackList>\n");
+    for entry in &playlist.entries {
+        out.push_str("    <track>\n");
+        out.push_str(&format!(
+            "      <location>{}</location>\n",
+            escape(entry.location.as_str())
+        ));
+        if let Some(title) = &entry.title {
+            out.push_str(&format!(
+                "      <title>{}</title>\n",
+                escape(title.as_str())
+            ));
+        }
+        if let Some(artist) = &entry.artist {
+            out.push_str(&format!(
+                "      <creator>{}</creator>\n",
+                escape(artist.as_str())
+            ));
+        }
+        if let Some(duration) = entry.duration {
+            out.push_str(&format!(
+                "      <duration>{}</duration>\n",
+                duration as i64 * 1000
+            ));
+        }
+        out.push_str("    </track>\n");
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <playlist version="1" xmlns="http://xspf.org/ns/0/">
+              <title>Late &amp; Night</title>
+              <trackList>
+                <track>
+                  <location>file:///music/Queen/11%20Bohemian%20Rhapsody.flac</location>
+                  <location>http://example.com/alt.mp3</location>
+                  <title>Bohemian Rhapsody</title>
+                  <creator>Queen</creator>
+                  <duration>354321</duration>
+                </track>
+                <track><title>No location</title></track>
+              </trackList>
+            </playlist>"#;
+        let playlist = parse(text).unwrap();
+        assert_eq!(playlist.name.as_deref(), Some("Late & Night"));
+        assert_eq!(
+            playlist.entries,
+            vec![PlaylistEntry {
+                location: "file:///music/Queen/11%20Bohemian%20Rhapsody.flac".to_string(),
+                artist: Some("Queen".to_string()),
+                title: Some("Bohemian Rhapsody".to_string()),
+                duration: Some(354),
+            }]
+        );
+        assert!(parse("<playlist><trackList></playlist>").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let playlist = PlaylistFile {
+            name: Some("<Mix>".to_string()),
+            entries: vec![PlaylistEntry {
+                location: "Guns%20N'%20Roses/a.mp3".to_string(),
+                artist: Some("Guns N' Roses".to_string()),
+                title: Some("Rock & Roll".to_string()),
+                duration: Some(61),
+            }],
+        };
+        assert_eq!(parse(&write(&playlist)).unwrap(), playlist);
+    }
+}
@@ -0,0 +1,356 @@
+// Bulk playlist operations: sort, dedupe, split and shuffle
+//
+// These work on a playlist's entries in memory and return the new order (or
+// the entries to drop); writing the result back is left to the caller, so
+// one operation is one transaction however many entries it touches.
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// One playlist entry with the track fields the operations look at.
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistItem {
+    pub entry_id: i64,
+    pub track_id: i64,
+    pub path: String,
+    pub source_type: Option<String>,
+    pub content_hash: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<i64>,
+    pub disc_number: Option<i64>,
+    pub track_number: Option<i64>,
+    pub duration: Option<i64>,
+    pub bitrate: Option<i64>,
+    pub bpm: Option<f64>,
+    pub date_added: Option<String>,
+    pub last_played: Option<String>,
+    pub play_count: i64,
+}
+
+impl PlaylistItem {
+    /// Whether this is a library file (not a stream), so it can be missing.
+    pub fn is_local(&self) -> bool {
+        matches!(self.source_type.as_deref(), None | Some("local"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistSortField {
+    Title,
+    Artist,
+    AlbumArtist,
+    Album,
+    Genre,
+    Year,
+    DiscNumber,
+    TrackNumber,
+    Duration,
+    Bitrate,
+    Bpm,
+    DateAdded,
+    LastPlayed,
+    PlayCount,
+    Path,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistSortKey {
+    pub field: PlaylistSortField,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+enum SortValue {
+    Text(String),
+    Number(f64),
+}
+
+fn sort_value(item: &PlaylistItem, field: PlaylistSortField) -> Option<SortValue> {
+    use PlaylistSortField::*;
+    let text = |s: &Option<String>| {
+        s.as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| SortValue::Text(s.to_lowercase()))
+    };
+    let number = |n: Option<i64>| n.map(|n| SortValue::Number(n as f64));
+    match field {
+        Title => text(&item.title),
+        Artist => text(&item.artist),
+        AlbumArtist => text(&item.album_artist).or_else(|| text(&item.artist)),
+        Album => text(&item.album),
+        Genre => text(&item.genre),
+        Year => number(item.year),
+        DiscNumber => number(item.disc_number),
+        TrackNumber => number(item.track_number),
+        Duration => number(item.duration),
+        Bitrate => number(item.bitrate),
+        Bpm => item.bpm.map(SortValue::Number),
+        DateAdded => text(&item.date_added),
+        LastPlayed => text(&item.last_played),
+        PlayCount => Some(SortValue::Number(item.play_count as f64)),
+        Path => Some(SortValue::Text(item.path.to_lowercase())),
+    }
+}
+
+/// Stable sort by each key in turn. Missing values sort last in either
+/// direction, so descending by year doesn't put undated tracks first.
+pub fn sort(items: &mut [PlaylistItem], keys: &[PlaylistSortKey]) {
+    items.sort_by(|a, b| {
+        for key in keys {
+            let ordering = match (sort_value(a, key.field), sort_value(b, key.field)) {
+                (Some(x), Some(y)) => {
+                    let ordering = x.partial_cmp(&y).unwrap_or(Ordering::Equal);
+                    if key.descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                }
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    });
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupeBy {
+    /// The same library track
+    #[default]
+    TrackId,
+    /// The same audio, even if it is in the library twice; tracks without a
+    /// hash fall back to their id
+    ContentHash,
+}
+
+/// Entries repeating an earlier one; the first occurrence is kept.
+pub fn duplicates(items: &[PlaylistItem], by: DedupeBy) -> Vec<i64> {
+    let mut seen = HashSet::new();
+    items
+        .iter()
+        .filter(|item| {
+            let key = match (by, &item.content_hash) {
+                (DedupeBy::ContentHash, Some(hash)) => format!("h:{}", hash),
+                _ => format!("t:{}", item.track_id),
+            };
+            !seen.insert(key)
+        })
+        .map(|item| item.entry_id)
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "by", content = "value", rename_all = "snake_case")]
+pub enum SplitBy {
+    Artist,
+    Album,
+    /// Consecutive chunks of this many entries
+    Size(usize),
+}
+
+/// A group of entries for a new playlist, named after what they share.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitGroup {
+    pub label: String,
+    pub entry_ids: Vec<i64>,
+}
+
+/// Group entries, keeping playlist order within each group. Groups come
+/// in order of first appearance.
+pub fn split(items: &[PlaylistItem], by: SplitBy) -> Vec<SplitGroup> {
+    if let SplitBy::Size(size) = by {
+        let size = size.max(1);
+        return items
+            .chunks(size)
+            .enumerate()
+            .map(|(i, chunk)| SplitGroup {
+                label: (i + 1).to_string(),
+                entry_ids: chunk.iter().map(|item| item.entry_id).collect(),
+            })
+            .collect();
+    }
+
+    let mut groups: Vec<SplitGroup> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for item in items {
+        let non_empty = |s: &Option<String>| {
+            s.as_deref()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        let label = match by {
+            SplitBy::Artist => non_empty(&item.album_artist)
+                .or_else(|| non_empty(&item.artist))
+                .unwrap_or_else(|| "Unknown Artist".to_string()),
+            _ => non_empty(&item.album).unwrap_or_else(|| "Unknown Album".to_string()),
+        };
+        // Albums of the same name by different artists stay apart
+        let key = match by {
+            SplitBy::Album => format!(
+                "{}\u{0}{}",
+                label.to_lowercase(),
+                non_empty(&item.album_artist)
+                    .or_else(|| non_empty(&item.artist))
+                    .unwrap_or_default()
+                    .to_lowercase()
+            ),
+            _ => label.to_lowercase(),
+        };
+        let i = *index.entry(key).or_insert_with(|| {
+            groups.push(SplitGroup {
+                label,
+                entry_ids: Vec::new(),
+            });
+            groups.len() - 1
+        });
+        groups[i].entry_ids.push(item.entry_id);
+    }
+    groups
+}
+
+/// Small seeded generator (SplitMix64); shuffles don't need more, and a
+/// seed makes them reproducible in tests.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Fisher-Yates shuffle.
+pub fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut rng = SplitMix64(seed);
+    for i in (1..items.len()).rev() {
+        let j = rng.below(i + 1);
+        items.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(
+        entry_id: i64,
+        track_id: i64,
+        artist: &str,
+        album: &str,
+        year: Option<i64>,
+    ) -> PlaylistItem {
+        PlaylistItem {
+            entry_id,
+            track_id,
+            artist: Some(artist.to_string()),
+            album: Some(album.to_string()),
+            year,
+            ..PlaylistItem::default()
+        }
+    }
+
+    fn ids(items: &[PlaylistItem]) -> Vec<i64> {
+        items.iter().map(|item| item.entry_id).collect()
+    }
+
+    #[test]
+    fn test_multi_key_sort() {
+        let mut items = vec![
+            item(1, 1, "b", "x", Some(2001)),
+            item(2, 2, "A", "y", None),
+            item(3, 3, "a", "z", Some(1999)),
+            item(4, 4, "b", "w", Some(2005)),
+        ];
+        sort(
+            &mut items,
+            &[
+                PlaylistSortKey {
+                    field: PlaylistSortField::Artist,
+                    descending: false,
+                },
+                PlaylistSortKey {
+                    field: PlaylistSortField::Year,
+                    descending: true,
+                },
+            ],
+        );
+        // Case-insensitive artist, then newest first with undated last
+        assert_eq!(ids(&items), vec![3, 2, 4, 1]);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut items = vec![
+            item(1, 10, "a", "x", None),
+            item(2, 11, "a", "x", None),
+            item(3, 10, "a", "x", None),
+            item(4, 12, "a", "x", None),
+        ];
+        items[1].content_hash = Some("same".to_string());
+        items[3].content_hash = Some("same".to_string());
+        assert_eq!(duplicates(&items, DedupeBy::TrackId), vec![3]);
+        assert_eq!(duplicates(&items, DedupeBy::ContentHash), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_split() {
+        let mut items = vec![
+            item(1, 1, "Queen", "Jazz", None),
+            item(2, 2, "Nas", "Illmatic", None),
+            item(3, 3, "queen", "Innuendo", None),
+            item(4, 4, "", "Jazz", None),
+        ];
+        items[3].artist = None;
+        let by_artist = split(&items, SplitBy::Artist);
+        assert_eq!(
+            by_artist
+                .iter()
+                .map(|g| (g.label.as_str(), g.entry_ids.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Queen", vec![1, 3]),
+                ("Nas", vec![2]),
+                ("Unknown Artist", vec![4])
+            ]
+        );
+        // Same album name, different artist: separate groups
+        assert_eq!(split(&items, SplitBy::Album).len(), 4);
+        let chunks = split(&items, SplitBy::Size(3));
+        assert_eq!(chunks[0].entry_ids, vec![1, 2, 3]);
+        assert_eq!(chunks[1].label, "2");
+        assert_eq!(chunks[1].entry_ids, vec![4]);
+    }
+
+    #[test]
+    fn test_shuffle_is_a_permutation() {
+        let mut values: Vec<i32> = (0..50).collect();
+        shuffle(&mut values, 42);
+        assert_ne!(values, (0..50).collect::<Vec<_>>());
+        let mut again: Vec<i32> = (0..50).collect();
+        shuffle(&mut again, 42);
+        assert_eq!(values, again);
+        values.sort();
+        assert_eq!(values, (0..50).collect::<Vec<_>>());
+    }
+}
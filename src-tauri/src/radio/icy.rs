@@ -0,0 +1,196 @@
+// ICY (Shoutcast/Icecast) in-band metadata handling
+//
+// When a client sends `Icy-MetaData: 1`, the server interleaves a metadata
+// block after every `icy-metaint` bytes of audio:
+//
+//   [metaint audio bytes][1 length byte = N][N * 16 bytes of metadata] ...
+//
+// The metadata is a string such as `StreamTitle='Artist - Title';StreamUrl='';`
+// padded with NULs. Blocks with N = 0 mean "no change".
+
+/// Splits a raw ICY byte stream into audio bytes and metadata blocks.
+/// Keeps state between calls, so chunk boundaries can fall anywhere.
+pub struct IcyDemuxer {
+    metaint: Option<usize>,
+    state: DemuxState,
+}
+
+enum DemuxState {
+    Audio { remaining: usize },
+    Length,
+    Metadata { remaining: usize, block: Vec<u8> },
+}
+
+impl IcyDemuxer {
+    /// `metaint` is the value of the `icy-metaint` response header, or `None`
+    /// if the server does not interleave metadata (the stream is then passed
+    /// through untouched).
+    pub fn new(metaint: Option<usize>) -> Self {
+        let metaint = metaint.filter(|&m| m > 0);
+        Self {
+            metaint,
+            state: DemuxState::Audio {
+                remaining: metaint.unwrap_or(0),
+            },
+        }
+    }
+
+    /// Appends the audio part of `input` to `audio` and returns every metadata
+    /// block completed by this chunk (empty blocks are not reported).
+    pub fn feed(&mut self, mut input: &[u8], audio: &mut Vec<u8>) -> Vec<String> {
+        let Some(metaint) = self.metaint else {
+            audio.extend_from_slice(input);
+            return Vec::new();
+        };
+
+        let mut blocks = Vec::new();
+        while !input.is_empty() {
+            match &mut self.state {
+                DemuxState::Audio { remaining } => {
+                    let take = (*remaining).min(input.len());
+                    audio.extend_from_slice(&input[..take]);
+                    input = &input[take..];
+                    *remaining -= take;
+                    if *remaining == 0 {
+                        self.state = DemuxState::Length;
+                    }
+                }
+                DemuxState::Length => {
+                    let len = input[0] as usize * 16;
+                    input = &input[1..];
+                    self.state = if len == 0 {
+                        DemuxState::Audio { remaining: metaint }
+                    } else {
+                        DemuxState::Metadata {
+                            remaining: len,
+                            block: Vec::with_capacity(len),
+                        }
+                    };
+                }
+                DemuxState::Metadata { remaining, block } => {
+                    let take = (*remaining).min(input.len());
+                    block.extend_from_slice(&input[..take]);
+                    input = &input[take..];
+                    *remaining -= take;
+                    if *remaining == 0 {
+                        let text = decode_metadata(block);
+                        if !text.is_empty() {
+                            blocks.push(text);
+                        }
+                        self.state = DemuxState::Audio { remaining: metaint };
+                    }
+                }
+            }
+        }
+        blocks
+    }
+}
+
+/// Metadata is nominally UTF-8 but plenty of servers send Latin-1.
+fn decode_metadata(block: &[u8]) -> String {
+    let trimmed: &[u8] = match block.iter().position(|&b| b == 0) {
+        Some(end) => &block[..end],
+        None => block,
+    };
+    match std::str::from_utf8(trimmed) {
+        Ok(s) => s.trim().to_string(),
+        Err(_) => trimmed.iter().map(|&b| b as char).collect::<String>().trim().to_string(),
+    }
+}
+
+/// Now-playing information parsed from a `StreamTitle`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NowPlaying {
+    pub raw: String,
+    pub artist: Option<String>,
+    pub title: String,
+}
+
+/// Extracts `StreamTitle` from a metadata block. Titles may themselves
+/// contain apostrophes, so the value runs up to the last `';` terminator.
+pub fn parse_stream_title(block: &str) -> Option<NowPlaying> {
+    const KEY: &str = "StreamTitle='";
+    let start = block.find(KEY)? + KEY.len();
+    let rest = &block[start..];
+
+    // The next field (e.g. StreamUrl) starts with `';Key='`; otherwise the
+    // value ends at the final `';` or a bare trailing quote.
+    let end = rest
+        .find("';StreamUrl=")
+        .or_else(|| rest.rfind("';"))
+        .or_else(|| rest.strip_suffix('\'').map(|s| s.len()))
+        .unwrap_or(rest.len());
+
+    let raw = rest[..end].trim().to_string();
+    if raw.is_empty() {
+        return None;
+    }
+
+    let (artist, title) = match raw.split_once(" - ") {
+        Some((a, t)) if !a.trim().is_empty() && !t.trim().is_empty() => {
+            (Some(a.trim().to_string()), t.trim().to_string())
+        }
+        _ => (None, raw.clone()),
+    };
+
+    Some(NowPlaying { raw, artist, title })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta_block(text: &str) -> Vec<u8> {
+        let blocks = text.len().div_ceil(16);
+        let mut out = vec![blocks as u8];
+        out.extend_from_slice(text.as_bytes());
+        out.resize(1 + blocks * 16, 0);
+        out
+    }
+
+    #[test]
+    fn test_demuxer_splits_audio_and_metadata() {
+        let mut stream = b"abcd".to_vec();
+        stream.extend(meta_block("StreamTitle='A - B';"));
+        stream.extend_from_slice(b"efgh");
+        stream.push(0); // empty metadata block
+        stream.extend_from_slice(b"ij");
+
+        let mut demuxer = IcyDemuxer::new(Some(4));
+        let mut audio = Vec::new();
+        let mut titles = Vec::new();
+        // Feed one byte at a time to exercise every chunk boundary.
+        for byte in &stream {
+            titles.extend(demuxer.feed(std::slice::from_ref(byte), &mut audio));
+        }
+
+        assert_eq!(audio, b"abcdefghij");
+        assert_eq!(titles, vec!["StreamTitle='A - B';".to_string()]);
+    }
+
+    #[test]
+    fn test_demuxer_passthrough_without_metaint() {
+        let mut demuxer = IcyDemuxer::new(None);
+        let mut audio = Vec::new();
+        assert!(demuxer.feed(b"\x01raw", &mut audio).is_empty());
+        assert_eq!(audio, b"\x01raw");
+    }
+
+    #[test]
+    fn test_parse_stream_title() {
+        let np = parse_stream_title("StreamTitle='Daft Punk - One More Time';StreamUrl='';").unwrap();
+        assert_eq!(np.artist.as_deref(), Some("Daft Punk"));
+        assert_eq!(np.title, "One More Time");
+
+        let np = parse_stream_title("StreamTitle='Guns N' Roses - Don't Cry';").unwrap();
+        assert_eq!(np.artist.as_deref(), Some("Guns N' Roses"));
+        assert_eq!(np.title, "Don't Cry");
+
+        let np = parse_stream_title("StreamTitle='Station Jingle';").unwrap();
+        assert_eq!(np.artist, None);
+        assert_eq!(np.title, "Station Jingle");
+
+        assert!(parse_stream_title("StreamTitle='';").is_none());
+        assert!(parse_stream_title("StreamUrl='http://x';").is_none());
+    }
+}
@@ -0,0 +1,374 @@
+// =============================================================================
+// INTERNET RADIO
+// =============================================================================
+// Architecture:
+//
+//   connect()        — opens the HTTP stream with `Icy-MetaData: 1`, resolves
+//                      .pls/.m3u indirections, then hands the response to a
+//                      dedicated network thread.
+//
+//   network thread   — reads the response, strips ICY metadata via IcyDemuxer,
+//                      pushes audio bytes into a bounded crossbeam channel and
+//                      reports StreamTitle changes through a callback.
+//                      On disconnect it reconnects with exponential backoff;
+//                      the decoder keeps waiting on the channel meanwhile.
+//
+//   StreamReader     — the receiving end. Implements io::Read so it can be
+//                      wrapped in symphonia's ReadOnlySource and played by
+//                      the native engine like any other source. Dropping it
+//                      (stop / track change) makes the network thread exit.
+// =============================================================================
+
+pub mod icy;
+pub mod stations;
+
+use std::io::Read;
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use reqwest::blocking::{Client, Response};
+
+use icy::{IcyDemuxer, NowPlaying};
+
+const USER_AGENT: &str = concat!("Audion/", env!("CARGO_PKG_VERSION"));
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long the decoder waits for data before treating the stream as dead.
+const STARVATION_TIMEOUT: Duration = Duration::from_secs(60);
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+const MAX_RECONNECT_ATTEMPTS: u32 = 8;
+/// Chunks buffered between the network thread and the decoder (~8 KiB each).
+const CHANNEL_CAPACITY: usize = 64;
+const READ_CHUNK: usize = 8192;
+/// Playlist indirections (station URL → .pls → stream) followed at most this deep.
+const MAX_PLAYLIST_HOPS: usize = 3;
+
+/// Stream properties advertised by the server.
+#[derive(Debug, Clone, Default)]
+pub struct StreamInfo {
+    /// Final stream URL after playlist resolution.
+    pub url: String,
+    pub content_type: Option<String>,
+    pub icy_name: Option<String>,
+    pub icy_genre: Option<String>,
+    pub bitrate: Option<u32>,
+}
+
+impl StreamInfo {
+    /// Symphonia probe hint derived from the content type.
+    pub fn extension_hint(&self) -> Option<&'static str> {
+        let ct = self.content_type.as_deref()?.to_ascii_lowercase();
+        if ct.contains("mpeg") || ct.contains("mp3") {
+            Some("mp3")
+        } else if ct.contains("aac") {
+            Some("aac")
+        } else if ct.contains("ogg") || ct.contains("opus") || ct.contains("vorbis") {
+            Some("ogg")
+        } else if ct.contains("flac") {
+            Some("flac")
+        } else {
+            None
+        }
+    }
+}
+
+/// Receiving end of a radio stream. Blocks until audio arrives.
+pub struct StreamReader {
+    rx: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for StreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos >= self.chunk.len() {
+            match self.rx.recv_timeout(STARVATION_TIMEOUT) {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                // Sender gone (reconnects exhausted) or starved: end of stream.
+                Err(RecvTimeoutError::Disconnected) | Err(RecvTimeoutError::Timeout) => {
+                    return Ok(0);
+                }
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Connect to a station and start the network thread. `on_title` is called
+/// from that thread whenever the ICY `StreamTitle` changes.
+///
+/// Blocking — call from a worker thread (`spawn_blocking`), not the async runtime.
+pub fn connect<F>(url: &str, on_title: F) -> Result<(StreamReader, StreamInfo), String>
+where
+    F: FnMut(NowPlaying) + Send + 'static,
+{
+    let client = Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(None::<Duration>)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let stream_url = resolve_stream_url(&client, url)?;
+    let (response, info) = open_stream(&client, &stream_url)?;
+
+    tracing::info!(
+        "[RADIO] Connected: {} ({:?}, {:?} kbps)",
+        info.url,
+        info.content_type,
+        info.bitrate
+    );
+
+    let (tx, rx) = bounded::<Vec<u8>>(CHANNEL_CAPACITY);
+    let thread_url = info.url.clone();
+    thread::Builder::new()
+        .name("radio-stream".into())
+        .spawn(move || pump(client, thread_url, response, tx, on_title))
+        .map_err(|e| format!("Failed to spawn radio thread: {}", e))?;
+
+    Ok((
+        StreamReader {
+            rx,
+            chunk: Vec::new(),
+            pos: 0,
+        },
+        info,
+    ))
+}
+
+/// Follow .pls / .m3u indirections until we reach an audio stream URL.
+fn resolve_stream_url(client: &Client, url: &str) -> Result<String, String> {
+    let mut current = url.to_string();
+    for _ in 0..MAX_PLAYLIST_HOPS {
+        let lower = current.to_ascii_lowercase();
+        let path = lower.split(['?', '#']).next().unwrap_or("");
+        let looks_like_playlist =
+            path.ends_with(".pls") || path.ends_with(".m3u") || path.ends_with(".m3u8");
+        if !looks_like_playlist {
+            return Ok(current);
+        }
+
+        let body = client
+            .get(&current)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.text())
+            .map_err(|e| format!("Failed to fetch station playlist {}: {}", current, e))?;
+
+        // An HLS playlist is not a station list; let the stream open fail clearly.
+        if body.contains("#EXT-X-") {
+            return Err(format!("HLS streams are not supported: {}", current));
+        }
+
+        current = stations::parse_station_list(&body)
+            .into_iter()
+            .next()
+            .map(|e| e.url)
+            .ok_or_else(|| format!("Station playlist has no stream URLs: {}", current))?;
+    }
+    Ok(current)
+}
+
+fn open_stream(client: &Client, url: &str) -> Result<(Response, StreamInfo), String> {
+    let response = client
+        .get(url)
+        .header("Icy-MetaData", "1")
+        .send()
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to connect to {}: {}", url, e))?;
+
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    let info = StreamInfo {
+        url: response.url().to_string(),
+        content_type: header("content-type"),
+        icy_name: header("icy-name"),
+        icy_genre: header("icy-genre"),
+        bitrate: header("icy-br").and_then(|b| b.split(',').next()?.trim().parse().ok()),
+    };
+
+    Ok((response, info))
+}
+
+fn metaint_of(response: &Response) -> Option<usize> {
+    response
+        .headers()
+        .get("icy-metaint")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+/// Network thread body: read → demux → forward, reconnecting on failure.
+fn pump<F>(client: Client, url: String, mut response: Response, tx: Sender<Vec<u8>>, mut on_title: F)
+where
+    F: FnMut(NowPlaying),
+{
+    let mut last_title: Option<String> = None;
+
+    loop {
+        let mut demuxer = IcyDemuxer::new(metaint_of(&response));
+        let mut buf = vec![0u8; READ_CHUNK];
+
+        loop {
+            let n = match response.read(&mut buf) {
+                Ok(0) => {
+                    tracing::warn!("[RADIO] Stream ended by server: {}", url);
+                    break;
+                }
+                Ok(n) => n,
+                Err(e) => {
+                    tracing::warn!("[RADIO] Stream read error ({}): {}", url, e);
+                    break;
+                }
+            };
+
+            let mut audio = Vec::with_capacity(n);
+            for block in demuxer.feed(&buf[..n], &mut audio) {
+                if let Some(now_playing) = icy::parse_stream_title(&block) {
+                    if last_title.as_deref() != Some(now_playing.raw.as_str()) {
+                        tracing::info!("[RADIO] Now playing: {}", now_playing.raw);
+                        last_title = Some(now_playing.raw.clone());
+                        on_title(now_playing);
+                    }
+                }
+            }
+
+            if !audio.is_empty() && tx.send(audio).is_err() {
+                // Reader dropped — playback stopped or switched.
+                tracing::info!("[RADIO] Listener gone, closing {}", url);
+                return;
+            }
+        }
+
+        match reconnect(&client, &url, &tx) {
+            Some(r) => response = r,
+            None => return,
+        }
+    }
+}
+
+/// Reconnect with exponential backoff. Returns `None` when the listener has
+/// gone away or every attempt failed (dropping `tx` then ends the stream).
+fn reconnect(client: &Client, url: &str, tx: &Sender<Vec<u8>>) -> Option<Response> {
+    for attempt in 0..MAX_RECONNECT_ATTEMPTS {
+        let delay = backoff_delay(attempt);
+        tracing::info!(
+            "[RADIO] Reconnecting to {} in {:?} (attempt {}/{})",
+            url,
+            delay,
+            attempt + 1,
+            MAX_RECONNECT_ATTEMPTS
+        );
+        thread::sleep(delay);
+
+        // Nobody listening any more — don't bother reconnecting.
+        if tx.send(Vec::new()).is_err() {
+            return None;
+        }
+
+        match open_stream(client, url) {
+            Ok((response, _)) => {
+                tracing::info!("[RADIO] Reconnected: {}", url);
+                return Some(response);
+            }
+            Err(e) => tracing::warn!("[RADIO] {}", e),
+        }
+    }
+    tracing::error!("[RADIO] Giving up on {} after {} attempts", url, MAX_RECONNECT_ATTEMPTS);
+    None
+}
+
+fn backoff_delay(attempt: u32) -> Duration {
+    RECONNECT_BASE_DELAY
+        .saturating_mul(1u32 << attempt.min(16))
+        .min(RECONNECT_MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    /// Minimal Icecast-style server: serves each body in `sessions` to one
+    /// connection, with `icy-metaint: 8`, then closes the socket.
+    fn spawn_stub(sessions: Vec<Vec<u8>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for body in sessions {
+                let Ok((mut socket, _)) = listener.accept() else { return };
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request);
+                let head = "HTTP/1.0 200 OK\r\nContent-Type: audio/mpeg\r\nicy-name: Stub FM\r\nicy-br: 128\r\nicy-metaint: 8\r\n\r\n";
+                let _ = socket.write_all(head.as_bytes());
+                let _ = socket.write_all(&body);
+            }
+        });
+        format!("http://{}/stream", addr)
+    }
+
+    fn meta(text: &str) -> Vec<u8> {
+        let blocks = text.len().div_ceil(16);
+        let mut out = vec![blocks as u8];
+        out.extend_from_slice(text.as_bytes());
+        out.resize(1 + blocks * 16, 0);
+        out
+    }
+
+    #[test]
+    fn test_stream_metadata_and_reconnect() {
+        let mut first = b"AAAAAAAA".to_vec();
+        first.extend(meta("StreamTitle='Artist One - Song One';"));
+        let mut second = b"BBBBBBBB".to_vec();
+        second.extend(meta("StreamTitle='Artist Two - Song Two';"));
+        let url = spawn_stub(vec![first, second]);
+
+        let titles = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&titles);
+        let (mut reader, info) = connect(&url, move |np| sink.lock().unwrap().push(np)).unwrap();
+
+        assert_eq!(info.icy_name.as_deref(), Some("Stub FM"));
+        assert_eq!(info.bitrate, Some(128));
+        assert_eq!(info.extension_hint(), Some("mp3"));
+
+        // The second half only arrives after the client reconnects.
+        let mut audio = [0u8; 16];
+        reader.read_exact(&mut audio).unwrap();
+        assert_eq!(&audio, b"AAAAAAAABBBBBBBB");
+
+        // The title of the second session is parsed after its audio.
+        for _ in 0..50 {
+            if titles.lock().unwrap().len() == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        let titles = titles.lock().unwrap();
+        assert_eq!(titles.len(), 2);
+        assert_eq!(titles[0].artist.as_deref(), Some("Artist One"));
+        assert_eq!(titles[1].title, "Song Two");
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        assert_eq!(backoff_delay(0), Duration::from_millis(500));
+        assert_eq!(backoff_delay(2), Duration::from_secs(2));
+        assert_eq!(backoff_delay(10), RECONNECT_MAX_DELAY);
+    }
+}
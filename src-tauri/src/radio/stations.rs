@@ -0,0 +1,188 @@
+// Station list import/export (PLS and M3U)
+
+/// A station entry as read from / written to a playlist file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationEntry {
+    pub name: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StationListFormat {
+    Pls,
+    M3u,
+}
+
+impl StationListFormat {
+    /// Pick a format from a file extension (`pls`, `m3u`, `m3u8`).
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "pls" => Some(Self::Pls),
+            "m3u" | "m3u8" => Some(Self::M3u),
+            _ => None,
+        }
+    }
+}
+
+/// Only http(s) URLs can be streamed by the radio engine.
+pub fn is_stream_url(url: &str) -> bool {
+    let lower = url.trim().to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Parse a station list, detecting PLS by its `[playlist]` header.
+pub fn parse_station_list(content: &str) -> Vec<StationEntry> {
+    let is_pls = content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .map(|l| l.eq_ignore_ascii_case("[playlist]"))
+        .unwrap_or(false);
+
+    if is_pls {
+        parse_pls(content)
+    } else {
+        parse_m3u(content)
+    }
+}
+
+/// PLS: `FileN=url`, `TitleN=name`, numbered entries in any order.
+pub fn parse_pls(content: &str) -> Vec<StationEntry> {
+    let mut entries: Vec<(u32, StationEntry)> = Vec::new();
+
+    fn entry_for(n: u32, entries: &mut Vec<(u32, StationEntry)>) -> usize {
+        match entries.iter().position(|(i, _)| *i == n) {
+            Some(idx) => idx,
+            None => {
+                entries.push((n, StationEntry { name: None, url: String::new() }));
+                entries.len() - 1
+            }
+        }
+    }
+
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+
+        if let Some(n) = key.strip_prefix("file").and_then(|n| n.parse::<u32>().ok()) {
+            let idx = entry_for(n, &mut entries);
+            entries[idx].1.url = value.to_string();
+        } else if let Some(n) = key.strip_prefix("title").and_then(|n| n.parse::<u32>().ok()) {
+            let idx = entry_for(n, &mut entries);
+            if !value.is_empty() {
+                entries[idx].1.name = Some(value.to_string());
+            }
+        }
+    }
+
+    entries.sort_by_key(|(n, _)| *n);
+    entries
+        .into_iter()
+        .map(|(_, e)| e)
+        .filter(|e| is_stream_url(&e.url))
+        .collect()
+}
+
+/// M3U / extended M3U: `#EXTINF:-1,Name` followed by the URL line.
+pub fn parse_m3u(content: &str) -> Vec<StationEntry> {
+    let mut entries = Vec::new();
+    let mut pending_name: Option<String> = None;
+
+    for line in content.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            pending_name = info
+                .split_once(',')
+                .map(|(_, name)| name.trim().to_string())
+                .filter(|name| !name.is_empty());
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        if is_stream_url(line) {
+            entries.push(StationEntry {
+                name: pending_name.take(),
+                url: line.to_string(),
+            });
+        } else {
+            pending_name = None;
+        }
+    }
+
+    entries
+}
+
+pub fn write_station_list(entries: &[StationEntry], format: StationListFormat) -> String {
+    match format {
+        StationListFormat::Pls => write_pls(entries),
+        StationListFormat::M3u => write_m3u(entries),
+    }
+}
+
+pub fn write_pls(entries: &[StationEntry]) -> String {
+    let mut out = String::from("[playlist]\n");
+    for (i, entry) in entries.iter().enumerate() {
+        let n = i + 1;
+        out.push_str(&format!("File{}={}\n", n, entry.url));
+        if let Some(name) = &entry.name {
+            out.push_str(&format!("Title{}={}\n", n, name));
+        }
+        out.push_str(&format!("Length{}=-1\n", n));
+    }
+    out.push_str(&format!("NumberOfEntries={}\nVersion=2\n", entries.len()));
+    out
+}
+
+pub fn write_m3u(entries: &[StationEntry]) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    for entry in entries {
+        let name = entry.name.as_deref().unwrap_or(&entry.url);
+        out.push_str(&format!("#EXTINF:-1,{}\n{}\n", name, entry.url));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pls() {
+        let pls = "[playlist]\nFile2=http://b.example/stream\nTitle1=First\nFile1=http://a.example/live\nTitle2=Second\nFile3=/local/file.mp3\nNumberOfEntries=3\n";
+        let entries = parse_station_list(pls);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].url, "http://a.example/live");
+        assert_eq!(entries[0].name.as_deref(), Some("First"));
+        assert_eq!(entries[1].name.as_deref(), Some("Second"));
+    }
+
+    #[test]
+    fn test_parse_m3u() {
+        let m3u = "#EXTM3U\n#EXTINF:-1,Jazz FM\nhttps://jazz.example/aac\n\nhttp://plain.example/mp3\n";
+        let entries = parse_station_list(m3u);
+        assert_eq!(
+            entries,
+            vec![
+                StationEntry { name: Some("Jazz FM".into()), url: "https://jazz.example/aac".into() },
+                StationEntry { name: None, url: "http://plain.example/mp3".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let entries = vec![
+            StationEntry { name: Some("One".into()), url: "http://one.example/".into() },
+            StationEntry { name: Some("Two".into()), url: "https://two.example/ogg".into() },
+        ];
+        assert_eq!(parse_station_list(&write_pls(&entries)), entries);
+        assert_eq!(parse_station_list(&write_m3u(&entries)), entries);
+    }
+}
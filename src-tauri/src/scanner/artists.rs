@@ -0,0 +1,414 @@
+// Artist credits: splitting artist strings into individual artists with roles
+//
+// A track's credits come from its artist string ("A feat. B", "A; B"), guest
+// and remix credits in its title ("Song (feat. C)", "Song (D Remix)") and its
+// composer and remixer tags. Multi-value tags are joined with "; " when read
+// (see `join_tag_values`), so everything can be rebuilt from stored columns
+// when the split rules change.
+use serde::{Deserialize, Serialize};
+
+/// Separator used to join multi-value tags into one column.
+pub const MULTI_VALUE_SEPARATOR: &str = "; ";
+
+/// Markers that introduce guest artists, matched case-insensitively.
+const FEATURING_MARKERS: &[&str] = &[" feat. ", " feat ", " ft. ", " ft ", " featuring "];
+
+/// Extra separators inside a featured list: "A feat. B & C", "A feat. B, C".
+const FEATURED_LIST_SEPARATORS: &[&str] = &[", ", " & ", " and "];
+
+/// Extra separators for composer credits: "Lennon/McCartney", "A, B".
+const COMPOSER_SEPARATORS: &[&str] = &["/", ", ", " & "];
+
+/// Bracketed "remix" credits that name a version, not a person.
+const GENERIC_REMIX_NAMES: &[&str] = &[
+    "original", "extended", "radio", "club", "dub", "album", "single", "official", "vip",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtistRole {
+    Main,
+    Featured,
+    Remixer,
+    Composer,
+}
+
+impl ArtistRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Main => "main",
+            Self::Featured => "featured",
+            Self::Remixer => "remixer",
+            Self::Composer => "composer",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "main" => Some(Self::Main),
+            "featured" => Some(Self::Featured),
+            "remixer" => Some(Self::Remixer),
+            "composer" => Some(Self::Composer),
+            _ => None,
+        }
+    }
+}
+
+/// How artist strings are split. Stored in `library_settings`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArtistSplitRules {
+    /// Strings that separate artists ("; ", " / ", " & ", ", "...).
+    pub separators: Vec<String>,
+    /// Names that contain a separator but are one artist ("Simon & Garfunkel").
+    pub exceptions: Vec<String>,
+}
+
+impl Default for ArtistSplitRules {
+    fn default() -> Self {
+        Self {
+            separators: vec![";".into(), " / ".into(), "\0".into()],
+            exceptions: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtistCredit {
+    pub name: String,
+    pub role: ArtistRole,
+}
+
+/// Join the values of a multi-value tag for storage, or None when empty.
+pub fn join_tag_values<'a>(values: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let mut joined: Vec<&str> = Vec::new();
+    for value in values {
+        // ID3v2.4 stores multiple values in one frame, separated by NUL
+        for part in value.split('\0').map(str::trim).filter(|s| !s.is_empty()) {
+            if !joined.iter().any(|v| v.eq_ignore_ascii_case(part)) {
+                joined.push(part);
+            }
+        }
+    }
+    if joined.is_empty() {
+        None
+    } else {
+        Some(joined.join(MULTI_VALUE_SEPARATOR))
+    }
+}
+
+/// Split `text` on `separators`, never inside one of `exceptions`.
+fn split_names(text: &str, separators: &[&str], exceptions: &[String]) -> Vec<String> {
+    let lower = text.to_lowercase();
+    // `to_lowercase` can change byte offsets; fall back to no splitting
+    if lower.len() != text.len() {
+        let name = text.trim();
+        return if name.is_empty() {
+            Vec::new()
+        } else {
+            vec![name.to_string()]
+        };
+    }
+
+    let protected: Vec<(usize, usize)> = exceptions
+        .iter()
+        .map(|e| e.trim().to_lowercase())
+        .filter(|e| !e.is_empty())
+        .flat_map(|e| {
+            lower
+                .match_indices(e.as_str())
+                .map(|(start, m)| (start, start + m.len()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let mut names = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+    while pos < text.len() {
+        let inside_exception = protected.iter().any(|&(s, e)| pos > s && pos < e);
+        let separator = separators
+            .iter()
+            .filter(|sep| !sep.is_empty())
+            .find(|sep| lower[pos..].starts_with(&sep.to_lowercase()));
+        match separator {
+            Some(sep) if !inside_exception => {
+                names.push(text[start..pos].trim().to_string());
+                pos += sep.len();
+                start = pos;
+            }
+            _ => {
+                pos += text[pos..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+    }
+    names.push(text[start..].trim().to_string());
+
+    names.retain(|n| !n.is_empty());
+    names
+}
+
+/// Split an artist string into main and featured parts at the first
+/// featuring marker, plain ("A feat. B") or bracketed ("A (feat. B)").
+fn split_featuring(artist: &str) -> (&str, Option<&str>) {
+    let lower = artist.to_lowercase();
+    if lower.len() != artist.len() {
+        return (artist, None);
+    }
+
+    let mut best: Option<(usize, usize)> = None;
+    for marker in FEATURING_MARKERS {
+        if let Some(pos) = lower.find(marker) {
+            if best.is_none_or(|(b, _)| pos < b) {
+                best = Some((pos, pos + marker.len()));
+            }
+        }
+    }
+    for open in ["(", "["] {
+        for marker in ["feat. ", "feat ", "ft. ", "ft ", "featuring "] {
+            let needle = format!("{}{}", open, marker);
+            if let Some(pos) = lower.find(&needle) {
+                if best.is_none_or(|(b, _)| pos < b) {
+                    best = Some((pos, pos + needle.len()));
+                }
+            }
+        }
+    }
+
+    match best {
+        Some((start, end)) => {
+            let featured = artist[end..].trim().trim_end_matches([')', ']']).trim();
+            (
+                artist[..start].trim(),
+                Some(featured).filter(|s| !s.is_empty()),
+            )
+        }
+        None => (artist, None),
+    }
+}
+
+/// Guest and remix credits in bracketed title parts.
+fn title_credits(title: &str, rules: &ArtistSplitRules) -> Vec<ArtistCredit> {
+    let featured_separators = featured_separators(rules);
+    let mut credits = Vec::new();
+
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        let mut rest = title;
+        while let Some(start) = rest.find(open) {
+            let Some(len) = rest[start + 1..].find(close) else {
+                break;
+            };
+            let inner = rest[start + 1..start + 1 + len].trim();
+            rest = &rest[start + 1 + len + 1..];
+
+            let lower = inner.to_lowercase();
+            if lower.len() != inner.len() {
+                continue;
+            }
+            let feat = ["feat. ", "feat ", "ft. ", "ft ", "featuring "]
+                .iter()
+                .find(|m| lower.starts_with(*m));
+            if let Some(marker) = feat {
+                credits.extend(
+                    split_names(
+                        &inner[marker.len()..],
+                        &featured_separators,
+                        &rules.exceptions,
+                    )
+                    .into_iter()
+                    .map(|name| ArtistCredit {
+                        name,
+                        role: ArtistRole::Featured,
+                    }),
+                );
+            } else if let Some(name) = lower
+                .strip_suffix(" remix")
+                .map(|n| inner[..n.len()].trim())
+                .filter(|n| !GENERIC_REMIX_NAMES.contains(&n.to_lowercase().as_str()))
+            {
+                let name = name
+                    .strip_suffix("'s")
+                    .or_else(|| name.strip_suffix("’s"))
+                    .unwrap_or(name);
+                credits.extend(
+                    split_names(name, &featured_separators, &rules.exceptions)
+                        .into_iter()
+                        .map(|name| ArtistCredit {
+                            name,
+                            role: ArtistRole::Remixer,
+                        }),
+                );
+            }
+        }
+    }
+
+    credits
+}
+
+fn featured_separators(rules: &ArtistSplitRules) -> Vec<&str> {
+    rules
+        .separators
+        .iter()
+        .map(String::as_str)
+        .chain(FEATURED_LIST_SEPARATORS.iter().copied())
+        .collect()
+}
+
+/// Every credit of a track, in order, without duplicate (name, role) pairs.
+/// An artist credited as main is not also listed as featured.
+pub fn parse_credits(
+    artist: Option<&str>,
+    title: Option<&str>,
+    composer: Option<&str>,
+    remixer: Option<&str>,
+    rules: &ArtistSplitRules,
+) -> Vec<ArtistCredit> {
+    let separators: Vec<&str> = rules.separators.iter().map(String::as_str).collect();
+    let featured_separators = featured_separators(rules);
+    let mut credits = Vec::new();
+
+    if let Some(artist) = artist {
+        // Each joined tag value can carry its own "feat." part
+        for value in split_names(artist, &separators, &rules.exceptions) {
+            let (main, featured) = split_featuring(&value);
+            for name in split_names(main, &separators, &rules.exceptions) {
+                credits.push(ArtistCredit {
+                    name,
+                    role: ArtistRole::Main,
+                });
+            }
+            if let Some(featured) = featured {
+                for name in split_names(featured, &featured_separators, &rules.exceptions) {
+                    credits.push(ArtistCredit {
+                        name,
+                        role: ArtistRole::Featured,
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(title) = title {
+        credits.extend(title_credits(title, rules));
+    }
+
+    if let Some(remixer) = remixer {
+        for name in split_names(remixer, &featured_separators, &rules.exceptions) {
+            credits.push(ArtistCredit {
+                name,
+                role: ArtistRole::Remixer,
+            });
+        }
+    }
+
+    if let Some(composer) = composer {
+        let composer_separators: Vec<&str> = separators
+            .iter()
+            .copied()
+            .chain(COMPOSER_SEPARATORS.iter().copied())
+            .collect();
+        for name in split_names(composer, &composer_separators, &rules.exceptions) {
+            credits.push(ArtistCredit {
+                name,
+                role: ArtistRole::Composer,
+            });
+        }
+    }
+
+    let mut unique: Vec<ArtistCredit> = Vec::with_capacity(credits.len());
+    for credit in credits {
+        let duplicate = unique.iter().any(|c| {
+            c.name.eq_ignore_ascii_case(&credit.name)
+                && (c.role == credit.role
+                    || (c.role == ArtistRole::Main && credit.role == ArtistRole::Featured))
+        });
+        if !duplicate {
+            unique.push(credit);
+        }
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(credits: &[ArtistCredit], role: ArtistRole) -> Vec<&str> {
+        credits
+            .iter()
+            .filter(|c| c.role == role)
+            .map(|c| c.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_featuring_and_separators() {
+        let rules = ArtistSplitRules::default();
+
+        let credits = parse_credits(Some("A feat. B & C"), None, None, None, &rules);
+        assert_eq!(names(&credits, ArtistRole::Main), vec!["A"]);
+        assert_eq!(names(&credits, ArtistRole::Featured), vec!["B", "C"]);
+
+        let credits = parse_credits(Some("A; B"), None, None, None, &rules);
+        assert_eq!(names(&credits, ArtistRole::Main), vec!["A", "B"]);
+
+        let credits = parse_credits(Some("A (ft. B)"), None, None, None, &rules);
+        assert_eq!(names(&credits, ArtistRole::Main), vec!["A"]);
+        assert_eq!(names(&credits, ArtistRole::Featured), vec!["B"]);
+
+        // Ampersands only split main artists when configured
+        let credits = parse_credits(Some("Simon & Garfunkel"), None, None, None, &rules);
+        assert_eq!(names(&credits, ArtistRole::Main), vec!["Simon & Garfunkel"]);
+
+        let rules = ArtistSplitRules {
+            separators: vec![";".into(), " & ".into()],
+            exceptions: vec!["Simon & Garfunkel".into()],
+        };
+        let credits = parse_credits(Some("Simon & Garfunkel & Z"), None, None, None, &rules);
+        assert_eq!(
+            names(&credits, ArtistRole::Main),
+            vec!["Simon & Garfunkel", "Z"]
+        );
+        // "Feather" is not a marker
+        let credits = parse_credits(Some("Feather"), None, None, None, &rules);
+        assert_eq!(names(&credits, ArtistRole::Main), vec!["Feather"]);
+    }
+
+    #[test]
+    fn test_title_and_tag_credits() {
+        let rules = ArtistSplitRules::default();
+        let credits = parse_credits(
+            Some("A"),
+            Some("Song (feat. B) [C's Remix]"),
+            Some("Lennon/McCartney"),
+            None,
+            &rules,
+        );
+        assert_eq!(names(&credits, ArtistRole::Featured), vec!["B"]);
+        assert_eq!(names(&credits, ArtistRole::Remixer), vec!["C"]);
+        assert_eq!(
+            names(&credits, ArtistRole::Composer),
+            vec!["Lennon", "McCartney"]
+        );
+
+        let credits = parse_credits(
+            Some("A"),
+            Some("Song (Extended Remix)"),
+            None,
+            Some("D"),
+            &rules,
+        );
+        assert_eq!(names(&credits, ArtistRole::Remixer), vec!["D"]);
+
+        // A main artist repeated as a guest stays main only
+        let credits = parse_credits(Some("A; B"), Some("Song (feat. b)"), None, None, &rules);
+        assert!(names(&credits, ArtistRole::Featured).is_empty());
+    }
+
+    #[test]
+    fn test_join_tag_values() {
+        assert_eq!(join_tag_values(["A", "B"]).as_deref(), Some("A; B"));
+        assert_eq!(join_tag_values(["A\0B", "a"]).as_deref(), Some("A; B"));
+        assert_eq!(join_tag_values([" "]), None);
+    }
+}
@@ -62,6 +62,26 @@ impl ImageFormat {
 /// Uses the app data dir set by Tauri (cross-platform),
 /// with fallback to APPDATA on Windows for backwards compatibility.
 pub fn get_covers_directory() -> std::result::Result<PathBuf, String> {
+    let covers_dir = get_app_data_base_dir()?.join("covers");
+
+    // Create directories if they don't exist
+    fs::create_dir_all(&covers_dir)
+        .map_err(|e| format!("Failed to create covers directory: {}", e))?;
+
+    Ok(covers_dir)
+}
+
+/// Get the waveform peak cache directory (sibling of the covers directory)
+pub fn get_waveforms_directory() -> std::result::Result<PathBuf, String> {
+    let waveforms_dir = get_app_data_base_dir()?.join("waveforms");
+
+    fs::create_dir_all(&waveforms_dir)
+        .map_err(|e| format!("Failed to create waveforms directory: {}", e))?;
+
+    Ok(waveforms_dir)
+}
+
+fn get_app_data_base_dir() -> std::result::Result<PathBuf, String> {
     let base_dir = if let Some(dir) = APP_DATA_DIR.get() {
         // Use Tauri-provided app data dir (works on all platforms)
         dir.clone()
@@ -81,13 +101,7 @@ pub fn get_covers_directory() -> std::result::Result<PathBuf, String> {
         }
     };
 
-    let covers_dir = base_dir.join("covers");
-
-    // Create directories if they don't exist
-    fs::create_dir_all(&covers_dir)
-        .map_err(|e| format!("Failed to create covers directory: {}", e))?;
-
-    Ok(covers_dir)
+    Ok(base_dir)
 }
 
 /// Get the tracks covers subdirectory
@@ -159,6 +173,31 @@ pub fn save_album_art(album_id: i64, image_data: &[u8]) -> Result<String, String
     Ok(file_path.to_string_lossy().to_string())
 }
 
+/// Copy an existing album art file to another album (files are named by
+/// album id, so a shared path would be removed as orphaned)
+pub fn copy_album_art(album_id: i64, source_path: &str) -> Result<String, String> {
+    let image_data =
+        fs::read(source_path).map_err(|e| format!("Failed to read album art file: {}", e))?;
+    save_album_art(album_id, &image_data)
+}
+
+/// Give albums created by a regroup the art of the album their tracks came
+/// from. Returns how many were copied.
+pub fn copy_regrouped_album_art(conn: &Connection, art_to_copy: &[(i64, String)]) -> usize {
+    let mut copied = 0;
+    for (album_id, source_path) in art_to_copy {
+        match copy_album_art(*album_id, source_path) {
+            Ok(path) => {
+                if crate::db::queries::update_album_art_path(conn, *album_id, Some(&path)).is_ok() {
+                    copied += 1;
+                }
+            }
+            Err(e) => log::warn!("[Covers] Album {}: {}", album_id, e),
+        }
+    }
+    copied
+}
+
 /// Save album art from base64 string (for migration)
 pub fn save_album_art_from_base64(album_id: i64, base64_data: &str) -> Result<String, String> {
     // Decode base64
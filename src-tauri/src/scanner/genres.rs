@@ -0,0 +1,159 @@
+// Genre normalization: multi-value genre tags and an alias map
+//
+// A genre tag can hold several genres ("Rock; Pop", "Rock/Pop", separate
+// values joined by `artists::join_tag_values`). Each one is trimmed and
+// looked up in the alias map, so "Hip Hop", "hiphop" and "Hip-Hop" browse
+// as one genre. User aliases (stored in `library_settings`) override the
+// built-in ones.
+use std::collections::HashMap;
+
+/// Separators between genres in one tag value.
+const GENRE_SEPARATORS: &[char] = &[';', '/', ',', '|', '\0'];
+
+/// Built-in aliases: lowercase spelling -> canonical name.
+const BUILTIN_ALIASES: &[(&str, &str)] = &[
+    ("hip hop", "Hip-Hop"),
+    ("hiphop", "Hip-Hop"),
+    ("hip-hop", "Hip-Hop"),
+    ("r&b", "R&B"),
+    ("rnb", "R&B"),
+    ("r'n'b", "R&B"),
+    ("r and b", "R&B"),
+    ("rhythm and blues", "R&B"),
+    ("rhythm & blues", "R&B"),
+    ("drum and bass", "Drum & Bass"),
+    ("drum & bass", "Drum & Bass"),
+    ("drum n bass", "Drum & Bass"),
+    ("drum'n'bass", "Drum & Bass"),
+    ("dnb", "Drum & Bass"),
+    ("d&b", "Drum & Bass"),
+    ("electronica", "Electronic"),
+    ("rock & roll", "Rock & Roll"),
+    ("rock and roll", "Rock & Roll"),
+    ("rock n roll", "Rock & Roll"),
+    ("rock'n'roll", "Rock & Roll"),
+    ("lo-fi", "Lo-Fi"),
+    ("lofi", "Lo-Fi"),
+    ("lo fi", "Lo-Fi"),
+    ("synthpop", "Synth-Pop"),
+    ("synth pop", "Synth-Pop"),
+    ("synth-pop", "Synth-Pop"),
+    ("k-pop", "K-Pop"),
+    ("kpop", "K-Pop"),
+    ("j-pop", "J-Pop"),
+    ("jpop", "J-Pop"),
+    ("soundtrack", "Soundtrack"),
+    ("ost", "Soundtrack"),
+    ("classical music", "Classical"),
+];
+
+/// Lowercase alias -> canonical genre name.
+#[derive(Debug, Clone)]
+pub struct GenreAliases {
+    map: HashMap<String, String>,
+}
+
+impl Default for GenreAliases {
+    fn default() -> Self {
+        Self::new(&HashMap::new())
+    }
+}
+
+impl GenreAliases {
+    /// Built-in aliases with `user` entries layered on top.
+    pub fn new(user: &HashMap<String, String>) -> Self {
+        let mut map: HashMap<String, String> = BUILTIN_ALIASES
+            .iter()
+            .map(|(alias, name)| (alias.to_string(), name.to_string()))
+            .collect();
+        for (alias, name) in user {
+            let alias = alias.trim().to_lowercase();
+            let name = name.trim();
+            if !alias.is_empty() && !name.is_empty() {
+                map.insert(alias, name.to_string());
+            }
+        }
+        Self { map }
+    }
+
+    pub fn canonical(&self, genre: &str) -> String {
+        let genre = genre.trim();
+        match self.map.get(&genre.to_lowercase()) {
+            Some(name) => name.clone(),
+            None => genre.to_string(),
+        }
+    }
+}
+
+/// Split a stored genre value into canonical genres, without duplicates.
+pub fn split_genres(value: &str, aliases: &GenreAliases) -> Vec<String> {
+    let mut genres: Vec<String> = Vec::new();
+    for part in value.split(GENRE_SEPARATORS) {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        // "R&B" and "Drum & Bass" must not split; only whole-value aliases
+        // are checked, so '&' is never a separator
+        let genre = aliases.canonical(part);
+        if !genres.iter().any(|g| g.eq_ignore_ascii_case(&genre)) {
+            genres.push(genre);
+        }
+    }
+    genres
+}
+
+/// Year of a date tag: "1994", "1994-05-01", "1994/05" -> 1994.
+pub fn parse_year(value: &str) -> Option<i32> {
+    let value = value.trim();
+    let digits: String = value.chars().take(4).collect();
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // More digits only make sense as a compact date ("19940501")
+    let rest = &value[digits.len()..];
+    if rest.len() != 4 && rest.chars().next().is_some_and(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits
+        .parse()
+        .ok()
+        .filter(|year| (1000..=2999).contains(year))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_genres() {
+        let aliases = GenreAliases::default();
+        assert_eq!(split_genres("Rock; Pop", &aliases), vec!["Rock", "Pop"]);
+        assert_eq!(
+            split_genres("Rock/Pop, rock", &aliases),
+            vec!["Rock", "Pop"]
+        );
+
+        let aliases = GenreAliases::new(&HashMap::from([(
+            "Alt Rock".to_string(),
+            "Alternative Rock".to_string(),
+        )]));
+        assert_eq!(
+            split_genres("hip hop; Hip-Hop; R&B; alt rock", &aliases),
+            vec!["Hip-Hop", "R&B", "Alternative Rock"]
+        );
+        assert_eq!(split_genres("Drum & Bass", &aliases), vec!["Drum & Bass"]);
+        assert!(split_genres(" ; ", &aliases).is_empty());
+    }
+
+    #[test]
+    fn test_parse_year() {
+        assert_eq!(parse_year("1994"), Some(1994));
+        assert_eq!(parse_year("1994-05-01"), Some(1994));
+        assert_eq!(parse_year("19940501"), Some(1994));
+        assert_eq!(parse_year(" 2003/07 "), Some(2003));
+        assert_eq!(parse_year("12345"), None);
+        assert_eq!(parse_year("94"), None);
+        assert_eq!(parse_year("0000"), None);
+    }
+}
@@ -0,0 +1,217 @@
+// Album grouping: which album artist a track is filed under
+//
+// Albums are keyed on (album, album artist, release MBID). The album artist
+// comes from the AlbumArtist tag when present; compilations (TCMP/cpil or an
+// AlbumArtist of "Various Artists") are filed according to the
+// `VariousArtistsPolicy`; otherwise the track artist is used with any
+// "feat." guests removed, so guest spots don't split an album.
+use serde::{Deserialize, Serialize};
+
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+
+/// Spellings taggers use for "Various Artists".
+const VARIOUS_ARTISTS_ALIASES: &[&str] = &[
+    "various artists",
+    "various artist",
+    "various",
+    "va",
+    "v.a.",
+    "v/a",
+    "v.a",
+    "various interprets",
+    "verschiedene interpreten",
+    "artistes divers",
+    "varios artistas",
+];
+
+/// Separators that introduce guest artists in a track artist.
+const FEATURING_MARKERS: &[&str] = &[" feat. ", " feat ", " ft. ", " ft ", " featuring "];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariousArtistsPolicy {
+    /// Every compilation is filed under "Various Artists".
+    #[default]
+    Group,
+    /// A compilation keeps a specific AlbumArtist (a DJ, label or curator);
+    /// only those without one, or tagged with a "Various Artists" alias, are
+    /// filed under "Various Artists".
+    KeepAlbumArtist,
+}
+
+impl VariousArtistsPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Group => "group",
+            Self::KeepAlbumArtist => "keep_album_artist",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "group" => Some(Self::Group),
+            "keep_album_artist" => Some(Self::KeepAlbumArtist),
+            _ => None,
+        }
+    }
+}
+
+pub fn is_various_artists(name: &str) -> bool {
+    let name = name.trim().to_lowercase();
+    VARIOUS_ARTISTS_ALIASES.contains(&name.as_str())
+}
+
+/// Truthy values of compilation flags (TCMP "1", cpil, COMPILATION=true).
+pub fn parse_compilation_flag(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "1" | "true" | "yes" | "y"
+    )
+}
+
+/// The main artist of a track artist string, without featured guests:
+/// "A feat. B" and "A (feat. B)" both give "A".
+pub fn primary_artist(artist: &str) -> &str {
+    let lower = artist.to_lowercase();
+    let mut end = artist.len();
+
+    for marker in FEATURING_MARKERS {
+        if let Some(pos) = lower.find(marker) {
+            end = end.min(pos);
+        }
+    }
+    // Bracketed guests: "A (feat. B)", "A [ft. B]"
+    for open in ["(", "["] {
+        for marker in ["feat.", "feat ", "ft.", "ft ", "featuring "] {
+            if let Some(pos) = lower.find(&format!("{}{}", open, marker)) {
+                end = end.min(pos);
+            }
+        }
+    }
+
+    // `lower` can differ in byte length for some characters; only cut at a
+    // boundary that's valid in the original
+    if !artist.is_char_boundary(end) {
+        return artist.trim();
+    }
+    let primary = artist[..end].trim();
+    if primary.is_empty() {
+        artist.trim()
+    } else {
+        primary
+    }
+}
+
+/// The album artist a track is grouped under, or None when nothing names one.
+pub fn resolve_album_artist(
+    album_artist: Option<&str>,
+    artist: Option<&str>,
+    compilation: bool,
+    policy: VariousArtistsPolicy,
+) -> Option<String> {
+    let album_artist = album_artist.map(str::trim).filter(|s| !s.is_empty());
+
+    if let Some(name) = album_artist {
+        if is_various_artists(name) {
+            return Some(VARIOUS_ARTISTS.to_string());
+        }
+        if compilation && policy == VariousArtistsPolicy::Group {
+            return Some(VARIOUS_ARTISTS.to_string());
+        }
+        return Some(name.to_string());
+    }
+    if compilation {
+        return Some(VARIOUS_ARTISTS.to_string());
+    }
+
+    artist
+        .map(primary_artist)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primary_artist() {
+        assert_eq!(primary_artist("Daft Punk"), "Daft Punk");
+        assert_eq!(
+            primary_artist("Daft Punk feat. Pharrell Williams"),
+            "Daft Punk"
+        );
+        assert_eq!(primary_artist("Calvin Harris ft. Rihanna"), "Calvin Harris");
+        assert_eq!(
+            primary_artist("Mark Ronson (feat. Bruno Mars)"),
+            "Mark Ronson"
+        );
+        assert_eq!(primary_artist("Artist [Featuring Guest]"), "Artist");
+        assert_eq!(primary_artist("Simon & Garfunkel"), "Simon & Garfunkel");
+        // "Feat" inside a name is not a separator
+        assert_eq!(primary_artist("Feather"), "Feather");
+        assert_eq!(primary_artist("  "), "");
+    }
+
+    #[test]
+    fn test_various_artists() {
+        assert!(is_various_artists("Various Artists"));
+        assert!(is_various_artists(" VA "));
+        assert!(is_various_artists("V.A."));
+        assert!(!is_various_artists("Vangelis"));
+        assert!(parse_compilation_flag("1"));
+        assert!(parse_compilation_flag("True"));
+        assert!(!parse_compilation_flag("0"));
+        assert!(!parse_compilation_flag(""));
+    }
+
+    #[test]
+    fn test_resolve_album_artist() {
+        use VariousArtistsPolicy::*;
+        let resolve = |aa, a, comp, policy| resolve_album_artist(aa, a, comp, policy);
+
+        // AlbumArtist wins over the track artist
+        assert_eq!(
+            resolve(Some("Queen"), Some("Freddie Mercury"), false, Group).as_deref(),
+            Some("Queen")
+        );
+        // Guests don't split an album
+        assert_eq!(
+            resolve(None, Some("Queen feat. David Bowie"), false, Group).as_deref(),
+            Some("Queen")
+        );
+        // Compilations
+        assert_eq!(
+            resolve(None, Some("Blur"), true, Group).as_deref(),
+            Some(VARIOUS_ARTISTS)
+        );
+        assert_eq!(
+            resolve(Some("VA"), Some("Blur"), false, Group).as_deref(),
+            Some(VARIOUS_ARTISTS)
+        );
+        assert_eq!(
+            resolve(Some("Ministry of Sound"), Some("Blur"), true, Group).as_deref(),
+            Some(VARIOUS_ARTISTS)
+        );
+        assert_eq!(
+            resolve(
+                Some("Ministry of Sound"),
+                Some("Blur"),
+                true,
+                KeepAlbumArtist
+            )
+            .as_deref(),
+            Some("Ministry of Sound")
+        );
+        assert_eq!(
+            resolve(None, Some("Blur"), true, KeepAlbumArtist).as_deref(),
+            Some(VARIOUS_ARTISTS)
+        );
+        assert_eq!(resolve(Some(" "), None, false, Group), None);
+
+        assert_eq!(
+            VariousArtistsPolicy::parse(KeepAlbumArtist.as_str()),
+            Some(KeepAlbumArtist)
+        );
+    }
+}
@@ -6,7 +6,14 @@ use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::path::Path;
 
+use crate::analysis::key::MusicalKey;
+use crate::analysis::tempo::parse_bpm_tag;
 use crate::db::queries::TrackInsert;
+use crate::scanner::artists::join_tag_values;
+use crate::scanner::genres::parse_year;
+use crate::scanner::grouping::parse_compilation_flag;
+use crate::scanner::walker::FileStamp;
+use crate::tag_editor;
 
 /// Generate a content hash based on metadata for duplicate detection
 fn generate_content_hash(
@@ -34,6 +41,16 @@ fn generate_content_hash(
 }
 
 pub fn extract_metadata(path: &str) -> Option<TrackInsert> {
+    // Stamp before reading, so a write that races the read shows up as a
+    // change on the next rescan
+    let stamp = FileStamp::of(Path::new(path)).ok();
+    let mut track = read_metadata(path)?;
+    track.file_mtime = stamp.map(|s| s.mtime_millis());
+    track.file_size = stamp.map(|s| s.size as i64);
+    Some(track)
+}
+
+fn read_metadata(path: &str) -> Option<TrackInsert> {
     let path = Path::new(path);
 
     // Try to read the file
@@ -97,6 +114,24 @@ pub fn extract_metadata(path: &str) -> Option<TrackInsert> {
             let artist = tag.artist().map(|s| s.to_string());
             let album = tag.album().map(|s| s.to_string());
 
+            // Multi-value artist credits, joined for storage (see `scanner::artists`)
+            let artists = join_tag_values(tag.get_strings(&ItemKey::TrackArtist));
+            let composer = join_tag_values(tag.get_strings(&ItemKey::Composer));
+            let remixer = join_tag_values(tag.get_strings(&ItemKey::Remixer));
+
+            // Browsing columns: genres (multi-value), release year and label
+            let genre = join_tag_values(tag.get_strings(&ItemKey::Genre));
+            let year = tag
+                .get_string(&ItemKey::Year)
+                .or_else(|| tag.get_string(&ItemKey::RecordingDate))
+                .and_then(parse_year);
+            let label = tag
+                .get_string(&ItemKey::Label)
+                .or_else(|| tag.get_string(&ItemKey::Publisher))
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string());
+
             // Extract track number, handling both simple numbers and "X/Y" format
             let track_number = tag.track().map(|n| n as i32).or_else(|| {
                 // If tag.track() fails, try to parse track number from text
@@ -133,17 +168,50 @@ pub fn extract_metadata(path: &str) -> Option<TrackInsert> {
             ));
 
             // Extract MusicBrainz Recording ID for ListenBrainz matching
+            // (MUSICBRAINZ_TRACKID / "MusicBrainz Track Id" hold the recording)
             let musicbrainz_recording_id = tag
-                .get_string(&ItemKey::MusicBrainzTrackId)
+                .get_string(&ItemKey::MusicBrainzRecordingId)
+                .or_else(|| tag.get_string(&ItemKey::MusicBrainzTrackId))
+                .map(|s| s.to_string());
+
+            // Album grouping: AlbumArtist, compilation flag and release MBID
+            let album_artist = tag
+                .get_string(&ItemKey::AlbumArtist)
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string());
+            let compilation = tag
+                .get_string(&ItemKey::FlagCompilation)
+                .is_some_and(parse_compilation_flag);
+            let musicbrainz_release_id = tag
+                .get_string(&ItemKey::MusicBrainzReleaseId)
+                .map(|s| s.to_string());
+            let musicbrainz_release_group_id = tag
+                .get_string(&ItemKey::MusicBrainzReleaseGroupId)
+                .map(|s| s.to_string());
+            let musicbrainz_artist_id = tag
+                .get_string(&ItemKey::MusicBrainzArtistId)
                 .map(|s| s.to_string());
 
+            // Tempo and key tags (TBPM/TKEY, BPM/INITIALKEY, tmpo) for DJ sorting
+            let bpm = tag
+                .get_string(&ItemKey::Bpm)
+                .or_else(|| tag.get_string(&ItemKey::IntegerBpm))
+                .and_then(parse_bpm_tag);
+            let musical_key = tag
+                .get_string(&ItemKey::InitialKey)
+                .and_then(MusicalKey::parse)
+                .map(|k| k.to_string());
+
             // Extract all available metadata keys into JSON
             let metadata_json = collect_all_metadata(tag);
 
             Some(TrackInsert {
                 path: path.to_string_lossy().to_string(),
                 title,
-                artist,
+                // The content hash above keeps using the first artist, so
+                // joining values doesn't change track identity
+                artist: artists.or(artist),
                 album,
                 track_number,
                 disc_number,
@@ -159,6 +227,21 @@ pub fn extract_metadata(path: &str) -> Option<TrackInsert> {
                 local_src: None,
                 musicbrainz_recording_id,
                 metadata_json,
+                bpm,
+                musical_key,
+                file_mtime: None,
+                file_size: None,
+                album_artist,
+                compilation,
+                musicbrainz_release_id,
+                musicbrainz_release_group_id,
+                musicbrainz_artist_id,
+                composer,
+                remixer,
+                genre,
+                year,
+                label,
+                rating: tag_editor::rating_in_tag(tag),
             })
         }
         None => {
@@ -189,7 +272,9 @@ fn collect_all_metadata(tag: &LoftyTag) -> Option<String> {
         ItemKey::TrackArtist,
         ItemKey::AlbumTitle,
         ItemKey::AlbumArtist,
+        ItemKey::FlagCompilation,
         ItemKey::Composer,
+        ItemKey::Remixer,
         ItemKey::Genre,
         ItemKey::TrackNumber,
         ItemKey::TrackTotal,
@@ -197,6 +282,7 @@ fn collect_all_metadata(tag: &LoftyTag) -> Option<String> {
         ItemKey::DiscTotal,
         ItemKey::Year,
         ItemKey::Bpm,
+        ItemKey::InitialKey,
         ItemKey::Isrc,
         ItemKey::Label,
         ItemKey::CatalogNumber,
@@ -241,6 +327,21 @@ fn create_fallback_metadata(path: &Path) -> TrackInsert {
         local_src: None,
         musicbrainz_recording_id: None,
         metadata_json: None,
+        bpm: None,
+        musical_key: None,
+        file_mtime: None,
+        file_size: None,
+        album_artist: None,
+        compilation: false,
+        musicbrainz_release_id: None,
+        composer: None,
+        remixer: None,
+        genre: None,
+        year: None,
+        label: None,
+        musicbrainz_release_group_id: None,
+        musicbrainz_artist_id: None,
+        rating: None,
     }
 }
 
@@ -264,10 +365,56 @@ fn extract_flac_metadata_fallback(path: &Path, _duration_hint: Option<i32>) -> O
                 .and_then(|v| v.title().map(|s| s[0].clone()))
                 .or_else(|| get_filename_without_ext(path));
             let artist = vorbis.and_then(|v| v.artist().map(|s| s[0].clone()));
+            let artists = vorbis.and_then(|v| {
+                v.artist()
+                    .and_then(|values| join_tag_values(values.iter().map(String::as_str)))
+            });
+            let composer = vorbis.and_then(|v| {
+                v.get("COMPOSER")
+                    .and_then(|values| join_tag_values(values.iter().map(String::as_str)))
+            });
+            let remixer = vorbis.and_then(|v| {
+                v.get("REMIXER")
+                    .and_then(|values| join_tag_values(values.iter().map(String::as_str)))
+            });
+            let genre = vorbis.and_then(|v| {
+                v.get("GENRE")
+                    .and_then(|values| join_tag_values(values.iter().map(String::as_str)))
+            });
+            let year = vorbis.and_then(|v| {
+                v.get("DATE")
+                    .or_else(|| v.get("YEAR"))
+                    .and_then(|d| parse_year(&d[0]))
+            });
+            let label = vorbis.and_then(|v| {
+                v.get("LABEL")
+                    .or_else(|| v.get("ORGANIZATION"))
+                    .or_else(|| v.get("PUBLISHER"))
+                    .map(|l| l[0].trim().to_string())
+                    .filter(|l| !l.is_empty())
+            });
             let album = vorbis.and_then(|v| v.album().map(|s| s[0].clone()));
             let track_number = vorbis.and_then(|v| v.track().map(|n| n as i32));
             let disc_number =
                 vorbis.and_then(|v| v.get("DISCNUMBER").and_then(|d| d[0].parse::<i32>().ok()));
+            let bpm = vorbis.and_then(|v| v.get("BPM").and_then(|b| parse_bpm_tag(&b[0])));
+            let musical_key = vorbis.and_then(|v| {
+                v.get("INITIALKEY")
+                    .and_then(|k| MusicalKey::parse(&k[0]))
+                    .map(|k| k.to_string())
+            });
+            let album_artist = vorbis.and_then(|v| v.album_artist().map(|s| s[0].clone()));
+            let compilation = vorbis
+                .and_then(|v| v.get("COMPILATION"))
+                .is_some_and(|c| parse_compilation_flag(&c[0]));
+            let musicbrainz_release_id =
+                vorbis.and_then(|v| v.get("MUSICBRAINZ_ALBUMID").map(|id| id[0].clone()));
+            let musicbrainz_release_group_id =
+                vorbis.and_then(|v| v.get("MUSICBRAINZ_RELEASEGROUPID").map(|id| id[0].clone()));
+            let musicbrainz_artist_id =
+                vorbis.and_then(|v| v.get("MUSICBRAINZ_ARTISTID").map(|id| id[0].clone()));
+            let musicbrainz_recording_id =
+                vorbis.and_then(|v| v.get("MUSICBRAINZ_TRACKID").map(|id| id[0].clone()));
 
             // Extract picture
             let album_art = tag.pictures().next().map(|p| p.data.clone());
@@ -295,7 +442,7 @@ fn extract_flac_metadata_fallback(path: &Path, _duration_hint: Option<i32>) -> O
             Some(TrackInsert {
                 path: path.to_string_lossy().to_string(),
                 title,
-                artist,
+                artist: artists.or(artist),
                 album,
                 track_number,
                 disc_number,
@@ -309,8 +456,23 @@ fn extract_flac_metadata_fallback(path: &Path, _duration_hint: Option<i32>) -> O
                 external_id: None,
                 content_hash: content_hash,
                 local_src: None,
-                musicbrainz_recording_id: None,
+                musicbrainz_recording_id,
                 metadata_json: None,
+                bpm,
+                musical_key,
+                file_mtime: None,
+                file_size: None,
+                album_artist,
+                compilation,
+                musicbrainz_release_id,
+                musicbrainz_release_group_id,
+                musicbrainz_artist_id,
+                composer,
+                remixer,
+                genre,
+                year,
+                label,
+                rating: None,
             })
         }
         Err(e) => {
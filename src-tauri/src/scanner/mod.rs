@@ -1,7 +1,12 @@
 // Scanner module for file walking, metadata extraction, and cover storage
 pub mod walker;
 pub mod metadata;
+pub mod artists;
 pub mod cover_storage;
+pub mod genres;
+pub mod grouping;
+pub mod moves;
+pub mod rules;
 
-pub use walker::scan_directory;
+pub use walker::{scan_directory, scan_directory_with_rules, FileStamp};
 pub use metadata::extract_metadata;
\ No newline at end of file
@@ -0,0 +1,200 @@
+// Moved and renamed files
+//
+// A track whose file vanished keeps its row (and with it plays, likes and
+// playlist entries) when a file the library doesn't know yet is the same
+// file. Candidates are read through callbacks, so the caller can do the
+// slow part without holding the database lock.
+use crate::analysis::fingerprint;
+use crate::db::queries::MissingTrack;
+use crate::scanner::walker::FileStamp;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Content hash and duration of a file, as the scanner stores them.
+pub type FileIdentity = (Option<String>, Option<i32>);
+
+fn extension(path: &str) -> Option<String> {
+    Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+}
+
+/// Match the `missing` tracks against `new_files`, returning each matched
+/// track with its new path. A match needs the same extension, content hash
+/// and duration, and then proof that it is the same file: the recorded
+/// size and mtime (a plain move or rename keeps both), or failing that the
+/// track's stored fingerprint. Tracks with neither stay unmatched. Each
+/// candidate is read with `identify` and `fingerprint_file` at most once.
+pub fn find_moves(
+    missing: Vec<MissingTrack>,
+    mut new_files: Vec<String>,
+    stamps: &HashMap<String, FileStamp>,
+    mut identify: impl FnMut(&str) -> Option<FileIdentity>,
+    mut fingerprint_file: impl FnMut(&str) -> Option<Vec<u32>>,
+) -> Vec<(MissingTrack, String)> {
+    let mut identities: HashMap<String, Option<FileIdentity>> = HashMap::new();
+    let mut fingerprints: HashMap<String, Option<Vec<u32>>> = HashMap::new();
+    let mut moves = Vec::new();
+
+    for track in missing {
+        if new_files.is_empty() {
+            break;
+        }
+        let Some(hash) = track.content_hash.as_deref() else {
+            continue;
+        };
+        let ext = extension(&track.path);
+        let stored_fingerprint = track.fingerprint.as_deref().map(fingerprint::from_bytes);
+
+        let found = new_files.iter().position(|path| {
+            if extension(path) != ext {
+                return false;
+            }
+            let stamp = stamps.get(path).copied();
+            if matches!((track.file_size, stamp), (Some(a), Some(b)) if a != b.size as i64) {
+                return false;
+            }
+            let identity = identities
+                .entry(path.clone())
+                .or_insert_with(|| identify(path));
+            let Some((Some(candidate_hash), duration)) = identity else {
+                return false;
+            };
+            if candidate_hash.as_str() != hash || *duration != track.duration {
+                return false;
+            }
+            if stamp.is_some_and(|s| s.matches((track.file_mtime, track.file_size))) {
+                return true;
+            }
+            let Some(stored) = &stored_fingerprint else {
+                return false;
+            };
+            fingerprints
+                .entry(path.clone())
+                .or_insert_with(|| fingerprint_file(path))
+                .as_deref()
+                .is_some_and(|fp| {
+                    fingerprint::similarity(stored, fp) >= fingerprint::DUPLICATE_THRESHOLD
+                })
+        });
+
+        if let Some(index) = found {
+            let to = new_files.swap_remove(index);
+            moves.push((track, to));
+        }
+    }
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::queries;
+    use rusqlite::Connection;
+
+    const STAMP: FileStamp = FileStamp {
+        size: 4096,
+        mtime_secs: 1_700_000_000,
+        mtime_nanos: 0,
+    };
+
+    fn library() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::schema::init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn track(conn: &Connection, path: &str, fingerprint: Option<&[u32]>) -> i64 {
+        conn.execute(
+            "INSERT INTO tracks (path, content_hash, duration, file_mtime, file_size, fingerprint)
+             VALUES (?1, 'hash', 200, ?2, ?3, ?4)",
+            rusqlite::params![
+                path,
+                STAMP.mtime_millis(),
+                STAMP.size as i64,
+                fingerprint.map(fingerprint::to_bytes)
+            ],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    fn audio(seed: u32) -> Vec<u32> {
+        (0..200u32)
+            .map(|i| i.wrapping_mul(2_654_435_761).rotate_left(seed))
+            .collect()
+    }
+
+    /// Moves of `track_ids` among `files`, whose stamps are given; every
+    /// file reads as the same tags and sounds like `audio(1)`.
+    fn moves(
+        conn: &Connection,
+        track_ids: &[i64],
+        files: &[(&str, FileStamp)],
+    ) -> Vec<(i64, String)> {
+        let stamps = files
+            .iter()
+            .map(|(path, stamp)| (path.to_string(), *stamp))
+            .collect();
+        let new_files = files.iter().map(|(path, _)| path.to_string()).collect();
+        let missing = queries::get_missing_tracks(conn, track_ids).unwrap();
+        find_moves(
+            missing,
+            new_files,
+            &stamps,
+            |_| Some((Some("hash".to_string()), Some(200))),
+            |_| Some(audio(1)),
+        )
+        .into_iter()
+        .map(|(track, to)| (track.id, to))
+        .collect()
+    }
+
+    #[test]
+    fn test_move_with_same_stamp() {
+        let conn = library();
+        let id = track(&conn, "/music/old/song.mp3", None);
+        assert_eq!(
+            moves(&conn, &[id], &[("/music/new/song.mp3", STAMP)]),
+            vec![(id, "/music/new/song.mp3".to_string())]
+        );
+        // Another format is never the same file
+        assert!(moves(&conn, &[id], &[("/music/new/song.flac", STAMP)]).is_empty());
+    }
+
+    #[test]
+    fn test_changed_stamp_needs_fingerprint() {
+        let conn = library();
+        let copied = FileStamp {
+            mtime_secs: STAMP.mtime_secs + 60,
+            ..STAMP
+        };
+        let plain = track(&conn, "/music/a.mp3", None);
+        assert!(moves(&conn, &[plain], &[("/music/b.mp3", copied)]).is_empty());
+
+        let same = track(&conn, "/music/c.mp3", Some(&audio(1)));
+        assert_eq!(
+            moves(&conn, &[same], &[("/music/d.mp3", copied)]),
+            vec![(same, "/music/d.mp3".to_string())]
+        );
+
+        let other = track(&conn, "/music/e.mp3", Some(&audio(7)));
+        assert!(moves(&conn, &[other], &[("/music/f.mp3", copied)]).is_empty());
+
+        // A different size rules a file out before it is read
+        let resized = FileStamp {
+            size: STAMP.size + 1,
+            ..copied
+        };
+        assert!(moves(&conn, &[same], &[("/music/g.mp3", resized)]).is_empty());
+    }
+
+    #[test]
+    fn test_each_file_matches_once() {
+        let conn = library();
+        let first = track(&conn, "/music/one/song.mp3", None);
+        let second = track(&conn, "/music/two/song.mp3", None);
+        let found = moves(&conn, &[first, second], &[("/music/three/song.mp3", STAMP)]);
+        assert_eq!(found.len(), 1);
+    }
+}
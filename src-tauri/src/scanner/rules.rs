@@ -0,0 +1,343 @@
+// Library exclusion rules
+//
+// Each music folder can carry a rule set: gitignore-style include/exclude
+// patterns rooted at the folder, plus minimum file size and duration. On top
+// of that, `.audionignore` files anywhere in the tree are honoured with
+// gitignore semantics (deeper files take precedence, `!pattern` re-includes).
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::walker::{scan_directory, scan_directory_with_rules, ScanResult};
+
+pub const IGNORE_FILE_NAME: &str = ".audionignore";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanRules {
+    /// When non-empty, only files matching one of these are imported.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub min_size_bytes: Option<u64>,
+    /// Checked when a file's metadata is read, not during the walk.
+    pub min_duration_secs: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExclusionReason {
+    ExcludePattern { pattern: String },
+    NotIncluded,
+    IgnoreFile { file: String, pattern: String },
+    TooSmall { size: u64 },
+    TooShort { duration: i32 },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExcludedFile {
+    /// A file, or a directory whose whole subtree was skipped.
+    pub path: String,
+    pub reason: ExclusionReason,
+}
+
+/// A compiled rule set for one folder.
+pub struct RuleMatcher {
+    root: PathBuf,
+    include: Option<Gitignore>,
+    exclude: Gitignore,
+    min_size_bytes: Option<u64>,
+    min_duration_secs: Option<u32>,
+    /// Parsed `.audionignore` per directory (None when there is none).
+    ignore_files: Mutex<HashMap<PathBuf, Option<Gitignore>>>,
+}
+
+impl RuleMatcher {
+    pub fn new(root: impl AsRef<Path>, rules: &ScanRules) -> Result<Self, String> {
+        let root = root.as_ref().to_path_buf();
+        let include = if rules.include.is_empty() {
+            None
+        } else {
+            Some(build_patterns(&root, &rules.include)?)
+        };
+        Ok(Self {
+            exclude: build_patterns(&root, &rules.exclude)?,
+            include,
+            min_size_bytes: rules.min_size_bytes,
+            min_duration_secs: rules.min_duration_secs,
+            ignore_files: Mutex::new(HashMap::new()),
+            root,
+        })
+    }
+
+    /// No rules of its own; only `.audionignore` files apply.
+    pub fn ignore_files_only(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            include: None,
+            exclude: Gitignore::empty(),
+            min_size_bytes: None,
+            min_duration_secs: None,
+            ignore_files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn min_duration_secs(&self) -> Option<u32> {
+        self.min_duration_secs
+    }
+
+    /// Why `path` should be skipped, if it should. Directories are checked
+    /// against ignore files and exclude patterns only; `size` is ignored
+    /// for them.
+    pub fn check(&self, path: &Path, is_dir: bool, size: u64) -> Option<ExclusionReason> {
+        if path == self.root || !path.starts_with(&self.root) {
+            return None;
+        }
+
+        if let Some(reason) = self.check_ignore_files(path, is_dir) {
+            return Some(reason);
+        }
+        if let Match::Ignore(glob) = self.exclude.matched_path_or_any_parents(path, is_dir) {
+            return Some(ExclusionReason::ExcludePattern {
+                pattern: glob.original().to_string(),
+            });
+        }
+        if is_dir {
+            return None;
+        }
+        if let Some(include) = &self.include {
+            if !include.matched_path_or_any_parents(path, false).is_ignore() {
+                return Some(ExclusionReason::NotIncluded);
+            }
+        }
+        match self.min_size_bytes {
+            Some(min) if size < min => Some(ExclusionReason::TooSmall { size }),
+            _ => None,
+        }
+    }
+
+    /// Duration check, for once a file's metadata has been read.
+    pub fn check_duration(&self, duration_secs: Option<i32>) -> Option<ExclusionReason> {
+        match (self.min_duration_secs, duration_secs) {
+            (Some(min), Some(duration)) if duration < min as i32 => {
+                Some(ExclusionReason::TooShort { duration })
+            }
+            _ => None,
+        }
+    }
+
+    /// Ignore files from the closest directory up to the root; the first one
+    /// with an opinion on `path` (ignore or `!` re-include) decides.
+    fn check_ignore_files(&self, path: &Path, is_dir: bool) -> Option<ExclusionReason> {
+        let mut cache = self.ignore_files.lock().ok()?;
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(&self.root) {
+                break;
+            }
+            let ignore = cache
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| load_ignore_file(dir));
+            match ignore.as_ref().map(|gi| gi.matched_path_or_any_parents(path, is_dir)) {
+                Some(Match::Ignore(glob)) => {
+                    return Some(ExclusionReason::IgnoreFile {
+                        file: dir.join(IGNORE_FILE_NAME).to_string_lossy().to_string(),
+                        pattern: glob.original().to_string(),
+                    })
+                }
+                Some(Match::Whitelist(_)) => return None,
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// The rule sets of all music folders.
+#[derive(Default)]
+pub struct LibraryRules {
+    matchers: Vec<RuleMatcher>,
+}
+
+impl LibraryRules {
+    /// Compile each folder's rules. A folder with invalid patterns falls back
+    /// to ignore files only, and the error is returned alongside.
+    pub fn new(folders: &[(String, ScanRules)]) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+        let matchers = folders
+            .iter()
+            .map(|(folder, rules)| {
+                RuleMatcher::new(folder, rules).unwrap_or_else(|e| {
+                    errors.push(format!("Invalid scan rules for {}: {}", folder, e));
+                    RuleMatcher::ignore_files_only(folder)
+                })
+            })
+            .collect();
+        (Self { matchers }, errors)
+    }
+
+    /// Matcher of the innermost folder containing `path`.
+    pub fn matcher_for(&self, path: &Path) -> Option<&RuleMatcher> {
+        self.matchers
+            .iter()
+            .filter(|m| path.starts_with(&m.root))
+            .max_by_key(|m| m.root.components().count())
+    }
+
+    /// Walk `path` with the rules of the folder containing it.
+    pub fn scan(&self, path: &str) -> ScanResult {
+        match self.matcher_for(Path::new(path)) {
+            Some(matcher) => scan_directory_with_rules(path, matcher),
+            None => scan_directory(path),
+        }
+    }
+
+    /// `(folder, minimum duration)` for folders that set one.
+    pub fn min_durations(&self) -> Vec<(String, u32)> {
+        self.matchers
+            .iter()
+            .filter_map(|m| {
+                let min = m.min_duration_secs?;
+                Some((m.root.to_string_lossy().to_string(), min))
+            })
+            .collect()
+    }
+}
+
+/// Minimum duration that applies to `path`, from `LibraryRules::min_durations`.
+pub fn min_duration_for(min_durations: &[(String, u32)], path: &str) -> Option<u32> {
+    min_durations
+        .iter()
+        .filter(|(root, _)| Path::new(path).starts_with(root))
+        .max_by_key(|(root, _)| root.len())
+        .map(|(_, min)| *min)
+}
+
+fn build_patterns(root: &Path, patterns: &[String]) -> Result<Gitignore, String> {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        builder
+            .add_line(None, pattern)
+            .map_err(|e| format!("'{}': {}", pattern, e))?;
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+fn load_ignore_file(dir: &Path) -> Option<Gitignore> {
+    let file = dir.join(IGNORE_FILE_NAME);
+    if !file.is_file() {
+        return None;
+    }
+    let (ignore, error) = Gitignore::new(&file);
+    if let Some(e) = error {
+        log::warn!("[SCAN] Problem in {:?}: {}", file, e);
+    }
+    Some(ignore)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn rules(include: &[&str], exclude: &[&str]) -> ScanRules {
+        ScanRules {
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_patterns() {
+        let root = Path::new("/music");
+        let m = RuleMatcher::new(root, &rules(&[], &["Samples/", "*stem*.wav"])).unwrap();
+        assert!(m.check(Path::new("/music/Samples"), true, 0).is_some());
+        assert!(m.check(Path::new("/music/a/Samples/kick.wav"), false, 0).is_some());
+        assert!(m.check(Path::new("/music/a/vocal_stem_1.wav"), false, 0).is_some());
+        assert!(m.check(Path::new("/music/a/song.wav"), false, 0).is_none());
+        // Outside the root nothing applies
+        assert!(m.check(Path::new("/other/Samples/x.wav"), false, 0).is_none());
+
+        let m = RuleMatcher::new(root, &rules(&["*.flac", "Vinyl/"], &[])).unwrap();
+        assert!(m.check(Path::new("/music/a/b.flac"), false, 0).is_none());
+        assert!(m.check(Path::new("/music/Vinyl/side-a.wav"), false, 0).is_none());
+        assert_eq!(
+            m.check(Path::new("/music/a/b.mp3"), false, 0),
+            Some(ExclusionReason::NotIncluded)
+        );
+        // Include patterns never prune directories
+        assert!(m.check(Path::new("/music/a"), true, 0).is_none());
+
+        assert!(RuleMatcher::new(root, &rules(&[], &["{a,b"])).is_err());
+    }
+
+    #[test]
+    fn test_size_and_duration() {
+        let m = RuleMatcher::new(
+            "/music",
+            &ScanRules {
+                min_size_bytes: Some(1000),
+                min_duration_secs: Some(30),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let song = Path::new("/music/a.mp3");
+        assert_eq!(m.check(song, false, 10), Some(ExclusionReason::TooSmall { size: 10 }));
+        assert!(m.check(song, false, 5000).is_none());
+        assert_eq!(m.check_duration(Some(4)), Some(ExclusionReason::TooShort { duration: 4 }));
+        assert!(m.check_duration(Some(30)).is_none());
+        assert!(m.check_duration(None).is_none());
+    }
+
+    #[test]
+    fn test_ignore_files() {
+        let root = std::env::temp_dir().join(format!("audion_rules_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Album/Stems")).unwrap();
+        fs::write(root.join(IGNORE_FILE_NAME), "Stems/\n*.m4a\n").unwrap();
+        fs::write(root.join("Album").join(IGNORE_FILE_NAME), "!keep.m4a\n").unwrap();
+
+        let m = RuleMatcher::ignore_files_only(&root);
+        assert!(matches!(
+            m.check(&root.join("Album/Stems"), true, 0),
+            Some(ExclusionReason::IgnoreFile { .. })
+        ));
+        // Checked from a nested path directly, as the folder watcher does
+        assert!(m.check(&root.join("Album/Stems/bass.wav"), false, 0).is_some());
+        assert!(m.check(&root.join("Album/memo.m4a"), false, 0).is_some());
+        assert!(m.check(&root.join("Album/keep.m4a"), false, 0).is_none());
+        assert!(m.check(&root.join("Album/song.mp3"), false, 0).is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_library_rules() {
+        let (rules, errors) = LibraryRules::new(&[
+            ("/music".to_string(), rules(&[], &["x/"])),
+            (
+                "/music/live".to_string(),
+                ScanRules {
+                    min_duration_secs: Some(60),
+                    ..Default::default()
+                },
+            ),
+            ("/bad".to_string(), rules(&["{a,b"], &[])),
+        ]);
+        assert_eq!(errors.len(), 1);
+        let m = rules.matcher_for(Path::new("/music/live/a.mp3")).unwrap();
+        assert_eq!(m.root(), Path::new("/music/live"));
+        assert!(rules.matcher_for(Path::new("/elsewhere/a.mp3")).is_none());
+
+        let mins = rules.min_durations();
+        assert_eq!(min_duration_for(&mins, "/music/live/set.flac"), Some(60));
+        assert_eq!(min_duration_for(&mins, "/music/a.flac"), None);
+    }
+}
@@ -1,33 +1,123 @@
 // Directory walking and file discovery
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
 use std::path::Path;
+use std::time::UNIX_EPOCH;
 use walkdir::WalkDir;
 
+use super::rules::{ExcludedFile, RuleMatcher};
+
 const SUPPORTED_EXTENSIONS: &[&str] = &["flac", "mp3", "wav", "ogg", "m4a", "aac"];
 
+/// Size + mtime of a file, used to detect modifications: a rescan only
+/// re-reads files whose stamp differs from the one stored with the track,
+/// and cached waveforms are regenerated when their source's stamp changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    pub mtime_secs: i64,
+    pub mtime_nanos: u32,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Result<Self, String> {
+        let meta = fs::metadata(path).map_err(|e| format!("Failed to stat {:?}: {}", path, e))?;
+        Ok(Self::from_metadata(&meta))
+    }
+
+    fn from_metadata(metadata: &Metadata) -> Self {
+        let (mtime_secs, mtime_nanos) = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| (d.as_secs() as i64, d.subsec_nanos()))
+            .unwrap_or((0, 0));
+        Self {
+            size: metadata.len(),
+            mtime_secs,
+            mtime_nanos,
+        }
+    }
+
+    /// Modification time in milliseconds since the Unix epoch, the
+    /// precision stored with tracks.
+    pub fn mtime_millis(&self) -> i64 {
+        self.mtime_secs * 1000 + i64::from(self.mtime_nanos / 1_000_000)
+    }
+
+    /// Whether a stored `(mtime, size)` pair still describes this file.
+    pub fn matches(&self, stored: (Option<i64>, Option<i64>)) -> bool {
+        stored == (Some(self.mtime_millis()), Some(self.size as i64))
+    }
+}
+
 pub struct ScanResult {
     pub audio_files: Vec<String>,
+    /// Stamps of `audio_files`, taken during the walk.
+    pub stamps: HashMap<String, FileStamp>,
     pub total_scanned: usize,
+    /// Audio files and directories skipped by exclusion rules.
+    pub excluded: Vec<ExcludedFile>,
     pub errors: Vec<String>,
 }
 
+/// Walk `path`, honouring any `.audionignore` files in it.
 pub fn scan_directory(path: &str) -> ScanResult {
+    scan_directory_with_rules(path, &RuleMatcher::ignore_files_only(path))
+}
+
+/// Walk `path` (a directory or a single file) applying `rules`, whose root
+/// may be `path` itself or any folder above it.
+pub fn scan_directory_with_rules(path: &str, rules: &RuleMatcher) -> ScanResult {
     let mut audio_files = Vec::new();
+    let mut stamps = HashMap::new();
     let mut errors = Vec::new();
     let mut total_scanned = 0;
+    let excluded = RefCell::new(Vec::new());
 
-    for entry in WalkDir::new(path)
+    let walker = WalkDir::new(path)
         .follow_links(true)
         .into_iter()
-        .filter_map(|e| e.ok())
-    {
+        .filter_entry(|entry| {
+            if !entry.file_type().is_dir() {
+                return true;
+            }
+            // Prune excluded directories instead of walking them
+            match rules.check(entry.path(), true, 0) {
+                Some(reason) => {
+                    excluded.borrow_mut().push(ExcludedFile {
+                        path: entry.path().to_string_lossy().to_string(),
+                        reason,
+                    });
+                    false
+                }
+                None => true,
+            }
+        });
+
+    for entry in walker.filter_map(|e| e.ok()) {
         let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
         
-        if path.is_file() {
+        if metadata.is_file() {
             total_scanned += 1;
             
             if is_supported_audio_file(path) {
+                if let Some(reason) = rules.check(path, false, metadata.len()) {
+                    excluded.borrow_mut().push(ExcludedFile {
+                        path: path.to_string_lossy().to_string(),
+                        reason,
+                    });
+                    continue;
+                }
                 match path.to_str() {
-                    Some(path_str) => audio_files.push(path_str.to_string()),
+                    Some(path_str) => {
+                        stamps.insert(path_str.to_string(), FileStamp::from_metadata(&metadata));
+                        audio_files.push(path_str.to_string());
+                    }
                     None => errors.push(format!("Invalid path encoding: {:?}", path)),
                 }
             }
@@ -36,12 +126,14 @@ pub fn scan_directory(path: &str) -> ScanResult {
 
     ScanResult {
         audio_files,
+        stamps,
         total_scanned,
+        excluded: excluded.into_inner(),
         errors,
     }
 }
 
-fn is_supported_audio_file(path: &Path) -> bool {
+pub fn is_supported_audio_file(path: &Path) -> bool {
     path.extension()
         .and_then(|ext| ext.to_str())
         .map(|ext| SUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
@@ -66,4 +158,65 @@ mod tests {
         assert!(!is_supported_audio_file(Path::new("song.mp4")));
         assert!(!is_supported_audio_file(Path::new("song.txt")));
     }
+
+    #[test]
+    fn test_scan_directory_stamps() {
+        let dir = std::env::temp_dir().join(format!("audion_walker_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let song = dir.join("song.mp3");
+        std::fs::write(&song, b"not really audio").unwrap();
+        std::fs::write(dir.join("cover.jpg"), b"jpg").unwrap();
+
+        let result = scan_directory(dir.to_str().unwrap());
+        assert_eq!(result.audio_files, vec![song.to_string_lossy().to_string()]);
+        let stamp = result.stamps[&result.audio_files[0]];
+        assert_eq!(stamp.size, 16);
+        assert_eq!(FileStamp::of(&song), Ok(stamp));
+        assert!(stamp.matches((Some(stamp.mtime_millis()), Some(16))));
+        assert!(!stamp.matches((Some(stamp.mtime_millis()), Some(17))));
+        assert!(!stamp.matches((None, None)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_directory_with_rules() {
+        use crate::scanner::rules::{ScanRules, IGNORE_FILE_NAME};
+
+        let dir = std::env::temp_dir().join(format!("audion_walker_rules_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("Samples")).unwrap();
+        std::fs::create_dir_all(dir.join("Album")).unwrap();
+        std::fs::write(dir.join("Samples/kick.wav"), b"kick").unwrap();
+        std::fs::write(dir.join("Album/song.mp3"), b"a song").unwrap();
+        std::fs::write(dir.join("Album/tiny.mp3"), b"x").unwrap();
+        std::fs::write(dir.join("Album/memo.m4a"), b"a voice memo").unwrap();
+        std::fs::write(dir.join("Album").join(IGNORE_FILE_NAME), b"*.m4a\n").unwrap();
+
+        let rules = RuleMatcher::new(
+            &dir,
+            &ScanRules {
+                exclude: vec!["Samples/".into()],
+                min_size_bytes: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let result = scan_directory_with_rules(dir.to_str().unwrap(), &rules);
+        let song = dir.join("Album/song.mp3").to_string_lossy().to_string();
+        assert_eq!(result.audio_files, vec![song]);
+        let mut excluded: Vec<String> = result.excluded.into_iter().map(|e| e.path).collect();
+        excluded.sort();
+        assert_eq!(
+            excluded,
+            ["Album/memo.m4a", "Album/tiny.mp3", "Samples"]
+                .map(|p| dir.join(p).to_string_lossy().to_string())
+        );
+
+        // A single file is checked against the rules of its folder
+        let single = scan_directory_with_rules(dir.join("Album/memo.m4a").to_str().unwrap(), &rules);
+        assert!(single.audio_files.is_empty());
+        assert_eq!(single.excluded.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
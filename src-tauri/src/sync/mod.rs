@@ -7,8 +7,11 @@
 pub mod auth;
 
 use crate::db::queries::{self, SyncQueueEntry};
+use crate::db::smart_rules::SmartPlaylistRules;
 use crate::db::Database;
+use crate::tag_editor::rating;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tauri::Emitter;
@@ -90,6 +93,14 @@ struct SyncFullResponse {
     #[serde(rename = "libraryTracks")]
     library_tracks: Vec<serde_json::Value>,
     settings: Option<serde_json::Value>,
+    /// Missing from servers that predate playlist folders
+    #[serde(rename = "playlistFolders", default)]
+    playlist_folders: Vec<serde_json::Value>,
+    /// Missing from servers that predate ratings
+    #[serde(rename = "trackRatings", default)]
+    track_ratings: Vec<serde_json::Value>,
+    #[serde(rename = "albumRatings", default)]
+    album_ratings: Vec<serde_json::Value>,
 }
 
 // ─── SyncState (shared, managed by Tauri) ───────────────────────────────────
@@ -370,12 +381,16 @@ async fn perform_full_sync_inner(
         apply_settings_from_server(db, settings)?;
     }
 
-    // Apply playlists (with their tracks) from server
+    // Apply playlist folders, then playlists (with their tracks) from server
+    apply_full_sync_playlist_folders(db, &response.playlist_folders)?;
     apply_full_sync_playlists(db, &response.playlists, sync_state)?;
 
     // Apply liked tracks from server
     apply_full_sync_liked_tracks(db, &response.liked_tracks, sync_state)?;
 
+    // Apply track and album ratings from server
+    apply_full_sync_ratings(db, &response.track_ratings, &response.album_ratings)?;
+
     // Apply library tracks from server
     apply_full_sync_library_tracks(db, &response.library_tracks, sync_state)?;
 
@@ -431,9 +446,15 @@ async fn push_local_data_to_server(db: &Database, sync_state: &SyncState) -> Res
         let conn = db.conn.lock().map_err(|e| e.to_string())?;
         let playlists = queries::get_all_playlists(&conn).map_err(|e| e.to_string())?;
 
-        let mut playlist_tracks_map: Vec<(queries::Playlist, Vec<queries::Track>)> = Vec::new();
+        let mut playlist_tracks_map: Vec<(queries::Playlist, Vec<queries::PlaylistTrack>)> =
+            Vec::new();
         for pl in &playlists {
-            let tracks = queries::get_playlist_tracks(&conn, pl.id).map_err(|e| e.to_string())?;
+            // Smart playlists sync their rules; each device fills them itself
+            let tracks = if pl.smart_rules.is_some() {
+                Vec::new()
+            } else {
+                queries::get_playlist_entries(&conn, pl.id).map_err(|e| e.to_string())?
+            };
             playlist_tracks_map.push((pl.clone(), tracks));
         }
 
@@ -443,6 +464,38 @@ async fn push_local_data_to_server(db: &Database, sync_state: &SyncState) -> Res
     // 2. Build changes list
     let mut changes: Vec<ClientChange> = Vec::new();
 
+    // Playlist folders, parents first
+    {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let folders = queries::get_playlist_folders(&conn).map_err(|e| e.to_string())?;
+        for folder in &folders {
+            let folder_server_id =
+                queries::get_or_create_server_id(&conn, &folder.id.to_string(), "playlist_folder")
+                    .map_err(|e| e.to_string())?;
+            let parent_server_id = match folder.parent_id {
+                Some(parent_id) => Some(
+                    queries::get_or_create_server_id(
+                        &conn,
+                        &parent_id.to_string(),
+                        "playlist_folder",
+                    )
+                    .map_err(|e| e.to_string())?,
+                ),
+                None => None,
+            };
+            changes.push(ClientChange {
+                entity_type: "playlist_folder".to_string(),
+                entity_id: folder_server_id,
+                operation: "create".to_string(),
+                payload: serde_json::json!({
+                    "name": folder.name,
+                    "parentId": parent_server_id,
+                    "position": folder.position,
+                }),
+            });
+        }
+    }
+
     // Playlists
     for (playlist, tracks) in &playlist_tracks_map {
         let conn = db.conn.lock().map_err(|e| e.to_string())?;
@@ -452,6 +505,13 @@ async fn push_local_data_to_server(db: &Database, sync_state: &SyncState) -> Res
                 .map_err(|e| e.to_string())?;
         // Also store on the playlists table for apply_server_changes lookups
         let _ = queries::set_playlist_server_id(&conn, playlist.id, &playlist_server_id);
+        let folder_server_id = match playlist.folder_id {
+            Some(folder_id) => Some(
+                queries::get_or_create_server_id(&conn, &folder_id.to_string(), "playlist_folder")
+                    .map_err(|e| e.to_string())?,
+            ),
+            None => None,
+        };
         drop(conn);
 
         changes.push(ClientChange {
@@ -462,13 +522,20 @@ async fn push_local_data_to_server(db: &Database, sync_state: &SyncState) -> Res
                 "name": playlist.name,
                 "coverUrl": playlist.cover_url,
                 "createdAt": playlist.created_at,
+                "smartRules": playlist.smart_rules,
+                "folderId": folder_server_id,
+                "position": playlist.position,
             }),
         });
 
-        // Tracks in this playlist
-        for (position, track) in tracks.iter().enumerate() {
+        // Entries in this playlist
+        for entry in tracks {
+            let (Some(entry_id), Some(position)) = (entry.entry_id, entry.position) else {
+                continue;
+            };
+            let track = &entry.track;
             let track_hash = build_track_hash(track);
-            let pt_local_id = format!("{}_{}", playlist.id, track.id);
+            let pt_local_id = format!("entry_{}", entry_id);
             let conn = db.conn.lock().map_err(|e| e.to_string())?;
             let pt_server_id =
                 queries::get_or_create_server_id(&conn, &pt_local_id, "playlist_track")
@@ -524,6 +591,68 @@ async fn push_local_data_to_server(db: &Database, sync_state: &SyncState) -> Res
         }
     }
 
+    // Ratings
+    {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let track_ratings = queries::get_track_ratings(&conn).map_err(|e| e.to_string())?;
+        for rated in &track_ratings {
+            let Some(track) =
+                queries::get_track_by_id(&conn, rated.id).map_err(|e| e.to_string())?
+            else {
+                continue;
+            };
+            let rating_server_id = queries::get_or_create_server_id(
+                &conn,
+                &format!("rating_{}", track.id),
+                "track_rating",
+            )
+            .map_err(|e| e.to_string())?;
+
+            changes.push(ClientChange {
+                entity_type: "track_rating".to_string(),
+                entity_id: rating_server_id,
+                operation: "update".to_string(),
+                payload: serde_json::json!({
+                    "trackHash": build_track_hash(&track),
+                    "title": track.title,
+                    "artist": track.artist,
+                    "album": track.album,
+                    "duration": track.duration,
+                    "externalId": track.external_id,
+                    "sourceType": track.source_type,
+                    "coverUrl": track.cover_url,
+                    "rating": rated.rating,
+                }),
+            });
+        }
+
+        let album_ratings = queries::get_album_ratings(&conn).map_err(|e| e.to_string())?;
+        for rated in &album_ratings {
+            let Some(album) =
+                queries::get_album_by_id(&conn, rated.id).map_err(|e| e.to_string())?
+            else {
+                continue;
+            };
+            let rating_server_id = queries::get_or_create_server_id(
+                &conn,
+                &format!("album_rating_{}", album.id),
+                "album_rating",
+            )
+            .map_err(|e| e.to_string())?;
+
+            changes.push(ClientChange {
+                entity_type: "album_rating".to_string(),
+                entity_id: rating_server_id,
+                operation: "update".to_string(),
+                payload: serde_json::json!({
+                    "albumName": album.name,
+                    "albumArtist": album.artist,
+                    "rating": rated.rating,
+                }),
+            });
+        }
+    }
+
     // Full track library
     {
         let conn = db.conn.lock().map_err(|e| e.to_string())?;
@@ -649,6 +778,23 @@ async fn push_local_data_to_server(db: &Database, sync_state: &SyncState) -> Res
 
 // ─── Apply full sync data from server ────────────────────────────────────────
 
+/// Apply playlist folders from the full sync response; the server lists
+/// parents before their subfolders.
+fn apply_full_sync_playlist_folders(
+    db: &Database,
+    folders: &[serde_json::Value],
+) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    for folder in folders {
+        let Some(server_id) = folder.get("id").and_then(|v| v.as_str()) else {
+            tracing::warn!("Skipping playlist folder with missing server_id");
+            continue;
+        };
+        apply_synced_playlist_folder(&conn, server_id, folder).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 /// Apply playlists (with their tracks) from the full sync response to the local DB.
 fn apply_full_sync_playlists(
     db: &Database,
@@ -707,6 +853,7 @@ fn apply_full_sync_playlists(
             );
             id
         };
+        apply_playlist_placement(&conn, local_playlist_id, pl).map_err(|e| e.to_string())?;
 
         // Add tracks to the playlist
         if let Some(tracks) = tracks {
@@ -739,7 +886,20 @@ fn apply_full_sync_playlists(
                 );
 
                 if let Ok(Some(track_id)) = local_track_id {
-                    let _ = queries::add_track_to_playlist(&conn, local_playlist_id, track_id);
+                    let position = track_val.get("position").and_then(|v| v.as_f64());
+                    let _ = match track_val.get("id").and_then(|v| v.as_str()) {
+                        Some(entry_server_id) => apply_synced_entry(
+                            &conn,
+                            entry_server_id,
+                            local_playlist_id,
+                            track_id,
+                            position,
+                        ),
+                        // Without entry ids, only add tracks the playlist lacks
+                        None => {
+                            queries::add_track_to_playlist_once(&conn, local_playlist_id, track_id)
+                        }
+                    };
                 }
             }
         }
@@ -825,6 +985,55 @@ fn apply_full_sync_liked_tracks(
     Ok(())
 }
 
+/// Apply ratings from the full sync response to the local DB. As with
+/// delta changes, they only reach tracks and albums already in the library.
+fn apply_full_sync_ratings(
+    db: &Database,
+    track_ratings: &[serde_json::Value],
+    album_ratings: &[serde_json::Value],
+) -> Result<(), String> {
+    if track_ratings.is_empty() && album_ratings.is_empty() {
+        tracing::info!("No ratings to import from server");
+        return Ok(());
+    }
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+
+    let mut imported = 0;
+    for rated in track_ratings {
+        let Some(rating) = payload_rating(rated) else {
+            continue;
+        };
+        if let Some(track_id) = local_track_for_hash(&conn, rated) {
+            if let Ok(true) = queries::set_track_rating(&conn, track_id, Some(rating)) {
+                imported += 1;
+            }
+        }
+    }
+
+    for rated in album_ratings {
+        let Some(rating) = payload_rating(rated) else {
+            continue;
+        };
+        let Some(name) = rated.get("albumName").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let artist = rated.get("albumArtist").and_then(|v| v.as_str());
+        if let Ok(Some(album_id)) = queries::find_album_by_name(&conn, name, artist) {
+            if let Ok(true) = queries::set_album_rating(&conn, album_id, Some(rating)) {
+                imported += 1;
+            }
+        }
+    }
+
+    tracing::info!(
+        "Imported {} of {} ratings from server",
+        imported,
+        track_ratings.len() + album_ratings.len()
+    );
+    Ok(())
+}
+
 /// Find an existing local track by metadata, or create a placeholder track.
 /// Returns the local track ID, or None if the track can't be created.
 fn find_or_create_synced_track(
@@ -883,6 +1092,21 @@ fn find_or_create_synced_track(
         local_src: None,
         musicbrainz_recording_id: None,
         metadata_json: None,
+        bpm: None,
+        musical_key: None,
+        file_mtime: None,
+        file_size: None,
+        album_artist: None,
+        compilation: false,
+        musicbrainz_release_id: None,
+        composer: None,
+        remixer: None,
+        genre: None,
+        year: None,
+        label: None,
+        musicbrainz_release_group_id: None,
+        musicbrainz_artist_id: None,
+        rating: None,
     };
 
     match queries::insert_or_update_track(conn, &track) {
@@ -999,6 +1223,21 @@ fn apply_full_sync_library_tracks(
             local_src: None,
             musicbrainz_recording_id: None,
             metadata_json: None,
+            bpm: None,
+            musical_key: None,
+            file_mtime: None,
+            file_size: None,
+            album_artist: None,
+            compilation: false,
+            musicbrainz_release_id: None,
+            composer: None,
+            remixer: None,
+            genre: None,
+            year: None,
+            label: None,
+            musicbrainz_release_group_id: None,
+            musicbrainz_artist_id: None,
+            rating: None,
         };
 
         match queries::insert_or_update_track(&conn, &track) {
@@ -1055,6 +1294,14 @@ fn apply_server_changes(db: &Database, changes: &[ServerChange]) -> Result<(), S
     Ok(())
 }
 
+/// Smart playlist rules carried by a playlist payload, if any.
+fn smart_rules_from_payload(payload: &serde_json::Value) -> Option<SmartPlaylistRules> {
+    payload
+        .get("smartRules")
+        .filter(|v| !v.is_null())
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
 fn apply_single_server_change(db: &Database, change: &ServerChange) -> Result<(), String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
@@ -1072,6 +1319,7 @@ fn apply_single_server_change(db: &Database, change: &ServerChange) -> Result<()
                         .and_then(|v| v.as_str())
                         .unwrap_or("Untitled Playlist");
                     let cover_url = change.payload.get("coverUrl").and_then(|v| v.as_str());
+                    let smart_rules = smart_rules_from_payload(&change.payload);
 
                     // Check if a playlist with this server_id already exists locally
                     let existing = queries::find_playlist_by_server_id(&conn, &change.entity_id)
@@ -1079,8 +1327,11 @@ fn apply_single_server_change(db: &Database, change: &ServerChange) -> Result<()
 
                     if existing.is_none() {
                         // Create the playlist locally
-                        let local_id =
-                            queries::create_playlist(&conn, name).map_err(|e| e.to_string())?;
+                        let local_id = match &smart_rules {
+                            Some(rules) => queries::create_smart_playlist(&conn, name, rules),
+                            None => queries::create_playlist(&conn, name),
+                        }
+                        .map_err(|e| e.to_string())?;
                         // Map it to the server ID
                         queries::set_playlist_server_id(&conn, local_id, &change.entity_id)
                             .map_err(|e| e.to_string())?;
@@ -1096,6 +1347,8 @@ fn apply_single_server_change(db: &Database, change: &ServerChange) -> Result<()
                             queries::update_playlist_cover(&conn, local_id, Some(url))
                                 .map_err(|e| e.to_string())?;
                         }
+                        apply_playlist_placement(&conn, local_id, &change.payload)
+                            .map_err(|e| e.to_string())?;
                         tracing::info!(
                             "Created local playlist {} (server_id={})",
                             local_id,
@@ -1117,6 +1370,12 @@ fn apply_single_server_change(db: &Database, change: &ServerChange) -> Result<()
                             queries::update_playlist_cover(&conn, local_id, Some(cover))
                                 .map_err(|e| e.to_string())?;
                         }
+                        if let Some(rules) = smart_rules_from_payload(&change.payload) {
+                            queries::update_smart_playlist_rules(&conn, local_id, &rules)
+                                .map_err(|e| e.to_string())?;
+                        }
+                        apply_playlist_placement(&conn, local_id, &change.payload)
+                            .map_err(|e| e.to_string())?;
                         tracing::info!("Updated local playlist {} from server", local_id);
                     }
                 }
@@ -1135,63 +1394,82 @@ fn apply_single_server_change(db: &Database, change: &ServerChange) -> Result<()
                 }
             }
         }
-        "playlist_track" => {
-            let playlist_server_id = change
-                .payload
-                .get("playlistId")
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-            let track_hash = change
-                .payload
-                .get("trackHash")
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-
-            if !playlist_server_id.is_empty() && !track_hash.is_empty() {
-                // 1. Find local playlist ID
-                if let Ok(Some(local_playlist_id)) =
-                    queries::find_playlist_by_server_id(&conn, playlist_server_id)
+        "playlist_folder" => match change.operation.as_str() {
+            "create" | "update" => {
+                apply_synced_playlist_folder(&conn, &change.entity_id, &change.payload)
+                    .map_err(|e| e.to_string())?;
+            }
+            "delete" => {
+                if let Some(local_id) =
+                    queries::find_playlist_folder_by_server_id(&conn, &change.entity_id)
+                        .map_err(|e| e.to_string())?
                 {
-                    // 2. Find local track ID
-                    let parts: Vec<&str> = track_hash.splitn(3, '|').collect();
-                    if parts.len() == 3 {
-                        if let (Some(t), Some(a)) = (Some(parts[0]), Some(parts[1])) {
-                            if !t.is_empty() && !a.is_empty() {
-                                if let Ok(local_track_id) =
-                                    find_local_track_by_metadata(&conn, t, a)
-                                {
-                                    match change.operation.as_str() {
-                                        "create" | "update" => {
-                                            let _ = queries::add_track_to_playlist(
-                                                &conn,
-                                                local_playlist_id,
-                                                local_track_id,
-                                            );
-                                            tracing::info!(
-                                                "Added track {} to playlist {} via sync",
-                                                local_track_id,
-                                                local_playlist_id
-                                            );
-                                        }
-                                        "delete" => {
-                                            let _ = queries::remove_track_from_playlist(
-                                                &conn,
-                                                local_playlist_id,
-                                                local_track_id,
-                                            );
-                                            tracing::info!(
-                                                "Removed track {} from playlist {} via sync",
-                                                local_track_id,
-                                                local_playlist_id
-                                            );
-                                        }
-                                        _ => {}
-                                    }
-                                }
-                            }
+                    queries::delete_playlist_folder(&conn, local_id).map_err(|e| e.to_string())?;
+                    tracing::info!("Deleted local playlist folder {} from server", local_id);
+                }
+            }
+            _ => {
+                tracing::warn!("Unknown playlist_folder operation: {}", change.operation);
+            }
+        },
+        "playlist_snapshot" => {
+            let snapshots = change.payload.get("playlists").and_then(|v| v.as_array());
+            for snapshot in snapshots.into_iter().flatten() {
+                apply_playlist_snapshot(&conn, snapshot).map_err(|e| e.to_string())?;
+            }
+        }
+        "playlist_track" => {
+            // Entries are matched on their server id, so a track that is in a
+            // playlist twice on another device is in it twice here too
+            let mapped_entry = local_entry_for_server_id(&conn, &change.entity_id);
+            let position = change.payload.get("position").and_then(|v| v.as_f64());
+            match change.operation.as_str() {
+                "create" | "update" => {
+                    if let Some(entry_id) = mapped_entry {
+                        if let Some(position) = position {
+                            queries::set_playlist_entry_position(&conn, entry_id, position)
+                                .map_err(|e| e.to_string())?;
                         }
+                    } else if let Some((local_playlist_id, local_track_id)) =
+                        find_synced_entry_target(&conn, &change.payload)
+                    {
+                        apply_synced_entry(
+                            &conn,
+                            &change.entity_id,
+                            local_playlist_id,
+                            local_track_id,
+                            position,
+                        )
+                        .map_err(|e| e.to_string())?;
+                        tracing::info!(
+                            "Added track {} to playlist {} via sync",
+                            local_track_id,
+                            local_playlist_id
+                        );
                     }
                 }
+                "delete" => {
+                    if let Some(entry_id) = mapped_entry {
+                        queries::remove_playlist_entry(&conn, entry_id)
+                            .map_err(|e| e.to_string())?;
+                        tracing::info!("Removed playlist entry {} via sync", entry_id);
+                    } else if let Some((local_playlist_id, local_track_id)) =
+                        find_synced_entry_target(&conn, &change.payload)
+                    {
+                        // Entry we never mapped (e.g. pulled before entries had ids)
+                        let _ = queries::remove_track_from_playlist(
+                            &conn,
+                            local_playlist_id,
+                            local_track_id,
+                        );
+                        tracing::info!(
+                            "Removed track {} from playlist {} via sync",
+                            local_track_id,
+                            local_playlist_id
+                        );
+                    }
+                }
+                _ => {}
             }
         }
         "liked_track" => {
@@ -1267,6 +1545,34 @@ fn apply_single_server_change(db: &Database, change: &ServerChange) -> Result<()
                 _ => {}
             }
         }
+        "track_rating" => {
+            // Ratings from other devices land in the library only; files are
+            // rewritten when rated here
+            if let Some(track_id) = local_track_for_hash(&conn, &change.payload) {
+                let rating = synced_rating(change);
+                let _ = queries::set_track_rating(&conn, track_id, rating);
+                tracing::info!(
+                    "Rated local track {} {:?} from server sync",
+                    track_id,
+                    rating
+                );
+            }
+        }
+        "album_rating" => {
+            let name = change.payload.get("albumName").and_then(|v| v.as_str());
+            let artist = change.payload.get("albumArtist").and_then(|v| v.as_str());
+            if let Some(name) = name {
+                if let Ok(Some(album_id)) = queries::find_album_by_name(&conn, name, artist) {
+                    let rating = synced_rating(change);
+                    let _ = queries::set_album_rating(&conn, album_id, rating);
+                    tracing::info!(
+                        "Rated local album {} {:?} from server sync",
+                        album_id,
+                        rating
+                    );
+                }
+            }
+        }
         "library_track" => {
             tracing::info!(
                 "Server change: library_track {} {} (track library synced)",
@@ -1289,6 +1595,7 @@ fn apply_single_server_change(db: &Database, change: &ServerChange) -> Result<()
                     .get("durationPlayed")
                     .and_then(|v| v.as_i64())
                     .unwrap_or(0);
+                let position = change.payload.get("position").and_then(|v| v.as_i64());
                 let _played_at = change.payload.get("playedAt").and_then(|v| v.as_str());
 
                 if !track_hash.is_empty() {
@@ -1307,8 +1614,13 @@ fn apply_single_server_change(db: &Database, change: &ServerChange) -> Result<()
 
                         if let (Some(t), Some(a)) = (title, artist) {
                             if let Ok(track_id) = find_local_track_by_metadata(&conn, t, a) {
-                                let _ =
-                                    queries::record_play(&conn, track_id, None, duration_played);
+                                let _ = queries::record_play(
+                                    &conn,
+                                    track_id,
+                                    None,
+                                    duration_played,
+                                    position,
+                                );
                             }
                         }
                     }
@@ -1336,6 +1648,34 @@ fn find_local_track_by_metadata(
     .map_err(|e| format!("Track not found: {}", e))
 }
 
+/// Local track a synced change refers to, by the title and artist in its
+/// "title|artist|album" hash.
+fn local_track_for_hash(conn: &rusqlite::Connection, payload: &serde_json::Value) -> Option<i64> {
+    let track_hash = payload.get("trackHash").and_then(|v| v.as_str())?;
+    let mut parts = track_hash.splitn(3, '|');
+    let title = parts.next().filter(|s| !s.is_empty())?;
+    let artist = parts.next().filter(|s| !s.is_empty())?;
+    find_local_track_by_metadata(conn, title, artist).ok()
+}
+
+/// Rating carried by a synced rating change; deletes and out-of-range
+/// values clear it.
+fn synced_rating(change: &ServerChange) -> Option<u8> {
+    if change.operation == "delete" {
+        return None;
+    }
+    payload_rating(&change.payload)
+}
+
+/// The "rating" of a synced payload, if it is a valid half-star count.
+fn payload_rating(payload: &serde_json::Value) -> Option<u8> {
+    payload
+        .get("rating")
+        .and_then(|v| v.as_u64())
+        .and_then(|r| u8::try_from(r).ok())
+        .filter(|&r| r <= rating::MAX_RATING)
+}
+
 fn apply_settings_from_server(db: &Database, _settings: &serde_json::Value) -> Result<(), String> {
     // Settings sync will be fully implemented in Phase 2
     // For now, just log that we received settings
@@ -1346,7 +1686,235 @@ fn apply_settings_from_server(db: &Database, _settings: &serde_json::Value) -> R
 
 // ─── Helpers ────────────────────────────────────────────────────────────────
 
-/// Translate a local entity_id (like "local_12" or "local_12_1147") to a server UUID.
+/// The local folder a payload field refers to: `None` when the field is
+/// absent, `Some(None)` for the top level (or a folder we don't have).
+fn folder_from_payload(
+    conn: &rusqlite::Connection,
+    payload: &serde_json::Value,
+    key: &str,
+) -> Option<Option<i64>> {
+    let value = payload.get(key)?;
+    Some(
+        value
+            .as_str()
+            .and_then(|server_id| queries::find_playlist_folder_by_server_id(conn, server_id).ok())
+            .flatten(),
+    )
+}
+
+/// Create or update a folder received from the server.
+fn apply_synced_playlist_folder(
+    conn: &rusqlite::Connection,
+    server_id: &str,
+    payload: &serde_json::Value,
+) -> rusqlite::Result<()> {
+    let name = payload.get("name").and_then(|v| v.as_str());
+    let parent_id = folder_from_payload(conn, payload, "parentId");
+    let position = payload.get("position").and_then(|v| v.as_f64());
+
+    match queries::find_playlist_folder_by_server_id(conn, server_id)? {
+        Some(local_id) => {
+            if let Some(name) = name {
+                queries::rename_playlist_folder(conn, local_id, name)?;
+            }
+            if let Some(folder) = queries::get_playlist_folder(conn, local_id)? {
+                let mut parent_id = parent_id.unwrap_or(folder.parent_id);
+                // A move that would nest the folder inside itself (moves made on two
+                // devices at once) keeps it where it is
+                if let Some(parent) = parent_id {
+                    if queries::playlist_folder_is_within(conn, parent, local_id)? {
+                        parent_id = folder.parent_id;
+                    }
+                }
+                let position = position.unwrap_or(folder.position);
+                queries::set_playlist_folder_parent(conn, local_id, parent_id, position)?;
+            }
+        }
+        None => {
+            let local_id = queries::create_playlist_folder(
+                conn,
+                name.unwrap_or("Untitled Folder"),
+                parent_id.flatten(),
+                position,
+            )?;
+            queries::store_id_mapping(conn, &local_id.to_string(), "playlist_folder", server_id)?;
+            tracing::info!(
+                "Created local playlist folder {} (server_id={})",
+                local_id,
+                server_id
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Put a playlist in the folder and position a payload gives, if any.
+fn apply_playlist_placement(
+    conn: &rusqlite::Connection,
+    playlist_id: i64,
+    payload: &serde_json::Value,
+) -> rusqlite::Result<()> {
+    if let Some(folder_id) = folder_from_payload(conn, payload, "folderId") {
+        let position = payload.get("position").and_then(|v| v.as_f64());
+        queries::set_playlist_folder(conn, playlist_id, folder_id, position)?;
+    }
+    Ok(())
+}
+
+/// Replace a "local_{key}" reference in a payload with the server id of
+/// that entity, returning the local key and server id.
+fn translate_local_ref(
+    conn: &rusqlite::Connection,
+    payload: &mut serde_json::Value,
+    field: &str,
+    entity_type: &str,
+) -> Option<(String, String)> {
+    let obj = payload.as_object_mut()?;
+    let local_key = obj
+        .get(field)
+        .and_then(|v| v.as_str())
+        .and_then(|v| v.strip_prefix("local_"))
+        .map(|s| s.to_string())?;
+    let server_id = queries::get_or_create_server_id(conn, &local_key, entity_type).ok()?;
+    obj.insert(field.to_string(), serde_json::json!(server_id));
+    Some((local_key, server_id))
+}
+
+/// Like `translate_local_ref` for a playlist reference, also recording the
+/// server id on the playlist.
+fn translate_playlist_ref(
+    conn: &rusqlite::Connection,
+    payload: &mut serde_json::Value,
+    field: &str,
+) {
+    if let Some((local_key, server_id)) = translate_local_ref(conn, payload, field, "playlist") {
+        if let Ok(id) = local_key.parse::<i64>() {
+            let _ = queries::set_playlist_server_id(conn, id, &server_id);
+        }
+    }
+}
+
+/// Replace a playlist's entries with a snapshot from another device:
+/// entries are matched on their server id, moved, added, and any not in the
+/// snapshot removed. Creates the playlist if we don't have it.
+fn apply_playlist_snapshot(
+    conn: &rusqlite::Connection,
+    snapshot: &serde_json::Value,
+) -> rusqlite::Result<()> {
+    let Some(server_id) = snapshot.get("id").and_then(|v| v.as_str()) else {
+        return Ok(());
+    };
+    let playlist_id = match queries::find_playlist_by_server_id(conn, server_id)? {
+        Some(id) => id,
+        None => {
+            let name = snapshot
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or("Untitled Playlist");
+            let id = queries::create_playlist(conn, name)?;
+            queries::set_playlist_server_id(conn, id, server_id)?;
+            queries::store_id_mapping(conn, &id.to_string(), "playlist", server_id)?;
+            id
+        }
+    };
+    apply_playlist_placement(conn, playlist_id, snapshot)?;
+
+    let mut kept = HashSet::new();
+    let entries = snapshot.get("entries").and_then(|v| v.as_array());
+    for entry in entries.into_iter().flatten() {
+        let Some(entry_server_id) = entry.get("id").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let position = entry.get("position").and_then(|v| v.as_f64());
+        if let Some(entry_id) = local_entry_for_server_id(conn, entry_server_id) {
+            if let Some(position) = position {
+                queries::set_playlist_entry_position(conn, entry_id, position)?;
+            }
+            kept.insert(entry_id);
+        } else if let Some(track_id) = find_synced_track(conn, entry) {
+            let entry_id =
+                apply_synced_entry(conn, entry_server_id, playlist_id, track_id, position)?;
+            kept.insert(entry_id);
+        }
+    }
+    for entry in queries::get_playlist_entries(conn, playlist_id)? {
+        if let Some(entry_id) = entry.entry_id.filter(|id| !kept.contains(id)) {
+            queries::remove_playlist_entry(conn, entry_id)?;
+        }
+    }
+    tracing::info!(
+        "Applied snapshot of playlist {} ({} entries)",
+        playlist_id,
+        kept.len()
+    );
+    Ok(())
+}
+
+/// The existing local playlist entry a server playlist_track id maps to.
+fn local_entry_for_server_id(conn: &rusqlite::Connection, server_id: &str) -> Option<i64> {
+    let local_id = queries::get_local_id_from_server(conn, server_id, "playlist_track")
+        .ok()
+        .flatten()?;
+    let entry_id = local_id.strip_prefix("entry_")?.parse().ok()?;
+    match queries::get_playlist_entry(conn, entry_id) {
+        Ok(Some(_)) => Some(entry_id),
+        _ => None,
+    }
+}
+
+/// Local (playlist_id, track_id) for a playlist_track payload.
+fn find_synced_entry_target(
+    conn: &rusqlite::Connection,
+    payload: &serde_json::Value,
+) -> Option<(i64, i64)> {
+    let playlist_server_id = payload.get("playlistId").and_then(|v| v.as_str())?;
+    let local_playlist_id = queries::find_playlist_by_server_id(conn, playlist_server_id)
+        .ok()
+        .flatten()?;
+    Some((local_playlist_id, find_synced_track(conn, payload)?))
+}
+
+/// The local track a payload's trackHash refers to, matched on its title
+/// and artist.
+fn find_synced_track(conn: &rusqlite::Connection, payload: &serde_json::Value) -> Option<i64> {
+    let track_hash = payload.get("trackHash").and_then(|v| v.as_str())?;
+    let parts: Vec<&str> = track_hash.splitn(3, '|').collect();
+    if parts.len() != 3 || parts[0].is_empty() || parts[1].is_empty() {
+        return None;
+    }
+    find_local_track_by_metadata(conn, parts[0], parts[1]).ok()
+}
+
+/// Add a playlist entry received from the server, or move it if it is
+/// already here, and remember its server id. Entries without a position
+/// are appended.
+fn apply_synced_entry(
+    conn: &rusqlite::Connection,
+    entry_server_id: &str,
+    playlist_id: i64,
+    track_id: i64,
+    position: Option<f64>,
+) -> rusqlite::Result<i64> {
+    if let Some(entry_id) = local_entry_for_server_id(conn, entry_server_id) {
+        if let Some(position) = position {
+            queries::set_playlist_entry_position(conn, entry_id, position)?;
+        }
+        return Ok(entry_id);
+    }
+    let entry_id = match position {
+        Some(position) => queries::insert_playlist_entry(conn, playlist_id, track_id, position)?,
+        None => queries::add_track_to_playlist(conn, playlist_id, track_id)?,
+    };
+    queries::store_id_mapping(
+        conn,
+        &format!("entry_{}", entry_id),
+        "playlist_track",
+        entry_server_id,
+    )?;
+    Ok(entry_id)
+}
+
+/// Translate a local entity_id (like "local_12" or "local_entry_1147") to a server UUID.
 /// Also updates payload fields that contain local IDs (e.g., playlistId).
 fn translate_entity_id(
     conn: &rusqlite::Connection,
@@ -1366,15 +1934,45 @@ fn translate_entity_id(
             if let Ok(id) = raw_id.parse::<i64>() {
                 let _ = queries::set_playlist_server_id(conn, id, &server_id);
             }
+            translate_local_ref(conn, payload, "folderId", "playlist_folder");
 
             server_id
         }
+        "playlist_folder" => {
+            let raw_id = local_entity_id
+                .strip_prefix("local_")
+                .unwrap_or(local_entity_id);
+            translate_local_ref(conn, payload, "parentId", "playlist_folder");
+            queries::get_or_create_server_id(conn, raw_id, "playlist_folder")
+                .unwrap_or_else(|_| local_entity_id.to_string())
+        }
+        "playlist_snapshot" => {
+            // entity_id format: "local_{playlist_id}"; the payload lists whole
+            // playlists with their entries, all referenced by local id
+            let raw_id = local_entity_id
+                .strip_prefix("local_")
+                .unwrap_or(local_entity_id);
+            let server_id = queries::get_or_create_server_id(conn, raw_id, "playlist")
+                .unwrap_or_else(|_| local_entity_id.to_string());
+            let snapshots = payload.get_mut("playlists").and_then(|v| v.as_array_mut());
+            for snapshot in snapshots.into_iter().flatten() {
+                translate_playlist_ref(conn, snapshot, "id");
+                translate_local_ref(conn, snapshot, "folderId", "playlist_folder");
+                let entries = snapshot.get_mut("entries").and_then(|v| v.as_array_mut());
+                for entry in entries.into_iter().flatten() {
+                    translate_local_ref(conn, entry, "id", "playlist_track");
+                    translate_playlist_ref(conn, entry, "playlistId");
+                }
+            }
+            server_id
+        }
         "playlist_track" => {
-            // entity_id format: "local_{playlist_id}_{track_id}" or "local_{playlist_id}_reorder"
+            // entity_id format: "local_entry_{entry_id}" (older queued changes may
+            // still use "local_{playlist_id}_{track_id}")
             let stripped = local_entity_id
                 .strip_prefix("local_")
                 .unwrap_or(local_entity_id);
-            // Generate a UUID for this playlist_track entry
+            // Generate a UUID for this playlist entry
             let pt_server_id = queries::get_or_create_server_id(conn, stripped, "playlist_track")
                 .unwrap_or_else(|_| local_entity_id.to_string());
 
@@ -1407,6 +2005,22 @@ fn translate_entity_id(
             queries::get_or_create_server_id(conn, &liked_local_key, "liked_track")
                 .unwrap_or_else(|_| local_entity_id.to_string())
         }
+        "track_rating" => {
+            // entity_id format: "local_rating_{track_id}"
+            let stripped = local_entity_id
+                .strip_prefix("local_")
+                .unwrap_or(local_entity_id);
+            queries::get_or_create_server_id(conn, stripped, "track_rating")
+                .unwrap_or_else(|_| local_entity_id.to_string())
+        }
+        "album_rating" => {
+            // entity_id format: "local_album_rating_{album_id}"
+            let stripped = local_entity_id
+                .strip_prefix("local_")
+                .unwrap_or(local_entity_id);
+            queries::get_or_create_server_id(conn, stripped, "album_rating")
+                .unwrap_or_else(|_| local_entity_id.to_string())
+        }
         "library_track" => {
             let stripped = local_entity_id.strip_prefix("local_lib_").unwrap_or(
                 local_entity_id
@@ -0,0 +1,249 @@
+// Editable tag fields and value validation
+//
+// Edits travel as a map of field -> value, where a missing field is left
+// alone and `None` (or an empty string) clears it. Values are validated and
+// normalized here once, before any file is touched, so a batch either
+// writes clean values everywhere or fails up front.
+use crate::analysis::key::MusicalKey;
+use crate::scanner::artists::MULTI_VALUE_SEPARATOR;
+use crate::scanner::genres::parse_year;
+use crate::scanner::grouping::parse_compilation_flag;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagField {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Genre,
+    Year,
+    TrackNumber,
+    TrackTotal,
+    DiscNumber,
+    DiscTotal,
+    Composer,
+    Label,
+    Comment,
+    Compilation,
+    Bpm,
+    Key,
+    MusicBrainzRecordingId,
+    MusicBrainzReleaseId,
+    MusicBrainzReleaseGroupId,
+    MusicBrainzArtistId,
+    MusicBrainzAlbumArtistId,
+}
+
+impl TagField {
+    pub const ALL: [TagField; 21] = [
+        TagField::Title,
+        TagField::Artist,
+        TagField::Album,
+        TagField::AlbumArtist,
+        TagField::Genre,
+        TagField::Year,
+        TagField::TrackNumber,
+        TagField::TrackTotal,
+        TagField::DiscNumber,
+        TagField::DiscTotal,
+        TagField::Composer,
+        TagField::Label,
+        TagField::Comment,
+        TagField::Compilation,
+        TagField::Bpm,
+        TagField::Key,
+        TagField::MusicBrainzRecordingId,
+        TagField::MusicBrainzReleaseId,
+        TagField::MusicBrainzReleaseGroupId,
+        TagField::MusicBrainzArtistId,
+        TagField::MusicBrainzAlbumArtistId,
+    ];
+
+    /// Vorbis comment name (FLAC, Ogg, Opus).
+    pub fn vorbis_key(&self) -> &'static str {
+        match self {
+            Self::Title => "TITLE",
+            Self::Artist => "ARTIST",
+            Self::Album => "ALBUM",
+            Self::AlbumArtist => "ALBUMARTIST",
+            Self::Genre => "GENRE",
+            Self::Year => "DATE",
+            Self::TrackNumber => "TRACKNUMBER",
+            Self::TrackTotal => "TRACKTOTAL",
+            Self::DiscNumber => "DISCNUMBER",
+            Self::DiscTotal => "DISCTOTAL",
+            Self::Composer => "COMPOSER",
+            Self::Label => "LABEL",
+            Self::Comment => "COMMENT",
+            Self::Compilation => "COMPILATION",
+            Self::Bpm => "BPM",
+            Self::Key => "INITIALKEY",
+            Self::MusicBrainzRecordingId => "MUSICBRAINZ_TRACKID",
+            Self::MusicBrainzReleaseId => "MUSICBRAINZ_ALBUMID",
+            Self::MusicBrainzReleaseGroupId => "MUSICBRAINZ_RELEASEGROUPID",
+            Self::MusicBrainzArtistId => "MUSICBRAINZ_ARTISTID",
+            Self::MusicBrainzAlbumArtistId => "MUSICBRAINZ_ALBUMARTISTID",
+        }
+    }
+
+    /// Fields that hold several values, stored joined with "; ".
+    pub fn is_multi_value(&self) -> bool {
+        matches!(self, Self::Artist | Self::Genre | Self::Composer)
+    }
+
+    pub fn is_musicbrainz_id(&self) -> bool {
+        matches!(
+            self,
+            Self::MusicBrainzRecordingId
+                | Self::MusicBrainzReleaseId
+                | Self::MusicBrainzReleaseGroupId
+                | Self::MusicBrainzArtistId
+                | Self::MusicBrainzAlbumArtistId
+        )
+    }
+
+    /// Split a stored value into the values written to the file.
+    pub fn split_values(&self, value: &str) -> Vec<String> {
+        if self.is_multi_value() {
+            value
+                .split(MULTI_VALUE_SEPARATOR.trim())
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect()
+        } else {
+            vec![value.to_string()]
+        }
+    }
+}
+
+/// Field values, as read from a file or requested by an edit.
+/// `None` means the field is absent (or is to be removed).
+pub type TagValues = BTreeMap<TagField, Option<String>>;
+
+const MAX_TEXT_LEN: usize = 1024;
+
+/// Validate and normalize one value. `Ok(None)` clears the field.
+pub fn normalize_value(field: TagField, value: Option<&str>) -> Result<Option<String>, String> {
+    let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+
+    let invalid = |what: &str| Err(format!("Invalid {} for {:?}: {:?}", what, field, value));
+
+    match field {
+        TagField::TrackNumber
+        | TagField::TrackTotal
+        | TagField::DiscNumber
+        | TagField::DiscTotal => match value.parse::<u16>() {
+            Ok(n) if n > 0 => Ok(Some(n.to_string())),
+            _ => invalid("number"),
+        },
+        TagField::Year => match parse_year(value) {
+            Some(year) => Ok(Some(year.to_string())),
+            None => invalid("year"),
+        },
+        TagField::Bpm => match value.parse::<f64>() {
+            Ok(bpm) if bpm > 0.0 && bpm < 1000.0 => {
+                let rounded = (bpm * 100.0).round() / 100.0;
+                Ok(Some(rounded.to_string()))
+            }
+            _ => invalid("tempo"),
+        },
+        TagField::Compilation => Ok(parse_compilation_flag(value).then(|| "1".to_string())),
+        TagField::Key => match MusicalKey::parse(value) {
+            Some(key) => Ok(Some(key.to_string())),
+            None => invalid("key"),
+        },
+        _ if field.is_musicbrainz_id() => {
+            let is_uuid = value.len() == 36
+                && value.chars().enumerate().all(|(i, c)| match i {
+                    8 | 13 | 18 | 23 => c == '-',
+                    _ => c.is_ascii_hexdigit(),
+                });
+            if is_uuid {
+                Ok(Some(value.to_ascii_lowercase()))
+            } else {
+                invalid("MusicBrainz ID")
+            }
+        }
+        _ => {
+            if value.len() > MAX_TEXT_LEN || value.chars().any(|c| c.is_control() && c != '\n') {
+                return invalid("text");
+            }
+            if field.is_multi_value() {
+                let values = field.split_values(value);
+                return Ok((!values.is_empty()).then(|| values.join(MULTI_VALUE_SEPARATOR)));
+            }
+            Ok(Some(value.to_string()))
+        }
+    }
+}
+
+/// Validate every value of an edit.
+pub fn normalize_values(values: &TagValues) -> Result<TagValues, String> {
+    values
+        .iter()
+        .map(|(field, value)| Ok((*field, normalize_value(*field, value.as_deref())?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_value() {
+        assert_eq!(
+            normalize_value(TagField::Title, Some("  Song ")),
+            Ok(Some("Song".into()))
+        );
+        assert_eq!(normalize_value(TagField::Title, Some("")), Ok(None));
+        assert_eq!(normalize_value(TagField::Title, None), Ok(None));
+        assert_eq!(
+            normalize_value(TagField::TrackNumber, Some("07")),
+            Ok(Some("7".into()))
+        );
+        assert!(normalize_value(TagField::TrackNumber, Some("0")).is_err());
+        assert!(normalize_value(TagField::DiscTotal, Some("two")).is_err());
+        assert_eq!(
+            normalize_value(TagField::Year, Some("1994-05-01")),
+            Ok(Some("1994".into()))
+        );
+        assert!(normalize_value(TagField::Year, Some("soon")).is_err());
+        assert_eq!(
+            normalize_value(TagField::Bpm, Some("127.996")),
+            Ok(Some("128".into()))
+        );
+        assert_eq!(
+            normalize_value(TagField::Compilation, Some("true")),
+            Ok(Some("1".into()))
+        );
+        assert_eq!(normalize_value(TagField::Compilation, Some("0")), Ok(None));
+        assert_eq!(
+            normalize_value(TagField::Artist, Some("A ;B; ")),
+            Ok(Some("A; B".into()))
+        );
+        assert!(normalize_value(TagField::Album, Some("a\u{0}b")).is_err());
+        assert_eq!(
+            normalize_value(
+                TagField::MusicBrainzReleaseId,
+                Some("8A2A6E6B-3F5B-4C8E-9E63-0C1C2D1A3B4F")
+            ),
+            Ok(Some("8a2a6e6b-3f5b-4c8e-9e63-0c1c2d1a3b4f".into()))
+        );
+        assert!(normalize_value(TagField::MusicBrainzArtistId, Some("not-an-id")).is_err());
+    }
+
+    #[test]
+    fn test_split_values() {
+        assert_eq!(
+            TagField::Genre.split_values("Rock; Pop"),
+            vec!["Rock", "Pop"]
+        );
+        assert_eq!(TagField::Title.split_values("A; B"), vec!["A; B"]);
+    }
+}
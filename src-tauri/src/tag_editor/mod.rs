@@ -0,0 +1,855 @@
+// Tag editor: read and write the editable tag fields of an audio file
+//
+// Dispatches on the file extension the same way the download writer does:
+// MP4 containers go through mp4ameta, FLAC through metaflac and everything
+// else (MP3, Ogg Vorbis, Opus, ...) through lofty. Values read here are the
+// raw file values, so writing a snapshot back restores the file as it was.
+pub mod fields;
+pub mod rating;
+pub mod release_match;
+
+use crate::scanner::cover_storage::ImageFormat;
+use fields::{TagField, TagValues};
+use lofty::config::{ParseOptions, WriteOptions};
+use lofty::id3::v2::{Frame, FrameId, Id3v2Tag, PopularimeterFrame};
+use lofty::mpeg::MpegFile;
+use lofty::picture::{MimeType, Picture, PictureType};
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use lofty::tag::{ItemValue, Tag, TagItem, TagType};
+use metaflac::block::{BlockType, PictureType as FlacPictureType};
+use metaflac::Tag as FlacTag;
+use mp4ameta::{Data, Fourcc, FreeformIdent, Img, Tag as Mp4Tag};
+use std::fs::File;
+use std::path::Path;
+
+/// Largest cover image written into a file.
+const MAX_COVER_BYTES: usize = 10 * 1024 * 1024;
+
+/// What to do with the front cover of a file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoverChange {
+    Keep,
+    Set(Vec<u8>),
+    Remove,
+}
+
+/// Tag values and front cover of a file.
+#[derive(Debug, Clone, Default)]
+pub struct FileTags {
+    pub values: TagValues,
+    pub cover: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TagFormat {
+    Mp4,
+    Flac,
+    Lofty,
+}
+
+fn tag_format(path: &Path) -> Result<TagFormat, String> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "m4a" | "m4b" | "mp4" | "alac" => Ok(TagFormat::Mp4),
+        "flac" => Ok(TagFormat::Flac),
+        "mp3" | "ogg" | "oga" | "opus" | "wav" | "aiff" | "aif" | "wv" | "ape" => {
+            Ok(TagFormat::Lofty)
+        }
+        _ => Err(format!("Tag editing is not supported for .{} files", ext)),
+    }
+}
+
+/// Read every editable field and the front cover of a file.
+pub fn read_file_tags(path: &Path) -> Result<FileTags, String> {
+    match tag_format(path)? {
+        TagFormat::Mp4 => read_mp4(path),
+        TagFormat::Flac => read_flac(path),
+        TagFormat::Lofty => read_lofty(path),
+    }
+}
+
+/// Write `values` (`None` removes a field) and apply `cover` to a file.
+/// Fields not in `values` are left untouched.
+pub fn write_file_tags(path: &Path, values: &TagValues, cover: &CoverChange) -> Result<(), String> {
+    if let CoverChange::Set(data) = cover {
+        if data.is_empty() || data.len() > MAX_COVER_BYTES {
+            return Err(format!("Cover image size invalid: {} bytes", data.len()));
+        }
+        if ImageFormat::from_bytes(data).is_none() {
+            return Err("Unsupported or invalid image format".to_string());
+        }
+    }
+    match tag_format(path)? {
+        TagFormat::Mp4 => write_mp4(path, values, cover),
+        TagFormat::Flac => write_flac(path, values, cover),
+        TagFormat::Lofty => write_lofty(path, values, cover),
+    }
+}
+
+fn joined<'a>(field: TagField, values: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let mut values = values.into_iter();
+    if field.is_multi_value() {
+        crate::scanner::artists::join_tag_values(values)
+    } else {
+        values.next().map(str::to_string).filter(|v| !v.is_empty())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// lofty (MP3, Ogg Vorbis, Opus, WAV, AIFF, ...)
+// ---------------------------------------------------------------------------
+
+fn item_key(field: TagField) -> ItemKey {
+    match field {
+        TagField::Title => ItemKey::TrackTitle,
+        TagField::Artist => ItemKey::TrackArtist,
+        TagField::Album => ItemKey::AlbumTitle,
+        TagField::AlbumArtist => ItemKey::AlbumArtist,
+        TagField::Genre => ItemKey::Genre,
+        TagField::Year => ItemKey::Year,
+        TagField::TrackNumber => ItemKey::TrackNumber,
+        TagField::TrackTotal => ItemKey::TrackTotal,
+        TagField::DiscNumber => ItemKey::DiscNumber,
+        TagField::DiscTotal => ItemKey::DiscTotal,
+        TagField::Composer => ItemKey::Composer,
+        TagField::Label => ItemKey::Label,
+        TagField::Comment => ItemKey::Comment,
+        TagField::Compilation => ItemKey::FlagCompilation,
+        TagField::Bpm => ItemKey::Bpm,
+        TagField::Key => ItemKey::InitialKey,
+        TagField::MusicBrainzRecordingId => ItemKey::MusicBrainzRecordingId,
+        TagField::MusicBrainzReleaseId => ItemKey::MusicBrainzReleaseId,
+        TagField::MusicBrainzReleaseGroupId => ItemKey::MusicBrainzReleaseGroupId,
+        TagField::MusicBrainzArtistId => ItemKey::MusicBrainzArtistId,
+        TagField::MusicBrainzAlbumArtistId => ItemKey::MusicBrainzReleaseArtistId,
+    }
+}
+
+/// Keys the scanner falls back to for a field; cleared on write so a stale
+/// fallback never resurfaces on the next scan.
+fn fallback_item_keys(field: TagField) -> &'static [ItemKey] {
+    match field {
+        TagField::Year => &[ItemKey::RecordingDate],
+        TagField::Label => &[ItemKey::Publisher],
+        TagField::Bpm => &[ItemKey::IntegerBpm],
+        _ => &[],
+    }
+}
+
+fn read_lofty(path: &Path) -> Result<FileTags, String> {
+    let tagged_file = Probe::open(path)
+        .map_err(|e| format!("Failed to open file: {}", e))?
+        .guess_file_type()
+        .map_err(|e| format!("Failed to detect file type: {}", e))?
+        .read()
+        .map_err(|e| format!("Failed to read tags: {}", e))?;
+
+    let Some(tag) = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())
+    else {
+        return Ok(FileTags::default());
+    };
+
+    let values = TagField::ALL
+        .iter()
+        .map(|&field| {
+            let mut value = joined(field, tag.get_strings(&item_key(field)));
+            for key in fallback_item_keys(field) {
+                value = value.or_else(|| joined(field, tag.get_strings(key)));
+            }
+            (field, value)
+        })
+        .collect();
+    let cover = tag.pictures().first().map(|pic| pic.data().to_vec());
+
+    Ok(FileTags { values, cover })
+}
+
+fn write_lofty(path: &Path, values: &TagValues, cover: &CoverChange) -> Result<(), String> {
+    let mut tagged_file = Probe::open(path)
+        .map_err(|e| format!("Failed to open file: {}", e))?
+        .guess_file_type()
+        .map_err(|e| format!("Failed to detect file type: {}", e))?
+        .read()
+        .map_err(|e| format!("Failed to read tags: {}", e))?;
+
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .ok_or_else(|| "Failed to create tag".to_string())?;
+
+    for (&field, value) in values {
+        let key = item_key(field);
+        let fallbacks = fallback_item_keys(field);
+        tag.retain(|item| item.key() != &key && !fallbacks.contains(item.key()));
+        if let Some(value) = value {
+            for value in field.split_values(value) {
+                tag.push(TagItem::new(key.clone(), ItemValue::Text(value)));
+            }
+        }
+    }
+
+    if *cover != CoverChange::Keep {
+        // The scanner takes the first picture, so the new cover goes first
+        // and any other pictures (back cover, booklet) follow it
+        let mut pictures: Vec<Picture> = tag
+            .pictures()
+            .iter()
+            .filter(|pic| pic.pic_type() != PictureType::CoverFront)
+            .cloned()
+            .collect();
+        while !tag.pictures().is_empty() {
+            tag.remove_picture(0);
+        }
+        if let CoverChange::Set(data) = cover {
+            let mime = match ImageFormat::from_bytes(data) {
+                Some(ImageFormat::Png) => MimeType::Png,
+                Some(ImageFormat::Webp) => MimeType::Unknown("image/webp".to_string()),
+                _ => MimeType::Jpeg,
+            };
+            pictures.insert(
+                0,
+                Picture::new_unchecked(PictureType::CoverFront, Some(mime), None, data.clone()),
+            );
+        }
+        for picture in pictures {
+            tag.push_picture(picture);
+        }
+    }
+
+    tag.save_to_path(path, WriteOptions::default())
+        .map_err(|e| format!("Failed to write tags: {}", e))
+}
+
+// ---------------------------------------------------------------------------
+// FLAC (Vorbis comments)
+// ---------------------------------------------------------------------------
+
+/// Vorbis names the scanner falls back to for a field.
+fn fallback_vorbis_keys(field: TagField) -> &'static [&'static str] {
+    match field {
+        TagField::Year => &["YEAR"],
+        TagField::TrackTotal => &["TOTALTRACKS"],
+        TagField::DiscTotal => &["TOTALDISCS"],
+        TagField::Label => &["ORGANIZATION", "PUBLISHER"],
+        _ => &[],
+    }
+}
+
+fn read_flac(path: &Path) -> Result<FileTags, String> {
+    let tag =
+        FlacTag::read_from_path(path).map_err(|e| format!("Failed to read FLAC tag: {}", e))?;
+
+    let values = match tag.vorbis_comments() {
+        Some(vorbis) => TagField::ALL
+            .iter()
+            .map(|&field| {
+                let value = std::iter::once(field.vorbis_key())
+                    .chain(fallback_vorbis_keys(field).iter().copied())
+                    .find_map(|key| {
+                        vorbis
+                            .get(key)
+                            .and_then(|v| joined(field, v.iter().map(String::as_str)))
+                    });
+                (field, value)
+            })
+            .collect(),
+        None => TagValues::new(),
+    };
+    let cover = tag.pictures().next().map(|pic| pic.data.clone());
+
+    Ok(FileTags { values, cover })
+}
+
+fn write_flac(path: &Path, values: &TagValues, cover: &CoverChange) -> Result<(), String> {
+    let mut tag =
+        FlacTag::read_from_path(path).map_err(|e| format!("Failed to read FLAC tag: {}", e))?;
+
+    for (&field, value) in values {
+        for key in fallback_vorbis_keys(field) {
+            tag.remove_vorbis(key);
+        }
+        match value {
+            Some(value) => tag.set_vorbis(field.vorbis_key(), field.split_values(value)),
+            None => tag.remove_vorbis(field.vorbis_key()),
+        }
+    }
+
+    if *cover != CoverChange::Keep {
+        let others: Vec<_> = tag
+            .pictures()
+            .filter(|pic| pic.picture_type != FlacPictureType::CoverFront)
+            .map(|pic| (pic.mime_type.clone(), pic.picture_type, pic.data.clone()))
+            .collect();
+        tag.remove_blocks(BlockType::Picture);
+        if let CoverChange::Set(data) = cover {
+            let mime = match ImageFormat::from_bytes(data) {
+                Some(ImageFormat::Png) => "image/png",
+                Some(ImageFormat::Webp) => "image/webp",
+                _ => "image/jpeg",
+            };
+            tag.add_picture(mime, FlacPictureType::CoverFront, data.clone());
+        }
+        for (mime, picture_type, data) in others {
+            tag.add_picture(mime, picture_type, data);
+        }
+    }
+
+    tag.write_to_path(path)
+        .map_err(|e| format!("Failed to write FLAC tag: {}", e))
+}
+
+// ---------------------------------------------------------------------------
+// MP4 / M4A (iTunes atoms)
+// ---------------------------------------------------------------------------
+
+/// Fields stored as iTunes freeform ("----") atoms, with the names Picard
+/// and iTunes use.
+fn freeform_ident(field: TagField) -> Option<FreeformIdent<'static>> {
+    let name = match field {
+        TagField::Label => "LABEL",
+        TagField::Key => "initialkey",
+        TagField::MusicBrainzRecordingId => "MusicBrainz Track Id",
+        TagField::MusicBrainzReleaseId => "MusicBrainz Album Id",
+        TagField::MusicBrainzReleaseGroupId => "MusicBrainz Release Group Id",
+        TagField::MusicBrainzArtistId => "MusicBrainz Artist Id",
+        TagField::MusicBrainzAlbumArtistId => "MusicBrainz Album Artist Id",
+        _ => return None,
+    };
+    Some(FreeformIdent::new("com.apple.iTunes", name))
+}
+
+fn read_mp4(path: &Path) -> Result<FileTags, String> {
+    let tag = Mp4Tag::read_from_path(path).map_err(|e| format!("Failed to read M4A tag: {}", e))?;
+
+    let values = TagField::ALL
+        .iter()
+        .map(|&field| {
+            let value = match field {
+                TagField::Title => tag.title().map(str::to_string),
+                TagField::Artist => joined(field, tag.artists()),
+                TagField::Album => tag.album().map(str::to_string),
+                TagField::AlbumArtist => joined(field, tag.album_artists()),
+                TagField::Genre => joined(field, tag.genres()),
+                TagField::Year => tag.year().map(str::to_string),
+                TagField::TrackNumber => tag.track_number().map(|n| n.to_string()),
+                TagField::TrackTotal => tag.total_tracks().map(|n| n.to_string()),
+                TagField::DiscNumber => tag.disc_number().map(|n| n.to_string()),
+                TagField::DiscTotal => tag.total_discs().map(|n| n.to_string()),
+                TagField::Composer => joined(field, tag.composers()),
+                TagField::Comment => joined(field, tag.comments()),
+                TagField::Compilation => tag.compilation().then(|| "1".to_string()),
+                TagField::Bpm => tag.bpm().map(|n| n.to_string()),
+                _ => freeform_ident(field).and_then(|ident| joined(field, tag.strings_of(&ident))),
+            };
+            (field, value)
+        })
+        .collect();
+    let cover = tag.artwork().map(|img| img.data.to_vec());
+
+    Ok(FileTags { values, cover })
+}
+
+fn write_mp4(path: &Path, values: &TagValues, cover: &CoverChange) -> Result<(), String> {
+    let mut tag =
+        Mp4Tag::read_from_path(path).map_err(|e| format!("Failed to read M4A tag: {}", e))?;
+
+    // Numeric atoms; values are validated before they get here, anything
+    // that still doesn't fit a u16 clears the atom
+    let number = |value: &str| value.parse::<f64>().ok().map(|n| n.round() as u16);
+
+    for (&field, value) in values {
+        let value = value.as_deref();
+        match field {
+            TagField::Title => match value {
+                Some(v) => tag.set_title(v),
+                None => tag.remove_title(),
+            },
+            TagField::Artist => match value {
+                Some(v) => tag.set_artists(field.split_values(v)),
+                None => tag.remove_artists(),
+            },
+            TagField::Album => match value {
+                Some(v) => tag.set_album(v),
+                None => tag.remove_album(),
+            },
+            TagField::AlbumArtist => match value {
+                Some(v) => tag.set_album_artists(field.split_values(v)),
+                None => tag.remove_album_artists(),
+            },
+            TagField::Genre => match value {
+                Some(v) => tag.set_genres(field.split_values(v)),
+                None => tag.remove_genres(),
+            },
+            TagField::Year => match value {
+                Some(v) => tag.set_year(v),
+                None => tag.remove_year(),
+            },
+            TagField::TrackNumber => match value.and_then(number) {
+                Some(n) => tag.set_track_number(n),
+                None => tag.remove_track_number(),
+            },
+            TagField::TrackTotal => match value.and_then(number) {
+                Some(n) => tag.set_total_tracks(n),
+                None => tag.remove_total_tracks(),
+            },
+            TagField::DiscNumber => match value.and_then(number) {
+                Some(n) => tag.set_disc_number(n),
+                None => tag.remove_disc_number(),
+            },
+            TagField::DiscTotal => match value.and_then(number) {
+                Some(n) => tag.set_total_discs(n),
+                None => tag.remove_total_discs(),
+            },
+            TagField::Composer => match value {
+                Some(v) => tag.set_composers(field.split_values(v)),
+                None => tag.remove_composers(),
+            },
+            TagField::Comment => match value {
+                Some(v) => tag.set_comment(v),
+                None => tag.remove_comments(),
+            },
+            TagField::Compilation => match value {
+                Some(_) => tag.set_compilation(),
+                None => tag.remove_compilation(),
+            },
+            TagField::Bpm => match value.and_then(number) {
+                Some(n) => tag.set_bpm(n),
+                None => tag.remove_bpm(),
+            },
+            _ => {
+                if let Some(ident) = freeform_ident(field) {
+                    tag.remove_data_of(&ident);
+                    if let Some(v) = value {
+                        tag.set_data(ident, Data::Utf8(v.to_string()));
+                    }
+                }
+            }
+        }
+    }
+
+    match cover {
+        CoverChange::Keep => {}
+        CoverChange::Remove => tag.remove_artworks(),
+        CoverChange::Set(data) => match ImageFormat::from_bytes(data) {
+            Some(ImageFormat::Png) => tag.set_artwork(Img::png(data.clone())),
+            Some(ImageFormat::Jpeg) => tag.set_artwork(Img::jpeg(data.clone())),
+            _ => return Err("M4A cover art must be JPEG or PNG".to_string()),
+        },
+    }
+
+    tag.write_to_path(path)
+        .map_err(|e| format!("Failed to write M4A tag: {}", e))
+}
+
+// ---------------------------------------------------------------------------
+// Ratings (see `rating` for the scales)
+// ---------------------------------------------------------------------------
+
+const FMPS_RATING: &str = "FMPS_RATING";
+const VORBIS_RATING: &str = "RATING";
+/// MP4 rating atom, as written by Mp3tag and MediaMonkey
+const MP4_RATE: Fourcc = Fourcc(*b"rate");
+/// The same atom as lofty names it in a generic tag
+const MP4_RATE_KEY: &str = "rate";
+/// POPM owner for new frames; most players read this one
+const POPM_EMAIL: &str = "Windows Media Player 9 Series";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RatingTag {
+    Popm,
+    LoftyVorbis,
+    FlacVorbis,
+    Mp4,
+}
+
+fn rating_tag(path: &Path) -> Result<RatingTag, String> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match (tag_format(path)?, ext.as_str()) {
+        (TagFormat::Mp4, _) => Ok(RatingTag::Mp4),
+        (TagFormat::Flac, _) => Ok(RatingTag::FlacVorbis),
+        (_, "mp3") => Ok(RatingTag::Popm),
+        (_, "ogg" | "oga" | "opus") => Ok(RatingTag::LoftyVorbis),
+        _ => Err(format!("Ratings are not supported for .{} files", ext)),
+    }
+}
+
+/// FMPS_RATING first (it is unambiguous), then RATING. `None` when
+/// neither field is there.
+fn vorbis_rating<'a>(get: impl Fn(&str) -> Option<&'a str>) -> Option<Option<u8>> {
+    let (fmps, percent) = (get(FMPS_RATING), get(VORBIS_RATING));
+    if fmps.is_none() && percent.is_none() {
+        return None;
+    }
+    Some(
+        fmps.and_then(rating::from_fmps)
+            .or_else(|| percent.and_then(rating::from_percent)),
+    )
+}
+
+/// Whether the file's format has a rating tag we can write.
+pub fn supports_rating(path: &Path) -> bool {
+    rating_tag(path).is_ok()
+}
+
+/// The rating byte of a POPM frame body: owner, NUL, rating, counter.
+fn popm_rating_byte(body: &[u8]) -> Option<u8> {
+    let owner_end = body.iter().position(|&b| b == 0)?;
+    body.get(owner_end + 1).copied()
+}
+
+/// Read a rating in half stars from a tag lofty already loaded, so a scan
+/// doesn't open each file twice. `None` when the tag has no rating field
+/// (or its format has none), `Some(None)` when the field is there but says
+/// unrated, as after another player cleared it.
+pub fn rating_in_tag(tag: &Tag) -> Option<Option<u8>> {
+    match tag.tag_type() {
+        TagType::Id3v2 => tag
+            .get(&ItemKey::Popularimeter)
+            .map(|item| match item.value() {
+                ItemValue::Binary(body) => popm_rating_byte(body).and_then(rating::from_popm),
+                _ => None,
+            }),
+        TagType::VorbisComments => {
+            vorbis_rating(|key| tag.get_string(&ItemKey::Unknown(key.to_string())))
+        }
+        TagType::Mp4Ilst => {
+            let rate = ItemKey::Unknown(MP4_RATE_KEY.to_string());
+            let values: Vec<&str> = tag.get_strings(&rate).collect();
+            (!values.is_empty()).then(|| values.into_iter().find_map(rating::from_percent))
+        }
+        _ => None,
+    }
+}
+
+/// Write a rating in half stars to a file; `None` removes it. Only the
+/// rating tags are touched.
+pub fn write_rating(path: &Path, value: Option<u8>) -> Result<(), String> {
+    let value = rating::validate(value)?;
+    match rating_tag(path)? {
+        RatingTag::Popm => {
+            let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+            let mpeg = MpegFile::read_from(&mut file, ParseOptions::new())
+                .map_err(|e| format!("Failed to read tags: {}", e))?;
+            drop(file);
+            let mut tag = mpeg.id3v2().cloned().unwrap_or_else(Id3v2Tag::new);
+
+            // Keep the owner and play counter of an existing frame
+            let popm = FrameId::new("POPM").map_err(|e| e.to_string())?;
+            let removed: Vec<_> = tag.remove(&popm).collect();
+            let (email, counter) = removed
+                .into_iter()
+                .find_map(|frame| match frame {
+                    Frame::Popularimeter(popm) => Some((popm.email.to_string(), popm.counter)),
+                    _ => None,
+                })
+                .unwrap_or_else(|| (POPM_EMAIL.to_string(), 0));
+            if let Some(byte) = value.and_then(rating::to_popm) {
+                tag.insert(Frame::Popularimeter(PopularimeterFrame::new(
+                    email, byte, counter,
+                )));
+            }
+            tag.save_to_path(path, WriteOptions::default())
+                .map_err(|e| format!("Failed to write tags: {}", e))
+        }
+        RatingTag::LoftyVorbis => {
+            let mut tagged_file = Probe::open(path)
+                .map_err(|e| format!("Failed to open file: {}", e))?
+                .read()
+                .map_err(|e| format!("Failed to read tags: {}", e))?;
+            if tagged_file.primary_tag().is_none() {
+                let tag_type = tagged_file.primary_tag_type();
+                tagged_file.insert_tag(Tag::new(tag_type));
+            }
+            let tag = tagged_file
+                .primary_tag_mut()
+                .ok_or_else(|| "Failed to create tag".to_string())?;
+            let fmps = ItemKey::Unknown(FMPS_RATING.to_string());
+            let percent = ItemKey::Unknown(VORBIS_RATING.to_string());
+            tag.remove_key(&fmps);
+            tag.remove_key(&percent);
+            if let Some(value) = value {
+                tag.insert_text(fmps, rating::to_fmps(value));
+                tag.insert_text(percent, rating::to_percent(value));
+            }
+            tag.save_to_path(path, WriteOptions::default())
+                .map_err(|e| format!("Failed to write tags: {}", e))
+        }
+        RatingTag::FlacVorbis => {
+            let mut tag = FlacTag::read_from_path(path)
+                .map_err(|e| format!("Failed to read FLAC tag: {}", e))?;
+            match value {
+                Some(value) => {
+                    tag.set_vorbis(FMPS_RATING, vec![rating::to_fmps(value)]);
+                    tag.set_vorbis(VORBIS_RATING, vec![rating::to_percent(value)]);
+                }
+                None => {
+                    tag.remove_vorbis(FMPS_RATING);
+                    tag.remove_vorbis(VORBIS_RATING);
+                }
+            }
+            tag.write_to_path(path)
+                .map_err(|e| format!("Failed to write FLAC tag: {}", e))
+        }
+        RatingTag::Mp4 => {
+            let mut tag = Mp4Tag::read_from_path(path)
+                .map_err(|e| format!("Failed to read M4A tag: {}", e))?;
+            tag.remove_data_of(&MP4_RATE);
+            if let Some(value) = value {
+                tag.set_data(MP4_RATE, Data::Utf8(rating::to_percent(value)));
+            }
+            tag.write_to_path(path)
+                .map_err(|e| format!("Failed to write M4A tag: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A PNG signature is all `ImageFormat` and the tag writers look at.
+    const COVER: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    /// Twenty silent MPEG-1 Layer III frames, 128 kbps at 44.1 kHz.
+    fn mp3() -> Vec<u8> {
+        let mut frame = vec![0u8; 417];
+        frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+        frame.repeat(20)
+    }
+
+    /// A FLAC stream with a STREAMINFO block (stereo, 16 bit, 44.1 kHz)
+    /// and no frames.
+    fn flac() -> Vec<u8> {
+        let mut data = b"fLaC".to_vec();
+        data.extend([0x80, 0, 0, 34]);
+        data.extend([0x10, 0, 0x10, 0, 0, 0, 0, 0, 0, 0]);
+        data.extend([0x0A, 0xC4, 0x42, 0xF0]);
+        data.extend([0; 20]);
+        data
+    }
+
+    fn atom(name: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut atom = (payload.len() as u32 + 8).to_be_bytes().to_vec();
+        atom.extend(name);
+        atom.extend(payload);
+        atom
+    }
+
+    /// An M4A whose only track has no samples.
+    fn m4a() -> Vec<u8> {
+        let empty = |name, len| atom(name, &vec![0; len]);
+        // Version 0 mvhd/mdhd: timescale at 12, duration at 16
+        let header = |name, len, timescale: u32| {
+            let mut payload = vec![0; len];
+            payload[12..16].copy_from_slice(&timescale.to_be_bytes());
+            payload[16..20].copy_from_slice(&timescale.to_be_bytes());
+            atom(name, &payload)
+        };
+        let mut hdlr = vec![0; 25];
+        hdlr[8..12].copy_from_slice(b"soun");
+        // AAC LC, stereo, 44.1 kHz
+        let mut esds = vec![0, 0, 0, 0, 0x03, 25, 0, 1, 0, 0x04, 17, 0x40, 0x15];
+        esds.extend([0; 11]);
+        esds.extend([0x05, 2, 0x12, 0x10, 0x06, 1, 0x02]);
+        let mut mp4a = vec![0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 16];
+        mp4a.extend([0, 0, 0, 0, 0xAC, 0x44, 0, 0]);
+        mp4a.extend(atom(b"esds", &esds));
+        let mut stsd = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        stsd.extend(atom(b"mp4a", &mp4a));
+
+        let stbl = [
+            atom(b"stsd", &stsd),
+            empty(b"stts", 8),
+            empty(b"stsc", 8),
+            empty(b"stsz", 12),
+            empty(b"stco", 8),
+        ]
+        .concat();
+        let mdia = [
+            header(b"mdhd", 24, 44_100),
+            atom(b"hdlr", &hdlr),
+            atom(b"minf", &atom(b"stbl", &stbl)),
+        ]
+        .concat();
+        let trak = [empty(b"tkhd", 84), atom(b"mdia", &mdia)].concat();
+        let moov = [header(b"mvhd", 100, 1000), atom(b"trak", &trak)].concat();
+        [
+            atom(b"ftyp", b"M4A \0\0\0\0M4A mp42isom"),
+            atom(b"moov", &moov),
+            atom(b"mdat", &[0; 16]),
+        ]
+        .concat()
+    }
+
+    /// Ogg's CRC-32: polynomial 0x04C11DB7, unreflected, no final xor.
+    fn ogg_crc(data: &[u8]) -> u32 {
+        let mut crc = 0u32;
+        for &byte in data {
+            crc ^= u32::from(byte) << 24;
+            for _ in 0..8 {
+                crc = if crc & 0x8000_0000 != 0 {
+                    (crc << 1) ^ 0x04C1_1DB7
+                } else {
+                    crc << 1
+                };
+            }
+        }
+        crc
+    }
+
+    /// One page of stream 1 holding whole `packets`.
+    fn ogg_page(flags: u8, granule: u64, sequence: u32, packets: &[&[u8]]) -> Vec<u8> {
+        let mut lacing = Vec::new();
+        for packet in packets {
+            lacing.resize(lacing.len() + packet.len() / 255, 255);
+            lacing.push((packet.len() % 255) as u8);
+        }
+        let mut page = b"OggS\0".to_vec();
+        page.push(flags);
+        page.extend(granule.to_le_bytes());
+        page.extend(1u32.to_le_bytes());
+        page.extend(sequence.to_le_bytes());
+        page.extend([0; 4]);
+        page.push(lacing.len() as u8);
+        page.extend(lacing);
+        for packet in packets {
+            page.extend(*packet);
+        }
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    /// An empty comment header body: vendor "test", no comments.
+    fn no_comments(magic: &[u8]) -> Vec<u8> {
+        let mut packet = magic.to_vec();
+        packet.extend(4u32.to_le_bytes());
+        packet.extend(b"test");
+        packet.extend(0u32.to_le_bytes());
+        packet
+    }
+
+    /// Ogg Vorbis headers (stereo, 44.1 kHz) and one second of audio pages.
+    fn ogg() -> Vec<u8> {
+        let mut ident = b"\x01vorbis".to_vec();
+        ident.extend(0u32.to_le_bytes());
+        ident.push(2);
+        ident.extend(44_100u32.to_le_bytes());
+        ident.extend([0; 12]);
+        // Block sizes 256/2048, framing bit
+        ident.extend([0xB8, 1]);
+        let mut comments = no_comments(b"\x03vorbis");
+        comments.push(1);
+        [
+            ogg_page(0x02, 0, 0, &[&ident]),
+            ogg_page(0, 0, 1, &[&comments, b"\x05vorbis\0"]),
+            ogg_page(0x04, 44_100, 2, &[&[0; 32]]),
+        ]
+        .concat()
+    }
+
+    /// Ogg Opus headers (stereo) and one second of audio pages.
+    fn opus() -> Vec<u8> {
+        let mut head = b"OpusHead".to_vec();
+        head.extend([1, 2]);
+        head.extend(312u16.to_le_bytes());
+        head.extend(48_000u32.to_le_bytes());
+        head.extend([0, 0, 0]);
+        [
+            ogg_page(0x02, 0, 0, &[&head]),
+            ogg_page(0, 0, 1, &[&no_comments(b"OpusTags")]),
+            ogg_page(0x04, 48_312, 2, &[&[0; 32]]),
+        ]
+        .concat()
+    }
+
+    fn values(pairs: &[(TagField, Option<&str>)]) -> TagValues {
+        pairs
+            .iter()
+            .map(|(field, value)| (*field, value.map(str::to_string)))
+            .collect()
+    }
+
+    /// Tag a fresh `name` file holding `data` with a cover, read it back,
+    /// then clear a field and the cover.
+    fn round_trip(name: &str, data: &[u8]) {
+        let dir = std::env::temp_dir().join(format!("audion_tags_{}_{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, data).unwrap();
+
+        let edit = values(&[
+            (TagField::Title, Some("Song")),
+            (TagField::Artist, Some("First; Second")),
+            (TagField::Album, Some("Album")),
+            (TagField::Genre, Some("Rock; Jazz")),
+            (TagField::TrackNumber, Some("3")),
+        ]);
+        write_file_tags(&path, &edit, &CoverChange::Set(COVER.to_vec())).unwrap();
+        let tags = read_file_tags(&path).unwrap();
+        for (field, value) in &edit {
+            assert_eq!(&tags.values[field], value, "{} {:?}", name, field);
+        }
+        assert_eq!(tags.cover.as_deref(), Some(COVER), "{}", name);
+
+        // Fields left out of an edit are kept
+        let clear = values(&[(TagField::Album, None)]);
+        write_file_tags(&path, &clear, &CoverChange::Remove).unwrap();
+        let tags = read_file_tags(&path).unwrap();
+        assert_eq!(tags.values[&TagField::Album], None, "{}", name);
+        assert_eq!(
+            tags.values[&TagField::Title].as_deref(),
+            Some("Song"),
+            "{}",
+            name
+        );
+        assert_eq!(
+            tags.values[&TagField::Artist].as_deref(),
+            Some("First; Second"),
+            "{}",
+            name
+        );
+        assert_eq!(tags.cover, None, "{}", name);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_round_trip_mp3() {
+        round_trip("song.mp3", &mp3());
+    }
+
+    #[test]
+    fn test_round_trip_flac() {
+        round_trip("song.flac", &flac());
+    }
+
+    #[test]
+    fn test_round_trip_m4a() {
+        round_trip("song.m4a", &m4a());
+    }
+
+    #[test]
+    fn test_round_trip_ogg() {
+        round_trip("song.ogg", &ogg());
+    }
+
+    #[test]
+    fn test_round_trip_opus() {
+        round_trip("song.opus", &opus());
+    }
+}
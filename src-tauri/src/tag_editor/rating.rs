@@ -0,0 +1,148 @@
+// Star ratings and how file tags store them
+//
+// Ratings are 0–5 stars in half-star steps, kept as a count of half stars
+// (0–10); `None` is unrated. Files spell them three ways:
+//   ID3v2 POPM   one byte, 1–255 (0 is unrated), on the Windows Media Player
+//                scale, with the in-between values MusicBee uses for halves
+//   Vorbis       FMPS_RATING as 0.0–1.0, RATING as 0–100 (or 0–5 stars)
+//   MP4          a `rate` atom holding 0–100
+// A rating of zero stars can't be told apart from "unrated" in POPM, so it
+// is written as no rating there.
+
+/// Highest rating, in half stars.
+pub const MAX_RATING: u8 = 10;
+
+/// Check a rating from the frontend or a sync peer.
+pub fn validate(rating: Option<u8>) -> Result<Option<u8>, String> {
+    match rating {
+        Some(r) if r > MAX_RATING => Err(format!(
+            "Rating must be between 0 and {} half stars: {}",
+            MAX_RATING, r
+        )),
+        _ => Ok(rating),
+    }
+}
+
+/// POPM byte written for each rating, zero stars through five.
+const POPM_VALUES: [u8; 11] = [0, 13, 1, 54, 64, 118, 128, 186, 196, 242, 255];
+
+pub fn from_popm(byte: u8) -> Option<u8> {
+    if byte == 0 {
+        return None;
+    }
+    // Half stars only where the byte is exactly one we'd write; whole stars
+    // by the ranges Windows Media Player reads
+    if let Some(half) = POPM_VALUES.iter().position(|&v| v == byte) {
+        return Some(half as u8);
+    }
+    Some(match byte {
+        1..=31 => 2,
+        32..=95 => 4,
+        96..=159 => 6,
+        160..=223 => 8,
+        _ => 10,
+    })
+}
+
+/// The POPM byte for a rating; `None` means no POPM frame.
+pub fn to_popm(rating: u8) -> Option<u8> {
+    POPM_VALUES
+        .get(rating.min(MAX_RATING) as usize)
+        .copied()
+        .filter(|&byte| byte > 0)
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .replace(',', ".")
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && *n >= 0.0)
+}
+
+fn from_fraction(fraction: f64) -> u8 {
+    (fraction.clamp(0.0, 1.0) * MAX_RATING as f64).round() as u8
+}
+
+/// FMPS_RATING: a fraction from 0.0 to 1.0.
+pub fn from_fmps(value: &str) -> Option<u8> {
+    parse_number(value).filter(|&n| n <= 1.0).map(from_fraction)
+}
+
+pub fn to_fmps(rating: u8) -> String {
+    format!("{:.1}", rating.min(MAX_RATING) as f64 / MAX_RATING as f64)
+}
+
+/// Vorbis RATING or the MP4 `rate` atom: 0–100, or 0–5 stars from
+/// players that write the star count.
+pub fn from_percent(value: &str) -> Option<u8> {
+    let n = parse_number(value)?;
+    // We write multiples of ten, so nothing up to 5 is ours as a percentage
+    if n <= 5.0 {
+        return Some(from_fraction(n / 5.0));
+    }
+    (n <= 100.0).then(|| from_fraction(n / 100.0))
+}
+
+pub fn to_percent(rating: u8) -> String {
+    (rating.min(MAX_RATING) as u32 * 10).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(None), Ok(None));
+        assert_eq!(validate(Some(0)), Ok(Some(0)));
+        assert_eq!(validate(Some(10)), Ok(Some(10)));
+        assert!(validate(Some(11)).is_err());
+    }
+
+    #[test]
+    fn test_popm() {
+        assert_eq!(from_popm(0), None);
+        // Windows Media Player whole stars
+        assert_eq!(from_popm(1), Some(2));
+        assert_eq!(from_popm(64), Some(4));
+        assert_eq!(from_popm(128), Some(6));
+        assert_eq!(from_popm(196), Some(8));
+        assert_eq!(from_popm(255), Some(10));
+        // MusicBee halves, and other bytes by range
+        assert_eq!(from_popm(118), Some(5));
+        assert_eq!(from_popm(242), Some(9));
+        assert_eq!(from_popm(100), Some(6));
+        assert_eq!(from_popm(230), Some(10));
+
+        assert_eq!(to_popm(0), None);
+        for rating in 1..=MAX_RATING {
+            assert_eq!(to_popm(rating).and_then(from_popm), Some(rating));
+        }
+    }
+
+    #[test]
+    fn test_vorbis_and_mp4() {
+        assert_eq!(from_fmps("0.7"), Some(7));
+        assert_eq!(from_fmps("0,5"), Some(5));
+        assert_eq!(from_fmps("1"), Some(10));
+        assert_eq!(from_fmps("1.5"), None);
+        assert_eq!(from_fmps("great"), None);
+        assert_eq!(to_fmps(7), "0.7");
+        assert_eq!(to_fmps(10), "1.0");
+
+        assert_eq!(from_percent("60"), Some(6));
+        assert_eq!(from_percent("100"), Some(10));
+        assert_eq!(from_percent("3"), Some(6));
+        assert_eq!(from_percent("4.5"), Some(9));
+        assert_eq!(from_percent("0"), Some(0));
+        assert_eq!(from_percent("120"), None);
+        assert_eq!(from_percent("-1"), None);
+        assert_eq!(to_percent(7), "70");
+        for rating in 0..=MAX_RATING {
+            assert_eq!(from_percent(&to_percent(rating)), Some(rating));
+            assert_eq!(from_fmps(&to_fmps(rating)), Some(rating));
+        }
+    }
+}
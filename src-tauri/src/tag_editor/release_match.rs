@@ -0,0 +1,240 @@
+// Matching a local album against a MusicBrainz release
+//
+// Every local track is paired with at most one release track. A pair is
+// scored on title similarity, duration difference and position; pairs are
+// then taken greedily, best first. A release's score combines how well its
+// track count fits with the average pair score, so a release whose tracks
+// all line up scores close to 1.0.
+use std::collections::HashSet;
+
+/// Pairs scoring below this are left unmatched.
+pub const MIN_PAIR_SCORE: f64 = 0.4;
+
+/// Durations further apart than this (in seconds) score zero.
+const DURATION_TOLERANCE_SECS: f64 = 15.0;
+
+const TITLE_WEIGHT: f64 = 0.5;
+const DURATION_WEIGHT: f64 = 0.3;
+const POSITION_WEIGHT: f64 = 0.2;
+const TRACK_COUNT_WEIGHT: f64 = 0.3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalTrack {
+    pub title: Option<String>,
+    pub duration_secs: Option<i32>,
+    pub track_number: Option<i32>,
+    pub disc_number: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseTrack {
+    pub title: String,
+    pub duration_ms: Option<u32>,
+    pub track_number: u32,
+    pub disc_number: u32,
+}
+
+/// Local track `local` matched to release track `release` (indices into
+/// the slices given to `match_tracks`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPair {
+    pub local: usize,
+    pub release: usize,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseMatch {
+    /// 0.0 – 1.0
+    pub score: f64,
+    pub pairs: Vec<TrackPair>,
+}
+
+/// Lowercase alphanumeric words, without bracketed suffixes such as
+/// "(Remastered 2011)" or "[Live]".
+fn normalize_title(title: &str) -> String {
+    let mut out = String::with_capacity(title.len());
+    let mut depth = 0usize;
+    for c in title.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ if depth > 0 => {}
+            c if c.is_alphanumeric() => out.extend(c.to_lowercase()),
+            _ => {
+                if !out.ends_with(' ') {
+                    out.push(' ');
+                }
+            }
+        }
+    }
+    out.trim().to_string()
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+/// 1.0 for titles that are equal once normalized, falling with edit distance.
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = normalize_title(a).chars().collect();
+    let b: Vec<char> = normalize_title(b).chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+    1.0 - levenshtein(&a, &b) as f64 / longest as f64
+}
+
+fn duration_score(local_secs: Option<i32>, release_ms: Option<u32>) -> f64 {
+    match (local_secs, release_ms) {
+        (Some(local), Some(release)) => {
+            let diff = (local as f64 - release as f64 / 1000.0).abs();
+            (1.0 - diff / DURATION_TOLERANCE_SECS).max(0.0)
+        }
+        // Unknown on either side: neither evidence for nor against
+        _ => 0.5,
+    }
+}
+
+fn position_score(local: &LocalTrack, release: &ReleaseTrack) -> f64 {
+    if local.track_number != Some(release.track_number as i32) {
+        return 0.0;
+    }
+    match local.disc_number {
+        Some(disc) if disc == release.disc_number as i32 => 1.0,
+        // Untagged disc number on a single-disc release
+        None if release.disc_number <= 1 => 1.0,
+        _ => 0.5,
+    }
+}
+
+pub fn pair_score(local: &LocalTrack, release: &ReleaseTrack) -> f64 {
+    let title = local
+        .title
+        .as_deref()
+        .map(|t| title_similarity(t, &release.title))
+        .unwrap_or(0.0);
+    TITLE_WEIGHT * title
+        + DURATION_WEIGHT * duration_score(local.duration_secs, release.duration_ms)
+        + POSITION_WEIGHT * position_score(local, release)
+}
+
+/// Pair local tracks with release tracks, best pairs first.
+pub fn match_tracks(local: &[LocalTrack], release: &[ReleaseTrack]) -> ReleaseMatch {
+    let mut candidates: Vec<TrackPair> = local
+        .iter()
+        .enumerate()
+        .flat_map(|(l, lt)| {
+            release.iter().enumerate().map(move |(r, rt)| TrackPair {
+                local: l,
+                release: r,
+                score: pair_score(lt, rt),
+            })
+        })
+        .filter(|pair| pair.score >= MIN_PAIR_SCORE)
+        .collect();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let mut used_local = HashSet::new();
+    let mut used_release = HashSet::new();
+    let mut pairs = Vec::new();
+    for pair in candidates {
+        if used_local.contains(&pair.local) || used_release.contains(&pair.release) {
+            continue;
+        }
+        used_local.insert(pair.local);
+        used_release.insert(pair.release);
+        pairs.push(pair);
+    }
+    pairs.sort_by_key(|pair| pair.local);
+
+    let score = if local.is_empty() || release.is_empty() {
+        0.0
+    } else {
+        let count_fit =
+            local.len().min(release.len()) as f64 / local.len().max(release.len()) as f64;
+        let mean_pair = pairs.iter().map(|p| p.score).sum::<f64>() / local.len() as f64;
+        TRACK_COUNT_WEIGHT * count_fit + (1.0 - TRACK_COUNT_WEIGHT) * mean_pair
+    };
+
+    ReleaseMatch { score, pairs }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(title: &str, secs: i32, n: i32) -> LocalTrack {
+        LocalTrack {
+            title: Some(title.to_string()),
+            duration_secs: Some(secs),
+            track_number: Some(n),
+            disc_number: None,
+        }
+    }
+
+    fn release(title: &str, secs: u32, n: u32) -> ReleaseTrack {
+        ReleaseTrack {
+            title: title.to_string(),
+            duration_ms: Some(secs * 1000),
+            track_number: n,
+            disc_number: 1,
+        }
+    }
+
+    #[test]
+    fn test_title_similarity() {
+        assert_eq!(title_similarity("Karma Police", "karma police"), 1.0);
+        assert_eq!(
+            title_similarity("Paranoid Android (Remastered)", "Paranoid Android"),
+            1.0
+        );
+        assert!(title_similarity("Lucky", "Luckey") > 0.8);
+        assert!(title_similarity("Airbag", "No Surprises") < 0.3);
+        assert_eq!(title_similarity("", ""), 0.0);
+    }
+
+    #[test]
+    fn test_match_tracks() {
+        // Local files in a shuffled order with one mistagged track number
+        let local_tracks = vec![
+            local("Subterranean Homesick Alien", 267, 3),
+            local("Airbag", 284, 1),
+            local("Paranoid Android", 383, 3),
+        ];
+        let release_tracks = vec![
+            release("Airbag", 284, 1),
+            release("Paranoid Android", 387, 2),
+            release("Subterranean Homesick Alien", 267, 3),
+        ];
+        let result = match_tracks(&local_tracks, &release_tracks);
+        let mapping: Vec<(usize, usize)> =
+            result.pairs.iter().map(|p| (p.local, p.release)).collect();
+        assert_eq!(mapping, vec![(0, 2), (1, 0), (2, 1)]);
+        assert!(result.score > 0.9);
+
+        // A release with extra tracks and different titles scores lower
+        let other = vec![
+            release("Intro", 60, 1),
+            release("Something Else", 200, 2),
+            release("Airbag", 284, 3),
+            release("Outro", 90, 4),
+        ];
+        let worse = match_tracks(&local_tracks, &other);
+        assert!(worse.score < result.score - 0.3);
+        assert!(worse.pairs.iter().all(|p| p.score >= MIN_PAIR_SCORE));
+
+        assert_eq!(match_tracks(&[], &release_tracks).score, 0.0);
+    }
+}
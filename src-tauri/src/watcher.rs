@@ -0,0 +1,540 @@
+// Live watching of the registered music folders
+//
+// Each folder gets a native notify watcher (inotify / FSEvents /
+// ReadDirectoryChangesW), or a polling watcher on network mounts where
+// native notifications miss writes made by other machines. Raw events are
+// gathered by one worker thread and applied once a burst has been quiet for
+// `DEBOUNCE`: new and modified files go through the regular scan pipeline
+// (so progress arrives as "scan-batch-ready" events like a rescan) and
+// vanished files are removed from the library.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{self, Receiver, RecvTimeoutError, Sender};
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::commands::folder_playlist;
+use crate::commands::library::{self, ScanSource};
+use crate::db::{queries, Database};
+use crate::scanner::rules::{LibraryRules, IGNORE_FILE_NAME};
+
+/// Quiet period that ends a burst of events.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+/// Flush even while events keep arriving (a long copy), so imports trickle
+/// in instead of waiting for the whole copy.
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(30);
+/// How often the folder list is re-read, which also retries folders that
+/// were unavailable (unmounted shares, unplugged drives).
+const FOLDER_REFRESH: Duration = Duration::from_secs(30);
+const TICK: Duration = Duration::from_millis(500);
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 120;
+
+/// Filesystems whose native change notifications don't see remote writes.
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+const NETWORK_FILESYSTEMS: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "afpfs", "9p", "ncpfs", "afs", "ceph",
+    "glusterfs", "davfs", "fuse.davfs2", "fuse.sshfs", "fuse.rclone",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatcherConfig {
+    pub enabled: bool,
+    /// Poll interval for network mounts.
+    pub poll_interval_secs: u64,
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poll_interval_secs: DEFAULT_POLL_INTERVAL_SECS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    Native,
+    Polling,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchedFolder {
+    pub path: String,
+    pub mode: WatchMode,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatcherStatus {
+    pub enabled: bool,
+    pub poll_interval_secs: u64,
+    pub folders: Vec<WatchedFolder>,
+    /// Changed paths waiting for the debounce window to close.
+    pub pending_changes: usize,
+}
+
+enum Message {
+    Fs(notify::Result<Event>),
+    Refresh,
+}
+
+struct Watch {
+    mode: WatchMode,
+    // Dropping the watcher stops it
+    _watcher: Box<dyn Watcher + Send>,
+}
+
+struct WatchSet {
+    config: WatcherConfig,
+    watches: HashMap<String, Watch>,
+}
+
+pub struct FolderWatcher {
+    app_handle: AppHandle,
+    tx: Sender<Message>,
+    state: Arc<Mutex<WatchSet>>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl FolderWatcher {
+    pub fn new(app_handle: AppHandle) -> Self {
+        let (tx, rx) = channel::unbounded();
+        let state = Arc::new(Mutex::new(WatchSet {
+            config: load_config(&app_handle),
+            watches: HashMap::new(),
+        }));
+        let pending = Arc::new(AtomicUsize::new(0));
+
+        let worker = Worker {
+            app_handle: app_handle.clone(),
+            tx: tx.clone(),
+            state: Arc::clone(&state),
+            pending: Arc::clone(&pending),
+        };
+        std::thread::Builder::new()
+            .name("folder-watcher".into())
+            .spawn(move || worker.run(rx))
+            .expect("failed to spawn folder watcher");
+
+        Self {
+            app_handle,
+            tx,
+            state,
+            pending,
+        }
+    }
+
+    /// Re-read the folder list now (after a folder was added or removed).
+    pub fn refresh(&self) {
+        let _ = self.tx.send(Message::Refresh);
+    }
+
+    pub fn set_config(&self, config: WatcherConfig) -> Result<(), String> {
+        save_config(&self.app_handle, &config)?;
+        let mut state = self.state.lock().map_err(|e| e.to_string())?;
+        // Poll interval changes only apply to newly created watchers
+        if state.config.poll_interval_secs != config.poll_interval_secs {
+            state.watches.retain(|_, w| w.mode != WatchMode::Polling);
+        }
+        state.config = config;
+        drop(state);
+        self.refresh();
+        Ok(())
+    }
+
+    pub fn status(&self) -> WatcherStatus {
+        let Ok(state) = self.state.lock() else {
+            return WatcherStatus {
+                enabled: false,
+                poll_interval_secs: DEFAULT_POLL_INTERVAL_SECS,
+                folders: Vec::new(),
+                pending_changes: 0,
+            };
+        };
+        let mut folders: Vec<WatchedFolder> = state
+            .watches
+            .iter()
+            .map(|(path, w)| WatchedFolder {
+                path: path.clone(),
+                mode: w.mode,
+            })
+            .collect();
+        folders.sort_by(|a, b| a.path.cmp(&b.path));
+        WatcherStatus {
+            enabled: state.config.enabled,
+            poll_interval_secs: state.config.poll_interval_secs,
+            folders,
+            pending_changes: self.pending.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct Worker {
+    app_handle: AppHandle,
+    tx: Sender<Message>,
+    state: Arc<Mutex<WatchSet>>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl Worker {
+    fn run(self, rx: Receiver<Message>) {
+        let mut changed: HashSet<PathBuf> = HashSet::new();
+        let mut first_event: Option<Instant> = None;
+        let mut last_event = Instant::now();
+        let mut last_refresh: Option<Instant> = None;
+
+        loop {
+            match rx.recv_timeout(TICK) {
+                Ok(Message::Fs(Ok(event))) => {
+                    // Reads and opens don't change anything we index
+                    if !matches!(event.kind, EventKind::Access(_)) && !event.paths.is_empty() {
+                        changed.extend(event.paths);
+                        first_event.get_or_insert_with(Instant::now);
+                        last_event = Instant::now();
+                        self.pending.store(changed.len(), Ordering::Relaxed);
+                    }
+                }
+                Ok(Message::Fs(Err(e))) => {
+                    tracing::warn!("[WATCHER] Watch error: {}", e);
+                }
+                Ok(Message::Refresh) => last_refresh = None,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+
+            if last_refresh.is_none_or(|t| t.elapsed() >= FOLDER_REFRESH) {
+                self.refresh_watches();
+                last_refresh = Some(Instant::now());
+            }
+
+            let quiet = last_event.elapsed() >= DEBOUNCE;
+            let overdue = first_event.is_some_and(|t| t.elapsed() >= MAX_BATCH_DELAY);
+            if !changed.is_empty() && (quiet || overdue) {
+                let paths = std::mem::take(&mut changed);
+                first_event = None;
+                self.pending.store(0, Ordering::Relaxed);
+                if let Err(e) = self.apply_changes(paths) {
+                    tracing::warn!("[WATCHER] Failed to apply changes: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Folders to watch: music folders plus folder-playlists outside them.
+    fn wanted_folders(&self) -> Vec<String> {
+        let db = self.app_handle.state::<Database>();
+        let Ok(conn) = db.conn.lock() else {
+            return Vec::new();
+        };
+        let mut folders = queries::get_music_folders(&conn).unwrap_or_default();
+        for (_, folder) in queries::get_folder_playlists(&conn).unwrap_or_default() {
+            if !folders.iter().any(|f| folder.starts_with(f.as_str())) {
+                folders.push(folder);
+            }
+        }
+        folders
+    }
+
+    fn refresh_watches(&self) {
+        let folders = self.wanted_folders();
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if !state.config.enabled {
+            state.watches.clear();
+            return;
+        }
+
+        state.watches.retain(|path, _| folders.contains(path));
+        let poll_interval = Duration::from_secs(state.config.poll_interval_secs.max(5));
+        for folder in folders {
+            if state.watches.contains_key(&folder) || !Path::new(&folder).is_dir() {
+                continue;
+            }
+            match start_watch(Path::new(&folder), self.tx.clone(), poll_interval) {
+                Ok(watch) => {
+                    tracing::info!("[WATCHER] Watching {} ({:?})", folder, watch.mode);
+                    state.watches.insert(folder, watch);
+                }
+                Err(e) => tracing::warn!("[WATCHER] Cannot watch {}: {}", folder, e),
+            }
+        }
+    }
+
+    /// Bring the library in line with the current state of `paths`.
+    fn apply_changes(&self, paths: HashSet<PathBuf>) -> Result<(), String> {
+        let db = self.app_handle.state::<Database>().inner().clone();
+        let folder_rules = {
+            let conn = db.conn.lock().map_err(|e| e.to_string())?;
+            queries::get_folder_scan_rules(&conn).unwrap_or_default()
+        };
+        let (rules, mut errors) = LibraryRules::new(&folder_rules);
+
+        // An edited ignore file re-evaluates everything next to it
+        let paths: HashSet<PathBuf> = paths
+            .into_iter()
+            .map(|path| match (path.file_name(), path.parent()) {
+                (Some(name), Some(parent)) if name == IGNORE_FILE_NAME => parent.to_path_buf(),
+                _ => path,
+            })
+            .collect();
+
+        let mut files = Vec::new();
+        let mut stamps = HashMap::new();
+        let mut excluded = Vec::new();
+        // Paths whose tracks may have to go: vanished or newly excluded
+        let mut roots = Vec::new();
+        let changed: Vec<PathBuf> = paths.iter().cloned().collect();
+
+        for path in paths {
+            let Some(path_str) = path.to_str().map(str::to_string) else {
+                continue;
+            };
+            if path.exists() {
+                // Walks a new directory, or yields just the file itself
+                let result = rules.scan(&path_str);
+                files.extend(result.audio_files);
+                stamps.extend(result.stamps);
+                roots.extend(result.excluded.iter().map(|e| e.path.clone()));
+                excluded.extend(result.excluded);
+                errors.extend(result.errors);
+            } else {
+                roots.push(path_str);
+            }
+        }
+        files.sort();
+        files.dedup();
+
+        let min_durations = rules.min_durations();
+        let diff = library::diff_against_library(
+            &db,
+            &roots,
+            &mut files,
+            &stamps,
+            &excluded,
+            &min_durations,
+            false,
+        )?;
+        let (folders, file_playlist_map, synced_playlists) = {
+            let conn = db.conn.lock().map_err(|e| e.to_string())?;
+            let folders: Vec<String> = queries::get_music_folders(&conn)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|f| files.iter().any(|p| p.starts_with(f.as_str())))
+                .collect();
+
+            let folder_playlists = queries::get_folder_playlists(&conn).unwrap_or_default();
+            let mut file_playlist_map: HashMap<String, Vec<i64>> = HashMap::new();
+            for file in &files {
+                for (playlist_id, folder) in &folder_playlists {
+                    if file.starts_with(folder.as_str()) {
+                        file_playlist_map.entry(file.clone()).or_default().push(*playlist_id);
+                    }
+                }
+            }
+            // Folder playlists with anything changed inside, .m3u8 files included
+            let synced_playlists: Vec<i64> = folder_playlists
+                .iter()
+                .filter(|(_, folder)| changed.iter().any(|p| p.starts_with(folder)))
+                .map(|(playlist_id, _)| *playlist_id)
+                .collect();
+            (folders, file_playlist_map, synced_playlists)
+        };
+
+        if files.is_empty()
+            && diff.tracks_deleted == 0
+            && diff.moves.is_empty()
+            && diff.relink_pending.is_empty()
+        {
+            // metadata-only events, unchanged files, or an edited playlist file
+            self.sync_playlist_files(&db, &synced_playlists)?;
+            return Ok(());
+        }
+        tracing::info!(
+            "[WATCHER] {} changed file(s), {} moved, {} removed",
+            files.len(),
+            diff.moves.len(),
+            diff.tracks_deleted + diff.relink_pending.len()
+        );
+
+        tauri::async_runtime::block_on(library::run_scan_and_import(
+            &self.app_handle,
+            Arc::clone(&db.conn),
+            files,
+            file_playlist_map,
+            &diff,
+            min_durations,
+            errors,
+            folders,
+            ScanSource::Rescan,
+        ))?;
+
+        self.sync_playlist_files(&db, &synced_playlists)?;
+        library::finish_scan_cleanup(&db, diff.relink_pending);
+        Ok(())
+    }
+
+    /// Keep folder playlists and their .m3u8 files in step after a change.
+    fn sync_playlist_files(&self, db: &Database, playlist_ids: &[i64]) -> Result<(), String> {
+        if playlist_ids.is_empty() {
+            return Ok(());
+        }
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        folder_playlist::sync_folder_playlists(&conn, playlist_ids);
+        Ok(())
+    }
+}
+
+fn start_watch(path: &Path, tx: Sender<Message>, poll_interval: Duration) -> Result<Watch, String> {
+    let handler = move |res: notify::Result<Event>| {
+        let _ = tx.send(Message::Fs(res));
+    };
+
+    if !is_network_path(path) {
+        let native = RecommendedWatcher::new(handler.clone(), Config::default())
+            .and_then(|mut w| w.watch(path, RecursiveMode::Recursive).map(|_| w));
+        match native {
+            Ok(watcher) => {
+                return Ok(Watch {
+                    mode: WatchMode::Native,
+                    _watcher: Box::new(watcher),
+                })
+            }
+            // e.g. inotify's max_user_watches exhausted by a huge library
+            Err(e) => tracing::warn!(
+                "[WATCHER] Native watch failed for {:?}, polling instead: {}",
+                path,
+                e
+            ),
+        }
+    }
+
+    let mut watcher = PollWatcher::new(handler, Config::default().with_poll_interval(poll_interval))
+        .map_err(|e| e.to_string())?;
+    watcher
+        .watch(path, RecursiveMode::Recursive)
+        .map_err(|e| e.to_string())?;
+    Ok(Watch {
+        mode: WatchMode::Polling,
+        _watcher: Box::new(watcher),
+    })
+}
+
+/// Whether `path` lives on a network share.
+fn is_network_path(path: &Path) -> bool {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+    #[cfg(windows)]
+    {
+        // UNC paths (\\server\share), also in their verbatim \\?\UNC\ form
+        let s = path.to_string_lossy();
+        if s.starts_with(r"\\?\UNC\") || (s.starts_with(r"\\") && !s.starts_with(r"\\?\")) {
+            return true;
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        if let Ok(mounts) = fs::read_to_string("/proc/self/mounts") {
+            if let Some(fs_type) = filesystem_type(&mounts, &path) {
+                return NETWORK_FILESYSTEMS.contains(&fs_type.as_str());
+            }
+        }
+    }
+
+    false
+}
+
+/// Filesystem type of the mount containing `path`, from /proc/mounts text.
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+fn filesystem_type(mounts: &str, path: &Path) -> Option<String> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = unescape_mount_field(fields.next()?);
+            let fs_type = fields.next()?;
+            Some((PathBuf::from(mount_point), fs_type.to_string()))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, fs_type)| fs_type)
+}
+
+/// /proc/mounts escapes space, tab, newline and backslash as octal.
+fn unescape_mount_field(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(pos) = rest.find('\\') {
+        out.push_str(&rest[..pos]);
+        let code = rest.get(pos + 1..pos + 4);
+        match code.and_then(|c| u8::from_str_radix(c, 8).ok()) {
+            Some(byte) => {
+                out.push(byte as char);
+                rest = &rest[pos + 4..];
+            }
+            None => {
+                out.push('\\');
+                rest = &rest[pos + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn config_path(app_handle: &AppHandle) -> Option<PathBuf> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join("watcher.json"))
+}
+
+fn load_config(app_handle: &AppHandle) -> WatcherConfig {
+    config_path(app_handle)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(app_handle: &AppHandle, config: &WatcherConfig) -> Result<(), String> {
+    let path = config_path(app_handle).ok_or("Failed to resolve app data directory")?;
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| format!("Failed to save watcher config: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filesystem_type() {
+        let mounts = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+nas:/export/music /mnt/music nfs4 rw,relatime 0 0
+//server/share /mnt/my\\040share cifs rw 0 0
+tmpfs /mnt/music/cache tmpfs rw 0 0
+";
+        let fs_type = |p: &str| filesystem_type(mounts, Path::new(p));
+        assert_eq!(fs_type("/home/me/Music").as_deref(), Some("ext4"));
+        assert_eq!(fs_type("/mnt/music/Artist/Album").as_deref(), Some("nfs4"));
+        assert_eq!(fs_type("/mnt/music/cache/x").as_deref(), Some("tmpfs"));
+        assert_eq!(fs_type("/mnt/my share/a").as_deref(), Some("cifs"));
+        // Component-wise prefix: /mnt/musicals is not under /mnt/music
+        assert_eq!(fs_type("/mnt/musicals").as_deref(), Some("ext4"));
+        assert_eq!(unescape_mount_field(r"a\134b\x"), r"a\b\x");
+    }
+}
@@ -0,0 +1,326 @@
+// Year in review as a standalone HTML page
+//
+// The page carries everything it shows: styles are inline and cover art is
+// embedded as data URIs, so the exported file can be opened or shared
+// without the app or its cover cache.
+use crate::db::queries::{Track, YearInReview};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Cover art as `data:` URIs, keyed by track id and album id.
+#[derive(Debug, Clone, Default)]
+pub struct ReviewCovers {
+    pub tracks: HashMap<i64, String>,
+    pub albums: HashMap<i64, String>,
+}
+
+impl ReviewCovers {
+    /// A track's own cover, falling back to its album's.
+    fn for_track(&self, track: &Track) -> Option<&str> {
+        self.tracks
+            .get(&track.id)
+            .or_else(|| track.album_id.and_then(|id| self.albums.get(&id)))
+            .map(String::as_str)
+    }
+}
+
+/// `data:` URI for image bytes of the given MIME type.
+pub fn data_uri(mime: &str, base64: &str) -> String {
+    format!("data:{};base64,{}", mime, base64)
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn track_title(track: &Track) -> String {
+    let title = track.title.as_deref().unwrap_or("Unknown title");
+    match track.artist.as_deref() {
+        Some(artist) => format!("{} — {}", escape(title), escape(artist)),
+        None => escape(title),
+    }
+}
+
+fn cover(uri: Option<&str>) -> String {
+    match uri {
+        Some(uri) => format!("<img class=\"cover\" src=\"{}\" alt=\"\">", escape(uri)),
+        None => "<div class=\"cover\"></div>".to_string(),
+    }
+}
+
+fn plays(count: i64) -> String {
+    if count == 1 {
+        "1 play".to_string()
+    } else {
+        format!("{} plays", count)
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; background: #121212; color: #eee; margin: 0; }
+main { max-width: 880px; margin: 0 auto; padding: 32px 24px; }
+h1 { font-size: 40px; margin: 0 0 24px; }
+h2 { font-size: 20px; margin: 32px 0 12px; }
+.totals { display: flex; flex-wrap: wrap; gap: 16px; }
+.total { background: #1e1e1e; border-radius: 8px; padding: 16px; min-width: 140px; }
+.total b { display: block; font-size: 28px; }
+ol { list-style: none; padding: 0; margin: 0; }
+li { display: flex; align-items: center; gap: 12px; padding: 6px 0; }
+.cover { width: 48px; height: 48px; border-radius: 4px; background: #333; object-fit: cover; flex: none; }
+.count { margin-left: auto; color: #aaa; white-space: nowrap; }
+.moment { background: #1e1e1e; border-radius: 8px; padding: 12px; margin-bottom: 8px; }
+.months { display: flex; align-items: flex-end; gap: 6px; height: 160px; }
+.month { flex: 1; display: flex; flex-direction: column; justify-content: flex-end; height: 100%; text-align: center; font-size: 12px; color: #aaa; }
+.bar { background: #1db954; border-radius: 3px 3px 0 0; min-height: 1px; }
+";
+
+/// Render `report` as a complete HTML document.
+pub fn render_html(report: &YearInReview, covers: &ReviewCovers) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{year} in review</title>\n<style>{style}</style>\n</head>\n<body>\n<main>\n\
+         <h1>{year} in review</h1>\n",
+        year = report.year,
+        style = STYLE
+    );
+
+    let _ = writeln!(
+        html,
+        "<section class=\"totals\">\
+         <div class=\"total\"><b>{}</b>minutes</div>\
+         <div class=\"total\"><b>{}</b>plays</div>\
+         <div class=\"total\"><b>{}</b>tracks</div>\
+         <div class=\"total\"><b>{}</b>artists</div>\
+         </section>",
+        report.total_minutes, report.total_plays, report.unique_tracks, report.unique_artists
+    );
+
+    if !report.top_tracks.is_empty() {
+        html.push_str("<h2>Top tracks</h2>\n<ol>\n");
+        for entry in &report.top_tracks {
+            let _ = writeln!(
+                html,
+                "<li>{}<span>{}</span><span class=\"count\">{}</span></li>",
+                cover(covers.for_track(&entry.track)),
+                track_title(&entry.track),
+                plays(entry.play_count)
+            );
+        }
+        html.push_str("</ol>\n");
+    }
+
+    if !report.top_artists.is_empty() {
+        html.push_str("<h2>Top artists</h2>\n<ol>\n");
+        for entry in &report.top_artists {
+            let _ = writeln!(
+                html,
+                "<li><span>{}</span><span class=\"count\">{}</span></li>",
+                escape(&entry.artist),
+                plays(entry.play_count)
+            );
+        }
+        html.push_str("</ol>\n");
+    }
+
+    if !report.top_albums.is_empty() {
+        html.push_str("<h2>Top albums</h2>\n<ol>\n");
+        for entry in &report.top_albums {
+            let album = &entry.album;
+            let name = match album.artist.as_deref() {
+                Some(artist) => format!("{} — {}", escape(&album.name), escape(artist)),
+                None => escape(&album.name),
+            };
+            let _ = writeln!(
+                html,
+                "<li>{}<span>{}</span><span class=\"count\">{}</span></li>",
+                cover(covers.albums.get(&album.id).map(String::as_str)),
+                name,
+                plays(entry.play_count)
+            );
+        }
+        html.push_str("</ol>\n");
+    }
+
+    if !report.top_genres.is_empty() {
+        html.push_str("<h2>Top genres</h2>\n<ol>\n");
+        for entry in &report.top_genres {
+            let _ = writeln!(
+                html,
+                "<li><span>{}</span><span class=\"count\">{}</span></li>",
+                escape(&entry.genre),
+                plays(entry.play_count)
+            );
+        }
+        html.push_str("</ol>\n");
+    }
+
+    let mut moments = String::new();
+    if let Some(first) = &report.first_play {
+        let _ = writeln!(
+            moments,
+            "<div class=\"moment\">First play: {} <span class=\"count\">{}</span></div>",
+            track_title(&first.track),
+            escape(&first.played_at)
+        );
+    }
+    if let Some(last) = &report.last_play {
+        let _ = writeln!(
+            moments,
+            "<div class=\"moment\">Last play: {} <span class=\"count\">{}</span></div>",
+            track_title(&last.track),
+            escape(&last.played_at)
+        );
+    }
+    if let Some(day) = &report.most_replayed_day {
+        let _ = writeln!(
+            moments,
+            "<div class=\"moment\">On {} you played {} {} times \
+             <span class=\"count\">{} that day</span></div>",
+            escape(&day.date),
+            track_title(&day.track),
+            day.track_plays,
+            plays(day.total_plays)
+        );
+    }
+    if !moments.is_empty() {
+        html.push_str("<h2>Moments</h2>\n");
+        html.push_str(&moments);
+    }
+
+    let busiest = report.monthly.iter().map(|m| m.minutes).max().unwrap_or(0);
+    html.push_str("<h2>Month by month</h2>\n<div class=\"months\">\n");
+    for (i, month) in report.monthly.iter().enumerate() {
+        let height = if busiest > 0 {
+            month.minutes * 100 / busiest
+        } else {
+            0
+        };
+        let mut tip = format!("{} minutes, {}", month.minutes, plays(month.plays));
+        if let Some(artist) = &month.top_artist {
+            let _ = write!(tip, ", mostly {}", artist);
+        }
+        let _ = writeln!(
+            html,
+            "<div class=\"month\" title=\"{}\"><div class=\"bar\" style=\"height: {}%\"></div>{}</div>",
+            escape(&tip),
+            height,
+            MONTHS.get(i).copied().unwrap_or("")
+        );
+    }
+    html.push_str("</div>\n</main>\n</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::queries::{ArtistWithCount, MonthTrend, TrackWithCount};
+
+    fn track(id: i64, title: &str, album_id: Option<i64>) -> Track {
+        Track {
+            id,
+            path: format!("/music/{}.mp3", id),
+            title: Some(title.to_string()),
+            artist: Some("A & B".to_string()),
+            album: None,
+            track_number: None,
+            duration: None,
+            album_id,
+            format: None,
+            bitrate: None,
+            source_type: None,
+            cover_url: None,
+            external_id: None,
+            local_src: None,
+            track_cover: None,
+            track_cover_path: None,
+            disc_number: None,
+            metadata_json: None,
+            date_added: None,
+        }
+    }
+
+    fn report() -> YearInReview {
+        YearInReview {
+            year: 2024,
+            total_plays: 3,
+            total_minutes: 10,
+            unique_tracks: 2,
+            unique_artists: 1,
+            top_tracks: vec![
+                TrackWithCount {
+                    track: track(1, "<Intro>", Some(7)),
+                    play_count: 2,
+                },
+                TrackWithCount {
+                    track: track(2, "Outro", None),
+                    play_count: 1,
+                },
+            ],
+            top_artists: vec![ArtistWithCount {
+                artist: "A & B".to_string(),
+                play_count: 3,
+            }],
+            top_albums: Vec::new(),
+            top_genres: Vec::new(),
+            first_play: None,
+            last_play: None,
+            most_replayed_day: None,
+            monthly: (1..=12)
+                .map(|m| MonthTrend {
+                    month: format!("2024-{:02}", m),
+                    plays: if m == 3 { 3 } else { 0 },
+                    minutes: if m == 3 { 10 } else { 0 },
+                    top_artist: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_render_html() {
+        let mut covers = ReviewCovers::default();
+        covers
+            .albums
+            .insert(7, data_uri("image/png", "iVBORw0KGgo="));
+        let html = render_html(&report(), &covers);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>2024 in review</title>"));
+        assert!(html.contains("&lt;Intro&gt; — A &amp; B"));
+        assert!(!html.contains("<Intro>"));
+        // The first track falls back to its album's cover, the second has none
+        assert!(html.contains("src=\"data:image/png;base64,iVBORw0KGgo=\""));
+        assert_eq!(html.matches("<div class=\"cover\"></div>").count(), 1);
+        // Empty sections are left out; all twelve months get a bar
+        assert!(!html.contains("Top albums"));
+        assert!(!html.contains("Moments"));
+        assert_eq!(html.matches("class=\"bar\"").count(), 12);
+        assert!(html.contains("height: 100%\"></div>Mar"));
+    }
+}
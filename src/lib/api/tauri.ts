@@ -113,6 +113,12 @@ export interface Track {
     date_added?: string | null;
 }
 
+// A track as it appears in a playlist; the same track can appear twice
+export interface PlaylistTrack extends Track {
+    entry_id: number | null;  // null for smart playlists
+    position: number | null;
+}
+
 export interface Album {
     id: number;
     name: string;
@@ -132,6 +138,19 @@ export interface Playlist {
     name: string;
     created_at: string | null;
     folder_path?: string | null;
+    smart_rules?: SmartPlaylistRules | null;
+    folder_id?: number | null;  // playlist folder, null at the top level
+    position?: number | null;   // order within the folder; null sorts by name
+}
+
+export type SmartRule =
+    | { type: 'group'; match?: 'all' | 'any'; rules: SmartRule[] }
+    | { type: 'condition'; field: string; op: string; value: unknown };
+
+export interface SmartPlaylistRules {
+    rules: SmartRule;
+    sort?: { field?: string; descending?: boolean };
+    limit?: { by: 'count' | 'duration'; value: number } | null;
 }
 
 export interface Library {
@@ -144,9 +163,18 @@ export interface ScanResult {
     tracks_added: number;
     tracks_updated: number;
     tracks_deleted: number;
+    tracks_unchanged: number;
+    moves: TrackMove[];
+    files_excluded: number;
     errors: string[];
 }
 
+export interface TrackMove {
+    track_id: number;
+    from: string;
+    to: string;
+}
+
 // Progressive scan types
 export interface ScanProgress {
     current: number;
@@ -187,8 +215,8 @@ export async function addFolder(path: string): Promise<void> {
     return await invoke('add_folder', { path });
 }
 
-export async function rescanMusic(): Promise<ScanResult> {
-    return await invoke('rescan_music');
+export async function rescanMusic(forceFull = false): Promise<ScanResult> {
+    return await invoke('rescan_music', { forceFull });
 }
 
 export async function getDefaultMusicDirs(): Promise<string[]> {
@@ -394,16 +422,17 @@ export async function getPlaylists(): Promise<Playlist[]> {
     return await invoke('get_playlists');
 }
 
-export async function getPlaylistTracks(playlistId: number): Promise<Track[]> {
+export async function getPlaylistTracks(playlistId: number): Promise<PlaylistTrack[]> {
     return await invoke('get_playlist_tracks', { playlistId });
 }
 
-export async function addTrackToPlaylist(playlistId: number, trackId: number): Promise<void> {
-    return await invoke('add_track_to_playlist', { playlistId, trackId });
+// A track already in the playlist is only added again with allowDuplicate
+export async function addTrackToPlaylist(playlistId: number, trackId: number, allowDuplicate = false): Promise<number> {
+    return await invoke('add_track_to_playlist', { playlistId, trackId, allowDuplicate });
 }
 
-export async function removeTrackFromPlaylist(playlistId: number, trackId: number): Promise<void> {
-    return await invoke('remove_track_from_playlist', { playlistId, trackId });
+export async function removeTrackFromPlaylist(playlistId: number, entryId: number): Promise<void> {
+    return await invoke('remove_track_from_playlist', { playlistId, entryId });
 }
 
 export async function deletePlaylist(playlistId: number): Promise<void> {
@@ -414,8 +443,8 @@ export async function renamePlaylist(playlistId: number, newName: string): Promi
     return await invoke('rename_playlist', { playlistId, newName });
 }
 
-export async function reorderPlaylistTracks(playlistId: number, fromIndex: number, toIndex: number): Promise<void> {
-    return await invoke('reorder_playlist_tracks', { playlistId, fromIndex, toIndex });
+export async function reorderPlaylistTracks(playlistId: number, entryId: number, toIndex: number): Promise<void> {
+    return await invoke('reorder_playlist_tracks', { playlistId, entryId, toIndex });
 }
 
 export async function beginFolderImport(folderPath: string): Promise<number> {
@@ -447,6 +476,11 @@ export interface StatsSummary {
     top_genre: string | null;
 }
 
+/** Period for stats; the current month when omitted. Custom dates are YYYY-MM-DD, both included. */
+export type StatsRange =
+    | { kind: 'day' | 'week' | 'month' | 'year' | 'all' }
+    | { kind: 'custom'; from?: string | null; to?: string | null };
+
 export async function likeTrack(trackId: number): Promise<void> {
     return await invoke('like_track', { trackId });
 }
@@ -467,28 +501,30 @@ export async function getLikedTracks(): Promise<Track[]> {
     return await invoke('get_liked_tracks');
 }
 
-export async function recordPlay(trackId: number, albumId: number | null, durationPlayed: number): Promise<void> {
-    return await invoke('record_play', { trackId, albumId, durationPlayed });
+export type PlayOutcome = 'completed' | 'skipped';
+
+export async function recordPlay(trackId: number, albumId: number | null, durationPlayed: number, position?: number): Promise<PlayOutcome> {
+    return await invoke('record_play', { trackId, albumId, durationPlayed, position });
 }
 
-export async function getTopTracks(limit: number): Promise<TrackWithCount[]> {
-    return await invoke('get_top_tracks', { limit });
+export async function getTopTracks(limit: number, range?: StatsRange): Promise<TrackWithCount[]> {
+    return await invoke('get_top_tracks', { limit, range });
 }
 
-export async function getTopAlbums(limit: number): Promise<AlbumWithCount[]> {
-    return await invoke('get_top_albums', { limit });
+export async function getTopAlbums(limit: number, range?: StatsRange): Promise<AlbumWithCount[]> {
+    return await invoke('get_top_albums', { limit, range });
 }
 
 export async function getRecentlyPlayed(limit: number): Promise<Track[]> {
     return await invoke('get_recently_played', { limit });
 }
 
-export async function getTopArtists(limit: number): Promise<ArtistWithCount[]> {
-    return await invoke('get_top_artists', { limit });
+export async function getTopArtists(limit: number, range?: StatsRange): Promise<ArtistWithCount[]> {
+    return await invoke('get_top_artists', { limit, range });
 }
 
-export async function getStatsSummary(): Promise<StatsSummary> {
-    return await invoke('get_stats_summary');
+export async function getStatsSummary(range?: StatsRange): Promise<StatsSummary> {
+    return await invoke('get_stats_summary', { range });
 }
 
 
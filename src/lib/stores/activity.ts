@@ -10,14 +10,14 @@ export const statsSummary = writable<StatsSummary | null>(null);
 export const isLoadingActivity = writable<boolean>(false);
 
 // Record a play event for a track
-export async function recordTrackPlay(trackId: number, albumId: number | null, durationPlayed: number): Promise<void> {
+export async function recordTrackPlay(trackId: number, albumId: number | null, durationPlayed: number, position?: number): Promise<void> {
     // Only record for tracks with numeric IDs (library tracks)
     if (typeof trackId !== 'number') {
         return;
     }
 
     try {
-        await recordPlay(trackId, albumId, durationPlayed);
+        await recordPlay(trackId, albumId, durationPlayed, position);
     } catch (error) {
         console.error('[Activity] Failed to record play:', error);
     }
@@ -658,6 +658,12 @@ function updateMediaSessionPosition(): void {
     }
 }
 
+// Whole seconds since playStartTime, at least 1: the backend reads 0 as
+// "length not recorded" and would count an instant skip as a full play
+function secondsPlayed(): number {
+    return Math.max(1, Math.floor((Date.now() - playStartTime) / 1000));
+}
+
 // Play a specific track
 export async function playTrack(track: Track, skipLocalSrc = false, startTime = 0): Promise<void> {
     const previousTrackObj = get(currentTrack);
@@ -665,22 +671,21 @@ export async function playTrack(track: Track, skipLocalSrc = false, startTime =
 
     // Record play for the previous track (if any)
     if (previousTrackObj && playStartTime > 0) {
-        const durationPlayed = Math.floor((Date.now() - playStartTime) / 1000);
-        if (durationPlayed > 5) { // Only record if played for more than 5 seconds
-            recordTrackPlay(previousTrackObj.id, previousTrackObj.album_id ?? null, durationPlayed);
-            // ListenBrainz: scrobble if >= 50 % of track duration or 4 minutes played
-            const trackDuration = previousTrackObj.duration ?? 0;
-            if (get(appSettings).listenBrainzEnabled && trackDuration > 0) {
-                const threshold = Math.min(Math.floor(trackDuration / 2), 240);
-                if (durationPlayed >= threshold) {
-                    submitListenbrainzListen(
-                        previousTrackObj.artist ?? 'Unknown Artist',
-                        previousTrackObj.title ?? 'Unknown',
-                        previousTrackObj.album,
-                        previousTrackObj.duration,
-                        false,
-                    ).catch(e => console.warn('[ListenBrainz] Scrobble failed:', e));
-                }
+        const durationPlayed = secondsPlayed();
+        // Short plays are recorded too; the backend counts them as skips
+        recordTrackPlay(previousTrackObj.id, previousTrackObj.album_id ?? null, durationPlayed, Math.floor(get(currentTime)));
+        // ListenBrainz: scrobble if >= 50 % of track duration or 4 minutes played
+        const trackDuration = previousTrackObj.duration ?? 0;
+        if (get(appSettings).listenBrainzEnabled && trackDuration > 0) {
+            const threshold = Math.min(Math.floor(trackDuration / 2), 240);
+            if (durationPlayed >= threshold) {
+                submitListenbrainzListen(
+                    previousTrackObj.artist ?? 'Unknown Artist',
+                    previousTrackObj.title ?? 'Unknown',
+                    previousTrackObj.album,
+                    previousTrackObj.duration,
+                    false,
+                ).catch(e => console.warn('[ListenBrainz] Scrobble failed:', e));
             }
         }
     }
@@ -1246,22 +1251,20 @@ function handleTrackEnd(): void {
     // Record play for the track that just ended
     const track = get(currentTrack);
     if (track && playStartTime > 0) {
-        const durationPlayed = Math.floor((Date.now() - playStartTime) / 1000);
-        if (durationPlayed > 5) {
-            recordTrackPlay(track.id, track.album_id ?? null, durationPlayed);
-            // ListenBrainz: scrobble if >= 50 % of duration or 4 minutes
-            const trackDuration = track.duration ?? 0;
-            if (get(appSettings).listenBrainzEnabled && trackDuration > 0) {
-                const threshold = Math.min(Math.floor(trackDuration / 2), 240);
-                if (durationPlayed >= threshold) {
-                    submitListenbrainzListen(
-                        track.artist ?? 'Unknown Artist',
-                        track.title ?? 'Unknown',
-                        track.album,
-                        track.duration,
-                        false,
-                    ).catch(e => console.warn('[ListenBrainz] Scrobble failed:', e));
-                }
+        const durationPlayed = secondsPlayed();
+        recordTrackPlay(track.id, track.album_id ?? null, durationPlayed, Math.floor(get(currentTime)));
+        // ListenBrainz: scrobble if >= 50 % of duration or 4 minutes
+        const trackDuration = track.duration ?? 0;
+        if (get(appSettings).listenBrainzEnabled && trackDuration > 0) {
+            const threshold = Math.min(Math.floor(trackDuration / 2), 240);
+            if (durationPlayed >= threshold) {
+                submitListenbrainzListen(
+                    track.artist ?? 'Unknown Artist',
+                    track.title ?? 'Unknown',
+                    track.album,
+                    track.duration,
+                    false,
+                ).catch(e => console.warn('[ListenBrainz] Scrobble failed:', e));
             }
         }
         playStartTime = 0; // Reset so playTrack doesn't double-record
@@ -1276,21 +1279,20 @@ function handleGaplessAdvance(): void {
     // Record play for the track that just ended
     const prevTrack = get(currentTrack);
     if (prevTrack && playStartTime > 0) {
-        const durationPlayed = Math.floor((Date.now() - playStartTime) / 1000);
-        if (durationPlayed > 5) {
-            recordTrackPlay(prevTrack.id, prevTrack.album_id ?? null, durationPlayed);
-            const trackDuration = prevTrack.duration ?? 0;
-            if (get(appSettings).listenBrainzEnabled && trackDuration > 0) {
-                const threshold = Math.min(Math.floor(trackDuration / 2), 240);
-                if (durationPlayed >= threshold) {
-                    submitListenbrainzListen(
-                        prevTrack.artist ?? 'Unknown Artist',
-                        prevTrack.title ?? 'Unknown',
-                        prevTrack.album,
-                        prevTrack.duration,
-                        false,
-                    ).catch(e => console.warn('[ListenBrainz] Scrobble failed:', e));
-                }
+        const durationPlayed = secondsPlayed();
+        // currentTime already belongs to the next track; this one played to its end
+        recordTrackPlay(prevTrack.id, prevTrack.album_id ?? null, durationPlayed, prevTrack.duration ?? undefined);
+        const trackDuration = prevTrack.duration ?? 0;
+        if (get(appSettings).listenBrainzEnabled && trackDuration > 0) {
+            const threshold = Math.min(Math.floor(trackDuration / 2), 240);
+            if (durationPlayed >= threshold) {
+                submitListenbrainzListen(
+                    prevTrack.artist ?? 'Unknown Artist',
+                    prevTrack.title ?? 'Unknown',
+                    prevTrack.album,
+                    prevTrack.duration,
+                    false,
+                ).catch(e => console.warn('[ListenBrainz] Scrobble failed:', e));
             }
         }
     }